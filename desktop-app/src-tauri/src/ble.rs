@@ -0,0 +1,244 @@
+//! BLE transport for Daemon nodes that speak the line protocol over a Nordic UART service
+//! instead of TCP. btleplug is async; `BleLink` bridges it to the blocking `Read`/`Write` that
+//! `NodeConnection` is built on, so BLE nodes share the node manager and manifest pipeline.
+
+use crate::BleNodeCandidate;
+use btleplug::api::{Central as _, CharPropFlags, Manager as _, Peripheral as _, ScanFilter, WriteType};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures_util::StreamExt;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The node receives on RX (we write) and sends on TX (we subscribe).
+const UART_SERVICE: Uuid = Uuid::from_u128(0x6e400001_b5a3_f393_e0a9_e50e24dcca9e);
+const UART_RX: Uuid = Uuid::from_u128(0x6e400002_b5a3_f393_e0a9_e50e24dcca9e);
+const UART_TX: Uuid = Uuid::from_u128(0x6e400003_b5a3_f393_e0a9_e50e24dcca9e);
+/// Bytes per RX write: the default ATT MTU (23) less its 3-byte header.
+const WRITE_CHUNK: usize = 20;
+/// Shortest scan used to find a peripheral, however short the connect timeout; advertising
+/// intervals on battery-powered boards can be a second or more.
+const MIN_DISCOVERY: Duration = Duration::from_secs(5);
+
+type WriteRequest = (Vec<u8>, mpsc::Sender<Result<(), String>>);
+
+/// Runs `task` on the async runtime and waits up to `timeout` for it, for blocking callers.
+fn run_blocking<T: Send + 'static>(
+    timeout: Duration,
+    task: impl Future<Output = Result<T, String>> + Send + 'static,
+) -> Result<T, String> {
+    let (tx, rx) = mpsc::channel();
+    tauri::async_runtime::spawn(async move {
+        let _ = tx.send(task.await);
+    });
+    rx.recv_timeout(timeout).map_err(|_| "BLE operation timed out".to_string())?
+}
+
+async fn first_adapter() -> Result<Adapter, String> {
+    let manager = Manager::new().await.map_err(|e| format!("BLE is unavailable: {e}"))?;
+    manager
+        .adapters()
+        .await
+        .map_err(|e| format!("Failed to list BLE adapters: {e}"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No BLE adapter found".to_string())
+}
+
+/// Peripherals seen so far that advertise the UART service.
+async fn uart_peripherals(adapter: &Adapter) -> Result<Vec<(Peripheral, BleNodeCandidate)>, String> {
+    let mut found = Vec::new();
+    for peripheral in adapter.peripherals().await.map_err(|e| format!("BLE scan failed: {e}"))? {
+        let Ok(Some(props)) = peripheral.properties().await else {
+            continue;
+        };
+        if !props.services.contains(&UART_SERVICE) {
+            continue;
+        }
+        let id = peripheral.id().to_string();
+        let candidate = BleNodeCandidate {
+            host: format!("{}{id}", crate::BLE_NODE_SCHEME),
+            id,
+            name: props.local_name,
+            address: props.address.to_string(),
+            rssi: props.rssi,
+            status: None,
+        };
+        found.push((peripheral, candidate));
+    }
+    Ok(found)
+}
+
+/// Scans for `duration` and returns the UART peripherals found, strongest signal first.
+pub(crate) async fn scan(duration: Duration) -> Result<Vec<BleNodeCandidate>, String> {
+    let adapter = first_adapter().await?;
+    adapter
+        .start_scan(ScanFilter { services: vec![UART_SERVICE] })
+        .await
+        .map_err(|e| format!("BLE scan failed: {e}"))?;
+    tokio::time::sleep(duration).await;
+    let found = uart_peripherals(&adapter).await;
+    let _ = adapter.stop_scan().await;
+    let mut found = found?.into_iter().map(|(_, candidate)| candidate).collect::<Vec<_>>();
+    found.sort_by_key(|c| std::cmp::Reverse(c.rssi.unwrap_or(i16::MIN)));
+    Ok(found)
+}
+
+/// Scans until a UART peripheral whose id, address or advertised name is `id` shows up.
+async fn find(adapter: &Adapter, id: &str, timeout: Duration) -> Result<Peripheral, String> {
+    adapter
+        .start_scan(ScanFilter { services: vec![UART_SERVICE] })
+        .await
+        .map_err(|e| format!("BLE scan failed: {e}"))?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    let found = async {
+        loop {
+            let matched = uart_peripherals(adapter).await?.into_iter().find(|(_, c)| {
+                c.id == id || c.address.eq_ignore_ascii_case(id) || c.name.as_deref() == Some(id)
+            });
+            if let Some((peripheral, _)) = matched {
+                return Ok(peripheral);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!("BLE node {id} not found"));
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+    .await;
+    let _ = adapter.stop_scan().await;
+    found
+}
+
+/// A connected UART peripheral as a blocking byte stream. Notifications are queued as they
+/// arrive; writes go through a task that owns the peripheral and disconnects it once the link
+/// is dropped.
+pub(crate) struct BleLink {
+    incoming: mpsc::Receiver<Vec<u8>>,
+    buffered: VecDeque<u8>,
+    writes: tokio::sync::mpsc::UnboundedSender<WriteRequest>,
+    read_timeout: Option<Duration>,
+    write_timeout: Duration,
+}
+
+impl BleLink {
+    /// Finds, connects to and subscribes to the peripheral `id` (see `find`).
+    pub(crate) fn open(id: &str, connect_timeout: Duration, write_timeout: Duration) -> Result<Self, String> {
+        let id = id.to_string();
+        let discovery = connect_timeout.max(MIN_DISCOVERY);
+        run_blocking(discovery + connect_timeout * 2, async move {
+            let adapter = first_adapter().await?;
+            let peripheral = find(&adapter, &id, discovery).await?;
+            tokio::time::timeout(connect_timeout, async {
+                peripheral.connect().await?;
+                peripheral.discover_services().await
+            })
+            .await
+            .map_err(|_| format!("BLE connect to {id} timed out"))?
+            .map_err(|e| format!("BLE connect to {id} failed: {e}"))?;
+            let characteristics = peripheral.characteristics();
+            let (Some(rx), Some(tx)) = (
+                characteristics.iter().find(|c| c.uuid == UART_RX).cloned(),
+                characteristics.iter().find(|c| c.uuid == UART_TX).cloned(),
+            ) else {
+                let _ = peripheral.disconnect().await;
+                return Err(format!("BLE node {id} has no UART RX/TX characteristics"));
+            };
+            let subscribed = async {
+                peripheral.subscribe(&tx).await?;
+                peripheral.notifications().await
+            };
+            let mut notifications = match subscribed.await {
+                Ok(notifications) => notifications,
+                Err(error) => {
+                    let _ = peripheral.disconnect().await;
+                    return Err(format!("BLE subscribe to {id} failed: {error}"));
+                }
+            };
+
+            let (incoming_tx, incoming) = mpsc::channel();
+            tauri::async_runtime::spawn(async move {
+                while let Some(notification) = notifications.next().await {
+                    if notification.uuid == UART_TX && incoming_tx.send(notification.value).is_err() {
+                        break;
+                    }
+                }
+            });
+            let write_type = if rx.properties.contains(CharPropFlags::WRITE_WITHOUT_RESPONSE) {
+                WriteType::WithoutResponse
+            } else {
+                WriteType::WithResponse
+            };
+            let (writes, mut queue) = tokio::sync::mpsc::unbounded_channel::<WriteRequest>();
+            tauri::async_runtime::spawn(async move {
+                // Some backends tie the peripheral to its adapter; keep the adapter alive with it.
+                let _adapter = adapter;
+                while let Some((data, done)) = queue.recv().await {
+                    let mut result = Ok(());
+                    for chunk in data.chunks(WRITE_CHUNK) {
+                        if let Err(error) = peripheral.write(&rx, chunk, write_type).await {
+                            result = Err(format!("BLE write failed: {error}"));
+                            break;
+                        }
+                    }
+                    let _ = done.send(result);
+                }
+                let _ = peripheral.disconnect().await;
+            });
+            Ok(BleLink {
+                incoming,
+                buffered: VecDeque::new(),
+                writes,
+                read_timeout: None,
+                write_timeout,
+            })
+        })
+    }
+
+    pub(crate) fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+}
+
+impl Read for BleLink {
+    /// Times out like a socket with a read timeout; a dropped peripheral reads as end of stream.
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.buffered.is_empty() {
+            let received = match self.read_timeout {
+                Some(timeout) => self.incoming.recv_timeout(timeout),
+                None => self.incoming.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(chunk) => self.buffered.extend(chunk),
+                Err(mpsc::RecvTimeoutError::Timeout) => return Err(std::io::ErrorKind::TimedOut.into()),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let count = buf.len().min(self.buffered.len());
+        for (slot, byte) in buf.iter_mut().zip(self.buffered.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for BleLink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let (done, result) = mpsc::channel();
+        self.writes
+            .send((buf.to_vec(), done))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        match result.recv_timeout(self.write_timeout) {
+            Ok(Ok(())) => Ok(buf.len()),
+            Ok(Err(error)) => Err(std::io::Error::other(error)),
+            Err(_) => Err(std::io::ErrorKind::TimedOut.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
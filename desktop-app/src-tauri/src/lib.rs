@@ -1,20 +1,86 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use serialport::SerialPort;
+use sha2::{Digest, Sha256};
 use base64::Engine as _;
+use regex::Regex;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::path::{Path, PathBuf};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
 use std::process::{Child, Command, Stdio};
 use std::{fs::OpenOptions};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+#[cfg(feature = "ble")]
+mod ble;
+#[cfg(feature = "parquet")]
+mod reward_parquet;
 
 const SERIAL_EVENT: &str = "serial_line";
-const OPENAI_RESPONSES_URL: &str = "https://api.openai.com/v1/responses";
+const SERIAL_TX_EVENT: &str = "serial_tx";
+const SERIAL_LINES_EVENT: &str = "serial_lines";
+const SERIAL_STATE_EVENT: &str = "serial_state";
+const FLASH_PROGRESS_EVENT: &str = "flash_progress";
+const SERIAL_STALLED_EVENT: &str = "serial_stalled";
+const ORCHESTRATOR_LOG_EVENT: &str = "orchestrator_log";
+const ORCHESTRATOR_LOG_FILE_EVENT: &str = "orchestrator_log_file";
+const DEFAULT_ORCHESTRATOR_INSTANCE: &str = "default";
+const ORCHESTRATOR_PROCESS_KIND: &str = "orchestrator";
+const VISION_PROCESS_KIND: &str = "vision";
+const VISION_LOG_EVENT: &str = "vision_log";
+const PROCESS_EVENT: &str = "process_state";
+const ORCHESTRATOR_CRASHED_EVENT: &str = "orchestrator_crashed";
+const CRASH_REPORT_TAIL_LINES: usize = 200;
+const ORCHESTRATOR_HEALTH_EVENT: &str = "orchestrator_health";
+const PLAN_QUEUE_EVENT: &str = "plan_queue";
+const PLAN_PROGRESS_EVENT: &str = "plan_progress";
+const CRITIC_STEP_EVENT: &str = "critic_step_result";
+const CRITIC_ERROR_EVENT: &str = "critic_error";
+const CRITIC_BUDGET_EXCEEDED_EVENT: &str = "critic_budget_exceeded";
+const CRITIC_CORRECTION_EVENT: &str = "critic_correction";
+const CRITIC_CRITICAL_FAILURE_EVENT: &str = "critic_critical_failure";
+const TASK_SUCCEEDED_EVENT: &str = "task_succeeded";
+const NODE_ONLINE_EVENT: &str = "node_online";
+const NODE_OFFLINE_EVENT: &str = "node_offline";
+const NODE_MANIFEST_CHANGED_EVENT: &str = "node_manifest_changed";
+const NODE_TELEMETRY_EVENT: &str = "node_telemetry";
+const NODE_CONNECTION_STATE_EVENT: &str = "node_connection_state";
+const NODE_STATUS_EVENT: &str = "node_status";
+const PLAN_QUEUE_RETAINED_FINISHED: usize = 100;
+const SERIAL_HISTORY_CAPACITY: usize = 2000;
+const NODE_REGISTRY_FILE: &str = "node_registry.json";
+const ENDPOINT_SETTINGS_FILE: &str = "endpoint_settings.json";
+const OPENAI_CONFIG_FILE: &str = "openai_config.json";
+const CRITIC_PROMPTS_DIR: &str = "critic_prompts";
+const DEFAULT_CRITIC_PROMPT_TEMPLATE: &str = "default";
+const DEFAULT_ROBOT_DESCRIPTION: &str = "a physical RC robot";
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Disambiguates correlation ids generated within the same millisecond.
+static CORRELATION_SEQ: AtomicU64 = AtomicU64::new(0);
+/// Per-orchestrator request counters keyed by normalized base URL, fed by `orchestrator_request`.
+static ORCHESTRATOR_COUNTERS: Mutex<Option<HashMap<String, OrchestratorCounters>>> = Mutex::new(None);
+/// Calls made to the mock critic since the last `critic_spawn`; indexes `mock_script`.
+static MOCK_CRITIC_SEQ: AtomicU64 = AtomicU64::new(0);
+/// Held across read-modify-write cycles of `NODE_REGISTRY_FILE`, which probes update concurrently.
+static NODE_REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+/// Per-node connection settings of registry entries, keyed by `host:port` (`tls://host:port` for TLS).
+/// Refreshed from the registry in `setup` and on every registry write.
+static NODE_SETTINGS: Mutex<Option<HashMap<String, NodeSettings>>> = Mutex::new(None);
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,9 +90,109 @@ struct CriticStatus {
     model: Option<String>,
     success_streak: u32,
     success_n: u32,
+    /// Set while the backend loop (rather than the webview) drives `critic_step`.
+    loop_interval_ms: Option<u64>,
+    usage: Option<CriticUsage>,
+    /// Episode log id under `logs/critic/`, for `read_critic_session`.
+    session_id: Option<String>,
+    /// Why the session stopped accepting steps, once a `critic_spawn` budget ran out.
+    budget_exceeded: Option<String>,
 }
 
-#[derive(Serialize)]
+/// Token totals and estimated spend for one critic session.
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticUsage {
+    steps: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    /// Part of `input_tokens` served from the provider's prompt cache.
+    cached_input_tokens: u64,
+    /// Steps where at least part of the prompt was a cache hit.
+    cache_hit_steps: u64,
+    /// Estimated from `TokenPricing`; `None` until a step with known pricing and usage completes.
+    cost_usd: Option<f64>,
+    /// Steps whose response carried no usage block.
+    steps_without_usage: u64,
+}
+
+#[derive(Clone, Copy, Default)]
+struct TokenUsage {
+    /// All prompt tokens, cached or not.
+    input_tokens: u64,
+    output_tokens: u64,
+    cached_input_tokens: u64,
+}
+
+/// USD per million tokens.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenPricing {
+    input_per_mtok: f64,
+    output_per_mtok: f64,
+    /// Price of prompt-cache reads; defaults to `input_per_mtok`.
+    #[serde(default)]
+    cached_input_per_mtok: Option<f64>,
+}
+
+impl TokenPricing {
+    /// List prices for common critic models, matched by longest prefix. Estimates only; pass
+    /// `backend.pricing` to `critic_spawn` for anything else.
+    fn for_model(model: &str) -> Option<TokenPricing> {
+        // (prefix, input, output, cached input)
+        const TABLE: &[(&str, f64, f64, f64)] = &[
+            ("gpt-4o-mini", 0.15, 0.60, 0.075),
+            ("gpt-4o", 2.50, 10.0, 1.25),
+            ("gpt-4.1-mini", 0.40, 1.60, 0.10),
+            ("gpt-4.1", 2.00, 8.00, 0.50),
+            ("gpt-5-mini", 0.25, 2.00, 0.025),
+            ("gpt-5", 1.25, 10.0, 0.125),
+            ("claude-3-5-haiku", 0.80, 4.00, 0.08),
+            ("claude-sonnet-4", 3.00, 15.0, 0.30),
+            ("gemini-2.5-flash", 0.30, 2.50, 0.075),
+            ("gemini-2.5-pro", 1.25, 10.0, 0.31),
+        ];
+        TABLE
+            .iter()
+            .filter(|(prefix, ..)| model.starts_with(prefix))
+            .max_by_key(|(prefix, ..)| prefix.len())
+            .map(|&(_, input_per_mtok, output_per_mtok, cached_input_per_mtok)| TokenPricing {
+                input_per_mtok,
+                output_per_mtok,
+                cached_input_per_mtok: Some(cached_input_per_mtok),
+            })
+    }
+
+    fn cost_usd(&self, usage: TokenUsage) -> f64 {
+        let cached = usage.cached_input_tokens.min(usage.input_tokens);
+        let uncached = usage.input_tokens - cached;
+        (uncached as f64 * self.input_per_mtok
+            + cached as f64 * self.cached_input_per_mtok.unwrap_or(self.input_per_mtok)
+            + usage.output_tokens as f64 * self.output_per_mtok)
+            / 1e6
+    }
+}
+
+impl CriticUsage {
+    fn record(&mut self, usage: Option<TokenUsage>, pricing: Option<TokenPricing>) {
+        self.steps += 1;
+        let Some(usage) = usage else {
+            self.steps_without_usage += 1;
+            return;
+        };
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cached_input_tokens += usage.cached_input_tokens;
+        if usage.cached_input_tokens > 0 {
+            self.cache_hit_steps += 1;
+        }
+        if let Some(pricing) = pricing {
+            self.cost_usd = Some(self.cost_usd.unwrap_or(0.0) + pricing.cost_usd(usage));
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct CriticStepResult {
     reward: f64,
@@ -34,6 +200,10 @@ struct CriticStepResult {
     success_confidence: f64,
     success_streak: u32,
     success_stable: bool,
+    /// Exponentially smoothed reward, when the session sets `reward_ema_alpha`.
+    reward_ema: Option<f64>,
+    /// Successful frames among the last `success_window` steps, when a window is configured.
+    window_successes: Option<u32>,
     motion_score: f64,
     motion_gate: bool,
     motion_gate_reason: String,
@@ -43,1292 +213,13350 @@ struct CriticStepResult {
     describe: String,
     evaluate: String,
     notes_short: String,
+    /// Short recovery action the critic proposed, empty if none.
+    suggested_correction: String,
+    /// The suggestion was sent to the orchestrator planner; the reply arrives as `critic_correction`.
+    correction_forwarded: bool,
+    /// The orchestrator accepted `/stop` after a critical failure.
     interrupt_sent: bool,
+    /// What the session's `critical_failure_actions` did; empty unless `critical_failure`.
+    critical_actions: Vec<CriticActionReport>,
+    /// The step was refused because it came sooner than the session's `min_step_interval_ms`;
+    /// no model was called and the streak is unchanged.
+    throttled: bool,
+    retry_after_ms: Option<u64>,
+    /// Reused the previous result because the scene had not changed; no API call was made.
+    cached: bool,
+    /// Values of the session's `extra_output_properties`, coerced to their declared JSON types
+    /// (`null` if the model omitted one or returned the wrong type).
+    extra_fields: serde_json::Map<String, Value>,
+    /// Model that produced the verdict (the primary's, for an ensemble); differs from the session
+    /// model when a fallback was used.
+    model: String,
+    /// Per-model outputs when the session runs an ensemble; empty for a single model.
+    ensemble: Vec<CriticEnsembleVote>,
+    /// Total base64 size of the frames as received vs as sent to the model.
+    frame_bytes_original: usize,
+    frame_bytes_sent: usize,
+    correlation_id: String,
+    /// `<session_id>:<step>`; pass to `critic_label_step`. Empty for throttled steps.
+    step_id: String,
     raw: Value,
 }
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticEnsembleVote {
+    model: String,
+    reward: Option<f64>,
+    success: Option<bool>,
+    success_confidence: Option<f64>,
+    critical_failure: bool,
+    /// Set if this model's call failed; it then does not take part in the vote.
+    error: Option<String>,
+}
+
 #[derive(Clone)]
 struct SerialSession {
-    writer: Arc<Mutex<Box<dyn SerialPort + Send>>>,
-    stop_tx: mpsc::Sender<()>,
+    cmd_tx: tokio::sync::mpsc::UnboundedSender<SerialCommand>,
+    /// Identifier the session was opened with: a device path or a `usb:` alias.
     port_name: String,
+    device_path: Arc<Mutex<String>>,
+    capture: Arc<Mutex<Option<SerialCapture>>>,
+    history: Arc<Mutex<VecDeque<SerialHistoryEntry>>>,
+    /// Woken by the worker after each RX line lands in `history`.
+    rx_notify: Arc<tokio::sync::Notify>,
+    filter: Arc<Mutex<SerialLineFilter>>,
+    conn_state: Arc<Mutex<SerialConnectionState>>,
+    rate_limit: Arc<Mutex<SerialRateLimit>>,
+    watchdog: Arc<Mutex<SerialWatchdog>>,
+    legacy_events: bool,
+    connected_ts_ms: u128,
 }
 
-#[derive(Clone)]
-struct NodeManifestSummary {
-    raw: Value,
-    device_name: Option<String>,
-    node_id: Option<String>,
-    tokens: Vec<String>,
+/// Backend-enforced cap on outgoing serial writes. Writes over the limit wait in a small
+/// queue; once the queue is full they are rejected instead of piling up in firmware buffers.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialRateLimit {
+    max_lines_per_sec: Option<f64>,
+    queue_capacity: usize,
+    queued: usize,
+    rejected_total: u64,
 }
 
-struct OrchestratorProcess {
-    child: Child,
-    args: Vec<String>,
-    http_base_url: String,
+impl SerialRateLimit {
+    fn new(max_lines_per_sec: Option<f64>, queue_capacity: Option<usize>) -> Result<Self, String> {
+        if let Some(rate) = max_lines_per_sec {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(format!("max_lines_per_sec must be a positive number, got {rate}"));
+            }
+        }
+        Ok(Self {
+            max_lines_per_sec,
+            queue_capacity: queue_capacity.unwrap_or(8),
+            queued: 0,
+            rejected_total: 0,
+        })
+    }
 }
 
-#[derive(Default)]
-struct AppState {
-    session: Mutex<Option<SerialSession>>,
-    orchestrator_proc: Mutex<Option<OrchestratorProcess>>,
-    critic_session: Mutex<Option<CriticSession>>,
+/// Silence watchdog: while a plan is executing, no RX bytes for `stall_timeout_secs` emits
+/// `serial_stalled` (once per silence) and optionally stops the orchestrator. Catches robots
+/// that browned out mid-run.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialWatchdog {
+    stall_timeout_secs: Option<f64>,
+    stop_orchestrator: bool,
+    stalled: bool,
+    stalls_total: u64,
 }
 
-#[derive(Clone)]
-struct CriticSession {
-    orchestrator_base_url: String,
-    task: String,
-    model: String,
-    success_streak: u32,
-    success_n: u32,
-    conf_threshold: f64,
-    reward_threshold: f64,
+impl SerialWatchdog {
+    fn new(stall_timeout_secs: Option<f64>, stop_orchestrator: bool) -> Result<Self, String> {
+        if let Some(secs) = stall_timeout_secs {
+            if !(secs.is_finite() && secs > 0.0) {
+                return Err(format!("stall_timeout_secs must be a positive number, got {secs}"));
+            }
+        }
+        Ok(Self {
+            stall_timeout_secs,
+            stop_orchestrator,
+            stalled: false,
+            stalls_total: 0,
+        })
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct SerialPortEntry {
-    port_name: String,
-    port_type: String,
+struct SerialStalledEvent {
+    port: String,
+    silent_ms: u128,
+    stall_timeout_ms: u128,
+    correlation_id: Option<String>,
+    stop_orchestrator: bool,
+    ts_ms: u128,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct ConnectionStatus {
-    connected: bool,
-    port_name: Option<String>,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SerialConnectionState {
+    Connecting,
+    Connected,
+    /// Port is open but recent writes failed or RX lines were dropped.
+    Degraded,
+    Reconnecting,
+    Closed,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct OrchestratorProcessStatus {
-    running: bool,
-    pid: Option<u32>,
-    http_base_url: Option<String>,
-    args: Option<Vec<String>>,
+struct SerialStateEvent {
+    port: String,
+    state: SerialConnectionState,
+    previous: SerialConnectionState,
+    reason: Option<String>,
+    ts_ms: u128,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct NodeProbeStatus {
-    ok: bool,
-    host: String,
-    port: u16,
-    target: String,
-    device_name: Option<String>,
-    node_id: Option<String>,
-    tokens: Vec<String>,
-    manifest: Option<Value>,
+type SerialReply = oneshot::Sender<Result<(), String>>;
+
+/// Requests handled by a session's serial worker task, which is the sole owner of the port.
+enum SerialCommand {
+    Write { bytes: Vec<u8>, reply: SerialReply },
+    ControlLines { dtr: Option<bool>, rts: Option<bool>, reply: SerialReply },
+    Break { duration: Duration, reply: SerialReply },
+    /// Release the OS handle (e.g. so a flasher can open the port) until `resume` fires.
+    Suspend { reply: SerialReply, resume: oneshot::Receiver<()> },
+    Stop,
 }
 
-fn port_type_name(port_type: &serialport::SerialPortType) -> String {
-    match port_type {
-        serialport::SerialPortType::UsbPort(info) => {
-            let mut label = String::from("usb");
-            if let Some(product) = &info.product {
-                label = format!("usb:{product}");
+impl SerialCommand {
+    fn reject(self, reason: &str) {
+        match self {
+            SerialCommand::Write { reply, .. }
+            | SerialCommand::ControlLines { reply, .. }
+            | SerialCommand::Break { reply, .. }
+            | SerialCommand::Suspend { reply, .. } => {
+                let _ = reply.send(Err(reason.to_string()));
             }
-            label
+            SerialCommand::Stop => {}
         }
-        serialport::SerialPortType::BluetoothPort => String::from("bluetooth"),
-        serialport::SerialPortType::PciPort => String::from("pci"),
-        serialport::SerialPortType::Unknown => String::from("unknown"),
     }
 }
 
-fn emit_serial_line(app: &AppHandle, line: String) {
-    let _ = app.emit(SERIAL_EVENT, line);
+/// A scripted line the mock serial device emits `delay_ms` after the previous one.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialMockLine {
+    line: String,
+    #[serde(default)]
+    delay_ms: u64,
+}
+
+/// In-process stand-in for a serial device, opened via `mock://echo` (loops writes back)
+/// or `mock://script` (only plays the script).
+struct MockSerialDevice {
+    echo: bool,
+    delimiter: Vec<u8>,
+    rx: VecDeque<u8>,
+    script: VecDeque<SerialMockLine>,
+    next_due: tokio::time::Instant,
 }
 
-fn stop_session_locked(slot: &mut Option<SerialSession>) {
-    if let Some(session) = slot.take() {
-        let _ = session.stop_tx.send(());
+impl MockSerialDevice {
+    fn open(target: &str, delimiter: &[u8], script: Vec<SerialMockLine>) -> Result<Self, String> {
+        let echo = match target.trim().strip_prefix("mock://") {
+            Some("echo") | Some("") => true,
+            Some("script") => false,
+            _ => return Err(format!("Unknown mock serial device {target} (expected mock://echo or mock://script)")),
+        };
+        let script: VecDeque<SerialMockLine> = script.into();
+        let first_delay = script.front().map(|l| l.delay_ms).unwrap_or(0);
+        Ok(Self {
+            echo,
+            delimiter: delimiter.to_vec(),
+            rx: VecDeque::new(),
+            script,
+            next_due: tokio::time::Instant::now() + Duration::from_millis(first_delay),
+        })
     }
-}
 
-fn stop_orchestrator_locked(slot: &mut Option<OrchestratorProcess>) {
-    if let Some(mut proc_) = slot.take() {
-        // Best-effort terminate. If this fails, we still drop the handle.
-        let _ = proc_.child.kill();
-        let _ = proc_.child.wait();
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.rx.is_empty() {
+            if self.script.is_empty() {
+                std::future::pending::<()>().await;
+            }
+            tokio::time::sleep_until(self.next_due).await;
+            if let Some(next) = self.script.pop_front() {
+                self.rx.extend(next.line.as_bytes());
+                self.rx.extend(self.delimiter.iter().copied());
+            }
+            let delay = self.script.front().map(|l| l.delay_ms).unwrap_or(0);
+            self.next_due = tokio::time::Instant::now() + Duration::from_millis(delay);
+        }
+        let n = buf.len().min(self.rx.len());
+        for (slot, byte) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
     }
-}
 
-fn normalize_base_url(raw: &str) -> Result<String, String> {
-    let trimmed = raw.trim().trim_end_matches('/');
-    if trimmed.is_empty() {
-        return Err("base_url is empty".to_string());
+    fn write(&mut self, bytes: &[u8]) {
+        if self.echo {
+            self.rx.extend(bytes.iter().copied());
+        }
     }
-    Ok(trimmed.to_string())
 }
 
-fn normalize_local_host(raw: &str) -> Result<IpAddr, String> {
-    let trimmed = raw.trim();
-    let normalized = if trimmed.eq_ignore_ascii_case("localhost") {
-        "127.0.0.1"
-    } else {
-        trimmed
-    };
-    normalized
-        .parse::<IpAddr>()
-        .map_err(|_| format!("http_host must be an IP address (e.g. 127.0.0.1), got: {trimmed}"))
+enum SerialTransport {
+    Port(SerialStream),
+    Mock(MockSerialDevice),
+    /// Placeholder while the session is suspended and the port is closed.
+    Released,
 }
 
-fn pick_free_tcp_port(host: IpAddr, preferred: u16) -> Result<u16, String> {
-    let preferred_addr = SocketAddr::new(host, preferred);
-    if let Ok(listener) = TcpListener::bind(preferred_addr) {
-        drop(listener);
-        return Ok(preferred);
+impl SerialTransport {
+    fn open(target: &str, baud: u32, delimiter: &[u8], mock_script: Vec<SerialMockLine>) -> Result<(String, Self), String> {
+        if target.trim().starts_with("mock://") {
+            let mock = MockSerialDevice::open(target, delimiter, mock_script)?;
+            return Ok((target.trim().to_string(), SerialTransport::Mock(mock)));
+        }
+        let path = resolve_serial_target(target)?;
+        let stream = open_serial_stream(&path, baud)?;
+        Ok((path, SerialTransport::Port(stream)))
     }
 
-    let listener = TcpListener::bind(SocketAddr::new(host, 0))
-        .map_err(|e| format!("Failed to bind ephemeral port on {host}: {e}"))?;
-    let port = listener
-        .local_addr()
-        .map_err(|e| format!("Failed to read ephemeral port: {e}"))?
-        .port();
-    drop(listener);
-    Ok(port)
-}
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            // SerialStream also implements the blocking std::io traits, so name the async ones explicitly.
+            SerialTransport::Port(stream) => AsyncReadExt::read(stream, buf).await,
+            SerialTransport::Mock(mock) => mock.read(buf).await,
+            SerialTransport::Released => std::future::pending().await,
+        }
+    }
 
-fn wait_for_tcp_listen(host: IpAddr, port: u16, child: &mut Child, timeout: Duration) -> Result<(), String> {
-    let addr = SocketAddr::new(host, port);
-    let start = std::time::Instant::now();
+    async fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            SerialTransport::Port(stream) => {
+                AsyncWriteExt::write_all(stream, bytes).await?;
+                AsyncWriteExt::flush(stream).await
+            }
+            SerialTransport::Mock(mock) => {
+                mock.write(bytes);
+                Ok(())
+            }
+            SerialTransport::Released => Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "serial port is suspended",
+            )),
+        }
+    }
 
-    loop {
-        if let Ok(Some(status)) = child.try_wait() {
-            return Err(format!("Orchestrator exited early with status {status}"));
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        match self {
+            SerialTransport::Port(stream) => stream.write_data_terminal_ready(level),
+            SerialTransport::Mock(_) => Ok(()),
+            SerialTransport::Released => Err(released_port_error()),
         }
+    }
 
-        if TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok() {
-            return Ok(());
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        match self {
+            SerialTransport::Port(stream) => stream.write_request_to_send(level),
+            SerialTransport::Mock(_) => Ok(()),
+            SerialTransport::Released => Err(released_port_error()),
         }
+    }
 
-        if start.elapsed() >= timeout {
-            return Err(format!("Timed out waiting for orchestrator to listen on {addr}"));
+    fn set_break(&self) -> serialport::Result<()> {
+        match self {
+            SerialTransport::Port(stream) => stream.set_break(),
+            SerialTransport::Mock(_) => Ok(()),
+            SerialTransport::Released => Err(released_port_error()),
         }
+    }
 
-        thread::sleep(Duration::from_millis(80));
+    fn clear_break(&self) -> serialport::Result<()> {
+        match self {
+            SerialTransport::Port(stream) => stream.clear_break(),
+            SerialTransport::Mock(_) => Ok(()),
+            SerialTransport::Released => Err(released_port_error()),
+        }
     }
 }
 
-fn resolve_socket_addrs(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
-    let addrs = (host, port)
-        .to_socket_addrs()
-        .map_err(|error| format!("Failed to resolve {host}:{port}: {error}"))?
-        .collect::<Vec<_>>();
-    if addrs.is_empty() {
-        return Err(format!("No addresses found for {host}:{port}"));
-    }
-    Ok(addrs)
+fn released_port_error() -> serialport::Error {
+    serialport::Error::new(serialport::ErrorKind::NoDevice, "serial port is suspended")
 }
 
-fn find_repo_root() -> Result<PathBuf, String> {
-    // Tauri apps often start with a CWD that isn't the repo root. We search upward from:
-    // - current_exe()
-    // - current_dir()
-    let mut candidates: Vec<PathBuf> = Vec::new();
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(dir) = exe.parent() {
-            candidates.push(dir.to_path_buf());
+/// Async owner of a serial port: frames RX bytes into lines, fans them out to
+/// history/capture/events, serves write/control requests, and reopens the port when the
+/// device drops off the bus.
+struct SerialWorker {
+    app: AppHandle,
+    port_name: String,
+    device_path: Arc<Mutex<String>>,
+    baud: u32,
+    stream: SerialTransport,
+    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<SerialCommand>,
+    delimiter: Vec<u8>,
+    capture: Arc<Mutex<Option<SerialCapture>>>,
+    history: Arc<Mutex<VecDeque<SerialHistoryEntry>>>,
+    rx_notify: Arc<tokio::sync::Notify>,
+    filter: Arc<Mutex<SerialLineFilter>>,
+    conn_state: Arc<Mutex<SerialConnectionState>>,
+    batcher: Option<SerialLineBatcher>,
+    auto_reconnect: bool,
+    rate_limit: Arc<Mutex<SerialRateLimit>>,
+    write_queue: VecDeque<(Vec<u8>, SerialReply)>,
+    last_write_at: Option<tokio::time::Instant>,
+    watchdog: Arc<Mutex<SerialWatchdog>>,
+    last_rx_at: tokio::time::Instant,
+    legacy_events: bool,
+}
+
+impl SerialWorker {
+    async fn run(mut self) {
+        let mut read_buf = [0_u8; 512];
+        let mut pending: Vec<u8> = Vec::new();
+        let tick = self
+            .batcher
+            .as_ref()
+            .map(|b| b.interval)
+            .unwrap_or(Duration::from_secs(3600));
+        let mut flush_tick = tokio::time::interval(tick);
+        flush_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut watchdog_tick = tokio::time::interval(Duration::from_millis(250));
+        watchdog_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            let write_slot = self.next_write_slot();
+            let writes_queued = !self.write_queue.is_empty();
+            let watchdog_armed = self.watchdog.lock().map(|w| w.stall_timeout_secs.is_some()).unwrap_or(false);
+            tokio::select! {
+                cmd = self.cmd_rx.recv() => match cmd {
+                    Some(SerialCommand::Stop) | None => break,
+                    Some(SerialCommand::Suspend { reply, resume }) => {
+                        pending.clear();
+                        if !self.suspend(reply, resume).await {
+                            break;
+                        }
+                    }
+                    Some(cmd) => self.handle_command(cmd).await,
+                },
+                _ = tokio::time::sleep_until(write_slot), if writes_queued => {
+                    if let Some((bytes, reply)) = self.write_queue.pop_front() {
+                        self.update_rate_limit(|limit| limit.queued = self.write_queue.len());
+                        self.perform_write(bytes, reply).await;
+                    }
+                },
+                read = self.stream.read(&mut read_buf) => {
+                    let error = match read {
+                        Ok(0) => "device closed".to_string(),
+                        Ok(size) => {
+                            self.last_rx_at = tokio::time::Instant::now();
+                            if let Ok(mut watchdog) = self.watchdog.lock() {
+                                watchdog.stalled = false;
+                            }
+                            if self.current_state() == SerialConnectionState::Degraded {
+                                self.transition(SerialConnectionState::Connected, None);
+                            }
+                            pending.extend_from_slice(&read_buf[..size]);
+                            while let Some(index) = find_subslice(&pending, &self.delimiter) {
+                                let raw = String::from_utf8_lossy(&pending[..index]).trim().to_string();
+                                pending.drain(..index + self.delimiter.len());
+                                if !raw.is_empty() {
+                                    self.handle_line(raw);
+                                }
+                            }
+                            continue;
+                        }
+                        Err(error) => error.to_string(),
+                    };
+                    if let Some(b) = self.batcher.as_mut() {
+                        b.flush(&self.app);
+                    }
+                    emit_serial_line(&self.app, &self.port_name, "error", format!("ERR SERIAL_READ {error}"), self.legacy_events);
+                    pending.clear();
+                    if !self.auto_reconnect || !self.reconnect(error).await {
+                        break;
+                    }
+                },
+                _ = watchdog_tick.tick(), if watchdog_armed => self.check_stall(),
+                _ = flush_tick.tick(), if self.batcher.is_some() => {
+                    if let Some(b) = self.batcher.as_mut() {
+                        let dropped_before = b.dropped_total;
+                        b.flush(&self.app);
+                        if b.dropped_total > dropped_before {
+                            let reason = format!("rx_overflow(dropped_total={})", b.dropped_total);
+                            self.transition(SerialConnectionState::Degraded, Some(reason));
+                        }
+                    }
+                },
+            }
         }
-    }
-    if let Ok(cwd) = std::env::current_dir() {
-        candidates.push(cwd);
+
+        if let Some(b) = self.batcher.as_mut() {
+            b.flush(&self.app);
+        }
+        self.transition(SerialConnectionState::Closed, None);
     }
 
-    for start in candidates {
-        let mut cur: Option<&Path> = Some(start.as_path());
-        while let Some(dir) = cur {
-            let orch = dir.join("orchestrator").join("orchestrator.py");
-            if orch.exists() {
-                return Ok(dir.to_path_buf());
+    async fn handle_command(&mut self, cmd: SerialCommand) {
+        match cmd {
+            SerialCommand::Write { bytes, reply } => {
+                let (max_rate, capacity) = self
+                    .rate_limit
+                    .lock()
+                    .map(|l| (l.max_lines_per_sec, l.queue_capacity))
+                    .unwrap_or((None, 0));
+                let slot_free = self.write_queue.is_empty() && self.next_write_slot() <= tokio::time::Instant::now();
+                if max_rate.is_none() || slot_free {
+                    self.perform_write(bytes, reply).await;
+                } else if self.write_queue.len() < capacity {
+                    self.write_queue.push_back((bytes, reply));
+                    self.update_rate_limit(|limit| limit.queued = self.write_queue.len());
+                } else {
+                    self.update_rate_limit(|limit| limit.rejected_total += 1);
+                    let _ = reply.send(Err(format!(
+                        "Serial write rate limit exceeded on {} (queue full)",
+                        self.port_name
+                    )));
+                }
             }
-            cur = dir.parent();
+            SerialCommand::ControlLines { dtr, rts, reply } => {
+                let mut result = Ok(());
+                if let Some(level) = dtr {
+                    result = self
+                        .stream
+                        .write_data_terminal_ready(level)
+                        .map_err(|error| format!("Failed to set DTR on {}: {error}", self.port_name));
+                }
+                if let (Ok(()), Some(level)) = (&result, rts) {
+                    result = self
+                        .stream
+                        .write_request_to_send(level)
+                        .map_err(|error| format!("Failed to set RTS on {}: {error}", self.port_name));
+                }
+                let _ = reply.send(result);
+            }
+            SerialCommand::Break { duration, reply } => {
+                let result = match self.stream.set_break() {
+                    Ok(()) => {
+                        tokio::time::sleep(duration).await;
+                        self.stream
+                            .clear_break()
+                            .map_err(|error| format!("Failed to clear break on {}: {error}", self.port_name))
+                    }
+                    Err(error) => Err(format!("Failed to set break on {}: {error}", self.port_name)),
+                };
+                let _ = reply.send(result);
+            }
+            // Suspend needs the command channel, so the run loop intercepts it before dispatch.
+            SerialCommand::Suspend { .. } | SerialCommand::Stop => {}
         }
     }
 
-    Err("Could not locate repo root (expected orchestrator/orchestrator.py). Run the app from the repo, or set VITE_ORCHESTRATOR_BASE_URL and start orchestrator manually.".to_string())
-}
-
-fn resolve_python3() -> String {
-    // GUI apps on macOS might not inherit the interactive shell PATH.
-    for candidate in ["/usr/bin/python3", "/opt/homebrew/bin/python3", "/usr/local/bin/python3"] {
-        if Path::new(candidate).exists() {
-            return candidate.to_string();
+    async fn perform_write(&mut self, bytes: Vec<u8>, reply: SerialReply) {
+        self.last_write_at = Some(tokio::time::Instant::now());
+        let result = self
+            .stream
+            .write_all(&bytes)
+            .await
+            .map_err(|error| format!("Serial write failed: {error}"));
+        match (&result, self.current_state()) {
+            (Err(error), SerialConnectionState::Connected) => {
+                self.transition(SerialConnectionState::Degraded, Some(error.clone()));
+            }
+            (Ok(()), SerialConnectionState::Degraded) => {
+                self.transition(SerialConnectionState::Connected, None);
+            }
+            _ => {}
         }
+        let _ = reply.send(result);
     }
-    "python3".to_string()
-}
 
-fn unix_ts_ms() -> u128 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis())
-        .unwrap_or(0)
-}
+    /// Earliest instant the next write may go out under the session's rate limit.
+    fn next_write_slot(&self) -> tokio::time::Instant {
+        let now = tokio::time::Instant::now();
+        let max_rate = self.rate_limit.lock().ok().and_then(|l| l.max_lines_per_sec);
+        match (max_rate, self.last_write_at) {
+            (Some(rate), Some(last)) => (last + Duration::from_secs_f64(1.0 / rate)).max(now),
+            _ => now,
+        }
+    }
 
-fn trunc_for_log(input: &str, max_len: usize) -> String {
-    if input.len() <= max_len {
-        return input.to_string();
+    fn update_rate_limit(&self, f: impl FnOnce(&mut SerialRateLimit)) {
+        if let Ok(mut limit) = self.rate_limit.lock() {
+            f(&mut limit);
+        }
     }
-    let cut = input
-        .char_indices()
-        .nth(max_len)
-        .map(|(idx, _)| idx)
-        .unwrap_or(input.len());
-    format!("{}...(truncated)", &input[..cut])
-}
 
-fn append_desktop_audit_log(event: &str, payload: &Value) {
-    let logs_dir = match repo_logs_dir() {
-        Ok(path) => path,
-        Err(_) => return,
-    };
-    if std::fs::create_dir_all(&logs_dir).is_err() {
-        return;
+    fn handle_line(&mut self, raw: String) {
+        record_serial_traffic(&self.history, &self.capture, &self.port_name, "rx", &raw);
+        self.rx_notify.notify_waiters();
+        let allowed = self.filter.lock().map(|f| f.allows(&raw)).unwrap_or(true);
+        if allowed {
+            match self.batcher.as_mut() {
+                Some(b) => b.push(raw),
+                None => emit_serial_line(&self.app, &self.port_name, "rx", raw, self.legacy_events),
+            }
+        }
     }
-    let log_path = logs_dir.join("backend_audit.jsonl");
-    let mut file = match OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-    {
-        Ok(f) => f,
-        Err(_) => return,
-    };
 
-    let line = json!({
-        "ts_ms": unix_ts_ms(),
-        "event": event,
-        "payload": payload
-    });
-    let _ = writeln!(file, "{}", line);
-}
-
-fn openai_api_key() -> Option<String> {
-    // Tauri GUI apps on macOS may not inherit shell env; but if launched via terminal it will.
-    // We keep this minimal: rely on OPENAI_API_KEY existing in the app environment.
-    std::env::var("OPENAI_API_KEY").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
-}
+    /// Reopens the port with capped exponential backoff. Returns false if the session was
+    /// stopped while waiting. Requests arriving mid-reconnect are rejected rather than queued.
+    async fn reconnect(&mut self, reason: String) -> bool {
+        self.transition(SerialConnectionState::Reconnecting, Some(reason));
+        let mut backoff = Duration::from_millis(250);
+        loop {
+            let deadline = tokio::time::Instant::now() + backoff;
+            loop {
+                tokio::select! {
+                    cmd = self.cmd_rx.recv() => match cmd {
+                        Some(SerialCommand::Stop) | None => return false,
+                        Some(cmd) => cmd.reject(&format!("Serial port {} is reconnecting", self.port_name)),
+                    },
+                    _ = tokio::time::sleep_until(deadline) => break,
+                }
+            }
 
-fn extract_first_text_field(resp: &Value) -> Option<String> {
-    // Best-effort: Responses API returns content under output[].content[].text.
-    // We scan for the first string "text" leaf.
-    fn walk(v: &Value) -> Option<String> {
-        match v {
-            Value::Object(map) => {
-                if let Some(Value::String(s)) = map.get("text") {
-                    if !s.trim().is_empty() {
-                        return Some(s.clone());
+            let reopened = resolve_serial_target(&self.port_name)
+                .and_then(|path| open_serial_stream(&path, self.baud).map(|stream| (path, stream)));
+            match reopened {
+                Ok((path, stream)) => {
+                    self.stream = SerialTransport::Port(stream);
+                    if let Ok(mut current) = self.device_path.lock() {
+                        *current = path;
                     }
+                    self.transition(SerialConnectionState::Connected, Some("reopened".to_string()));
+                    return true;
                 }
-                for (_k, child) in map.iter() {
-                    if let Some(found) = walk(child) {
-                        return Some(found);
-                    }
+                Err(_) => {
+                    backoff = (backoff * 2).min(Duration::from_secs(5));
                 }
-                None
             }
-            Value::Array(arr) => {
-                for child in arr {
-                    if let Some(found) = walk(child) {
-                        return Some(found);
-                    }
-                }
-                None
+        }
+    }
+
+    /// Fires the silence watchdog if a plan is executing and nothing has arrived since the later
+    /// of the last RX bytes and the plan start.
+    fn check_stall(&mut self) {
+        let Some(plan) = self
+            .app
+            .try_state::<AppState>()
+            .and_then(|state| state.active_plan.lock().ok().and_then(|p| p.clone()))
+        else {
+            return;
+        };
+        let (timeout, stop_orchestrator) = {
+            let Ok(watchdog) = self.watchdog.lock() else {
+                return;
+            };
+            match (watchdog.stall_timeout_secs, watchdog.stalled) {
+                (Some(secs), false) => (Duration::from_secs_f64(secs), watchdog.stop_orchestrator),
+                _ => return,
             }
-            _ => None,
+        };
+        let quiet_since = self.last_rx_at.max(tokio::time::Instant::from_std(plan.started));
+        let silent = quiet_since.elapsed();
+        if silent < timeout {
+            return;
+        }
+
+        if let Ok(mut watchdog) = self.watchdog.lock() {
+            watchdog.stalled = true;
+            watchdog.stalls_total += 1;
+        }
+        append_desktop_audit_log(
+            "serial.stalled",
+            &json!({
+                "port": self.port_name,
+                "silent_ms": silent.as_millis(),
+                "correlation_id": plan.correlation_id,
+                "stop_orchestrator": stop_orchestrator
+            }),
+        );
+        emit_serial_event(
+            &self.app,
+            SERIAL_STALLED_EVENT,
+            &self.port_name,
+            SerialStalledEvent {
+                port: self.port_name.clone(),
+                silent_ms: silent.as_millis(),
+                stall_timeout_ms: timeout.as_millis(),
+                correlation_id: plan.correlation_id.clone(),
+                stop_orchestrator,
+                ts_ms: unix_ts_ms(),
+            },
+        );
+        self.transition(
+            SerialConnectionState::Degraded,
+            Some(format!("stalled({}ms without RX)", silent.as_millis())),
+        );
+        if stop_orchestrator {
+            let app = self.app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<AppState>();
+                let _ = send_orchestrator_stop(&state.http, plan.orchestrator_base_url, plan.correlation_id, None).await;
+            });
         }
     }
-    walk(resp)
-}
 
-fn extract_output_text(resp: &Value) -> Option<String> {
-    // Prefer well-known Responses API fields.
-    if let Some(Value::String(s)) = resp.get("output_text") {
-        if !s.trim().is_empty() {
-            return Some(s.clone());
+    /// Closes the port until `resume` fires (or its sender is dropped), then reopens it through
+    /// the reconnect path. Queued writes are failed rather than replayed to a freshly flashed device.
+    async fn suspend(&mut self, reply: SerialReply, mut resume: oneshot::Receiver<()>) -> bool {
+        if let Some(b) = self.batcher.as_mut() {
+            b.flush(&self.app);
+        }
+        for (_, queued) in self.write_queue.drain(..) {
+            let _ = queued.send(Err(format!("Serial port {} was suspended", self.port_name)));
+        }
+        self.update_rate_limit(|limit| limit.queued = 0);
+        self.stream = SerialTransport::Released;
+        self.transition(SerialConnectionState::Reconnecting, Some("suspended".to_string()));
+        let _ = reply.send(Ok(()));
+
+        loop {
+            tokio::select! {
+                cmd = self.cmd_rx.recv() => match cmd {
+                    Some(SerialCommand::Stop) | None => return false,
+                    Some(cmd) => cmd.reject(&format!("Serial port {} is suspended", self.port_name)),
+                },
+                _ = &mut resume => break,
+            }
         }
+        self.reconnect("resumed".to_string()).await
     }
-    // Fall back to scanning for the first "text" field.
-    extract_first_text_field(resp)
-}
 
-fn clamp_f64(x: f64, lo: f64, hi: f64) -> f64 {
-    if x < lo {
-        lo
-    } else if x > hi {
-        hi
-    } else {
-        x
+    fn current_state(&self) -> SerialConnectionState {
+        self.conn_state.lock().map(|s| *s).unwrap_or(SerialConnectionState::Degraded)
+    }
+
+    fn transition(&self, next: SerialConnectionState, reason: Option<String>) {
+        transition_serial_state(&self.app, &self.conn_state, &self.port_name, next, reason);
     }
 }
 
-fn task_expects_motion(task: &str) -> bool {
-    let t = task.to_ascii_lowercase();
-    let keywords = [
-        "move", "drive", "go ", "forward", "backward", "reverse", "left", "right", "turn", "rotate", "strafe", "spin",
-    ];
-    keywords.iter().any(|k| t.contains(k))
+/// Include/exclude patterns applied to RX lines before they are emitted to the frontend.
+/// History and capture files still see every line.
+#[derive(Default)]
+struct SerialLineFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
 }
 
-fn decode_frame_luma_small(frame_b64: &str, w: u32, h: u32) -> Result<Vec<u8>, String> {
-    let b64 = frame_b64.trim();
-    if b64.is_empty() {
-        return Err("empty frame".to_string());
+impl SerialLineFilter {
+    fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, String> {
+        let compile = |label: &str, pattern: Option<&str>| -> Result<Option<Regex>, String> {
+            match pattern.map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                Some(p) => Regex::new(p)
+                    .map(Some)
+                    .map_err(|e| format!("Invalid {label} filter regex {p:?}: {e}")),
+                None => Ok(None),
+            }
+        };
+        Ok(Self {
+            include: compile("include", include)?,
+            exclude: compile("exclude", exclude)?,
+        })
     }
-    let bytes = base64::engine::general_purpose::STANDARD
-        .decode(b64)
-        .map_err(|e| format!("base64 decode failed: {e}"))?;
-    let img = image::load_from_memory(&bytes).map_err(|e| format!("image decode failed: {e}"))?;
-    let gray = img.to_luma8();
-    let small = image::imageops::resize(&gray, w, h, image::imageops::FilterType::Triangle);
-    Ok(small.into_raw())
-}
 
-fn mean_abs_diff(a: &[u8], b: &[u8]) -> Result<f64, String> {
-    if a.len() != b.len() || a.is_empty() {
-        return Err("mean_abs_diff: length mismatch".to_string());
+    fn allows(&self, line: &str) -> bool {
+        if let Some(include) = &self.include {
+            if !include.is_match(line) {
+                return false;
+            }
+        }
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(line) {
+                return false;
+            }
+        }
+        true
     }
-    let mut acc: u64 = 0;
-    for (x, y) in a.iter().zip(b.iter()) {
-        acc += x.abs_diff(*y) as u64;
+
+    fn status(&self) -> SerialFilterStatus {
+        SerialFilterStatus {
+            include: self.include.as_ref().map(|r| r.as_str().to_string()),
+            exclude: self.exclude.as_ref().map(|r| r.as_str().to_string()),
+        }
     }
-    let mean = (acc as f64) / (a.len() as f64);
-    Ok(mean / 255.0)
 }
 
-fn compute_motion_score(frames_b64: &[String]) -> Result<f64, String> {
-    let frames: Vec<&str> = frames_b64.iter().map(|s| s.as_str()).filter(|s| !s.trim().is_empty()).collect();
-    if frames.len() < 2 {
-        return Ok(0.0);
-    }
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialHistoryEntry {
+    seq: u64,
+    ts_ms: u128,
+    direction: String,
+    line: String,
+}
 
-    // Downscale heavily for robustness and speed; this is only a "stationary vs moving" gate.
-    let w = 96;
-    let h = 72;
-    let mut prev = decode_frame_luma_small(frames[0], w, h)?;
-    let mut scores: Vec<f64> = Vec::new();
-    for f in frames.iter().skip(1).take(5) {
-        let cur = decode_frame_luma_small(f, w, h)?;
-        let s = mean_abs_diff(&prev, &cur)?;
-        scores.push(s);
-        prev = cur;
-    }
-    if scores.is_empty() {
-        return Ok(0.0);
-    }
-    let avg = scores.iter().sum::<f64>() / (scores.len() as f64);
-    Ok(avg)
+struct SerialCapture {
+    file: std::fs::File,
+    path: PathBuf,
+    started_ts_ms: u128,
+    lines_written: u64,
 }
 
-fn repo_logs_dir() -> Result<PathBuf, String> {
-    Ok(find_repo_root()?.join("logs"))
+#[derive(Clone)]
+struct NodeManifestSummary {
+    raw: Value,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    tokens: Vec<String>,
+    issues: Vec<ManifestIssue>,
+    /// The MANIFEST's `protocol_version`; nodes that predate the field speak version 1.
+    protocol_version: u64,
 }
 
-fn sanitize_log_file_name(file_name: &str) -> Result<String, String> {
-    let trimmed = file_name.trim();
-    if trimmed.is_empty() {
-        return Err("file_name cannot be empty".to_string());
-    }
-    if trimmed.contains('/') || trimmed.contains('\\') || trimmed.contains("..") {
-        return Err("file_name must be a plain file name under logs/".to_string());
-    }
-    Ok(trimmed.to_string())
+/// One problem in a node MANIFEST, e.g. `{"path": "commands[1].safety.watchdog_ms",
+/// "message": "expected an integer >= 1, got string"}`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestIssue {
+    path: String,
+    message: String,
 }
 
-#[tauri::command]
-fn write_debug_log(file_name: String, payload: Value) -> Result<(), String> {
-    let safe_name = sanitize_log_file_name(&file_name)?;
-    let logs_dir = repo_logs_dir()?;
-    std::fs::create_dir_all(&logs_dir)
-        .map_err(|e| format!("Failed to create logs directory {}: {e}", logs_dir.display()))?;
-    let path = logs_dir.join(safe_name);
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&path)
-        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
-    let line = json!({
-        "ts_ms": unix_ts_ms(),
-        "payload": payload
-    });
-    writeln!(file, "{}", line)
-        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
-    Ok(())
+// Wire models for the orchestrator HTTP API (orchestrator.py). Field names stay snake_case to
+// match the Python side, so invoke() results look the same as a direct fetch. Unknown fields
+// are kept in `extra` where the planner or node manifests may add their own.
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PlanStep {
+    #[serde(rename = "type")]
+    step_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, Value>,
 }
 
-#[tauri::command]
-fn read_debug_log(file_name: String, tail_lines: Option<usize>) -> Result<String, String> {
-    let safe_name = sanitize_log_file_name(&file_name)?;
-    let path = repo_logs_dir()?.join(safe_name);
-    if !path.exists() {
-        return Ok(String::new());
+#[derive(Clone, Serialize, Deserialize)]
+struct CommandArgSpec {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(rename = "type")]
+    arg_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(default, rename = "enum", skip_serializing_if = "Option::is_none")]
+    allowed: Option<Vec<Value>>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CommandSpec {
+    token: String,
+    #[serde(default)]
+    args: Vec<CommandArgSpec>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ManifestNode {
+    name: String,
+    node_id: String,
+    display_name: String,
+    #[serde(default)]
+    commands: Vec<CommandSpec>,
+    #[serde(default)]
+    telemetry: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    services: Option<Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SystemManifest {
+    daemon_version: String,
+    nodes: Vec<ManifestNode>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct OrchestratorNodeStatus {
+    alias: String,
+    name: String,
+    node_id: String,
+    host: String,
+    port: u16,
+    connected: bool,
+    #[serde(default)]
+    commands: Vec<String>,
+    #[serde(default)]
+    services: Value,
+}
+
+/// `GET /status`.
+#[derive(Clone, Serialize, Deserialize)]
+struct OrchestratorStatusResponse {
+    ok: bool,
+    nodes: Vec<OrchestratorNodeStatus>,
+    system_manifest: SystemManifest,
+}
+
+/// `POST /execute_plan` and `POST /stop`.
+#[derive(Clone, Serialize, Deserialize)]
+struct OrchestratorAck {
+    ok: bool,
+    #[serde(default)]
+    correlation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A typed orchestrator response plus the untouched JSON it was parsed from, for debugging.
+#[derive(Clone, Serialize)]
+struct OrchestratorReply<T> {
+    #[serde(flatten)]
+    data: T,
+    /// Id the request was sent with (caller-supplied or generated); distinct from any
+    /// `correlation_id` the orchestrator echoes in `data`.
+    request_correlation_id: String,
+    raw: Value,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpClientConfig {
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout_ms: u64,
+    connect_timeout_ms: u64,
+    /// Whole-request timeout for OpenAI calls.
+    openai_timeout_ms: u64,
+    /// Default whole-request timeout for orchestrator calls (0 = none). `/execute_plan` is exempt,
+    /// since it legitimately blocks for the length of a plan; it has its own `timeout_ms`.
+    orchestrator_timeout_ms: u64,
+    /// Default whole-request timeout for vision calls (0 = none).
+    vision_timeout_ms: u64,
+}
+
+impl HttpClientConfig {
+    fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_millis(self.pool_idle_timeout_ms))
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
     }
-    let content = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
-    let limit = tail_lines.unwrap_or(300).max(1);
-    let lines: Vec<&str> = content.lines().collect();
-    let start = lines.len().saturating_sub(limit);
-    Ok(lines[start..].join("\n"))
 }
 
-fn parse_manifest_summary(manifest: &Value) -> NodeManifestSummary {
-    let device_name = manifest
-        .get("device")
-        .and_then(|d| d.get("name"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let node_id = manifest
-        .get("device")
-        .and_then(|d| d.get("node_id"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let mut tokens: Vec<String> = Vec::new();
-    if let Some(cmds) = manifest.get("commands").and_then(|v| v.as_array()) {
-        for cmd in cmds {
-            if let Some(tok) = cmd.get("token").and_then(|v| v.as_str()) {
-                tokens.push(tok.to_string());
-            }
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_ms: 90_000,
+            connect_timeout_ms: 5_000,
+            openai_timeout_ms: 60_000,
+            orchestrator_timeout_ms: 10_000,
+            vision_timeout_ms: 30_000,
         }
     }
-    tokens.sort();
-    tokens.dedup();
-    NodeManifestSummary {
-        raw: manifest.clone(),
-        device_name,
-        node_id,
-        tokens,
+}
+
+struct HttpClients {
+    config: HttpClientConfig,
+    local: reqwest::Client,
+    openai: reqwest::Client,
+    /// Per-socket clients for `unix://` orchestrator URLs, built on first use.
+    unix: HashMap<PathBuf, reqwest::Client>,
+    /// Per-base-URL clients for endpoints with custom TLS settings, built on first use.
+    endpoints: HashMap<String, reqwest::Client>,
+}
+
+impl HttpClients {
+    fn build(config: HttpClientConfig) -> Result<Self, String> {
+        let local = config
+            .client_builder()
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+        let openai = config
+            .client_builder()
+            .timeout(Duration::from_millis(config.openai_timeout_ms))
+            .build()
+            .map_err(|e| format!("Failed to build OpenAI HTTP client: {e}"))?;
+        Ok(Self {
+            config,
+            local,
+            openai,
+            unix: HashMap::new(),
+            endpoints: HashMap::new(),
+        })
     }
 }
 
-fn probe_daemon_node(host: &str, port: u16) -> Result<NodeManifestSummary, String> {
-    let host_trimmed = host.trim();
-    if host_trimmed.is_empty() {
-        return Err("host cannot be empty".to_string());
+/// Outgoing HTTP state shared by every command: the clients plus per-endpoint, OpenAI and retry
+/// settings. Each `Option` is `None` until set (or loaded in `setup`), meaning the default.
+#[derive(Default)]
+struct HttpState {
+    /// Built lazily and rebuilt by `set_http_client_config`. Shared so keep-alive connections
+    /// (and TLS sessions to OpenAI) are reused across commands.
+    clients: Mutex<Option<HttpClients>>,
+    /// Per-endpoint settings keyed by normalized base URL, mirrored to `ENDPOINT_SETTINGS_FILE`.
+    endpoints: Mutex<HashMap<String, EndpointSettings>>,
+    /// App-wide OpenAI endpoint overrides from `set_openai_config`, mirrored to
+    /// `OPENAI_CONFIG_FILE`; a critic session's own `backend.openai` takes precedence field by field.
+    openai: Mutex<Option<OpenAiEndpointConfig>>,
+    /// Retry policy for `orchestrator_request`.
+    orchestrator_retry: Mutex<Option<OrchestratorRetryPolicy>>,
+    /// Retry policy for cloud critic calls.
+    critic_retry: Mutex<Option<CriticRetryPolicy>>,
+}
+
+fn with_http_clients<T>(http: &HttpState, f: impl FnOnce(&mut HttpClients) -> T) -> Result<T, String> {
+    let mut lock = http.clients.lock().map_err(|_| "HTTP client lock poisoned".to_string())?;
+    let clients = match lock.take() {
+        Some(clients) => clients,
+        None => HttpClients::build(HttpClientConfig::default())?,
+    };
+    Ok(f(lock.insert(clients)))
+}
+
+/// Settings for one orchestrator or vision endpoint. Stored in plain JSON in the app data dir.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EndpointSettings {
+    /// Sent as `Authorization: Bearer <token>` on every request to this base URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bearer_token: Option<String>,
+    /// PEM file of the CA(s) an `https://` endpoint must chain to; replaces the built-in roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ca_cert_path: Option<String>,
+    /// Lowercase hex SHA-256 of the exact (typically self-signed) server certificate to accept.
+    /// Chain and hostname are not checked when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cert_sha256: Option<String>,
+}
+
+impl EndpointSettings {
+    fn has_tls_overrides(&self) -> bool {
+        self.ca_cert_path.is_some() || self.cert_sha256.is_some()
     }
 
-    let addrs = resolve_socket_addrs(host_trimmed, port)?;
-    let mut last_error = None;
-
-    for addr in addrs {
-        match TcpStream::connect_timeout(&addr, Duration::from_secs(2)) {
-            Ok(mut stream) => {
-                let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
-                let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
-                let _ = stream.set_nodelay(true);
-                stream
-                    .write_all(b"HELLO\n")
-                    .map_err(|error| format!("Node write failed: {error}"))?;
-                stream
-                    .flush()
-                    .map_err(|error| format!("Node flush failed: {error}"))?;
-
-                let mut reader = BufReader::new(stream);
-                let mut line = String::new();
-                reader
-                    .read_line(&mut line)
-                    .map_err(|error| format!("Node read failed: {error}"))?;
-                let line = line.trim().to_string();
-                if !line.starts_with("MANIFEST ") {
-                    return Err(format!("Expected MANIFEST from HELLO, got: {line}"));
-                }
-                let payload = line["MANIFEST ".len()..].trim();
-                let manifest: Value = serde_json::from_str(payload)
-                    .map_err(|error| format!("Invalid MANIFEST JSON: {error}"))?;
-                return Ok(parse_manifest_summary(&manifest));
-            }
-            Err(error) => {
-                last_error = Some(format!("Connect to {addr} failed: {error}"));
-            }
+    fn is_empty(&self) -> bool {
+        self.bearer_token.is_none() && !self.has_tls_overrides()
+    }
+
+    fn summary(&self, base_url: &str) -> EndpointSettingsSummary {
+        EndpointSettingsSummary {
+            base_url: base_url.to_string(),
+            has_bearer_token: self.bearer_token.is_some(),
+            ca_cert_path: self.ca_cert_path.clone(),
+            cert_sha256: self.cert_sha256.clone(),
         }
     }
+}
 
-    Err(last_error.unwrap_or_else(|| "Node connect failed".to_string()))
+/// What `list_endpoint_settings` reports; secrets are never sent back to the webview.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EndpointSettingsSummary {
+    base_url: String,
+    has_bearer_token: bool,
+    ca_cert_path: Option<String>,
+    cert_sha256: Option<String>,
 }
 
-async fn orchestrator_request(
-    method: reqwest::Method,
-    orchestrator_base_url: String,
-    path: &str,
-    body: Option<Value>,
-    correlation_id: Option<String>,
-) -> Result<Value, String> {
-    let base = normalize_base_url(&orchestrator_base_url)?;
-    let url = format!("{base}{path}");
-    let client = reqwest::Client::new();
-    let request_body = body.clone();
-    let request = client.request(method.clone(), &url);
-    let request = if let Some(cid) = correlation_id.as_ref() {
-        request.header("X-Correlation-Id", cid)
-    } else {
-        request
-    };
-    let request = if let Some(payload) = body {
-        request.json(&payload)
-    } else {
-        request
-    };
+fn endpoint_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join(ENDPOINT_SETTINGS_FILE))
+}
 
-    append_desktop_audit_log(
-        "orchestrator.request",
-        &json!({
-            "method": method.to_string(),
-            "url": url.clone(),
-            "body": request_body,
-            "correlation_id": correlation_id
-        }),
-    );
+fn load_endpoint_settings(app: &AppHandle) -> Result<HashMap<String, EndpointSettings>, String> {
+    let path = endpoint_settings_path(app)?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let raw = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Invalid endpoint settings {}: {e}", path.display()))
+}
 
-    let response = request.send().await.map_err(|error| {
-        let msg = format!("{method} {url} failed: network error: {error}");
-        append_desktop_audit_log(
-            "orchestrator.network_error",
-            &json!({
-                "method": method.to_string(),
-                "url": url.clone(),
-                "error": msg
-            }),
-        );
-        msg
-    })?;
+fn save_endpoint_settings(app: &AppHandle, settings: &HashMap<String, EndpointSettings>) -> Result<(), String> {
+    let path = endpoint_settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(settings).map_err(|e| format!("Failed to encode endpoint settings: {e}"))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    // The file holds bearer tokens; keep it private to the user.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
 
-    let status = response.status();
-    let response_text = response
-        .text()
-        .await
-        .map_err(|error| format!("{method} {url} failed: could not read response body: {error}"))?;
+fn endpoint_settings(http: &HttpState, base: &str) -> Result<EndpointSettings, String> {
+    let settings = http
+        .endpoints
+        .lock()
+        .map_err(|_| "Endpoint settings lock poisoned".to_string())?;
+    Ok(settings.get(base).cloned().unwrap_or_default())
+}
 
-    append_desktop_audit_log(
-        "orchestrator.response",
-        &json!({
-            "method": method.to_string(),
-            "url": url.clone(),
-            "status": status.as_u16(),
-            "body": trunc_for_log(&response_text, 8000)
-        }),
-    );
+/// Applies `update` to the settings for `base_url`, persists the result and drops any cached
+/// TLS client for it. Entries left empty are removed.
+fn update_endpoint_settings(
+    app: &AppHandle,
+    http: &HttpState,
+    base_url: &str,
+    update: impl FnOnce(&mut EndpointSettings),
+) -> Result<EndpointSettingsSummary, String> {
+    let mut lock = http
+        .endpoints
+        .lock()
+        .map_err(|_| "Endpoint settings lock poisoned".to_string())?;
+    let mut settings = lock.clone();
+    let entry = settings.entry(base_url.to_string()).or_default();
+    update(entry);
+    let summary = entry.summary(base_url);
+    if entry.is_empty() {
+        settings.remove(base_url);
+    }
+    save_endpoint_settings(app, &settings)?;
+    *lock = settings;
+    drop(lock);
+    with_http_clients(http, |clients| clients.endpoints.remove(base_url))?;
+    Ok(summary)
+}
 
-    if !status.is_success() {
-        return Err(format!(
-            "{method} {url} failed: HTTP {} body={}",
-            status.as_u16(),
-            response_text
-        ));
+/// Normalizes a SHA-256 fingerprint (`AB:CD:...` or plain hex) to lowercase hex.
+fn normalize_cert_sha256(raw: &str) -> Result<String, String> {
+    let hex = raw
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("cert_sha256 must be 32 hex bytes, got {raw:?}"));
     }
+    Ok(hex)
+}
 
-    serde_json::from_str::<Value>(&response_text).map_err(|error| {
-        format!("{method} {url} failed: invalid JSON response: {error}; body={response_text}")
-    })
+fn load_ca_certificates(path: &str) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = std::fs::read(path).map_err(|e| format!("Failed to read CA certificate {path}: {e}"))?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| format!("Invalid CA certificate {path}: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("No PEM certificates found in {path}"));
+    }
+    Ok(certs)
 }
 
-async fn vision_request(
-    method: reqwest::Method,
-    vision_base_url: String,
-    path: &str,
-    body: Option<Value>,
-    correlation_id: Option<String>,
-) -> Result<Value, String> {
-    let base = normalize_base_url(&vision_base_url)?;
-    let url = format!("{base}{path}");
-    let client = reqwest::Client::new();
-    let request_body = body.clone();
+/// Accepts exactly one server certificate, identified by the SHA-256 of its DER encoding, for
+/// hosts serving a self-signed cert. Handshake signatures are still verified against it.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    sha256: String,
+    provider: Arc<rustls::crypto::CryptoProvider>,
+}
 
-    let request = client.request(method.clone(), &url);
-    let request = if let Some(cid) = correlation_id.as_ref() {
-        request.header("X-Correlation-Id", cid)
-    } else {
-        request
-    };
-    let request = if let Some(payload) = body {
-        request.json(&payload)
-    } else {
-        request
-    };
+impl rustls::client::danger::ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        let digest = Sha256::digest(end_entity.as_ref());
+        let actual = digest.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        if actual == self.sha256 {
+            Ok(rustls::client::danger::ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
 
-    append_desktop_audit_log(
-        "vision.request",
-        &json!({
-            "method": method.to_string(),
-            "url": url.clone(),
-            "body": request_body,
-            "correlation_id": correlation_id
-        }),
-    );
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
 
-    let response = request.send().await.map_err(|error| {
-        let msg = format!("{method} {url} failed: network error: {error}");
-        append_desktop_audit_log(
-            "vision.network_error",
-            &json!({
-                "method": method.to_string(),
-                "url": url.clone(),
-                "error": msg
-            }),
-        );
-        msg
-    })?;
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
 
-    let status = response.status();
-    let response_text = response
-        .text()
-        .await
-        .map_err(|error| format!("{method} {url} failed: could not read response body: {error}"))?;
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
 
-    append_desktop_audit_log(
-        "vision.response",
-        &json!({
-            "method": method.to_string(),
-            "url": url.clone(),
-            "status": status.as_u16(),
-            "body": trunc_for_log(&response_text, 8000)
-        }),
-    );
+/// TLS client config that accepts only the certificate with fingerprint `sha256`.
+fn pinned_tls_config(sha256: &str) -> Result<rustls::ClientConfig, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    Ok(rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Failed to configure TLS: {e}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+            sha256: sha256.to_string(),
+            provider,
+        }))
+        .with_no_client_auth())
+}
 
-    if !status.is_success() {
-        return Err(format!(
-            "{method} {url} failed: HTTP {} body={}",
-            status.as_u16(),
-            trunc_for_log(&response_text, 3000),
-        ));
+fn build_endpoint_client(config: &HttpClientConfig, settings: &EndpointSettings) -> Result<reqwest::Client, String> {
+    let builder = config.client_builder();
+    let builder = if let Some(sha256) = &settings.cert_sha256 {
+        builder.use_preconfigured_tls(pinned_tls_config(sha256)?)
+    } else if let Some(path) = &settings.ca_cert_path {
+        load_ca_certificates(path)?
+            .into_iter()
+            .fold(builder.tls_built_in_root_certs(false), |b, cert| b.add_root_certificate(cert))
+    } else {
+        builder
+    };
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+}
+
+/// Shared client for plain endpoints; a cached per-endpoint client for `https://` bases with a
+/// pinned CA or certificate.
+fn endpoint_client(http: &HttpState, base: &str) -> Result<reqwest::Client, String> {
+    let settings = endpoint_settings(http, base)?;
+    if !base.starts_with("https://") || !settings.has_tls_overrides() {
+        return http_client(http);
     }
+    with_http_clients(http, |clients| {
+        if let Some(client) = clients.endpoints.get(base) {
+            return Ok(client.clone());
+        }
+        let client = build_endpoint_client(&clients.config, &settings)?;
+        clients.endpoints.insert(base.to_string(), client.clone());
+        Ok(client)
+    })?
+}
 
-    serde_json::from_str::<Value>(&response_text).map_err(|error| {
-        format!("{method} {url} failed: invalid JSON response: {error}; body={response_text}")
+/// Attaches the configured credentials for `base` (a normalized base URL) to `request`.
+fn with_endpoint_auth(
+    http: &HttpState,
+    request: reqwest::RequestBuilder,
+    base: &str,
+) -> Result<reqwest::RequestBuilder, String> {
+    Ok(match endpoint_settings(http, base)?.bearer_token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
     })
 }
 
-fn build_critic_system_prompt(task: &str) -> String {
-    // Keep it strict and conservative: fail closed if uncertain.
-    // We do not request hidden reasoning; we only need the structured fields.
-    format!(
-        "You are a Vision-Language Reward Critic for a physical RC robot.\n\
-Your job: evaluate the current camera frame against the user's task goal and produce a reward signal.\n\
-\n\
-You may be given multiple frames (ordered oldest -> newest). Use them to infer motion and progress.\n\
-\n\
-Hard constraints:\n\
-- If the robot is not clearly visible: success=false.\n\
-- If there is little/no visible motion across frames (or motion is ambiguous): success=false.\n\
-\n\
-Output rules:\n\
-- Return ONLY strict JSON that matches the provided schema.\n\
-- Be conservative: if uncertain, success=false and success_confidence<=0.5.\n\
-- Never claim success unless the goal is visually confirmed in the frame.\n\
-\n\
-Progress guidance:\n\
-- If you can see the robot moved in the intended direction between frames, reward should be positive (even if not yet complete).\n\
-- If you can see the robot moved in the wrong direction or did the opposite action, reward should be negative.\n\
-\n\
-Reward rules:\n\
-- reward in [-1.0, 1.0].\n\
-- +1.0 only when the task is clearly completed.\n\
-- 0.0 when no clear progress or unclear.\n\
-- negative when the robot regresses, interacts with the wrong object, or is unsafe.\n\
-\n\
-Safety rules:\n\
-- If imminent collision risk, falling risk, or leaving camera view: critical_failure=true.\n\
-\n\
-TASK: {task}\n"
-    )
+/// Transient-failure retries for orchestrator calls. GETs are retried on network errors and on
+/// `retry_on_status`. A POST is only resent when the connection itself failed, before anything
+/// was sent: orchestrator.py does not dedupe by correlation id, so a POST answered with a gateway
+/// error may already be running (a half-run `/execute_plan` must not run twice).
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorRetryPolicy {
+    max_attempts: u32,
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+    retry_on_status: Vec<u16>,
 }
 
-async fn openai_critic_eval(
-    model: &str,
-    task: &str,
-    frames_jpeg_base64: &[String],
-    motion_score: Option<f64>,
-    last_action_text: Option<&str>,
-    executed_plan: Option<&Value>,
-    correlation_id: Option<&str>,
-) -> Result<Value, String> {
-    let api_key = openai_api_key().ok_or_else(|| "OPENAI_API_KEY missing in app environment".to_string())?;
-    let sys = build_critic_system_prompt(task);
-    let mut user_lines = vec![
-        format!("Goal: {task}"),
-        "You will receive multiple frames in time order (oldest -> newest). Use them to detect motion and progress.".to_string(),
-    ];
-    if let Some(a) = last_action_text {
-        let a = a.trim();
-        if !a.is_empty() {
-            user_lines.push(format!("Last action: {a}"));
+impl Default for OrchestratorRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 2_000,
+            retry_on_status: vec![502, 503, 504],
         }
     }
-    if let Some(plan) = executed_plan {
-        // Keep it compact; tool output remains the source of truth.
-        let plan_short = trunc_for_log(&plan.to_string(), 800);
-        user_lines.push(format!("Executed plan (json): {plan_short}"));
+}
+
+fn orchestrator_retry_policy(http: &HttpState) -> Result<OrchestratorRetryPolicy, String> {
+    http.orchestrator_retry
+        .lock()
+        .map(|p| p.clone().unwrap_or_default())
+        .map_err(|_| "Retry policy lock poisoned".to_string())
+}
+
+#[derive(Clone, Default)]
+struct OrchestratorCounters {
+    requests: u64,
+    errors: u64,
+    execute_plan_count: u64,
+    execute_plan_total_ms: u128,
+    last_error: Option<String>,
+    last_request_ts_ms: Option<u128>,
+}
+
+fn record_orchestrator_request(base: &str, path: &str, elapsed: Duration, error: Option<&str>) {
+    let Ok(mut lock) = ORCHESTRATOR_COUNTERS.lock() else {
+        return;
+    };
+    let counters = lock.get_or_insert_with(HashMap::new).entry(base.to_string()).or_default();
+    counters.requests += 1;
+    counters.last_request_ts_ms = Some(unix_ts_ms());
+    if let Some(error) = error {
+        counters.errors += 1;
+        counters.last_error = Some(trunc_for_log(error, 500));
+    } else if path == "/execute_plan" {
+        counters.execute_plan_count += 1;
+        counters.execute_plan_total_ms += elapsed.as_millis();
     }
-    if let Some(ms) = motion_score {
-        user_lines.push(format!(
-            "Computed pixel motion score across frames: {:.4} (0.0 means no visible motion). If < 0.004 treat as no progress.",
-            ms
-        ));
+}
+
+fn orchestrator_counters(base: &str) -> OrchestratorCounters {
+    ORCHESTRATOR_COUNTERS
+        .lock()
+        .ok()
+        .and_then(|lock| lock.as_ref().and_then(|m| m.get(base).cloned()))
+        .unwrap_or_default()
+}
+
+/// Client for the orchestrator, vision service, and other LAN endpoints.
+fn http_client(http: &HttpState) -> Result<reqwest::Client, String> {
+    with_http_clients(http, |c| c.local.clone())
+}
+
+fn openai_http_client(http: &HttpState) -> Result<reqwest::Client, String> {
+    with_http_clients(http, |c| c.openai.clone())
+}
+
+/// `explicit_ms` if given, else the configured default; 0 means no timeout.
+fn request_timeout(
+    http: &HttpState,
+    explicit_ms: Option<u64>,
+    default_ms: impl FnOnce(&HttpClientConfig) -> u64,
+) -> Result<Option<Duration>, String> {
+    let ms = match explicit_ms {
+        Some(ms) => ms,
+        None => with_http_clients(http, |c| default_ms(&c.config))?,
+    };
+    Ok((ms > 0).then(|| Duration::from_millis(ms)))
+}
+
+#[cfg(unix)]
+fn unix_socket_client(http: &HttpState, path: &Path) -> Result<reqwest::Client, String> {
+    with_http_clients(http, |clients| {
+        if let Some(client) = clients.unix.get(path) {
+            return Ok(client.clone());
+        }
+        let client = reqwest::Client::builder()
+            .unix_socket(path)
+            .pool_max_idle_per_host(clients.config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_millis(clients.config.pool_idle_timeout_ms))
+            .build()
+            .map_err(|e| format!("Failed to build Unix socket client for {}: {e}", path.display()))?;
+        clients.unix.insert(path.to_path_buf(), client.clone());
+        Ok(client)
+    })?
+}
+
+#[cfg(not(unix))]
+fn unix_socket_client(_http: &HttpState, path: &Path) -> Result<reqwest::Client, String> {
+    Err(format!("unix:// orchestrator URLs are not supported on this platform ({})", path.display()))
+}
+
+/// Client plus URL prefix for an orchestrator base URL. `unix:///path/to.sock` bases are
+/// reached over that socket; the HTTP authority is then only a placeholder.
+fn orchestrator_endpoint(http: &HttpState, base: &str) -> Result<(reqwest::Client, String), String> {
+    match base.strip_prefix("unix://") {
+        Some(path) => Ok((unix_socket_client(http, Path::new(path))?, "http://localhost".to_string())),
+        None => Ok((endpoint_client(http, base)?, base.to_string())),
     }
-    user_lines.push("If robot/target is not clearly visible, do not claim success.".to_string());
-    let user_text = user_lines.join("\n");
+}
 
-    // JSON schema for strict structured output.
-    let schema = json!({
-        "type": "object",
-        "additionalProperties": false,
-        "properties": {
-            "describe": { "type": "string" },
-            "evaluate": { "type": "string" },
-            "reward": { "type": "number", "minimum": -1.0, "maximum": 1.0 },
-            "success": { "type": "boolean" },
-            "success_confidence": { "type": "number", "minimum": 0.0, "maximum": 1.0 },
-            "critical_failure": { "type": "boolean" },
-            "critical_failure_reason": { "type": "string" },
-            "failure_modes": {
-                "type": "array",
-                "items": {
-                    "type": "string",
-                    "enum": ["not_visible","target_not_visible","wrong_object","no_progress","regressing","collision_risk","edge_of_view","uncertain"]
-                }
-            },
-            "notes_short": { "type": "string" }
-        },
-        "required": ["describe","evaluate","reward","success","success_confidence","critical_failure","critical_failure_reason","failure_modes","notes_short"]
-    });
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PlanQueueState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
 
-    let frames = frames_jpeg_base64
-        .iter()
-        .filter(|s| !s.trim().is_empty())
-        .take(6)
-        .cloned()
-        .collect::<Vec<_>>();
-    if frames.is_empty() {
-        return Err("critic_step requires at least 1 frame".to_string());
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QueuedPlan {
+    plan_id: String,
+    orchestrator_base_url: String,
+    plan: Vec<PlanStep>,
+    correlation_id: String,
+    state: PlanQueueState,
+    enqueued_ts_ms: u128,
+    started_ts_ms: Option<u128>,
+    finished_ts_ms: Option<u128>,
+    result: Option<OrchestratorReply<OrchestratorAck>>,
+    error: Option<String>,
+}
+
+/// Backend-side FIFO of plans fed one at a time to `/execute_plan`. Finished entries are kept
+/// (up to `PLAN_QUEUE_RETAINED_FINISHED`) so the UI can show recent outcomes.
+#[derive(Default)]
+struct PlanQueue {
+    entries: VecDeque<QueuedPlan>,
+    next_id: u64,
+    worker_running: bool,
+}
+
+impl PlanQueue {
+    fn get_mut(&mut self, plan_id: &str) -> Option<&mut QueuedPlan> {
+        self.entries.iter_mut().find(|e| e.plan_id == plan_id)
+    }
+
+    fn prune_finished(&mut self) {
+        let finished = self
+            .entries
+            .iter()
+            .filter(|e| !matches!(e.state, PlanQueueState::Queued | PlanQueueState::Running))
+            .count();
+        let mut excess = finished.saturating_sub(PLAN_QUEUE_RETAINED_FINISHED);
+        self.entries.retain(|e| {
+            let finished = !matches!(e.state, PlanQueueState::Queued | PlanQueueState::Running);
+            if finished && excess > 0 {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+#[derive(Clone)]
+struct CachedNodeManifest {
+    alias: String,
+    /// Every identifier a plan step may use as `target`: alias, display name, node_id.
+    names: Vec<String>,
+    commands: Vec<NodeCommandSpec>,
+    connected: bool,
+}
+
+impl CachedNodeManifest {
+    fn command_spec(&self, token: &str) -> Option<&NodeCommandSpec> {
+        self.commands.iter().find(|command| command.token.eq_ignore_ascii_case(token))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum NodeArgType {
+    Int,
+    Float,
+    Bool,
+    String,
+    /// A type outside the manifest schema; every value is rejected.
+    Unsupported,
+}
+
+/// One argument of a manifest command.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeArgSpec {
+    name: String,
+    #[serde(rename = "type")]
+    arg_type: NodeArgType,
+    /// The manifest's spelling of the type, for errors about unsupported ones.
+    #[serde(skip)]
+    declared_type: String,
+    required: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// Allowed values, when the manifest restricts them.
+    #[serde(rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    allowed: Vec<Value>,
+    description: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCommandSafety {
+    rate_limit_hz: Option<u64>,
+    watchdog_ms: Option<u64>,
+    clamp: bool,
+}
+
+/// A manifest command parsed into types, for `node_capabilities` and argument validation.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCommandSpec {
+    token: String,
+    description: Option<String>,
+    args: Vec<NodeArgSpec>,
+    safety: Option<NodeCommandSafety>,
+    synonyms: Vec<String>,
+    examples: Vec<String>,
+}
+
+impl NodeArgSpec {
+    fn from_manifest(arg: &Value, index: usize) -> Self {
+        let declared_type = arg.get("type").and_then(|t| t.as_str()).unwrap_or("").to_ascii_lowercase();
+        let arg_type = match declared_type.as_str() {
+            "int" => NodeArgType::Int,
+            "float" => NodeArgType::Float,
+            "bool" => NodeArgType::Bool,
+            "string" => NodeArgType::String,
+            _ => NodeArgType::Unsupported,
+        };
+        NodeArgSpec {
+            name: arg
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("arg{index}")),
+            arg_type,
+            declared_type,
+            required: arg.get("required").and_then(|r| r.as_bool()).unwrap_or(true),
+            min: arg.get("min").and_then(|m| m.as_f64()),
+            max: arg.get("max").and_then(|m| m.as_f64()),
+            allowed: arg.get("enum").and_then(|e| e.as_array()).cloned().unwrap_or_default(),
+            description: arg.get("description").and_then(|d| d.as_str()).map(str::to_string),
+        }
+    }
+
+    /// Mirrors `Orchestrator._validate_arg_value`; returns (code, message) on failure.
+    fn validate(&self, value: &Value) -> Result<(), (String, String)> {
+        let arg_type = &self.declared_type;
+        let type_error = || Err(("arg_type".to_string(), format!("expected {arg_type}, got {value}")));
+        let numeric = match self.arg_type {
+            NodeArgType::Int => match value {
+                Value::Number(n) if n.is_i64() || n.is_u64() => n.as_f64(),
+                Value::Number(n) if n.as_f64().is_some_and(|f| f.fract() == 0.0) => n.as_f64(),
+                Value::String(s) => match s.trim().parse::<i64>() {
+                    Ok(i) => Some(i as f64),
+                    Err(_) => return type_error(),
+                },
+                _ => return type_error(),
+            },
+            NodeArgType::Float => match value {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => match s.trim().parse::<f64>() {
+                    Ok(f) => Some(f),
+                    Err(_) => return type_error(),
+                },
+                _ => return type_error(),
+            },
+            NodeArgType::Bool => match value {
+                Value::Bool(_) => None,
+                Value::String(s) if ["true", "false", "1", "0"].contains(&s.to_ascii_lowercase().as_str()) => None,
+                _ => return type_error(),
+            },
+            NodeArgType::String => match value {
+                Value::String(_) => None,
+                _ => return type_error(),
+            },
+            NodeArgType::Unsupported => {
+                return Err(("arg_type".to_string(), format!("unsupported arg type '{arg_type}'")))
+            }
+        };
+
+        if !self.allowed.is_empty() {
+            let as_text = |v: &Value| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+            if !self.allowed.contains(value) && !self.allowed.iter().any(|a| as_text(a) == as_text(value)) {
+                return Err((
+                    "arg_enum".to_string(),
+                    format!("value {value} not in enum {}", Value::Array(self.allowed.clone())),
+                ));
+            }
+        }
+        if let Some(numeric) = numeric {
+            if let Some(min) = self.min.filter(|min| numeric < *min) {
+                return Err(("arg_range".to_string(), format!("value {numeric} < min {min}")));
+            }
+            if let Some(max) = self.max.filter(|max| numeric > *max) {
+                return Err(("arg_range".to_string(), format!("value {numeric} > max {max}")));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NodeCommandSpec {
+    /// `None` for entries without a token, which cannot be invoked anyway.
+    fn from_manifest(command: &Value) -> Option<Self> {
+        let token = command.get("token")?.as_str()?.trim().to_string();
+        let strings = |pointer: &str| {
+            command
+                .pointer(pointer)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+        Some(NodeCommandSpec {
+            description: command.get("description").and_then(|d| d.as_str()).map(str::to_string),
+            args: command
+                .get("args")
+                .and_then(|a| a.as_array())
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(index, arg)| NodeArgSpec::from_manifest(arg, index))
+                .collect(),
+            safety: command.get("safety").filter(|s| s.is_object()).map(|safety| NodeCommandSafety {
+                rate_limit_hz: safety.get("rate_limit_hz").and_then(|v| v.as_u64()),
+                watchdog_ms: safety.get("watchdog_ms").and_then(|v| v.as_u64()),
+                clamp: safety.get("clamp").and_then(|v| v.as_bool()).unwrap_or(false),
+            }),
+            synonyms: strings("/nlp/synonyms"),
+            examples: strings("/nlp/examples"),
+            token,
+        })
+    }
+
+    /// Checks a direct invocation: every required argument present, none extra, each valid.
+    fn check_args(&self, args: &[Value]) -> Result<(), (String, String)> {
+        let required = self.args.iter().filter(|a| a.required).count();
+        if args.len() < required || args.len() > self.args.len() {
+            let expected = if required == self.args.len() {
+                required.to_string()
+            } else {
+                format!("{required}-{}", self.args.len())
+            };
+            return Err((
+                "arg_count".to_string(),
+                format!("token '{}' expects {expected} args, got {}", self.token, args.len()),
+            ));
+        }
+        for (index, (value, spec)) in args.iter().zip(&self.args).enumerate() {
+            spec.validate(value)
+                .map_err(|(code, message)| (code, format!("{} arg[{index}] ({}): {message}", self.token, spec.name)))?;
+        }
+        Ok(())
+    }
+}
+
+fn manifest_command_specs(manifest: &Value) -> Vec<NodeCommandSpec> {
+    manifest
+        .get("commands")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(NodeCommandSpec::from_manifest)
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlanValidationIssue {
+    step: Option<usize>,
+    code: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlanValidationResult {
+    ok: bool,
+    step_count: usize,
+    errors: Vec<PlanValidationIssue>,
+    /// Node alias each step would run on (None for STOP steps and unresolved steps).
+    resolved_nodes: Vec<Option<String>>,
+    known_nodes: Vec<String>,
+}
+
+/// What happens when a managed child exits on its own. An explicit stop never restarts it.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RestartMode {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct RestartPolicy {
+    mode: RestartMode,
+    max_restarts: u32,
+    backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RestartMode::Never,
+            max_restarts: 3,
+            backoff_ms: 1_000,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum ListenTarget {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for ListenTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenTarget::Tcp(addr) => write!(f, "{addr}"),
+            ListenTarget::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Everything needed to launch (and relaunch) a managed helper process.
+#[derive(Clone)]
+struct ProcessSpec {
+    /// Helper type, e.g. `orchestrator`; `(kind, name)` identifies the process.
+    kind: String,
+    name: String,
+    program: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    work_dir: PathBuf,
+    log_path: PathBuf,
+    /// Event the child's stdout/stderr lines are emitted on.
+    log_event: &'static str,
+    /// When set, launching only succeeds once the child accepts connections here.
+    listen: Option<ListenTarget>,
+    listen_timeout: Duration,
+    http_base_url: Option<String>,
+    /// Records the child's PID so a later launch can reap it if this app dies without stopping it.
+    pid_file: Option<PathBuf>,
+    /// Kind-specific launch parameters, kept so a relaunch with changes can rebuild `args`.
+    meta: Value,
+}
+
+struct ManagedProcess {
+    spec: ProcessSpec,
+    child: Child,
+    started: std::time::Instant,
+    restart_policy: RestartPolicy,
+    restarts: u32,
+    /// Set once `supervise_processes` has written a crash report for this child's exit.
+    exit_reported: bool,
+}
+
+impl ManagedProcess {
+    fn wants_restart(&self, exit: std::process::ExitStatus) -> bool {
+        self.restarts < self.restart_policy.max_restarts
+            && match self.restart_policy.mode {
+                RestartMode::Never => false,
+                RestartMode::OnFailure => !exit.success(),
+                RestartMode::Always => true,
+            }
+    }
+
+    fn status(&self, exit: Option<std::process::ExitStatus>) -> ManagedProcessStatus {
+        ManagedProcessStatus {
+            kind: self.spec.kind.clone(),
+            name: self.spec.name.clone(),
+            running: exit.is_none(),
+            pid: Some(self.child.id()),
+            http_base_url: self.spec.http_base_url.clone(),
+            args: self.spec.args.clone(),
+            log_path: self.spec.log_path.display().to_string(),
+            uptime_ms: exit.is_none().then(|| self.started.elapsed().as_millis()),
+            restarts: self.restarts,
+            restart_policy: self.restart_policy.clone(),
+            exit_code: exit.and_then(|e| e.code()),
+        }
+    }
+
+    /// Asks the child to exit (SIGTERM on unix) and kills it if it is still running after a few
+    /// seconds, so it can release its port and close node connections cleanly.
+    fn terminate(mut self) {
+        #[cfg(not(windows))]
+        {
+            let asked = Command::new("kill")
+                .args(["-TERM", &self.child.id().to_string()])
+                .status()
+                .is_ok_and(|s| s.success());
+            let deadline = std::time::Instant::now() + Duration::from_secs(3);
+            while asked && std::time::Instant::now() < deadline {
+                if matches!(self.child.try_wait(), Ok(Some(_))) {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        self.kill();
+    }
+
+    fn kill(mut self) {
+        // Best-effort terminate. If this fails, we still drop the handle.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(pid_file) = &self.spec.pid_file {
+            let _ = std::fs::remove_file(pid_file);
+        }
+    }
+}
+
+/// `(kind, name)`, e.g. `("orchestrator", "default")`.
+type ProcessKey = (String, String);
+
+/// Helper processes (orchestrator instances, vision server, simulators, ...) keyed by
+/// `(kind, name)`. Children that exit on their own are reaped lazily by `status`/`list` once
+/// their crash report is written, or relaunched by `supervise_processes` when their restart
+/// policy asks for it.
+#[derive(Default)]
+struct ProcessManager {
+    processes: Mutex<HashMap<ProcessKey, ManagedProcess>>,
+}
+
+impl ProcessManager {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<ProcessKey, ManagedProcess>>, String> {
+        self.processes.lock().map_err(|_| "State lock poisoned".to_string())
+    }
+
+    /// Launches `spec` (blocking until it listens, if `listen` is set) and replaces any
+    /// process already registered under the same kind and name.
+    fn spawn(&self, app: &AppHandle, spec: ProcessSpec, restart_policy: RestartPolicy) -> Result<ManagedProcessStatus, String> {
+        let child = launch_process(app, &spec)?;
+        let proc_ = ManagedProcess {
+            spec,
+            child,
+            started: std::time::Instant::now(),
+            restart_policy,
+            restarts: 0,
+            exit_reported: false,
+        };
+        let status = proc_.status(None);
+        let key = (proc_.spec.kind.clone(), proc_.spec.name.clone());
+        if let Some(previous) = self.lock()?.insert(key, proc_) {
+            previous.kill();
+        }
+        Ok(status)
+    }
+
+    fn status(&self, kind: &str, name: &str) -> Result<Option<ManagedProcessStatus>, String> {
+        managed_process_status(&mut *self.lock()?, &(kind.to_string(), name.to_string()))
+    }
+
+    fn list(&self, kind: Option<&str>) -> Result<Vec<ManagedProcessStatus>, String> {
+        let mut lock = self.lock()?;
+        let mut keys = lock
+            .keys()
+            .filter(|(k, _)| kind.is_none_or(|kind| k == kind))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        let mut statuses = Vec::new();
+        for key in keys {
+            if let Some(status) = managed_process_status(&mut lock, &key)? {
+                statuses.push(status);
+            }
+        }
+        Ok(statuses)
+    }
+
+    fn spec(&self, kind: &str, name: &str) -> Result<Option<(ProcessSpec, RestartPolicy)>, String> {
+        Ok(self
+            .lock()?
+            .get(&(kind.to_string(), name.to_string()))
+            .map(|p| (p.spec.clone(), p.restart_policy.clone())))
+    }
+
+    /// Unregisters a process without stopping it, for callers that stop it themselves.
+    fn take(&self, kind: &str, name: &str) -> Result<Option<ManagedProcess>, String> {
+        Ok(self.lock()?.remove(&(kind.to_string(), name.to_string())))
+    }
+
+    fn stop(&self, kind: &str, name: &str) -> Result<bool, String> {
+        let removed = self.lock()?.remove(&(kind.to_string(), name.to_string()));
+        Ok(removed.map(ManagedProcess::kill).is_some())
+    }
+
+    fn set_restart_policy(&self, kind: &str, name: &str, policy: RestartPolicy) -> Result<ManagedProcessStatus, String> {
+        let mut lock = self.lock()?;
+        let key = (kind.to_string(), name.to_string());
+        let proc_ = lock
+            .get_mut(&key)
+            .ok_or_else(|| format!("No managed {kind} process named {name:?}"))?;
+        proc_.restart_policy = policy;
+        managed_process_status(&mut lock, &key)?.ok_or_else(|| format!("{kind} process {name:?} has exited"))
+    }
+
+    fn pids(&self) -> Result<Vec<u32>, String> {
+        Ok(self.lock()?.values().map(|p| p.child.id()).collect())
+    }
+
+    /// Marks children that exited since the last call as reported and returns them.
+    fn take_unreported_exits(&self) -> Vec<ExitedProcess> {
+        let Ok(mut lock) = self.lock() else {
+            return Vec::new();
+        };
+        let mut exited = Vec::new();
+        for proc_ in lock.values_mut() {
+            if proc_.exit_reported {
+                continue;
+            }
+            if let Ok(Some(exit)) = proc_.child.try_wait() {
+                proc_.exit_reported = true;
+                exited.push(ExitedProcess {
+                    spec: proc_.spec.clone(),
+                    pid: proc_.child.id(),
+                    exit,
+                    uptime_ms: proc_.started.elapsed().as_millis(),
+                    restarts: proc_.restarts,
+                    will_restart: proc_.wants_restart(exit),
+                });
+            }
+        }
+        exited
+    }
+
+    /// Removes children that exited and should be relaunched, returning what to relaunch.
+    fn take_restartable(&self) -> Vec<(ProcessSpec, RestartPolicy, u32, Option<i32>)> {
+        let Ok(mut lock) = self.lock() else {
+            return Vec::new();
+        };
+        let mut exited = Vec::new();
+        for (key, proc_) in lock.iter_mut() {
+            if let Ok(Some(exit)) = proc_.child.try_wait() {
+                if proc_.wants_restart(exit) {
+                    exited.push((key.clone(), exit.code()));
+                }
+            }
+        }
+        exited
+            .into_iter()
+            .filter_map(|(key, exit_code)| {
+                let proc_ = lock.remove(&key)?;
+                let restart = (proc_.spec.clone(), proc_.restart_policy.clone(), proc_.restarts + 1, exit_code);
+                proc_.kill();
+                Some(restart)
+            })
+            .collect()
+    }
+}
+
+/// Status of a managed process, dropping it from `procs` if it exited and will not be restarted.
+fn managed_process_status(
+    procs: &mut HashMap<ProcessKey, ManagedProcess>,
+    key: &ProcessKey,
+) -> Result<Option<ManagedProcessStatus>, String> {
+    let Some(proc_) = procs.get_mut(key) else {
+        return Ok(None);
+    };
+    let exit = proc_
+        .child
+        .try_wait()
+        .map_err(|e| format!("Failed to query {} process: {e}", key.0))?;
+    if let Some(exit) = exit {
+        // Keep the entry until the supervisor has reported the exit, so it is never lost.
+        if !proc_.wants_restart(exit) && proc_.exit_reported {
+            if let Some(exited) = procs.remove(key) {
+                exited.kill();
+            }
+            return Ok(None);
+        }
+    }
+    Ok(Some(proc_.status(exit)))
+}
+
+struct OrchestratorHealthMonitor {
+    stop_tx: oneshot::Sender<()>,
+    base_url: Option<String>,
+    interval_ms: u64,
+}
+
+struct NodeHeartbeatMonitor {
+    stop_tx: oneshot::Sender<()>,
+    interval_ms: u64,
+    timeout_ms: u64,
+}
+
+struct InflightPlan {
+    abort_tx: oneshot::Sender<()>,
+    orchestrator_base_url: String,
+}
+
+#[derive(Clone)]
+struct ActivePlan {
+    orchestrator_base_url: String,
+    correlation_id: Option<String>,
+    started: std::time::Instant,
+}
+
+#[derive(Default)]
+struct AppState {
+    http: HttpState,
+    /// Open serial sessions keyed by the port name (or alias) they were opened with.
+    serial_sessions: Mutex<HashMap<String, SerialSession>>,
+    /// Set for the duration of an `orchestrator_execute_plan` call; arms serial watchdogs.
+    active_plan: Mutex<Option<ActivePlan>>,
+    /// Abort handles for in-flight `/execute_plan` requests, keyed by correlation id.
+    inflight_plans: Mutex<HashMap<String, InflightPlan>>,
+    /// Managed helper processes; orchestrator instances are kind `orchestrator`, keyed by
+    /// instance name (`default` unless the caller names one).
+    processes: ProcessManager,
+    /// Log-file followers started by `read_orchestrator_log(follow)`, keyed by instance name.
+    orchestrator_log_followers: Mutex<HashMap<String, oneshot::Sender<()>>>,
+    /// Background `/status` pollers keyed by orchestrator instance name.
+    orchestrator_health_monitors: Mutex<HashMap<String, OrchestratorHealthMonitor>>,
+    plan_queue: Mutex<PlanQueue>,
+    /// Node manifests seen via `orchestrator_status` / `node_probe`, keyed by node alias.
+    node_manifests: Mutex<HashMap<String, CachedNodeManifest>>,
+    /// Persistent node connections from `node_connect`.
+    nodes: NodeManager,
+    node_heartbeat: Mutex<Option<NodeHeartbeatMonitor>>,
+    /// Last heartbeat result per `host:port`, kept while the monitor runs.
+    node_liveness: Mutex<HashMap<String, NodeLiveness>>,
+    /// Status of every registry node, maintained by `supervise_node_status`.
+    fleet_status: Mutex<FleetStatus>,
+    /// `NODE_REGISTRY_FILE` as last read by `cached_node_registry`; cleared on every write.
+    node_registry: Mutex<Option<Vec<NodeRegistryEntry>>>,
+    /// In-process simulated nodes from `start_sim_node`, keyed by port.
+    sim_nodes: Mutex<HashMap<u16, SimNode>>,
+    critic_session: Mutex<Option<CriticSession>>,
+    /// Stops the backend critic loop started by `critic_spawn`, if any.
+    critic_loop: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+#[derive(Clone)]
+struct CriticSession {
+    session_id: String,
+    /// Steps started so far, not counting throttled ones; numbers the episode log records.
+    steps_started: u64,
+    started_at: std::time::Instant,
+    budget: CriticBudget,
+    budget_exceeded: Option<String>,
+    /// Also write each step's frames next to the episode log.
+    record_frames: bool,
+    orchestrator_base_url: String,
+    task: String,
+    model: String,
+    success_streak: u32,
+    success_n: u32,
+    conf_threshold: f64,
+    reward_threshold: f64,
+    /// When set, the reward threshold is applied to the EMA rather than the raw frame reward.
+    reward_ema_alpha: Option<f64>,
+    reward_ema: Option<f64>,
+    /// `(n, k)`: stable once `k` of the last `n` frames succeeded, instead of `success_n` in a row.
+    success_window: Option<(u32, u32)>,
+    recent_successes: VecDeque<bool>,
+    loop_interval_ms: Option<u64>,
+    backend: CriticBackendConfig,
+    /// Tried in order when `model` fails (quota, bad request, unparseable output).
+    fallback_models: Vec<String>,
+    /// Extra models scored on the same frames as `model`; results are combined by vote.
+    ensemble_models: Vec<String>,
+    /// Task-specific JSON-schema properties required alongside the standard critic fields.
+    extra_output_properties: serde_json::Map<String, Value>,
+    frame_policy: CriticFramePolicy,
+    /// Overrides `default_critic_frames_dir` as the only place `frame_paths` may point into.
+    frames_dir: Option<PathBuf>,
+    /// Orchestrator path that receives `suggested_correction` as an instruction after a failed
+    /// step; `None` leaves corrections advisory only.
+    correction_path: Option<String>,
+    correction_cooldown: Duration,
+    last_correction_at: Option<std::time::Instant>,
+    /// Run in order on a critical failure; empty only emits `critic_critical_failure`.
+    critical_actions: Vec<CriticCriticalAction>,
+    /// Latched the first time success is stable, so `task_succeeded` fires once per session.
+    task_succeeded: bool,
+    /// On `task_succeeded`, also stop the orchestrator and end the critic session.
+    auto_stop_on_success: bool,
+    usage: CriticUsage,
+    min_step_interval: Option<Duration>,
+    last_step_started: Option<std::time::Instant>,
+    /// Frames larger than this (longest side, px) are downscaled and re-encoded before sending.
+    frame_max_dimension: u32,
+    frame_jpeg_quality: u8,
+    prompt_template: String,
+    robot_description: String,
+    /// When set, a step whose frames hash the same as the last evaluated step reuses its result,
+    /// as long as that result is younger than this.
+    identical_frame_max_age: Option<Duration>,
+    last_evaluated: Option<(u64, std::time::Instant, CriticStepResult)>,
+}
+
+impl CriticSession {
+    fn status(&self) -> CriticStatus {
+        CriticStatus {
+            running: true,
+            task: Some(self.task.clone()),
+            model: Some(self.model.clone()),
+            success_streak: self.success_streak,
+            success_n: self.success_n,
+            loop_interval_ms: self.loop_interval_ms,
+            usage: Some(self.usage.clone()),
+            session_id: Some(self.session_id.clone()),
+            budget_exceeded: self.budget_exceeded.clone(),
+        }
+    }
+
+    fn window_successes(&self) -> Option<u32> {
+        self.success_window
+            .map(|_| self.recent_successes.iter().filter(|s| **s).count() as u32)
+    }
+
+    fn success_stable(&self) -> bool {
+        match (self.success_window, self.window_successes()) {
+            (Some((_, k)), Some(hits)) => hits >= k,
+            _ => self.success_streak >= self.success_n,
+        }
+    }
+
+    /// Folds one frame into the streak, EMA and window; returns whether the frame counted as a
+    /// success.
+    fn record_frame(&mut self, success: bool, confidence: f64, reward: f64) -> bool {
+        let scored_reward = match self.reward_ema_alpha {
+            Some(alpha) => {
+                let ema = self.reward_ema.map_or(reward, |prev| alpha * reward + (1.0 - alpha) * prev);
+                self.reward_ema = Some(ema);
+                ema
+            }
+            None => reward,
+        };
+        let hit = success && confidence >= self.conf_threshold && scored_reward >= self.reward_threshold;
+        self.success_streak = if hit { self.success_streak + 1 } else { 0 };
+        if let Some((n, _)) = self.success_window {
+            self.recent_successes.push_back(hit);
+            while self.recent_successes.len() > n as usize {
+                self.recent_successes.pop_front();
+            }
+        }
+        hit
+    }
+
+    fn new(orchestrator_base_url: String, task: String, loop_interval_ms: Option<u64>) -> Self {
+        Self {
+            session_id: format!("critic_{}", unix_ts_ms()),
+            steps_started: 0,
+            started_at: std::time::Instant::now(),
+            budget: CriticBudget::default(),
+            budget_exceeded: None,
+            record_frames: true,
+            orchestrator_base_url,
+            task,
+            model: "gpt-5.2".to_string(),
+            success_streak: 0,
+            success_n: 3,
+            conf_threshold: 0.9,
+            reward_threshold: 0.1,
+            reward_ema_alpha: None,
+            reward_ema: None,
+            success_window: None,
+            recent_successes: VecDeque::new(),
+            loop_interval_ms,
+            backend: CriticBackendConfig::default(),
+            fallback_models: Vec::new(),
+            ensemble_models: Vec::new(),
+            extra_output_properties: serde_json::Map::new(),
+            frame_policy: CriticFramePolicy::default(),
+            frames_dir: None,
+            correction_path: None,
+            correction_cooldown: Duration::from_millis(5_000),
+            last_correction_at: None,
+            critical_actions: vec![CriticCriticalAction::StopOrchestrator],
+            task_succeeded: false,
+            auto_stop_on_success: false,
+            usage: CriticUsage::default(),
+            min_step_interval: None,
+            last_step_started: None,
+            frame_max_dimension: 768,
+            frame_jpeg_quality: 80,
+            prompt_template: BUILTIN_CRITIC_PROMPT.to_string(),
+            robot_description: DEFAULT_ROBOT_DESCRIPTION.to_string(),
+            identical_frame_max_age: None,
+            last_evaluated: None,
+        }
+    }
+
+    fn apply_config(&mut self, app: &AppHandle, config: CriticConfig) -> Result<(), String> {
+        if let Some(dir) = config.frames_dir.map(|d| d.trim().to_string()) {
+            self.frames_dir = if dir.is_empty() {
+                None
+            } else {
+                Some(pinned_critic_frames_dir(app, Path::new(&dir))?)
+            };
+        }
+        if let Some(model) = config.model.map(|m| m.trim().to_string()).filter(|m| !m.is_empty()) {
+            self.model = model;
+        }
+        if let Some(models) = config.fallback_models {
+            self.fallback_models = trimmed_model_list(models);
+        }
+        if let Some(models) = config.ensemble_models {
+            self.ensemble_models = trimmed_model_list(models);
+        }
+        if let Some(mut backend) = config.backend {
+            backend.limits = self.backend.limits;
+            backend.local_base_url = backend
+                .local_base_url
+                .as_deref()
+                .filter(|url| !url.trim().is_empty())
+                .map(normalize_base_url)
+                .transpose()?;
+            backend.openai = backend.openai.map(OpenAiEndpointConfig::validate).transpose()?;
+            self.backend = backend;
+        }
+        if let Some(ms) = config.request_timeout_ms {
+            if ms == 0 {
+                return Err("request_timeout_ms must be positive".to_string());
+            }
+            self.backend.limits.timeout = Duration::from_millis(ms);
+        }
+        if let Some(max) = config.max_output_tokens {
+            self.backend.limits.max_output_tokens = max.clamp(64, 16_384);
+        }
+        if let Some(n) = config.success_consecutive_frames {
+            self.success_n = n.max(1);
+        }
+        if let Some(th) = config.success_confidence_threshold {
+            self.conf_threshold = th;
+        }
+        if let Some(th) = config.success_reward_threshold {
+            self.reward_threshold = th;
+        }
+        if let Some(alpha) = config.reward_ema_alpha {
+            if !(alpha > 0.0 && alpha <= 1.0) {
+                return Err("reward_ema_alpha must be in (0, 1]".to_string());
+            }
+            self.reward_ema_alpha = (alpha < 1.0).then_some(alpha);
+            if self.reward_ema_alpha.is_none() {
+                self.reward_ema = None;
+            }
+        }
+        if config.success_window.is_some() || config.success_window_required.is_some() {
+            let (n, k) = self.success_window.unwrap_or((0, 0));
+            let n = config.success_window.unwrap_or(n);
+            let k = config.success_window_required.unwrap_or(k);
+            let window = match (n, k) {
+                (0, _) => None,
+                (n, k) if k >= 1 && k <= n => Some((n, k)),
+                _ => return Err("success_window_required must be between 1 and success_window".to_string()),
+            };
+            if window != self.success_window {
+                self.success_window = window;
+                self.recent_successes.clear();
+            }
+        }
+        if let Some(ms) = config.min_step_interval_ms {
+            self.min_step_interval = (ms > 0).then(|| Duration::from_millis(ms));
+        }
+        if let Some(dim) = config.frame_max_dimension {
+            self.frame_max_dimension = dim.clamp(64, 4096);
+        }
+        if let Some(quality) = config.frame_jpeg_quality {
+            self.frame_jpeg_quality = quality.clamp(10, 100);
+        }
+        if config.skip_identical_frames.is_some() || config.identical_frame_max_age_ms.is_some() {
+            let enabled = config
+                .skip_identical_frames
+                .unwrap_or(self.identical_frame_max_age.is_some());
+            let max_age = config
+                .identical_frame_max_age_ms
+                .map(Duration::from_millis)
+                .or(self.identical_frame_max_age)
+                .unwrap_or(Duration::from_millis(10_000));
+            self.identical_frame_max_age = enabled.then_some(max_age);
+            self.last_evaluated = None;
+        }
+        if let Some(name) = config.prompt_template {
+            self.prompt_template = load_critic_prompt_template(app, &name)?;
+        }
+        if let Some(description) = config.robot_description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+            self.robot_description = description;
+        }
+        if let Some(record) = config.record_frames {
+            self.record_frames = record;
+        }
+        if let Some(max) = config.max_steps {
+            self.budget.max_steps = Some(max);
+        }
+        if let Some(ms) = config.max_elapsed_ms {
+            self.budget.max_elapsed = Some(Duration::from_millis(ms));
+        }
+        if let Some(max) = config.max_cost_usd {
+            self.budget.max_cost_usd = Some(max);
+        }
+        if let Some(stop) = config.stop_robot_on_budget {
+            self.budget.stop_robot = stop;
+        }
+        if let Some(extra) = config.extra_output_properties {
+            self.extra_output_properties = validate_extra_output_properties(extra)?;
+        }
+        if config.forward_corrections.is_some() || config.correction_path.is_some() {
+            let enabled = config.forward_corrections.unwrap_or(self.correction_path.is_some());
+            // The bridge forwards `instruction` to the planner and returns its plan.
+            let path = config
+                .correction_path
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .or_else(|| self.correction_path.clone())
+                .unwrap_or_else(|| "/pi_vision_step".to_string());
+            if !path.starts_with('/') {
+                return Err(format!("correction_path must start with '/', got: {path}"));
+            }
+            self.correction_path = enabled.then_some(path);
+        }
+        if let Some(ms) = config.correction_cooldown_ms {
+            self.correction_cooldown = Duration::from_millis(ms);
+        }
+        if let Some(actions) = config.critical_failure_actions {
+            for action in &actions {
+                match action {
+                    CriticCriticalAction::RecoveryPlan { plan } if plan.is_empty() => {
+                        return Err("recovery_plan action needs at least one step".to_string());
+                    }
+                    CriticCriticalAction::SerialEstop { token, .. } if token.trim().is_empty() => {
+                        return Err("serial_estop action needs a token".to_string());
+                    }
+                    _ => {}
+                }
+            }
+            self.critical_actions = actions;
+        }
+        if let Some(auto_stop) = config.auto_stop_on_success {
+            self.auto_stop_on_success = auto_stop;
+        }
+        if let Some(max) = config.max_frames {
+            self.frame_policy.max_frames = max.clamp(1, 16);
+        }
+        if let Some(selection) = config.frame_selection {
+            self.frame_policy.selection = selection;
+        }
+        if let Some(format) = config.frame_caption_format.filter(|f| !f.trim().is_empty()) {
+            self.frame_policy.caption_format = format;
+        }
+        Ok(())
+    }
+
+    fn check_budget(&self) -> Option<String> {
+        let budget = &self.budget;
+        if let Some(max) = budget.max_steps.filter(|max| self.steps_started >= *max) {
+            return Some(format!("step limit reached ({max} steps)"));
+        }
+        if let Some(max) = budget.max_elapsed.filter(|max| self.started_at.elapsed() >= *max) {
+            return Some(format!("time limit reached ({} s)", max.as_secs()));
+        }
+        if let (Some(max), Some(cost)) = (budget.max_cost_usd, self.usage.cost_usd) {
+            if cost >= max {
+                return Some(format!("cost limit reached (${cost:.4} of ${max:.4})"));
+            }
+        }
+        None
+    }
+
+    fn step_id(&self) -> String {
+        format!("{}:{}", self.session_id, self.steps_started)
+    }
+
+    /// One chain per ensemble member: the primary with its fallbacks, then each extra model alone.
+    fn model_chains(&self) -> Vec<Vec<String>> {
+        let primary = std::iter::once(self.model.clone())
+            .chain(self.fallback_models.iter().cloned())
+            .collect();
+        std::iter::once(primary)
+            .chain(self.ensemble_models.iter().map(|m| vec![m.clone()]))
+            .collect()
+    }
+
+    /// Explicit pricing, else free for a local server, else the built-in table.
+    fn pricing_for(&self, model: &str) -> Option<TokenPricing> {
+        if let Some(pricing) = self.backend.pricing {
+            return Some(pricing);
+        }
+        if self.backend.local_base_url.is_some() || model == "mock" || model.starts_with("mock-") {
+            return Some(TokenPricing {
+                input_per_mtok: 0.0,
+                output_per_mtok: 0.0,
+                cached_input_per_mtok: None,
+            });
+        }
+        TokenPricing::for_model(model)
+    }
+}
+
+/// Where the backend critic loop gets its frames.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CriticFrameSource {
+    /// `GET` returning a single JPEG, e.g. a camera's snapshot endpoint.
+    Http { url: String },
+    /// A JPEG file that some other process keeps overwriting.
+    File { path: PathBuf },
+}
+
+impl CriticFrameSource {
+    async fn fetch_jpeg_base64(&self, http: &HttpState) -> Result<String, String> {
+        let bytes = match self {
+            CriticFrameSource::Http { url } => {
+                let response = http_client(http)?
+                    .get(url)
+                    .timeout(Duration::from_secs(5))
+                    .send()
+                    .await
+                    .map_err(|e| format!("GET {url} failed: {e}"))?;
+                if !response.status().is_success() {
+                    return Err(format!("GET {url} failed: HTTP {}", response.status().as_u16()));
+                }
+                response
+                    .bytes()
+                    .await
+                    .map_err(|e| format!("GET {url} failed: could not read frame: {e}"))?
+                    .to_vec()
+            }
+            CriticFrameSource::File { path } => {
+                std::fs::read(path).map_err(|e| format!("Failed to read frame {}: {e}", path.display()))?
+            }
+        };
+        if bytes.is_empty() {
+            return Err("Frame source returned an empty frame".to_string());
+        }
+        Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+}
+
+/// Critic session settings for `critic_spawn` and `critic_update_config`. Every field is
+/// optional: at spawn, unset fields take their defaults; on update, they keep their current value.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticConfig {
+    model: Option<String>,
+    /// Tried in order when `model` fails (quota, bad request, unparseable output).
+    fallback_models: Option<Vec<String>>,
+    /// Extra models scored on the same frames as `model`; results are combined by vote.
+    ensemble_models: Option<Vec<String>>,
+    backend: Option<CriticBackendConfig>,
+    success_consecutive_frames: Option<u32>,
+    success_confidence_threshold: Option<f64>,
+    success_reward_threshold: Option<f64>,
+    /// In (0, 1]; applies the reward threshold to an EMA of the reward. 1 disables smoothing.
+    reward_ema_alpha: Option<f64>,
+    /// Stable once `success_window_required` of the last `success_window` frames succeeded;
+    /// a window of 0 goes back to the consecutive streak.
+    success_window: Option<u32>,
+    success_window_required: Option<u32>,
+    min_step_interval_ms: Option<u64>,
+    frame_max_dimension: Option<u32>,
+    frame_jpeg_quality: Option<u8>,
+    skip_identical_frames: Option<bool>,
+    identical_frame_max_age_ms: Option<u64>,
+    prompt_template: Option<String>,
+    robot_description: Option<String>,
+    record_frames: Option<bool>,
+    max_steps: Option<u64>,
+    max_elapsed_ms: Option<u64>,
+    max_cost_usd: Option<f64>,
+    stop_robot_on_budget: Option<bool>,
+    /// Task-specific JSON-schema properties required alongside the standard critic fields.
+    extra_output_properties: Option<serde_json::Map<String, Value>>,
+    forward_corrections: Option<bool>,
+    correction_path: Option<String>,
+    correction_cooldown_ms: Option<u64>,
+    /// Replaces the default `[{"type": "stop_orchestrator"}]`; `[]` only emits the event.
+    critical_failure_actions: Option<Vec<CriticCriticalAction>>,
+    /// Stop the orchestrator and the critic once the task first succeeds.
+    auto_stop_on_success: Option<bool>,
+    max_frames: Option<usize>,
+    frame_selection: Option<CriticFrameSelection>,
+    frame_caption_format: Option<String>,
+    /// Directory `critic_step` may read `frame_paths` from; defaults to `<app data>/critic_frames`.
+    /// Relative paths are under the app data dir, and it must stay inside it.
+    frames_dir: Option<String>,
+    /// Per-attempt deadline for a critic API call (default 60 s).
+    request_timeout_ms: Option<u64>,
+    /// Output token cap sent to the model (default 1024); long `describe` fields get cut short
+    /// below a few hundred.
+    max_output_tokens: Option<u32>,
+}
+
+fn trimmed_model_list(models: Vec<String>) -> Vec<String> {
+    models
+        .into_iter()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+/// Limits after which a critic session refuses further steps. The cost limit only applies once
+/// usage can be priced (see `CriticUsage::cost_usd`).
+#[derive(Clone, Default)]
+struct CriticBudget {
+    max_steps: Option<u64>,
+    max_elapsed: Option<Duration>,
+    max_cost_usd: Option<f64>,
+    /// Also send `/stop` to the orchestrator when a limit is hit.
+    stop_robot: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticBudgetExceededEvent {
+    session_id: String,
+    reason: String,
+    steps: u64,
+    elapsed_ms: u128,
+    usage: CriticUsage,
+    robot_stopped: bool,
+    ts_ms: u128,
+}
+
+/// A response to a critical failure, e.g. `{"type": "serial_estop", "token": "ESTOP"}`.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CriticCriticalAction {
+    /// POST `/stop` to the orchestrator.
+    StopOrchestrator,
+    /// Run this plan on the orchestrator, in the background.
+    RecoveryPlan { plan: Vec<PlanStep> },
+    /// Write `token` as a line to `port` (or the only open serial session), bypassing the
+    /// orchestrator.
+    SerialEstop {
+        token: String,
+        #[serde(default)]
+        port: Option<String>,
+    },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticActionReport {
+    action: &'static str,
+    ok: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticCriticalFailureEvent {
+    session_id: String,
+    correlation_id: String,
+    reason: String,
+    actions: Vec<CriticActionReport>,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskSucceededEvent {
+    session_id: String,
+    task: String,
+    correlation_id: String,
+    success_streak: u32,
+    steps: u64,
+    elapsed_ms: u128,
+    auto_stopped: bool,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticCorrectionEvent {
+    correlation_id: String,
+    suggested_correction: String,
+    ok: bool,
+    response: Option<Value>,
+    error: Option<String>,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticErrorEvent {
+    kind: CriticErrorKind,
+    error: String,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CriticErrorKind {
+    /// The model did not answer within `request_timeout_ms`.
+    Timeout,
+    Failed,
+}
+
+impl CriticErrorKind {
+    fn of(error: &str) -> Self {
+        if error.contains(CRITIC_TIMEOUT_ERROR) {
+            Self::Timeout
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialPortEntry {
+    port_name: String,
+    port_type: String,
+    vid: Option<u16>,
+    pid: Option<u16>,
+    serial_number: Option<String>,
+    manufacturer: Option<String>,
+    /// Stable `usb:<vid>:<pid>[:<serial>]` handle accepted by `connect_serial` in place of `port_name`.
+    alias: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConnectionStatus {
+    connected: bool,
+    port_name: Option<String>,
+    device_path: Option<String>,
+    state: SerialConnectionState,
+}
+
+/// One step of a serial macro: send `line`, optionally wait for an RX line containing `expect`,
+/// then sleep `delay_ms` before the next step.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialMacroStep {
+    line: String,
+    #[serde(default)]
+    delay_ms: u64,
+    expect: Option<String>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialMacroStepResult {
+    index: usize,
+    line: String,
+    sent_ts_ms: u128,
+    matched: Option<bool>,
+    response: Option<String>,
+    elapsed_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialMacroResult {
+    ok: bool,
+    steps: Vec<SerialMacroStepResult>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialLineEvent {
+    port: String,
+    ts_ms: u128,
+    direction: String,
+    line: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialLinesBatch {
+    port: String,
+    lines: Vec<String>,
+    dropped: u64,
+    dropped_total: u64,
+}
+
+/// Coalesces RX lines in the serial worker and emits them as one `serial_lines` event per
+/// flush interval. When more than `capacity` lines pile up between flushes, the oldest are dropped.
+struct SerialLineBatcher {
+    port_name: String,
+    interval: Duration,
+    capacity: usize,
+    pending: VecDeque<String>,
+    dropped: u64,
+    dropped_total: u64,
+}
+
+impl SerialLineBatcher {
+    fn new(port_name: &str, interval: Duration, capacity: usize) -> Self {
+        Self {
+            port_name: port_name.to_string(),
+            interval,
+            capacity: capacity.max(1),
+            pending: VecDeque::new(),
+            dropped: 0,
+            dropped_total: 0,
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+            self.dropped += 1;
+            self.dropped_total += 1;
+        }
+        self.pending.push_back(line);
+    }
+
+    fn flush(&mut self, app: &AppHandle) {
+        if self.pending.is_empty() && self.dropped == 0 {
+            return;
+        }
+        let batch = SerialLinesBatch {
+            port: self.port_name.clone(),
+            lines: self.pending.drain(..).collect(),
+            dropped: self.dropped,
+            dropped_total: self.dropped_total,
+        };
+        self.dropped = 0;
+        emit_serial_event(app, SERIAL_LINES_EVENT, &self.port_name, batch);
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialFilterStatus {
+    include: Option<String>,
+    exclude: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialReplayResult {
+    path: String,
+    lines_sent: usize,
+    lines_skipped: usize,
+    duration_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialCaptureStatus {
+    active: bool,
+    path: Option<String>,
+    started_ts_ms: Option<u128>,
+    lines_written: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialSessionInfo {
+    port_name: String,
+    device_path: Option<String>,
+    state: SerialConnectionState,
+    connected_ts_ms: u128,
+    capturing: bool,
+    history_len: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FlashProgressEvent {
+    port: String,
+    stage: String,
+    percent: Option<u8>,
+    line: Option<String>,
+    ts_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FlashFirmwareResult {
+    port: String,
+    device_path: String,
+    chip: String,
+    tool: String,
+    exit_code: Option<i32>,
+    duration_ms: u128,
+    session_resumed: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorLogEvent {
+    instance: String,
+    stream: String,
+    line: String,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum OrchestratorHealth {
+    Ok,
+    /// Reachable, but it reports `ok: false` or some nodes are disconnected.
+    Degraded,
+    Unreachable,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorHealthEvent {
+    instance: String,
+    base_url: Option<String>,
+    health: OrchestratorHealth,
+    latency_ms: Option<u128>,
+    disconnected_nodes: Vec<String>,
+    error: Option<String>,
+    ts_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorMetrics {
+    base_url: String,
+    reachable: bool,
+    status_latency_ms: Option<u128>,
+    status_error: Option<String>,
+    node_count: usize,
+    connected_nodes: usize,
+    /// Requests this app has sent to `base_url` since launch (including failed ones).
+    requests: u64,
+    errors: u64,
+    last_error: Option<String>,
+    last_request_ts_ms: Option<u128>,
+    execute_plan_count: u64,
+    avg_execute_plan_ms: Option<f64>,
+    managed_instance: Option<String>,
+    child_pid: Option<u32>,
+    child_uptime_ms: Option<u128>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscoveredOrchestrator {
+    base_url: String,
+    host: String,
+    port: u16,
+    latency_ms: u128,
+    node_count: usize,
+    connected_nodes: usize,
+    /// Name of the instance this app spawned on that URL, if any.
+    managed_instance: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeLiveness {
+    target: String,
+    /// Registry name or device name, when known.
+    name: Option<String>,
+    online: bool,
+    latency_ms: Option<u128>,
+    /// Smoothed protocol round trip from the node's persistent connection, if it has one.
+    rtt_ms: Option<f64>,
+    error: Option<String>,
+    /// When `online` last changed.
+    since_ts_ms: u128,
+    ts_ms: u128,
+}
+
+/// Backend-maintained status of one registry node; see `supervise_node_status`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeStatus {
+    /// Registry name.
+    name: String,
+    target: String,
+    online: bool,
+    /// State of the node's `node_connect` connection, if it has one.
+    link_state: Option<NodeLinkState>,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    protocol_version: Option<u64>,
+    firmware_version: Option<String>,
+    tokens: Vec<String>,
+    rtt_ms: Option<f64>,
+    error: Option<String>,
+    /// When `online` last changed.
+    since_ts_ms: u128,
+    checked_ts_ms: u128,
+    /// Fleet revision of the change this status belongs to.
+    revision: u64,
+    /// Only on the event for a node that left the registry; snapshots never include it.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    removed: bool,
+}
+
+impl NodeStatus {
+    /// Whether `other` differs in anything worth a `node_status` event. Round trips and error
+    /// wording alone change too often to count.
+    fn differs_from(&self, other: &NodeStatus) -> bool {
+        self.target != other.target
+            || self.online != other.online
+            || self.link_state != other.link_state
+            || self.device_name != other.device_name
+            || self.node_id != other.node_id
+            || self.protocol_version != other.protocol_version
+            || self.firmware_version != other.firmware_version
+            || self.tokens != other.tokens
+    }
+}
+
+#[derive(Default)]
+struct FleetStatus {
+    /// Bumped on every change, so a snapshot and later `node_status` events can be lined up.
+    revision: u64,
+    /// Keyed by registry name.
+    nodes: HashMap<String, NodeStatus>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FleetStatusSnapshot {
+    revision: u64,
+    nodes: Vec<NodeStatus>,
+    ts_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeHeartbeatStatus {
+    running: bool,
+    interval_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+    nodes: Vec<NodeLiveness>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorHealthMonitorStatus {
+    instance: String,
+    running: bool,
+    base_url: Option<String>,
+    interval_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorProcessStatus {
+    name: String,
+    running: bool,
+    pid: Option<u32>,
+    http_base_url: Option<String>,
+    args: Option<Vec<String>>,
+}
+
+impl From<ManagedProcessStatus> for OrchestratorProcessStatus {
+    fn from(status: ManagedProcessStatus) -> Self {
+        Self {
+            name: status.name,
+            running: status.running,
+            pid: status.pid,
+            http_base_url: status.http_base_url,
+            args: Some(status.args),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ManagedProcessStatus {
+    kind: String,
+    name: String,
+    running: bool,
+    pid: Option<u32>,
+    http_base_url: Option<String>,
+    args: Vec<String>,
+    log_path: String,
+    uptime_ms: Option<u128>,
+    restarts: u32,
+    restart_policy: RestartPolicy,
+    /// Set while an exited child waits for the supervisor to relaunch it.
+    exit_code: Option<i32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessEvent {
+    kind: String,
+    name: String,
+    /// `restarted` or `restart_failed`.
+    event: String,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    restarts: u32,
+    error: Option<String>,
+    ts_ms: u128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeProbeStatus {
+    ok: bool,
+    host: String,
+    port: u16,
+    target: String,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    tokens: Vec<String>,
+    manifest: Option<Value>,
+    /// Problems found by `validate_node_manifest`; empty for a well-formed manifest.
+    validation_errors: Vec<ManifestIssue>,
+    /// Node protocol version from the MANIFEST, when the probe succeeded.
+    protocol_version: Option<u64>,
+}
+
+fn port_type_name(port_type: &serialport::SerialPortType) -> String {
+    match port_type {
+        serialport::SerialPortType::UsbPort(info) => {
+            let mut label = String::from("usb");
+            if let Some(product) = &info.product {
+                label = format!("usb:{product}");
+            }
+            label
+        }
+        serialport::SerialPortType::BluetoothPort => String::from("bluetooth"),
+        serialport::SerialPortType::PciPort => String::from("pci"),
+        serialport::SerialPortType::Unknown => String::from("unknown"),
+    }
+}
+
+fn serial_port_alias(info: &serialport::UsbPortInfo) -> String {
+    match info.serial_number.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        Some(serial) => format!("usb:{:04x}:{:04x}:{serial}", info.vid, info.pid),
+        None => format!("usb:{:04x}:{:04x}", info.vid, info.pid),
+    }
+}
+
+/// Resolves a `usb:<vid>:<pid>[:<serial>]` alias to the device path it currently has
+/// (tty names change between replugs on macOS). Plain device paths pass through unchanged.
+fn resolve_serial_target(target: &str) -> Result<String, String> {
+    let target = target.trim();
+    let Some(rest) = target.strip_prefix("usb:") else {
+        return Ok(target.to_string());
+    };
+    let mut parts = rest.splitn(3, ':');
+    let parse_hex = |label: &str, raw: Option<&str>| -> Result<u16, String> {
+        let raw = raw.unwrap_or("").trim();
+        u16::from_str_radix(raw, 16).map_err(|_| format!("Invalid {label} in serial alias {target}: {raw:?}"))
+    };
+    let vid = parse_hex("vid", parts.next())?;
+    let pid = parse_hex("pid", parts.next())?;
+    let serial = parts.next().map(|s| s.trim()).filter(|s| !s.is_empty());
+
+    let ports = serialport::available_ports().map_err(|error| error.to_string())?;
+    let matches = ports
+        .into_iter()
+        .filter(|port| match &port.port_type {
+            serialport::SerialPortType::UsbPort(info) => {
+                info.vid == vid
+                    && info.pid == pid
+                    && serial.map(|s| info.serial_number.as_deref() == Some(s)).unwrap_or(true)
+            }
+            _ => false,
+        })
+        .map(|port| port.port_name)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => Err(format!("No serial device currently matches {target}")),
+        [only] => Ok(only.clone()),
+        many => Err(format!(
+            "Serial alias {target} is ambiguous ({}); include the serial number",
+            many.join(", ")
+        )),
+    }
+}
+
+/// Emits one line of serial traffic. RX (and read errors) go out on `serial_line`, TX on
+/// `serial_tx`; sessions opened with `legacy_events` get the bare line string on the global
+/// event, as before per-port channels, instead of the structured payload.
+fn emit_serial_line(app: &AppHandle, port_name: &str, direction: &str, line: String, legacy: bool) {
+    let event = if direction == "tx" { SERIAL_TX_EVENT } else { SERIAL_EVENT };
+    if legacy {
+        let _ = app.emit(event, line);
+        return;
+    }
+    emit_serial_event(
+        app,
+        event,
+        port_name,
+        SerialLineEvent {
+            port: port_name.to_string(),
+            ts_ms: unix_ts_ms(),
+            direction: direction.to_string(),
+            line,
+        },
+    );
+}
+
+/// Global companion of a per-port serial event, naming the port whose channel has the payload.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialEventNotice {
+    port: String,
+    channel: String,
+}
+
+/// Emits `event`'s payload on its per-port channel (`<event>:<port>`) only, so a panel bound to
+/// one device does not demultiplex every session's traffic. `event` itself gets a small
+/// `SerialEventNotice`, letting a global listener filter by port without a second payload copy.
+fn emit_serial_event<S: Serialize + Clone>(app: &AppHandle, event: &str, port_name: &str, payload: S) {
+    let channel = format!("{event}:{}", serial_event_channel(port_name));
+    let _ = app.emit(&channel, payload);
+    let _ = app.emit(event, SerialEventNotice { port: port_name.to_string(), channel });
+}
+
+/// Tauri event names only allow alphanumerics and `-/:_`, but port names like
+/// `/dev/cu.usbserial-1420` contain dots.
+fn serial_event_channel(port_name: &str) -> String {
+    port_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-/:_".contains(c) { c } else { '_' })
+        .collect()
+}
+
+fn record_serial_capture(capture: &Mutex<Option<SerialCapture>>, port_name: &str, direction: &str, line: &str) {
+    let Ok(mut lock) = capture.lock() else {
+        return;
+    };
+    let Some(cap) = &mut *lock else {
+        return;
+    };
+    let entry = json!({
+        "ts_ms": unix_ts_ms(),
+        "port": port_name,
+        "direction": direction,
+        "line": line
+    });
+    if writeln!(cap.file, "{}", entry).is_ok() {
+        cap.lines_written += 1;
+    }
+}
+
+fn record_serial_traffic(
+    history: &Mutex<VecDeque<SerialHistoryEntry>>,
+    capture: &Mutex<Option<SerialCapture>>,
+    port_name: &str,
+    direction: &str,
+    line: &str,
+) {
+    if let Ok(mut buf) = history.lock() {
+        let seq = buf.back().map(|e| e.seq + 1).unwrap_or(0);
+        if buf.len() >= SERIAL_HISTORY_CAPACITY {
+            buf.pop_front();
+        }
+        buf.push_back(SerialHistoryEntry {
+            seq,
+            ts_ms: unix_ts_ms(),
+            direction: direction.to_string(),
+            line: line.to_string(),
+        });
+    }
+    record_serial_capture(capture, port_name, direction, line);
+}
+
+fn decode_escaped_bytes(input: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0_u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('0') => out.push(0),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.chars().all(|h| h.is_ascii_hexdigit()) {
+                    return Err(format!("Invalid \\x escape: \\x{hex} (expected two hex digits)"));
+                }
+                out.push(u8::from_str_radix(&hex, 16).unwrap_or(0));
+            }
+            Some(other) => return Err(format!("Unsupported escape sequence: \\{other}")),
+            None => return Err("Trailing backslash in escaped data".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_serial_delimiter(raw: Option<&str>) -> Result<Vec<u8>, String> {
+    let Some(raw) = raw.filter(|s| !s.is_empty()) else {
+        return Ok(b"\n".to_vec());
+    };
+    let delimiter = match raw.trim().to_ascii_lowercase().as_str() {
+        "lf" => b"\n".to_vec(),
+        "cr" => b"\r".to_vec(),
+        "crlf" => b"\r\n".to_vec(),
+        _ => decode_escaped_bytes(raw)?,
+    };
+    if delimiter.is_empty() {
+        return Err("delimiter is empty".to_string());
+    }
+    Ok(delimiter)
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn escape_bytes_for_log(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    out
+}
+
+fn open_serial_stream(port_name: &str, baud: u32) -> Result<SerialStream, String> {
+    tokio_serial::new(port_name, baud)
+        .open_native_async()
+        .map_err(|error| format!("Failed to open serial port {port_name}: {error}"))
+}
+
+async fn serial_request(session: &SerialSession, make: impl FnOnce(SerialReply) -> SerialCommand) -> Result<(), String> {
+    let closed = || format!("Serial session for {} is closed", session.port_name);
+    let (reply_tx, reply_rx) = oneshot::channel();
+    session.cmd_tx.send(make(reply_tx)).map_err(|_| closed())?;
+    reply_rx.await.map_err(|_| closed())?
+}
+
+async fn write_serial_line(app: &AppHandle, session: &SerialSession, line: &str) -> Result<(), String> {
+    let line = line.trim();
+    let bytes = format!("{line}\n").into_bytes();
+    serial_request(session, |reply| SerialCommand::Write { bytes, reply }).await?;
+    record_serial_traffic(&session.history, &session.capture, &session.port_name, "tx", line);
+    emit_serial_line(app, &session.port_name, "tx", line.to_string(), session.legacy_events);
+    Ok(())
+}
+
+fn last_serial_history_seq(session: &SerialSession) -> Option<u64> {
+    session.history.lock().ok().and_then(|buf| buf.back().map(|e| e.seq))
+}
+
+async fn wait_for_serial_rx(session: &SerialSession, after_seq: Option<u64>, needle: &str, timeout: Duration) -> Option<String> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut cursor = after_seq;
+    loop {
+        // Registered before scanning, so a line appended mid-scan still wakes the wait below.
+        let appended = session.rx_notify.notified();
+        if let Ok(buf) = session.history.lock() {
+            let floor = cursor;
+            for entry in buf.iter().filter(|e| floor.map(|c| e.seq > c).unwrap_or(true)) {
+                cursor = Some(entry.seq);
+                if entry.direction == "rx" && entry.line.contains(needle) {
+                    return Some(entry.line.clone());
+                }
+            }
+        }
+        if tokio::time::timeout_at(deadline, appended).await.is_err() {
+            return None;
+        }
+    }
+}
+
+async fn run_serial_macro_steps(
+    app: &AppHandle,
+    session: &SerialSession,
+    steps: &[SerialMacroStep],
+    stop_on_failure: bool,
+) -> SerialMacroResult {
+    let macro_start = std::time::Instant::now();
+    let mut results: Vec<SerialMacroStepResult> = Vec::new();
+    let mut ok = true;
+    let mut error = None;
+
+    for (index, step) in steps.iter().enumerate() {
+        let step_start = tokio::time::Instant::now();
+        let before_seq = last_serial_history_seq(session);
+        if let Err(e) = write_serial_line(app, session, &step.line).await {
+            ok = false;
+            error = Some(format!("step {index}: {e}"));
+            break;
+        }
+        let sent_ts_ms = unix_ts_ms();
+
+        let (matched, response) = match step.expect.as_deref().filter(|s| !s.is_empty()) {
+            Some(needle) => {
+                let timeout = Duration::from_millis(step.timeout_ms.unwrap_or(1000));
+                let response = wait_for_serial_rx(session, before_seq, needle, timeout).await;
+                (Some(response.is_some()), response)
+            }
+            None => (None, None),
+        };
+
+        results.push(SerialMacroStepResult {
+            index,
+            line: step.line.trim().to_string(),
+            sent_ts_ms,
+            matched,
+            response,
+            elapsed_ms: step_start.elapsed().as_millis(),
+        });
+
+        if matched == Some(false) {
+            ok = false;
+            if stop_on_failure {
+                error = Some(format!("step {index}: timed out waiting for {:?}", step.expect.as_deref().unwrap_or("")));
+                break;
+            }
+        }
+
+        // Sleep relative to when this step started so delays don't accumulate write/expect latency.
+        tokio::time::sleep_until(step_start + Duration::from_millis(step.delay_ms)).await;
+    }
+
+    append_desktop_audit_log(
+        "serial.macro",
+        &json!({
+            "port": session.port_name,
+            "steps": steps.len(),
+            "completed": results.len(),
+            "ok": ok,
+            "elapsed_ms": macro_start.elapsed().as_millis() as u64,
+            "error": error
+        }),
+    );
+
+    SerialMacroResult { ok, steps: results, error }
+}
+
+/// Looks up the session for `port` (matching either the name it was opened with or its current
+/// device path). With no port given, the single open session is used.
+fn serial_session_for_port(state: &AppState, port: Option<&str>) -> Result<SerialSession, String> {
+    let lock = state
+        .serial_sessions
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    match port.map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        Some(port) => lock
+            .values()
+            .find(|session| serial_session_matches(session, port))
+            .cloned()
+            .ok_or_else(|| format!("No active serial session for port {port}")),
+        None => {
+            let mut sessions = lock.values();
+            match (sessions.next(), sessions.next()) {
+                (None, _) => Err("No active serial connection".to_string()),
+                (Some(only), None) => Ok(only.clone()),
+                (Some(_), Some(_)) => Err("Multiple serial sessions are open; specify port".to_string()),
+            }
+        }
+    }
+}
+
+fn serial_connection_status(session: &SerialSession) -> ConnectionStatus {
+    let conn_state = session
+        .conn_state
+        .lock()
+        .map(|s| *s)
+        .unwrap_or(SerialConnectionState::Degraded);
+    ConnectionStatus {
+        connected: matches!(conn_state, SerialConnectionState::Connected | SerialConnectionState::Degraded),
+        port_name: Some(session.port_name.clone()),
+        device_path: session.device_path.lock().map(|p| p.clone()).ok(),
+        state: conn_state,
+    }
+}
+
+fn disconnected_status() -> ConnectionStatus {
+    ConnectionStatus {
+        connected: false,
+        port_name: None,
+        device_path: None,
+        state: SerialConnectionState::Closed,
+    }
+}
+
+fn serial_session_matches(session: &SerialSession, port: &str) -> bool {
+    port == session.port_name || session.device_path.lock().map(|p| *p == port).unwrap_or(false)
+}
+
+fn serial_capture_status(capture: &Option<SerialCapture>) -> SerialCaptureStatus {
+    match capture {
+        Some(cap) => SerialCaptureStatus {
+            active: true,
+            path: Some(cap.path.display().to_string()),
+            started_ts_ms: Some(cap.started_ts_ms),
+            lines_written: cap.lines_written,
+        },
+        None => SerialCaptureStatus {
+            active: false,
+            path: None,
+            started_ts_ms: None,
+            lines_written: 0,
+        },
+    }
+}
+
+fn transition_serial_state(
+    app: &AppHandle,
+    conn_state: &Mutex<SerialConnectionState>,
+    port_name: &str,
+    next: SerialConnectionState,
+    reason: Option<String>,
+) {
+    let previous = {
+        let Ok(mut lock) = conn_state.lock() else {
+            return;
+        };
+        let previous = *lock;
+        if previous == next {
+            return;
+        }
+        *lock = next;
+        previous
+    };
+    emit_serial_event(
+        app,
+        SERIAL_STATE_EVENT,
+        port_name,
+        SerialStateEvent {
+            port: port_name.to_string(),
+            state: next,
+            previous,
+            reason,
+            ts_ms: unix_ts_ms(),
+        },
+    );
+}
+
+fn stop_serial_session(session: SerialSession) {
+    let _ = session.cmd_tx.send(SerialCommand::Stop);
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrphanCleanupEntry {
+    pid: u32,
+    name: Option<String>,
+    pid_file: String,
+    /// `terminated`, `not_running`, `not_orchestrator` (PID reused by another program), or `kill_failed`.
+    action: String,
+}
+
+/// `.build` dirs that may hold orchestrator PID files: the app-data install and the repo checkout.
+fn orchestrator_pid_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(data_dir) = app.path().app_data_dir() {
+        dirs.push(data_dir.join(".build"));
+    }
+    if let Ok(repo_root) = find_repo_root() {
+        dirs.push(repo_root.join(".build"));
+    }
+    dirs
+}
+
+fn process_command_line(pid: u32) -> Option<String> {
+    #[cfg(windows)]
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={pid}').CommandLine"),
+        ])
+        .output();
+    #[cfg(not(windows))]
+    let output = Command::new("ps").args(["-p", &pid.to_string(), "-o", "command="]).output();
+
+    let output = output.ok().filter(|o| o.status.success())?;
+    let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!command.is_empty()).then_some(command)
+}
+
+fn terminate_pid(pid: u32) -> bool {
+    #[cfg(windows)]
+    {
+        Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+    #[cfg(not(windows))]
+    {
+        let signal = |sig: &str| {
+            Command::new("kill")
+                .args([sig, &pid.to_string()])
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !signal("-TERM") {
+            return false;
+        }
+        // Give the HTTP server a moment to release its port before escalating.
+        for _ in 0..20 {
+            if process_command_line(pid).is_none() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        signal("-KILL")
+    }
+}
+
+/// What `orchestrator_spawn` does when its preferred port is held by something that is not a
+/// healthy orchestrator.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PortConflictAction {
+    /// Fall back to an ephemeral port (the historical behaviour).
+    #[default]
+    Ephemeral,
+    /// Fail and report the process holding the port.
+    Error,
+    /// Kill the holder if it is a stale orchestrator.py recorded in our PID files and not managed
+    /// by this instance, then take the port. Any other holder (one started by hand, or by another
+    /// app) is reported and left alone, and the orchestrator falls back to an ephemeral port.
+    KillStale,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PortHolder {
+    port: u16,
+    pid: u32,
+    command: Option<String>,
+    /// Command line points at orchestrator.py.
+    orchestrator: bool,
+    /// PID appears in one of our orchestrator PID files.
+    recorded_by_us: bool,
+    /// PID belongs to a process this app instance currently manages.
+    managed: bool,
+}
+
+/// PIDs listening on TCP `port`, via lsof (unix) or netstat (Windows).
+fn listening_pids(port: u16) -> Vec<u32> {
+    #[cfg(windows)]
+    {
+        let Ok(output) = Command::new("netstat").args(["-ano", "-p", "TCP"]).output() else {
+            return Vec::new();
+        };
+        let suffix = format!(":{port}");
+        let mut pids = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let cols = line.split_whitespace().collect::<Vec<_>>();
+                if cols.len() >= 5 && cols[3] == "LISTENING" && cols[1].ends_with(&suffix) {
+                    cols[4].parse::<u32>().ok()
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        pids.dedup();
+        pids
+    }
+    #[cfg(not(windows))]
+    {
+        let Ok(output) = Command::new("lsof")
+            .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-t"])
+            .output()
+        else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse::<u32>().ok())
+            .collect()
+    }
+}
+
+fn recorded_orchestrator_pids(app: &AppHandle) -> Vec<u32> {
+    orchestrator_pid_dirs(app)
+        .into_iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pid"))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .filter_map(|record| record.get("pid").and_then(|p| p.as_u64()))
+        .filter_map(|pid| u32::try_from(pid).ok())
+        .collect()
+}
+
+fn find_port_holders(app: &AppHandle, state: &AppState, port: u16) -> Vec<PortHolder> {
+    let recorded = recorded_orchestrator_pids(app);
+    let managed = state.processes.pids().unwrap_or_default();
+    listening_pids(port)
+        .into_iter()
+        .map(|pid| {
+            let command = process_command_line(pid);
+            PortHolder {
+                port,
+                pid,
+                orchestrator: command.as_deref().is_some_and(|c| c.contains("orchestrator.py")),
+                command,
+                recorded_by_us: recorded.contains(&pid),
+                managed: managed.contains(&pid),
+            }
+        })
+        .collect()
+}
+
+/// Reaps orchestrators recorded in PID files that this app instance does not manage, i.e.
+/// children of a previous run that crashed before stopping them. A PID is only killed if its
+/// command line still points at orchestrator.py, and records whose owning app instance is still
+/// running (another window of the app) are left alone.
+fn cleanup_orphaned_orchestrators(app: &AppHandle, managed_pids: &[u32]) -> Vec<OrphanCleanupEntry> {
+    let mut entries = Vec::new();
+    for dir in orchestrator_pid_dirs(app) {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in read_dir.flatten().map(|e| e.path()) {
+            let is_pid_file = path.extension().is_some_and(|ext| ext == "pid")
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("orchestrator_desktop"));
+            if !is_pid_file {
+                continue;
+            }
+            let record = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+            let Some(pid) = record
+                .as_ref()
+                .and_then(|r| r.get("pid"))
+                .and_then(|p| p.as_u64())
+                .and_then(|p| u32::try_from(p).ok())
+            else {
+                let _ = std::fs::remove_file(&path);
+                continue;
+            };
+            if managed_pids.contains(&pid) {
+                continue;
+            }
+            let owner = record
+                .as_ref()
+                .and_then(|r| r.get("owner_pid"))
+                .and_then(|p| p.as_u64())
+                .and_then(|p| u32::try_from(p).ok());
+            let owner_running =
+                owner.is_some_and(|owner| owner == std::process::id() || app_instance_running(owner));
+            let command = process_command_line(pid);
+            // Ours (possibly spawned after `managed_pids` was taken) or another instance's.
+            if owner_running && command.is_some() {
+                continue;
+            }
+
+            let action = match command {
+                None => "not_running",
+                Some(command) if !command.contains("orchestrator.py") => "not_orchestrator",
+                Some(_) if terminate_pid(pid) => "terminated",
+                Some(_) => "kill_failed",
+            };
+            if action != "kill_failed" {
+                let _ = std::fs::remove_file(&path);
+            }
+            entries.push(OrphanCleanupEntry {
+                pid,
+                name: record
+                    .as_ref()
+                    .and_then(|r| r.get("name"))
+                    .and_then(|n| n.as_str())
+                    .map(str::to_string),
+                pid_file: path.display().to_string(),
+                action: action.to_string(),
+            });
+        }
+    }
+    if !entries.is_empty() {
+        append_desktop_audit_log("orchestrator.orphan_cleanup", &json!({ "entries": entries }));
+    }
+    entries
+}
+
+/// Whether `pid` is a running copy of this app (so a reused PID does not count).
+fn app_instance_running(pid: u32) -> bool {
+    let exe = std::env::current_exe().ok();
+    let exe_name = exe.as_deref().and_then(|p| p.file_name()).and_then(|n| n.to_str());
+    match (process_command_line(pid), exe_name) {
+        (Some(command), Some(exe_name)) => command.contains(exe_name),
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+fn looks_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["key", "token", "secret", "password", "passwd", "auth"]
+        .iter()
+        .any(|needle| name.contains(needle))
+}
+
+/// Masks values of secret-looking env vars (API keys, tokens) before they reach the audit log.
+fn redact_secret_env(env: &HashMap<String, String>) -> Value {
+    let mut keys = env.keys().collect::<Vec<_>>();
+    keys.sort();
+    let map = keys
+        .into_iter()
+        .map(|key| {
+            let value = if looks_secret(key) { "<redacted>".to_string() } else { env[key].clone() };
+            (key.clone(), Value::String(value))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    Value::Object(map)
+}
+
+/// Masks the value after (or inline with) secret-looking flags such as `--api-key`.
+fn redact_secret_args(args: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(args.len());
+    let mut redact_next = false;
+    for arg in args {
+        if redact_next {
+            out.push("<redacted>".to_string());
+            redact_next = false;
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((flag, _)) if flag.starts_with('-') && looks_secret(flag) => {
+                out.push(format!("{flag}=<redacted>"));
+            }
+            None if arg.starts_with('-') && looks_secret(arg) => {
+                out.push(arg.clone());
+                redact_next = true;
+            }
+            _ => out.push(arg.clone()),
+        }
+    }
+    out
+}
+
+fn orchestrator_instance_name(name: Option<String>) -> Result<String, String> {
+    let name = name
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| DEFAULT_ORCHESTRATOR_INSTANCE.to_string());
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid orchestrator instance name {name:?} (use letters, digits, '-' or '_')"));
+    }
+    Ok(name)
+}
+
+fn stopped_orchestrator_status(name: &str) -> OrchestratorProcessStatus {
+    OrchestratorProcessStatus {
+        name: name.to_string(),
+        running: false,
+        pid: None,
+        http_base_url: None,
+        args: None,
+    }
+}
+
+fn managed_orchestrator_status(state: &AppState, name: &str) -> Result<Option<OrchestratorProcessStatus>, String> {
+    Ok(state.processes.status(ORCHESTRATOR_PROCESS_KIND, name)?.map(Into::into))
+}
+
+fn normalize_base_url(raw: &str) -> Result<String, String> {
+    let trimmed = raw.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err("base_url is empty".to_string());
+    }
+    Ok(trimmed.to_string())
+}
+
+fn normalize_local_host(raw: &str) -> Result<IpAddr, String> {
+    let trimmed = raw.trim();
+    let normalized = if trimmed.eq_ignore_ascii_case("localhost") {
+        "127.0.0.1"
+    } else {
+        trimmed
+    };
+    normalized
+        .parse::<IpAddr>()
+        .map_err(|_| format!("http_host must be an IP address (e.g. 127.0.0.1), got: {trimmed}"))
+}
+
+fn pick_free_tcp_port(host: IpAddr, preferred: u16) -> Result<u16, String> {
+    let preferred_addr = SocketAddr::new(host, preferred);
+    if let Ok(listener) = TcpListener::bind(preferred_addr) {
+        drop(listener);
+        return Ok(preferred);
+    }
+
+    let listener = TcpListener::bind(SocketAddr::new(host, 0))
+        .map_err(|e| format!("Failed to bind ephemeral port on {host}: {e}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read ephemeral port: {e}"))?
+        .port();
+    drop(listener);
+    Ok(port)
+}
+
+fn wait_for_listen(target: &ListenTarget, child: &mut Child, timeout: Duration, what: &str) -> Result<(), String> {
+    let start = std::time::Instant::now();
+
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("{what} exited early with status {status}"));
+        }
+
+        let listening = match target {
+            ListenTarget::Tcp(addr) => TcpStream::connect_timeout(addr, Duration::from_millis(200)).is_ok(),
+            #[cfg(unix)]
+            ListenTarget::Unix(path) => std::os::unix::net::UnixStream::connect(path).is_ok(),
+            #[cfg(not(unix))]
+            ListenTarget::Unix(_) => false,
+        };
+        if listening {
+            return Ok(());
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(format!("Timed out waiting for {what} to listen on {target}"));
+        }
+
+        thread::sleep(Duration::from_millis(80));
+    }
+}
+
+/// Tees one of a managed child's pipes into its log file and `event` (e.g. `orchestrator_log`).
+/// Runs until the child closes the pipe.
+fn forward_process_output(
+    app: AppHandle,
+    event: &'static str,
+    instance: String,
+    stream: &'static str,
+    reader: impl Read + Send + 'static,
+    log_file: Arc<Mutex<std::fs::File>>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if let Ok(mut file) = log_file.lock() {
+                let _ = file.write_all(&buf);
+            }
+            let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+            let _ = app.emit(
+                event,
+                OrchestratorLogEvent {
+                    instance: instance.clone(),
+                    stream: stream.to_string(),
+                    line,
+                    ts_ms: unix_ts_ms(),
+                },
+            );
+        }
+    });
+}
+
+fn launch_process(app: &AppHandle, spec: &ProcessSpec) -> Result<Child, String> {
+    if let Some(dir) = spec.log_path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&spec.log_path)
+        .map_err(|e| format!("Failed to open {} log file {}: {e}", spec.kind, spec.log_path.display()))?;
+    let log_file = Arc::new(Mutex::new(log_file));
+
+    let mut child = Command::new(&spec.program)
+        .args(&spec.args)
+        .envs(&spec.env)
+        .current_dir(&spec.work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {e}", spec.kind))?;
+    if let Some(pid_file) = &spec.pid_file {
+        let pid_record = json!({
+            "pid": child.id(),
+            "owner_pid": std::process::id(),
+            "kind": spec.kind,
+            "name": spec.name,
+            "script": spec.args.first(),
+            "started_ts_ms": unix_ts_ms()
+        });
+        if let Err(e) = std::fs::write(pid_file, pid_record.to_string()) {
+            append_desktop_audit_log(
+                "process.pid_file_error",
+                &json!({ "kind": spec.kind, "path": pid_file.display().to_string(), "error": e.to_string() }),
+            );
+        }
+    }
+    // Drain the pipes right away so a chatty child never blocks on a full pipe buffer.
+    if let Some(stdout) = child.stdout.take() {
+        forward_process_output(app.clone(), spec.log_event, spec.name.clone(), "stdout", stdout, log_file.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_process_output(app.clone(), spec.log_event, spec.name.clone(), "stderr", stderr, log_file);
+    }
+    if let Some(target) = &spec.listen {
+        if let Err(e) = wait_for_listen(target, &mut child, spec.listen_timeout, &spec.kind) {
+            let _ = child.kill();
+            let _ = child.wait();
+            if let Some(pid_file) = &spec.pid_file {
+                let _ = std::fs::remove_file(pid_file);
+            }
+            return Err(e);
+        }
+    }
+    Ok(child)
+}
+
+/// Relaunches managed processes that exited on their own, as their restart policy allows.
+fn supervise_processes(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        for exited in state.processes.take_unreported_exits() {
+            report_process_exit(&app, &exited);
+        }
+        for (spec, policy, restarts, exit_code) in state.processes.take_restartable() {
+            thread::sleep(Duration::from_millis(policy.backoff_ms));
+            let mut event = ProcessEvent {
+                kind: spec.kind.clone(),
+                name: spec.name.clone(),
+                event: "restarted".to_string(),
+                pid: None,
+                exit_code,
+                restarts,
+                error: None,
+                ts_ms: unix_ts_ms(),
+            };
+            match launch_process(&app, &spec) {
+                Ok(child) => {
+                    event.pid = Some(child.id());
+                    let key = (spec.kind.clone(), spec.name.clone());
+                    let proc_ = ManagedProcess {
+                        spec,
+                        child,
+                        started: std::time::Instant::now(),
+                        restart_policy: policy,
+                        restarts,
+                        exit_reported: false,
+                    };
+                    match state.processes.lock() {
+                        // A manual spawn took the slot while we were relaunching; it wins.
+                        Ok(mut lock) if !lock.contains_key(&key) => {
+                            lock.insert(key, proc_);
+                        }
+                        _ => proc_.kill(),
+                    }
+                }
+                Err(error) => {
+                    event.event = "restart_failed".to_string();
+                    event.error = Some(error);
+                }
+            }
+            append_desktop_audit_log("process.restart", &json!(event));
+            let _ = app.emit(PROCESS_EVENT, event);
+        }
+    });
+}
+
+/// A managed child that exited without being asked to.
+struct ExitedProcess {
+    spec: ProcessSpec,
+    pid: u32,
+    exit: std::process::ExitStatus,
+    uptime_ms: u128,
+    restarts: u32,
+    will_restart: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrashReport {
+    kind: String,
+    name: String,
+    pid: u32,
+    exit_status: String,
+    exit_code: Option<i32>,
+    /// Terminating signal on unix (e.g. 9 for SIGKILL, 11 for SIGSEGV).
+    signal: Option<i32>,
+    uptime_ms: u128,
+    restarts: u32,
+    will_restart: bool,
+    program: String,
+    /// With secret values redacted, as in the audit log.
+    args: Vec<String>,
+    http_base_url: Option<String>,
+    log_path: String,
+    /// Last `CRASH_REPORT_TAIL_LINES` lines of the child's log.
+    log_tail: Vec<String>,
+    ts_ms: u128,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorCrashedEvent {
+    name: String,
+    pid: u32,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    uptime_ms: u128,
+    will_restart: bool,
+    report_path: Option<String>,
+    /// Final few log lines, enough to show what happened without opening the report.
+    last_lines: Vec<String>,
+    ts_ms: u128,
+}
+
+fn exit_signal(exit: &std::process::ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        exit.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = exit;
+        None
+    }
+}
+
+fn log_tail(path: &Path, lines: usize) -> Vec<String> {
+    let Ok(content) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let content = String::from_utf8_lossy(&content);
+    let all = content.lines().collect::<Vec<_>>();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Writes `logs/crashes/<kind>_<name>_<ts>.json` for an unexpected exit and, for orchestrators,
+/// emits `orchestrator_crashed`.
+fn report_process_exit(app: &AppHandle, exited: &ExitedProcess) {
+    // Give the output forwarders a moment to flush the child's last lines to the log.
+    thread::sleep(Duration::from_millis(250));
+    let spec = &exited.spec;
+    let report = CrashReport {
+        kind: spec.kind.clone(),
+        name: spec.name.clone(),
+        pid: exited.pid,
+        exit_status: exited.exit.to_string(),
+        exit_code: exited.exit.code(),
+        signal: exit_signal(&exited.exit),
+        uptime_ms: exited.uptime_ms,
+        restarts: exited.restarts,
+        will_restart: exited.will_restart,
+        program: spec.program.clone(),
+        args: redact_secret_args(&spec.args),
+        http_base_url: spec.http_base_url.clone(),
+        log_path: spec.log_path.display().to_string(),
+        log_tail: log_tail(&spec.log_path, CRASH_REPORT_TAIL_LINES),
+        ts_ms: unix_ts_ms(),
+    };
+    let written = app_logs_dir(app).and_then(|logs| {
+        let dir = logs.join("crashes");
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        let path = dir.join(format!("{}_{}_{}.json", report.kind, report.name, report.ts_ms));
+        let raw = serde_json::to_string_pretty(&report).map_err(|e| format!("Failed to encode crash report: {e}"))?;
+        std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(path)
+    });
+
+    append_desktop_audit_log(
+        "process.crashed",
+        &json!({
+            "kind": report.kind,
+            "name": report.name,
+            "pid": report.pid,
+            "exit_status": report.exit_status,
+            "will_restart": report.will_restart,
+            "report_path": written.as_ref().ok().map(|p| p.display().to_string()),
+            "report_error": written.as_ref().err()
+        }),
+    );
+    if spec.kind == ORCHESTRATOR_PROCESS_KIND {
+        let event = OrchestratorCrashedEvent {
+            name: report.name.clone(),
+            pid: report.pid,
+            exit_code: report.exit_code,
+            signal: report.signal,
+            uptime_ms: report.uptime_ms,
+            will_restart: report.will_restart,
+            report_path: written.ok().map(|p| p.display().to_string()),
+            last_lines: report.log_tail[report.log_tail.len().saturating_sub(10)..].to_vec(),
+            ts_ms: report.ts_ms,
+        };
+        let _ = app.emit(ORCHESTRATOR_CRASHED_EVENT, event);
+    }
+}
+
+fn orchestrator_log_path(work_dir: &Path, name: &str) -> PathBuf {
+    let log_name = if name == DEFAULT_ORCHESTRATOR_INSTANCE {
+        "orchestrator_desktop.log".to_string()
+    } else {
+        format!("orchestrator_desktop_{name}.log")
+    };
+    work_dir.join(".build").join(log_name)
+}
+
+/// Polls the log file for appended bytes and emits each complete line. Survives truncation and
+/// the file not existing yet, so it can be started before the first spawn.
+async fn follow_orchestrator_log(
+    app: AppHandle,
+    instance: String,
+    path: PathBuf,
+    mut offset: u64,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut tick = tokio::time::interval(Duration::from_millis(500));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut partial = Vec::new();
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = tick.tick() => {}
+        }
+        let Ok(mut file) = std::fs::File::open(&path) else {
+            continue;
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if len < offset {
+            offset = 0;
+            partial.clear();
+        }
+        if len == offset {
+            continue;
+        }
+        let mut chunk = Vec::new();
+        if std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(offset)).is_err()
+            || file.read_to_end(&mut chunk).is_err()
+        {
+            continue;
+        }
+        offset += chunk.len() as u64;
+        partial.extend_from_slice(&chunk);
+        while let Some(pos) = partial.iter().position(|&b| b == b'\n') {
+            let raw = partial.drain(..=pos).collect::<Vec<_>>();
+            let _ = app.emit(
+                ORCHESTRATOR_LOG_FILE_EVENT,
+                OrchestratorLogEvent {
+                    instance: instance.clone(),
+                    stream: "file".to_string(),
+                    line: String::from_utf8_lossy(&raw).trim_end().to_string(),
+                    ts_ms: unix_ts_ms(),
+                },
+            );
+        }
+    }
+}
+
+fn resolve_socket_addrs(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|error| format!("Failed to resolve {host}:{port}: {error}"))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(format!("No addresses found for {host}:{port}"));
+    }
+    Ok(addrs)
+}
+
+/// Splits a node host into whether it uses TLS (`tls://host`) and the bare host. `tcp://` and
+/// no scheme both mean plain TCP.
+fn split_node_scheme(host: &str) -> (bool, &str) {
+    let host = host.trim();
+    match host.strip_prefix("tls://") {
+        Some(bare) => (true, bare),
+        None => (false, host.strip_prefix("tcp://").unwrap_or(host)),
+    }
+}
+
+/// One saved node in `<app data>/node_registry.json`. `host` may be a DNS or mDNS (`*.local`)
+/// name; it is resolved each time the entry is used rather than cached as an IP.
+#[derive(Clone, Serialize, Deserialize)]
+struct NodeRegistryEntry {
+    name: String,
+    host: String,
+    port: u16,
+    /// Alias the orchestrator should use for this node; defaults to `name`.
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Named groups for the `group_*` fleet commands.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+    /// Last time a probe, connection or discovery reached this node.
+    #[serde(default)]
+    last_seen_ts_ms: Option<u128>,
+    /// MANIFEST the node last answered HELLO with.
+    #[serde(default)]
+    manifest: Option<Value>,
+    /// For `tls://` hosts: lowercase hex SHA-256 of the node's (typically self-signed)
+    /// certificate. Without it the certificate must chain to the webpki roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cert_sha256: Option<String>,
+    /// The node expects `AUTH <token>` before HELLO. The token itself is kept in the OS keychain
+    /// under this entry's `host:port`, never in the registry file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    auth: bool,
+    /// Defaults for this node's connect/read/write timeouts; commands may still override them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    read_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    write_timeout_ms: Option<u64>,
+    /// Protocol version from the node's last MANIFEST.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol_version: Option<u64>,
+    /// Round trip of the last `node_ping`, or the smoothed keep-alive round trip of the last
+    /// `node_connect` connection when it was closed, in ms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rtt_ms: Option<f64>,
+    /// Firmware version from the node's last MANIFEST (see `manifest_firmware_version`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    firmware_version: Option<String>,
+}
+
+impl NodeRegistryEntry {
+    /// Whether a node reached at `host:port` (reporting `node_id`, if known) is this entry. The
+    /// node id catches entries saved by mDNS name that a scan reaches by IP.
+    fn matches(&self, host: &str, port: u16, node_id: Option<&str>) -> bool {
+        let saved_id = self
+            .manifest
+            .as_ref()
+            .and_then(|m| m.pointer("/device/node_id"))
+            .and_then(|v| v.as_str());
+        (self.port == port && self.host.trim().eq_ignore_ascii_case(host.trim()))
+            || (node_id.is_some() && saved_id == node_id)
+    }
+}
+
+fn node_registry_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join(NODE_REGISTRY_FILE))
+}
+
+fn load_node_registry(app: &AppHandle) -> Result<Vec<NodeRegistryEntry>, String> {
+    let path = node_registry_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Invalid node registry {}: {e}", path.display()))
+}
+
+/// `load_node_registry` for callers polling it, read from disk only after a registry write.
+fn cached_node_registry(app: &AppHandle, state: &AppState) -> Result<Vec<NodeRegistryEntry>, String> {
+    let mut cache = state
+        .node_registry
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    if let Some(entries) = cache.as_ref() {
+        return Ok(entries.clone());
+    }
+    let entries = load_node_registry(app)?;
+    Ok(cache.insert(entries).clone())
+}
+
+/// What `NodeConnection::open` needs from the registry for one target.
+#[derive(Clone, Default)]
+struct NodeSettings {
+    cert_sha256: Option<String>,
+    auth: bool,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+}
+
+fn cache_node_settings(entries: &[NodeRegistryEntry]) {
+    let settings = entries
+        .iter()
+        .map(|e| {
+            let settings = NodeSettings {
+                cert_sha256: e.cert_sha256.clone(),
+                auth: e.auth,
+                connect_timeout_ms: e.connect_timeout_ms,
+                read_timeout_ms: e.read_timeout_ms,
+                write_timeout_ms: e.write_timeout_ms,
+            };
+            (format!("{}:{}", e.host.trim(), e.port), settings)
+        })
+        .collect();
+    *NODE_SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(settings);
+}
+
+fn node_settings(host: &str, port: u16) -> NodeSettings {
+    NODE_SETTINGS
+        .lock()
+        .ok()
+        .and_then(|settings| settings.as_ref()?.get(&format!("{}:{port}", host.trim())).cloned())
+        .unwrap_or_default()
+}
+
+/// Node socket timeouts: `connect` bounds the TCP connect, `read` each reply, `write` each
+/// request line. Defaults 2s / 3s / 2s.
+#[derive(Clone, Copy)]
+struct NodeTimeouts {
+    connect: Duration,
+    read: Duration,
+    write: Duration,
+}
+
+impl Default for NodeTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(2),
+            read: Duration::from_secs(3),
+            write: Duration::from_secs(2),
+        }
+    }
+}
+
+impl NodeTimeouts {
+    fn uniform(timeout: Duration) -> Self {
+        Self {
+            connect: timeout,
+            read: timeout,
+            write: timeout,
+        }
+    }
+
+    /// Explicit `*_ms` values, else the registry entry's defaults for `host:port`, else the
+    /// built-in ones. Each is clamped to 100 ms..=120 s.
+    fn resolve(host: &str, port: u16, connect_ms: Option<u64>, read_ms: Option<u64>, write_ms: Option<u64>) -> Self {
+        let settings = node_settings(host, port);
+        let defaults = Self::default();
+        let pick = |explicit: Option<u64>, saved: Option<u64>, default: Duration| {
+            explicit
+                .or(saved)
+                .map(|ms| Duration::from_millis(ms.clamp(100, 120_000)))
+                .unwrap_or(default)
+        };
+        Self {
+            connect: pick(connect_ms, settings.connect_timeout_ms, defaults.connect),
+            read: pick(read_ms, settings.read_timeout_ms, defaults.read),
+            write: pick(write_ms, settings.write_timeout_ms, defaults.write),
+        }
+    }
+}
+
+const NODE_KEYCHAIN_SERVICE: &str = "daemon-node-auth";
+
+/// Runs a keychain CLI, feeding `input` on stdin, and returns its trimmed stdout.
+fn run_keychain_tool(program: &str, args: &[&str], input: Option<&str>) -> Result<String, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Keychain unavailable ({program}: {e})"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.unwrap_or("").as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Keychain {program} failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Keychain {program} failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Saves (or with `None`, deletes) the AUTH token for `target` in the OS keychain: the login
+/// keychain via `security` on macOS, the Secret Service via `secret-tool` on Linux. The token is
+/// always passed on stdin, never on the command line where `ps` could show it.
+fn keychain_set_node_token(tokens: &NodeAuthTokens, target: &str, token: Option<&str>) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let result = match token {
+        // `security -i` reads the command from stdin. Its parser splits on whitespace (already
+        // refused in tokens) and treats quotes and backslashes specially.
+        Some(token) if token.contains(['"', '\'', '\\']) => {
+            Err("token must not contain quotes or backslashes on macOS".to_string())
+        }
+        Some(token) => run_keychain_tool(
+            "security",
+            &["-i"],
+            Some(&format!("add-generic-password -U -s {NODE_KEYCHAIN_SERVICE} -a {target} -w {token}\n")),
+        )
+        .and_then(|_| {
+            // `security -i` exits 0 even when the command fails, so read the token back.
+            let stored =
+                run_keychain_tool("security", &["find-generic-password", "-s", NODE_KEYCHAIN_SERVICE, "-a", target, "-w"], None)?;
+            if stored == token {
+                Ok(stored)
+            } else {
+                Err(format!("Keychain did not store the AUTH token for {target}"))
+            }
+        }),
+        None => run_keychain_tool("security", &["delete-generic-password", "-s", NODE_KEYCHAIN_SERVICE, "-a", target], None)
+            .or_else(|e| if e.contains("could not be found") { Ok(String::new()) } else { Err(e) }),
+    };
+    #[cfg(target_os = "linux")]
+    let result = match token {
+        Some(token) => run_keychain_tool(
+            "secret-tool",
+            &["store", &format!("--label=DAEMON node {target}"), "service", NODE_KEYCHAIN_SERVICE, "target", target],
+            Some(token),
+        ),
+        None => run_keychain_tool("secret-tool", &["clear", "service", NODE_KEYCHAIN_SERVICE, "target", target], None),
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let result: Result<String, String> = {
+        let _ = (target, token);
+        Err("No supported keychain on this platform".to_string())
+    };
+    result?;
+    let mut cache = tokens.lock().map_err(|_| "Node token cache lock poisoned".to_string())?;
+    match token {
+        Some(token) => cache.insert(target.to_string(), token.to_string()),
+        None => cache.remove(target),
+    };
+    Ok(())
+}
+
+fn keychain_node_token(tokens: &NodeAuthTokens, target: &str) -> Result<String, String> {
+    if let Some(token) = tokens
+        .lock()
+        .map_err(|_| "Node token cache lock poisoned".to_string())?
+        .get(target)
+        .cloned()
+    {
+        return Ok(token);
+    }
+    #[cfg(target_os = "macos")]
+    let token = run_keychain_tool("security", &["find-generic-password", "-s", NODE_KEYCHAIN_SERVICE, "-a", target, "-w"], None)?;
+    #[cfg(target_os = "linux")]
+    let token = run_keychain_tool("secret-tool", &["lookup", "service", NODE_KEYCHAIN_SERVICE, "target", target], None)?;
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let token: String = Err("No supported keychain on this platform".to_string())?;
+    if token.is_empty() {
+        return Err(format!("No AUTH token for {target} in the keychain"));
+    }
+    tokens
+        .lock()
+        .map_err(|_| "Node token cache lock poisoned".to_string())?
+        .insert(target.to_string(), token.clone());
+    Ok(token)
+}
+
+/// Loads the registry, applies `change` and saves the result, all under `NODE_REGISTRY_LOCK`.
+/// Nothing is written if `change` fails.
+fn update_node_registry<T>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut Vec<NodeRegistryEntry>) -> Result<T, String>,
+) -> Result<T, String> {
+    update_node_registry_if_changed(app, |entries| change(entries).map(|out| (out, true)))
+}
+
+/// Like `update_node_registry`, but `change` also says whether it modified the entries; when it
+/// did not, registry.json is left alone (and is not created if missing).
+fn update_node_registry_if_changed<T>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut Vec<NodeRegistryEntry>) -> Result<(T, bool), String>,
+) -> Result<T, String> {
+    let _guard = NODE_REGISTRY_LOCK.lock().map_err(|_| "Registry lock poisoned".to_string())?;
+    let mut entries = load_node_registry(app)?;
+    let (out, changed) = change(&mut entries)?;
+    if !changed {
+        return Ok(out);
+    }
+    let path = node_registry_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(&entries).map_err(|e| format!("Failed to encode node registry: {e}"))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    cache_node_settings(&entries);
+    if let Some(state) = app.try_state::<AppState>() {
+        *state
+            .node_registry
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())? = None;
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeManifestChangedEvent {
+    /// Registry entry name.
+    name: String,
+    target: String,
+    added_tokens: Vec<String>,
+    removed_tokens: Vec<String>,
+    /// Tokens whose args, safety limits or description changed.
+    changed_tokens: Vec<String>,
+    previous_version: Option<String>,
+    version: Option<String>,
+    ts_ms: u128,
+}
+
+fn manifest_commands(manifest: &Value) -> HashMap<String, Value> {
+    manifest
+        .get("commands")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|cmd| Some((cmd.get("token")?.as_str()?.to_string(), cmd.clone())))
+        .collect()
+}
+
+/// What changed between two manifests of the same node, or `None` if they are identical.
+fn diff_node_manifests(name: &str, target: &str, previous: &Value, current: &Value) -> Option<NodeManifestChangedEvent> {
+    if previous == current {
+        return None;
+    }
+    let (before, after) = (manifest_commands(previous), manifest_commands(current));
+    let sorted = |mut tokens: Vec<String>| {
+        tokens.sort();
+        tokens
+    };
+    let version = |m: &Value| m.pointer("/device/version").and_then(|v| v.as_str()).map(str::to_string);
+    Some(NodeManifestChangedEvent {
+        name: name.to_string(),
+        target: target.to_string(),
+        added_tokens: sorted(after.keys().filter(|t| !before.contains_key(*t)).cloned().collect()),
+        removed_tokens: sorted(before.keys().filter(|t| !after.contains_key(*t)).cloned().collect()),
+        changed_tokens: sorted(
+            after
+                .iter()
+                .filter(|(t, spec)| before.get(*t).is_some_and(|old| old != *spec))
+                .map(|(t, _)| t.clone())
+                .collect(),
+        ),
+        previous_version: version(previous),
+        version: version(current),
+        ts_ms: unix_ts_ms(),
+    })
+}
+
+/// The firmware version a MANIFEST reports: `device.firmware_version`, `firmware.version` or a
+/// string `firmware`, falling back to the schema's required `device.version`.
+fn manifest_firmware_version(manifest: &Value) -> Option<String> {
+    [
+        manifest.pointer("/device/firmware_version"),
+        manifest.pointer("/firmware/version"),
+        manifest.get("firmware"),
+        manifest.pointer("/device/version"),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|v| v.as_str().map(str::trim).filter(|v| !v.is_empty()))
+    .map(str::to_string)
+}
+
+/// Orders versions like `1.10.0` > `1.9.2` > `v1.9`: a leading `v` is ignored, and dot/dash
+/// separated parts compare numerically when both are numbers, else as text.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |v: &str| {
+        v.trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['.', '-', '+'])
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for (x, y) in a.iter().zip(&b) {
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if order.is_ne() {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Newest release in `<releases_dir>/<key>/` for the first key with such a directory. Each
+/// entry there is one release, named by its version (`1.4.0/`, `1.4.0.bin`, `v1.4.0.hex`).
+fn latest_firmware_release(releases_dir: &Path, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        let entries = std::fs::read_dir(releases_dir.join(key)).ok()?;
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = if path.is_dir() { path.file_name() } else { path.file_stem() };
+                name.and_then(|n| n.to_str()).map(str::to_string)
+            })
+            .filter(|name| !name.starts_with('.') && name.chars().any(|c| c.is_ascii_digit()))
+            .max_by(|a, b| compare_versions(a, b))
+    })
+}
+
+/// Saves a measured round trip on the registry entries for `host:port`, writing registry.json only
+/// when a matching entry's value changes. Best-effort.
+fn record_node_rtt(app: &AppHandle, host: &str, port: u16, rtt_ms: f64) {
+    let result = update_node_registry_if_changed(app, |entries| {
+        let mut changed = false;
+        for entry in entries.iter_mut().filter(|e| e.matches(host, port, None)) {
+            changed |= entry.rtt_ms != Some(rtt_ms);
+            entry.rtt_ms = Some(rtt_ms);
+        }
+        Ok(((), changed))
+    });
+    if let Err(error) = result {
+        append_desktop_audit_log("node.registry_update_failed", &json!({ "target": format!("{host}:{port}"), "error": error }));
+    }
+}
+
+/// Stamps `last_seen_ts_ms` (and the manifest, when there is one) on the registry entries for a
+/// node that just answered, emitting `node_manifest_changed` when the manifest differs from the
+/// saved one. Best-effort: unknown nodes are not added (registry.json is only written when an entry
+/// matched) and errors are only logged.
+fn record_node_seen(app: &AppHandle, host: &str, port: u16, summary: Option<&NodeManifestSummary>) {
+    let node_id = summary.and_then(|s| s.node_id.as_deref());
+    let target = format!("{}:{port}", host.trim());
+    let result = update_node_registry_if_changed(app, |entries| {
+        let now = unix_ts_ms();
+        let mut changes = Vec::new();
+        let mut matched = false;
+        for entry in entries.iter_mut().filter(|e| e.matches(host, port, node_id)) {
+            matched = true;
+            entry.last_seen_ts_ms = Some(now);
+            if let Some(summary) = summary {
+                if let Some(previous) = &entry.manifest {
+                    changes.extend(diff_node_manifests(&entry.name, &target, previous, &summary.raw));
+                }
+                entry.manifest = Some(summary.raw.clone());
+                entry.protocol_version = Some(summary.protocol_version);
+                entry.firmware_version = manifest_firmware_version(&summary.raw);
+            }
+        }
+        Ok((changes, matched))
+    });
+    match result {
+        Ok(changes) => {
+            for change in changes {
+                append_desktop_audit_log("node.manifest_changed", &json!(change));
+                let _ = app.emit(NODE_MANIFEST_CHANGED_EVENT, change);
+            }
+        }
+        Err(error) => {
+            append_desktop_audit_log("node.registry_update_failed", &json!({ "host": host, "port": port, "error": error }));
+        }
+    }
+}
+
+fn validate_registry_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) || name.contains('=') {
+        return Err(format!("Invalid node name {name:?}: use a non-empty name without spaces or '='"));
+    }
+    Ok(name.to_string())
+}
+
+/// Turns registry entry names into `alias=ip:port` `--node` values, preferring IPv4 since the
+/// orchestrator cannot use scoped IPv6 link-local addresses.
+fn resolve_registry_nodes(registry: &[NodeRegistryEntry], names: &[String]) -> Result<Vec<String>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let entry = registry
+                .iter()
+                .find(|e| e.name == name.trim())
+                .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+            if entry.host.trim().starts_with(SERIAL_NODE_SCHEME) {
+                return Err(format!("Node {name:?} is attached over serial, which the orchestrator cannot connect to"));
+            }
+            if entry.host.trim().starts_with(BLE_NODE_SCHEME) {
+                return Err(format!("Node {name:?} is reached over BLE, which the orchestrator cannot connect to"));
+            }
+            let (tls, host) = split_node_scheme(&entry.host);
+            if tls {
+                return Err(format!("Node {name:?} uses TLS, which the orchestrator cannot connect to"));
+            }
+            let addrs = resolve_socket_addrs(host, entry.port)?;
+            let addr = addrs.iter().find(|a| a.is_ipv4()).unwrap_or(&addrs[0]);
+            let alias = entry.alias.as_deref().unwrap_or(&entry.name);
+            Ok(format!("{alias}={}:{}", addr.ip(), addr.port()))
+        })
+        .collect()
+}
+
+/// Where `orchestrator_spawn` runs orchestrator.py from.
+struct OrchestratorInstall {
+    script: PathBuf,
+    work_dir: PathBuf,
+    bundled: bool,
+}
+
+/// Prefers the orchestrator shipped in the app bundle (see `bundle.resources` in
+/// tauri.conf.json), so installed builds work outside a checkout; falls back to the repo.
+fn resolve_orchestrator_install(app: &AppHandle) -> Result<OrchestratorInstall, String> {
+    let bundle_error = match extract_bundled_orchestrator(app) {
+        Ok(script) => {
+            let work_dir = script
+                .parent()
+                .and_then(|dir| dir.parent())
+                .map(Path::to_path_buf)
+                .ok_or_else(|| format!("Invalid bundled orchestrator path {}", script.display()))?;
+            return Ok(OrchestratorInstall {
+                script,
+                work_dir,
+                bundled: true,
+            });
+        }
+        Err(error) => error,
+    };
+
+    let repo_root = find_repo_root().map_err(|e| format!("{e} (bundled orchestrator unavailable: {bundle_error})"))?;
+    let script = repo_root.join("orchestrator").join("orchestrator.py");
+    if !script.exists() {
+        return Err(format!("orchestrator.py not found at {}", script.display()));
+    }
+    Ok(OrchestratorInstall {
+        script,
+        work_dir: repo_root,
+        bundled: false,
+    })
+}
+
+/// Copies the bundled orchestrator.py into app-data (resource dirs can be read-only, e.g. inside
+/// a signed .app) and refreshes it whenever the shipped copy changes.
+fn extract_bundled_orchestrator(app: &AppHandle) -> Result<PathBuf, String> {
+    let resource = app
+        .path()
+        .resource_dir()
+        .map_err(|e| format!("Failed to resolve resource dir: {e}"))?
+        .join("orchestrator")
+        .join("orchestrator.py");
+    if !resource.is_file() {
+        return Err(format!("no bundled orchestrator at {}", resource.display()));
+    }
+    let bundled = std::fs::read(&resource).map_err(|e| format!("Failed to read {}: {e}", resource.display()))?;
+
+    let target_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join("orchestrator");
+    std::fs::create_dir_all(&target_dir)
+        .map_err(|e| format!("Failed to create {}: {e}", target_dir.display()))?;
+    let target = target_dir.join("orchestrator.py");
+    if std::fs::read(&target).ok().as_deref() != Some(bundled.as_slice()) {
+        std::fs::write(&target, &bundled).map_err(|e| format!("Failed to write {}: {e}", target.display()))?;
+        append_desktop_audit_log(
+            "orchestrator.bundled_extracted",
+            &json!({ "from": resource.display().to_string(), "to": target.display().to_string() }),
+        );
+    }
+    Ok(target)
+}
+
+fn find_repo_root() -> Result<PathBuf, String> {
+    // Tauri apps often start with a CWD that isn't the repo root. We search upward from:
+    // - current_exe()
+    // - current_dir()
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            candidates.push(dir.to_path_buf());
+        }
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push(cwd);
+    }
+
+    for start in candidates {
+        let mut cur: Option<&Path> = Some(start.as_path());
+        while let Some(dir) = cur {
+            let orch = dir.join("orchestrator").join("orchestrator.py");
+            if orch.exists() {
+                return Ok(dir.to_path_buf());
+            }
+            cur = dir.parent();
+        }
+    }
+
+    Err("Could not locate repo root (expected orchestrator/orchestrator.py). Run the app from the repo, or set VITE_ORCHESTRATOR_BASE_URL and start orchestrator manually.".to_string())
+}
+
+fn resolve_python3() -> String {
+    // GUI apps on macOS might not inherit the interactive shell PATH.
+    for candidate in ["/usr/bin/python3", "/opt/homebrew/bin/python3", "/usr/local/bin/python3"] {
+        if Path::new(candidate).exists() {
+            return candidate.to_string();
+        }
+    }
+    "python3".to_string()
+}
+
+fn resolve_node() -> String {
+    // Same PATH caveat as python3: Finder-launched apps don't see nvm/Homebrew shell setup.
+    for candidate in ["/opt/homebrew/bin/node", "/usr/local/bin/node", "/usr/bin/node"] {
+        if Path::new(candidate).exists() {
+            return candidate.to_string();
+        }
+    }
+    "node".to_string()
+}
+
+fn resolve_esptool() -> Option<String> {
+    for candidate in ["/opt/homebrew/bin/esptool.py", "/usr/local/bin/esptool.py", "/usr/bin/esptool.py"] {
+        if Path::new(candidate).exists() {
+            return Some(candidate.to_string());
+        }
+    }
+    None
+}
+
+fn resolve_avrdude() -> String {
+    for candidate in ["/opt/homebrew/bin/avrdude", "/usr/local/bin/avrdude", "/usr/bin/avrdude"] {
+        if Path::new(candidate).exists() {
+            return candidate.to_string();
+        }
+    }
+    "avrdude".to_string()
+}
+
+/// Builds the flasher invocation for `chip`: esptool for ESP boards, avrdude for AVR Arduinos.
+/// Returns (tool label, program, args).
+fn flash_firmware_command(
+    chip: &str,
+    device_path: &str,
+    file: &Path,
+    baud_rate: Option<u32>,
+    flash_address: Option<&str>,
+) -> Result<(String, String, Vec<String>), String> {
+    let file = file.to_string_lossy().to_string();
+    let chip = chip.trim().to_ascii_lowercase();
+    if chip.starts_with("esp") {
+        // esptool.py may be installed as a script or only as a python module.
+        let (program, mut args) = match resolve_esptool() {
+            Some(script) => (script, Vec::new()),
+            None => (resolve_python3(), vec!["-m".to_string(), "esptool".to_string()]),
+        };
+        let default_address = if chip == "esp8266" { "0x0" } else { "0x10000" };
+        args.extend([
+            "--chip".to_string(),
+            chip.clone(),
+            "--port".to_string(),
+            device_path.to_string(),
+            "--baud".to_string(),
+            baud_rate.unwrap_or(460_800).to_string(),
+            "write_flash".to_string(),
+            flash_address.unwrap_or(default_address).to_string(),
+            file,
+        ]);
+        return Ok(("esptool".to_string(), program, args));
+    }
+
+    let (part, programmer, default_baud) = match chip.as_str() {
+        "uno" | "nano" | "atmega328p" | "m328p" => ("m328p", "arduino", 115_200),
+        "nano-old" => ("m328p", "arduino", 57_600),
+        "mega" | "mega2560" | "atmega2560" | "m2560" => ("m2560", "wiring", 115_200),
+        _ => {
+            return Err(format!(
+                "Unsupported chip {chip} (expected esp32*/esp8266, uno, nano, nano-old, or mega)"
+            ))
+        }
+    };
+    let args = vec![
+        "-p".to_string(),
+        part.to_string(),
+        "-c".to_string(),
+        programmer.to_string(),
+        "-P".to_string(),
+        device_path.to_string(),
+        "-b".to_string(),
+        baud_rate.unwrap_or(default_baud).to_string(),
+        "-D".to_string(),
+        "-U".to_string(),
+        format!("flash:w:{file}:a"),
+    ];
+    Ok(("avrdude".to_string(), resolve_avrdude(), args))
+}
+
+/// Forwards flasher output as `flash_progress` events. Both tools redraw progress with `\r`,
+/// so lines are split on either terminator. Keeps the last few lines for error reporting.
+fn stream_flash_output(app: &AppHandle, port: &str, reader: impl Read, tail: &Mutex<VecDeque<String>>) {
+    let percent_re = Regex::new(r"(\d{1,3})\s*%").ok();
+    let mut reader = BufReader::new(reader);
+    let mut buf = [0_u8; 256];
+    let mut line: Vec<u8> = Vec::new();
+    loop {
+        let size = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => 0,
+            Ok(size) => size,
+        };
+        let eof = size == 0;
+        for &byte in &buf[..size] {
+            if byte != b'\n' && byte != b'\r' {
+                line.push(byte);
+                continue;
+            }
+            emit_flash_line(app, port, &mut line, percent_re.as_ref(), tail);
+        }
+        if eof {
+            emit_flash_line(app, port, &mut line, percent_re.as_ref(), tail);
+            return;
+        }
+    }
+}
+
+fn emit_flash_line(
+    app: &AppHandle,
+    port: &str,
+    line: &mut Vec<u8>,
+    percent_re: Option<&Regex>,
+    tail: &Mutex<VecDeque<String>>,
+) {
+    let text = String::from_utf8_lossy(line).trim().to_string();
+    line.clear();
+    if text.is_empty() {
+        return;
+    }
+    let percent = percent_re
+        .and_then(|re| re.captures(&text))
+        .and_then(|caps| caps[1].parse::<u8>().ok())
+        .filter(|p| *p <= 100);
+    if let Ok(mut tail) = tail.lock() {
+        if tail.len() >= 20 {
+            tail.pop_front();
+        }
+        tail.push_back(text.clone());
+    }
+    emit_flash_progress(app, port, "output", percent, Some(text));
+}
+
+fn emit_flash_progress(app: &AppHandle, port: &str, stage: &str, percent: Option<u8>, line: Option<String>) {
+    emit_serial_event(
+        app,
+        FLASH_PROGRESS_EVENT,
+        port,
+        FlashProgressEvent {
+            port: port.to_string(),
+            stage: stage.to_string(),
+            percent,
+            line,
+            ts_ms: unix_ts_ms(),
+        },
+    );
+}
+
+/// Backend-generated correlation id for calls the frontend did not tag, so every audit entry of a
+/// single user action can still be tied together.
+fn new_correlation_id() -> String {
+    format!("desktop-{}-{}", unix_ts_ms(), CORRELATION_SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+fn correlation_id_or_new(correlation_id: Option<String>) -> String {
+    correlation_id
+        .map(|cid| cid.trim().to_string())
+        .filter(|cid| !cid.is_empty())
+        .unwrap_or_else(new_correlation_id)
+}
+
+fn unix_ts_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn trunc_for_log(input: &str, max_len: usize) -> String {
+    if input.len() <= max_len {
+        return input.to_string();
+    }
+    let cut = input
+        .char_indices()
+        .nth(max_len)
+        .map(|(idx, _)| idx)
+        .unwrap_or(input.len());
+    format!("{}...(truncated)", &input[..cut])
+}
+
+fn append_desktop_audit_log(event: &str, payload: &Value) {
+    let logs_dir = match repo_logs_dir() {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    if std::fs::create_dir_all(&logs_dir).is_err() {
+        return;
+    }
+    let log_path = logs_dir.join("backend_audit.jsonl");
+    let mut file = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+    {
+        Ok(f) => f,
+        Err(_) => return,
+    };
+
+    let line = json!({
+        "ts_ms": unix_ts_ms(),
+        "event": event,
+        "payload": payload
+    });
+    let _ = writeln!(file, "{}", line);
+}
+
+fn openai_api_key() -> Option<String> {
+    // Tauri GUI apps on macOS may not inherit shell env; but if launched via terminal it will.
+    // We keep this minimal: rely on OPENAI_API_KEY existing in the app environment.
+    std::env::var("OPENAI_API_KEY").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn anthropic_api_key() -> Option<String> {
+    std::env::var("ANTHROPIC_API_KEY").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn gemini_api_key() -> Option<String> {
+    ["GEMINI_API_KEY", "GOOGLE_API_KEY"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|s| s.trim().to_string())
+        .find(|s| !s.is_empty())
+}
+
+fn extract_first_text_field(resp: &Value) -> Option<String> {
+    // Best-effort: Responses API returns content under output[].content[].text.
+    // We scan for the first string "text" leaf.
+    fn walk(v: &Value) -> Option<String> {
+        match v {
+            Value::Object(map) => {
+                if let Some(Value::String(s)) = map.get("text") {
+                    if !s.trim().is_empty() {
+                        return Some(s.clone());
+                    }
+                }
+                for (_k, child) in map.iter() {
+                    if let Some(found) = walk(child) {
+                        return Some(found);
+                    }
+                }
+                None
+            }
+            Value::Array(arr) => {
+                for child in arr {
+                    if let Some(found) = walk(child) {
+                        return Some(found);
+                    }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+    walk(resp)
+}
+
+fn extract_output_text(resp: &Value) -> Option<String> {
+    // Prefer well-known Responses API fields.
+    if let Some(Value::String(s)) = resp.get("output_text") {
+        if !s.trim().is_empty() {
+            return Some(s.clone());
+        }
+    }
+    // Fall back to scanning for the first "text" field.
+    extract_first_text_field(resp)
+}
+
+fn clamp_f64(x: f64, lo: f64, hi: f64) -> f64 {
+    if x < lo {
+        lo
+    } else if x > hi {
+        hi
+    } else {
+        x
+    }
+}
+
+fn task_expects_motion(task: &str) -> bool {
+    let t = task.to_ascii_lowercase();
+    let keywords = [
+        "move", "drive", "go ", "forward", "backward", "reverse", "left", "right", "turn", "rotate", "strafe", "spin",
+    ];
+    keywords.iter().any(|k| t.contains(k))
+}
+
+/// Downscales a base64 frame so its longest side is at most `max_dimension` and re-encodes it as
+/// JPEG at `quality`. Frames already within bounds are passed through untouched.
+fn prepare_critic_frame(frame_b64: &str, max_dimension: u32, quality: u8) -> Result<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(frame_b64.trim())
+        .map_err(|e| format!("base64 decode failed: {e}"))?;
+    let img = image::load_from_memory(&bytes).map_err(|e| format!("image decode failed: {e}"))?;
+    if img.width().max(img.height()) <= max_dimension {
+        return Ok(frame_b64.trim().to_string());
+    }
+    let resized = img.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle);
+    let mut out = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality)
+        .encode_image(&resized.to_rgb8())
+        .map_err(|e| format!("JPEG encode failed: {e}"))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(out))
+}
+
+/// Hamming distance (out of 64) at or below which two frame hashes count as the same scene.
+const FRAME_HASH_IDENTICAL_DISTANCE: u32 = 4;
+
+/// 64-bit difference hash: one bit per horizontally adjacent pixel pair of a 9x8 thumbnail.
+/// Insensitive to JPEG noise and small exposure changes, but flips on any real movement.
+fn frame_dhash(frame_b64: &str) -> Result<u64, String> {
+    let px = decode_frame_luma_small(frame_b64, 9, 8)?;
+    let mut hash = 0u64;
+    for row in px.chunks_exact(9) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] < pair[1]);
+        }
+    }
+    Ok(hash)
+}
+
+/// Hash of a whole step: every frame in it must match for the step to count as unchanged.
+fn frames_dhash(frames_b64: &[String]) -> Option<Vec<u64>> {
+    frames_b64.iter().map(|f| frame_dhash(f).ok()).collect()
+}
+
+fn decode_frame_luma_small(frame_b64: &str, w: u32, h: u32) -> Result<Vec<u8>, String> {
+    let b64 = frame_b64.trim();
+    if b64.is_empty() {
+        return Err("empty frame".to_string());
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64)
+        .map_err(|e| format!("base64 decode failed: {e}"))?;
+    let img = image::load_from_memory(&bytes).map_err(|e| format!("image decode failed: {e}"))?;
+    let gray = img.to_luma8();
+    let small = image::imageops::resize(&gray, w, h, image::imageops::FilterType::Triangle);
+    Ok(small.into_raw())
+}
+
+fn mean_abs_diff(a: &[u8], b: &[u8]) -> Result<f64, String> {
+    if a.len() != b.len() || a.is_empty() {
+        return Err("mean_abs_diff: length mismatch".to_string());
+    }
+    let mut acc: u64 = 0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        acc += x.abs_diff(*y) as u64;
+    }
+    let mean = (acc as f64) / (a.len() as f64);
+    Ok(mean / 255.0)
+}
+
+fn compute_motion_score(frames_b64: &[String]) -> Result<f64, String> {
+    let frames: Vec<&str> = frames_b64.iter().map(|s| s.as_str()).filter(|s| !s.trim().is_empty()).collect();
+    if frames.len() < 2 {
+        return Ok(0.0);
+    }
+
+    // Downscale heavily for robustness and speed; this is only a "stationary vs moving" gate.
+    let w = 96;
+    let h = 72;
+    let mut prev = decode_frame_luma_small(frames[0], w, h)?;
+    let mut scores: Vec<f64> = Vec::new();
+    for f in frames.iter().skip(1).take(5) {
+        let cur = decode_frame_luma_small(f, w, h)?;
+        let s = mean_abs_diff(&prev, &cur)?;
+        scores.push(s);
+        prev = cur;
+    }
+    if scores.is_empty() {
+        return Ok(0.0);
+    }
+    let avg = scores.iter().sum::<f64>() / (scores.len() as f64);
+    Ok(avg)
+}
+
+fn repo_logs_dir() -> Result<PathBuf, String> {
+    Ok(find_repo_root()?.join("logs"))
+}
+
+/// Where crash reports, serial captures and critic episodes go: the repo's `logs/` in dev builds
+/// run from a checkout, else the platform app log dir, since installed builds have no repo. Paths
+/// documented as `logs/...` for those are relative to this directory.
+fn app_logs_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if cfg!(debug_assertions) {
+        if let Ok(logs) = repo_logs_dir() {
+            return Ok(logs);
+        }
+    }
+    app.path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve app log dir: {e}"))
+}
+
+fn sanitize_log_file_name(file_name: &str) -> Result<String, String> {
+    let trimmed = file_name.trim();
+    if trimmed.is_empty() {
+        return Err("file_name cannot be empty".to_string());
+    }
+    if trimmed.contains('/') || trimmed.contains('\\') || trimmed.contains("..") {
+        return Err("file_name must be a plain file name under logs/".to_string());
+    }
+    Ok(trimmed.to_string())
+}
+
+#[tauri::command]
+fn write_debug_log(file_name: String, payload: Value) -> Result<(), String> {
+    let safe_name = sanitize_log_file_name(&file_name)?;
+    let logs_dir = repo_logs_dir()?;
+    std::fs::create_dir_all(&logs_dir)
+        .map_err(|e| format!("Failed to create logs directory {}: {e}", logs_dir.display()))?;
+    let path = logs_dir.join(safe_name);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let line = json!({
+        "ts_ms": unix_ts_ms(),
+        "payload": payload
+    });
+    writeln!(file, "{}", line)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(())
+}
+
+#[tauri::command]
+fn read_debug_log(file_name: String, tail_lines: Option<usize>) -> Result<String, String> {
+    let safe_name = sanitize_log_file_name(&file_name)?;
+    let path = repo_logs_dir()?.join(safe_name);
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let limit = tail_lines.unwrap_or(300).max(1);
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(limit);
+    Ok(lines[start..].join("\n"))
+}
+
+fn parse_manifest_summary(manifest: &Value) -> NodeManifestSummary {
+    let device_name = manifest
+        .get("device")
+        .and_then(|d| d.get("name"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let node_id = manifest
+        .get("device")
+        .and_then(|d| d.get("node_id"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let mut tokens: Vec<String> = Vec::new();
+    if let Some(cmds) = manifest.get("commands").and_then(|v| v.as_array()) {
+        for cmd in cmds {
+            if let Some(tok) = cmd.get("token").and_then(|v| v.as_str()) {
+                tokens.push(tok.to_string());
+            }
+        }
+    }
+    tokens.sort();
+    tokens.dedup();
+    NodeManifestSummary {
+        raw: manifest.clone(),
+        device_name,
+        node_id,
+        tokens,
+        issues: validate_node_manifest(manifest),
+        protocol_version: manifest.get("protocol_version").and_then(|v| v.as_u64()).unwrap_or(1),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ManifestKind {
+    Object,
+    Array,
+    String,
+    /// A string of at least one character.
+    Name,
+    Bool,
+    /// An integer of at least 1.
+    PositiveInt,
+    NumberOrNull,
+}
+
+impl ManifestKind {
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            ManifestKind::Object => value.is_object(),
+            ManifestKind::Array => value.is_array(),
+            ManifestKind::String => value.is_string(),
+            ManifestKind::Name => value.as_str().is_some_and(|s| !s.is_empty()),
+            ManifestKind::Bool => value.is_boolean(),
+            ManifestKind::PositiveInt => value.as_u64().is_some_and(|n| n >= 1),
+            ManifestKind::NumberOrNull => value.is_number() || value.is_null(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            ManifestKind::Object => "an object",
+            ManifestKind::Array => "an array",
+            ManifestKind::String => "a string",
+            ManifestKind::Name => "a non-empty string",
+            ManifestKind::Bool => "a boolean",
+            ManifestKind::PositiveInt => "an integer >= 1",
+            ManifestKind::NumberOrNull => "a number or null",
+        }
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[derive(Default)]
+struct ManifestChecker {
+    issues: Vec<ManifestIssue>,
+}
+
+impl ManifestChecker {
+    fn issue(&mut self, path: &str, message: impl Into<String>) {
+        self.issues.push(ManifestIssue {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// `parent.key` if present and of the right kind; reports it as missing (when `required`) or
+    /// mistyped otherwise.
+    fn field<'a>(&mut self, parent: &'a Value, path: &str, key: &str, kind: ManifestKind, required: bool) -> Option<&'a Value> {
+        let field_path = if path.is_empty() { key.to_string() } else { format!("{path}.{key}") };
+        match parent.get(key) {
+            None if required => {
+                self.issue(&field_path, format!("missing required field (expected {})", kind.describe()));
+                None
+            }
+            None => None,
+            Some(value) if kind.accepts(value) => Some(value),
+            Some(value) => {
+                let got = match value.as_str() {
+                    Some("") => "an empty string",
+                    _ => json_type_name(value),
+                };
+                self.issue(&field_path, format!("expected {}, got {got}", kind.describe()));
+                None
+            }
+        }
+    }
+
+    fn one_of(&mut self, value: Option<&Value>, path: &str, allowed: &[&str]) {
+        if let Some(s) = value.and_then(|v| v.as_str()) {
+            if !allowed.contains(&s) {
+                self.issue(path, format!("{s:?} is not one of {}", allowed.join(", ")));
+            }
+        }
+    }
+
+    fn string_array(&mut self, value: Option<&Value>, path: &str) {
+        for (i, item) in value.and_then(|v| v.as_array()).into_iter().flatten().enumerate() {
+            if !item.is_string() {
+                self.issue(&format!("{path}[{i}]"), format!("expected a string, got {}", json_type_name(item)));
+            }
+        }
+    }
+}
+
+const MANIFEST_VALUE_TYPES: &[&str] = &["int", "float", "bool", "string"];
+
+/// Checks a MANIFEST against the DAEMON manifest v0.1 schema (daemon-cli
+/// `schema/daemon.schema.v0_1.json`), reporting every missing or mistyped field rather than just
+/// the first. Unknown fields are allowed, since nodes add their own (e.g. `services`).
+fn validate_node_manifest(manifest: &Value) -> Vec<ManifestIssue> {
+    let mut c = ManifestChecker::default();
+    if !manifest.is_object() {
+        c.issue("", format!("expected the manifest to be an object, got {}", json_type_name(manifest)));
+        return c.issues;
+    }
+    let version = c.field(manifest, "", "daemon_version", ManifestKind::String, true);
+    c.one_of(version, "daemon_version", &["0.1"]);
+    c.field(manifest, "", "protocol_version", ManifestKind::PositiveInt, false);
+
+    if let Some(device) = c.field(manifest, "", "device", ManifestKind::Object, true) {
+        for key in ["name", "version", "node_id"] {
+            c.field(device, "device", key, ManifestKind::Name, true);
+        }
+    }
+
+    if let Some(commands) = c.field(manifest, "", "commands", ManifestKind::Array, true).and_then(|v| v.as_array()) {
+        if commands.is_empty() {
+            c.issue("commands", "must list at least one command");
+        }
+        let mut seen = Vec::new();
+        for (i, command) in commands.iter().enumerate() {
+            let path = format!("commands[{i}]");
+            if !command.is_object() {
+                c.issue(&path, format!("expected an object, got {}", json_type_name(command)));
+                continue;
+            }
+            if let Some(token) = c.field(command, &path, "token", ManifestKind::Name, true).and_then(|v| v.as_str()) {
+                let valid = token.len() <= 32 && token.chars().all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_');
+                if !valid {
+                    c.issue(&format!("{path}.token"), format!("{token:?} must be 1-32 characters of A-Z, 0-9 and _"));
+                } else if seen.contains(&token) {
+                    c.issue(&format!("{path}.token"), format!("duplicate token {token:?}"));
+                }
+                seen.push(token);
+            }
+            c.field(command, &path, "description", ManifestKind::Name, true);
+            if let Some(args) = c.field(command, &path, "args", ManifestKind::Array, true).and_then(|v| v.as_array()) {
+                for (j, arg) in args.iter().enumerate() {
+                    let arg_path = format!("{path}.args[{j}]");
+                    if !arg.is_object() {
+                        c.issue(&arg_path, format!("expected an object, got {}", json_type_name(arg)));
+                        continue;
+                    }
+                    c.field(arg, &arg_path, "name", ManifestKind::Name, true);
+                    let arg_type = c.field(arg, &arg_path, "type", ManifestKind::String, true);
+                    c.one_of(arg_type, &format!("{arg_path}.type"), MANIFEST_VALUE_TYPES);
+                    c.field(arg, &arg_path, "required", ManifestKind::Bool, true);
+                    let min = c.field(arg, &arg_path, "min", ManifestKind::NumberOrNull, false).and_then(|v| v.as_f64());
+                    let max = c.field(arg, &arg_path, "max", ManifestKind::NumberOrNull, false).and_then(|v| v.as_f64());
+                    if let (Some(min), Some(max)) = (min, max) {
+                        if min > max {
+                            c.issue(&arg_path, format!("min {min} is greater than max {max}"));
+                        }
+                    }
+                }
+            }
+            let safety_path = format!("{path}.safety");
+            if let Some(safety) = c.field(command, &path, "safety", ManifestKind::Object, true) {
+                c.field(safety, &safety_path, "rate_limit_hz", ManifestKind::PositiveInt, true);
+                c.field(safety, &safety_path, "watchdog_ms", ManifestKind::PositiveInt, true);
+                c.field(safety, &safety_path, "clamp", ManifestKind::Bool, true);
+            }
+            let nlp_path = format!("{path}.nlp");
+            if let Some(nlp) = c.field(command, &path, "nlp", ManifestKind::Object, true) {
+                for key in ["synonyms", "examples"] {
+                    let list = c.field(nlp, &nlp_path, key, ManifestKind::Array, true);
+                    c.string_array(list, &format!("{nlp_path}.{key}"));
+                }
+            }
+        }
+    }
+
+    if let Some(telemetry) = c.field(manifest, "", "telemetry", ManifestKind::Object, true) {
+        if let Some(keys) = c.field(telemetry, "telemetry", "keys", ManifestKind::Array, true).and_then(|v| v.as_array()) {
+            for (i, key) in keys.iter().enumerate() {
+                let path = format!("telemetry.keys[{i}]");
+                if !key.is_object() {
+                    c.issue(&path, format!("expected an object, got {}", json_type_name(key)));
+                    continue;
+                }
+                c.field(key, &path, "name", ManifestKind::Name, true);
+                let key_type = c.field(key, &path, "type", ManifestKind::String, true);
+                c.one_of(key_type, &format!("{path}.type"), MANIFEST_VALUE_TYPES);
+                c.field(key, &path, "unit", ManifestKind::String, false);
+            }
+        }
+    }
+
+    if let Some(transport) = c.field(manifest, "", "transport", ManifestKind::Object, true) {
+        let transport_type = c.field(transport, "transport", "type", ManifestKind::String, true);
+        c.one_of(transport_type, "transport.type", &["serial-line-v1"]);
+    }
+    c.issues
+}
+
+/// Refreshes the manifest cache from an orchestrator `/status` payload.
+fn cache_status_manifests(cache: &mut HashMap<String, CachedNodeManifest>, status: &Value) {
+    let connected: HashMap<&str, bool> = status
+        .get("nodes")
+        .and_then(|n| n.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|n| {
+            let alias = n.get("alias").and_then(|a| a.as_str())?;
+            Some((alias, n.get("connected").and_then(|c| c.as_bool()).unwrap_or(false)))
+        })
+        .collect();
+    let nodes = status
+        .get("system_manifest")
+        .and_then(|m| m.get("nodes"))
+        .and_then(|n| n.as_array())
+        .into_iter()
+        .flatten();
+    for node in nodes {
+        let Some(alias) = node.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let mut names = vec![alias.to_string()];
+        for key in ["display_name", "node_id"] {
+            if let Some(name) = node.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty()) {
+                names.push(name.to_string());
+            }
+        }
+        cache.insert(
+            alias.to_string(),
+            CachedNodeManifest {
+                alias: alias.to_string(),
+                names,
+                commands: manifest_command_specs(node),
+                connected: connected.get(alias).copied().unwrap_or(false),
+            },
+        );
+    }
+}
+
+fn cache_probed_manifest(cache: &mut HashMap<String, CachedNodeManifest>, target: &str, summary: &NodeManifestSummary) {
+    let alias = summary.device_name.clone().unwrap_or_else(|| target.to_string());
+    let mut names = vec![alias.clone(), target.to_string()];
+    names.extend(summary.node_id.clone());
+    cache.insert(
+        alias.clone(),
+        CachedNodeManifest {
+            alias,
+            names,
+            commands: manifest_command_specs(&summary.raw),
+            connected: true,
+        },
+    );
+}
+
+/// Mirrors `Orchestrator.resolve_node` in orchestrator.py against the cached manifests.
+fn resolve_plan_node<'a>(
+    nodes: &'a [CachedNodeManifest],
+    target: Option<&str>,
+    token: &str,
+) -> Result<(&'a CachedNodeManifest, String), (String, String)> {
+    if let Some(target) = target {
+        return nodes
+            .iter()
+            .find(|node| node.names.iter().any(|name| name == target))
+            .map(|node| (node, token.to_string()))
+            .ok_or_else(|| ("unknown_target".to_string(), format!("Unknown target '{target}'")));
+    }
+    if let Some((prefix, bare)) = token.split_once('.') {
+        let prefix = prefix.to_ascii_lowercase();
+        return nodes
+            .iter()
+            .find(|node| node.names.iter().any(|name| name.to_ascii_lowercase() == prefix))
+            .map(|node| (node, bare.to_string()))
+            .ok_or_else(|| ("unknown_token".to_string(), format!("Unknown namespaced token '{token}'")));
+    }
+    let owners = nodes
+        .iter()
+        .filter(|node| node.command_spec(token).is_some())
+        .collect::<Vec<_>>();
+    match owners.as_slice() {
+        [owner] => Ok((owner, token.to_string())),
+        [] => Err(("unknown_token".to_string(), format!("Token '{token}' not found"))),
+        _ => Err((
+            "ambiguous_token".to_string(),
+            format!("token '{token}' is ambiguous across nodes; explicit target is required"),
+        )),
+    }
+}
+
+
+/// Dry-run of `Orchestrator.validate_plan`: same rules, but every problem is collected instead
+/// of stopping at the first, and unreachable nodes are reported. Nothing is sent to hardware.
+fn validate_plan_against_manifests(plan: &Value, nodes: &[CachedNodeManifest]) -> PlanValidationResult {
+    let mut errors = Vec::new();
+    let mut resolved_nodes = Vec::new();
+    let mut known_nodes = nodes.iter().map(|n| n.alias.clone()).collect::<Vec<_>>();
+    known_nodes.sort();
+    let mut issue = |step: Option<usize>, code: &str, message: String| {
+        errors.push(PlanValidationIssue {
+            step,
+            code: code.to_string(),
+            message,
+        });
+    };
+
+    let steps = plan.get("plan").unwrap_or(plan);
+    let Some(steps) = steps.as_array() else {
+        issue(None, "invalid_plan", "plan must be a list".to_string());
+        return PlanValidationResult {
+            ok: false,
+            step_count: 0,
+            errors,
+            resolved_nodes,
+            known_nodes,
+        };
+    };
+    if nodes.is_empty() {
+        issue(
+            None,
+            "no_manifests",
+            "No node manifests cached; call orchestrator_status or node_probe first".to_string(),
+        );
+    }
+
+    for (index, step) in steps.iter().enumerate() {
+        resolved_nodes.push(None);
+        let at = Some(index);
+        let Some(step) = step.as_object() else {
+            issue(at, "invalid_step", "step must be an object".to_string());
+            continue;
+        };
+        let step_type = step.get("type").and_then(|t| t.as_str()).unwrap_or("").to_ascii_uppercase();
+        if step_type == "STOP" {
+            continue;
+        }
+        if step_type != "RUN" {
+            issue(at, "invalid_type", format!("invalid type {}", step.get("type").unwrap_or(&Value::Null)));
+            continue;
+        }
+
+        let target = match step.get("target") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(t)) if !t.trim().is_empty() => Some(t.as_str()),
+            Some(_) => {
+                issue(at, "invalid_target", "RUN target must be a non-empty string when provided".to_string());
+                continue;
+            }
+        };
+        let Some(token) = step.get("token").and_then(|t| t.as_str()).filter(|t| !t.trim().is_empty()) else {
+            issue(at, "invalid_token", "RUN requires non-empty string token".to_string());
+            continue;
+        };
+        let token = token.to_ascii_uppercase();
+
+        if let Some(duration) = step.get("duration_ms") {
+            if !duration.as_f64().is_some_and(|d| d >= 0.0) {
+                issue(at, "invalid_duration", "duration_ms must be a number >= 0".to_string());
+            }
+        }
+
+        let (node, bare_token) = match resolve_plan_node(nodes, target, &token) {
+            Ok(resolved) => resolved,
+            Err((code, message)) => {
+                issue(at, &code, message);
+                continue;
+            }
+        };
+        resolved_nodes[index] = Some(node.alias.clone());
+        if !node.connected {
+            issue(at, "node_unreachable", format!("node '{}' is not connected", node.alias));
+        }
+        let Some(spec) = node.command_spec(&bare_token) else {
+            issue(at, "unknown_token", format!("token '{bare_token}' not found on node '{}'", node.alias));
+            continue;
+        };
+
+        let args = match step.get("args") {
+            None => Vec::new(),
+            Some(Value::Array(args)) => args.clone(),
+            Some(_) => {
+                issue(at, "invalid_args", "args must be a list".to_string());
+                continue;
+            }
+        };
+        if args.len() != spec.args.len() {
+            issue(
+                at,
+                "arg_count",
+                format!("token '{bare_token}' expects {} args, got {}", spec.args.len(), args.len()),
+            );
+            continue;
+        }
+        for (arg_index, (value, arg_spec)) in args.iter().zip(&spec.args).enumerate() {
+            if let Err((code, message)) = arg_spec.validate(value) {
+                issue(at, &code, format!("{bare_token} arg[{arg_index}]: {message}"));
+            }
+        }
+    }
+
+    PlanValidationResult {
+        ok: errors.is_empty(),
+        step_count: steps.len(),
+        errors,
+        resolved_nodes,
+        known_nodes,
+    }
+}
+
+fn probe_daemon_node(
+    tokens: &NodeAuthTokens,
+    host: &str,
+    port: u16,
+    timeouts: NodeTimeouts,
+) -> Result<NodeManifestSummary, String> {
+    NodeConnection::open_with_timeouts(tokens, host, port, timeouts).map(|conn| conn.summary)
+}
+
+/// Registry host for a node attached over serial; such entries use port 0.
+const SERIAL_NODE_SCHEME: &str = "serial://";
+
+/// Host for a node reached over a BLE UART service, `ble://<peripheral id, address or name>`;
+/// such entries use port 0.
+const BLE_NODE_SCHEME: &str = "ble://";
+
+/// The HELLO/MANIFEST handshake over a serial port. Boards that reset when the port opens miss
+/// the first HELLO, so it is resent every second until `timeout`; other lines (boot banners,
+/// logs) are skipped.
+fn probe_serial_node(path: &str, baud: u32, timeout: Duration) -> Result<NodeManifestSummary, String> {
+    let port = serialport::new(path, baud)
+        .timeout(Duration::from_millis(200))
+        .open()
+        .map_err(|error| format!("Failed to open serial port {path}: {error}"))?;
+    let mut writer = port
+        .try_clone()
+        .map_err(|error| format!("Serial port clone failed: {error}"))?;
+    let mut reader = BufReader::new(port);
+    let deadline = std::time::Instant::now() + timeout;
+    let mut next_hello = std::time::Instant::now();
+    let mut line = Vec::new();
+    while std::time::Instant::now() < deadline {
+        if std::time::Instant::now() >= next_hello {
+            writer
+                .write_all(b"HELLO\n")
+                .and_then(|_| writer.flush())
+                .map_err(|error| format!("Serial write failed: {error}"))?;
+            next_hello = std::time::Instant::now() + Duration::from_secs(1);
+        }
+        match reader.read_until(b'\n', &mut line) {
+            Ok(_) if line.ends_with(b"\n") => {
+                let text = String::from_utf8_lossy(&line).trim().to_string();
+                line.clear();
+                if text.starts_with("MANIFEST ") {
+                    return parse_hello_reply(&text);
+                }
+            }
+            Ok(0) => thread::sleep(Duration::from_millis(50)),
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(e) => return Err(format!("Serial read failed: {e}")),
+        }
+    }
+    Err(format!("No MANIFEST from {path} within {} ms", timeout.as_millis()))
+}
+
+/// Hosts in an IPv4 CIDR block, without the network and broadcast addresses for blocks that
+/// have them. Limited to /20 (4094 hosts) so a typo cannot start a /8 sweep.
+fn subnet_hosts(cidr: &str) -> Result<Vec<std::net::Ipv4Addr>, String> {
+    let (addr, prefix) = cidr.trim().split_once('/').unwrap_or((cidr.trim(), "32"));
+    let addr: std::net::Ipv4Addr = addr.parse().map_err(|_| format!("Invalid IPv4 address in {cidr:?}"))?;
+    let prefix: u32 = prefix.parse().ok().filter(|p| *p <= 32).ok_or_else(|| format!("Invalid prefix in {cidr:?}"))?;
+    if prefix < 20 {
+        return Err(format!("Subnet /{prefix} is too large to scan (at most /20)"));
+    }
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let network = u32::from(addr) & mask;
+    let last = network | !mask;
+    // Skip the network and broadcast addresses, except on /31 and /32 where every address is a host.
+    let (first, last) = if prefix <= 30 { (network + 1, last - 1) } else { (network, last) };
+    Ok((first..=last).map(std::net::Ipv4Addr::from).collect())
+}
+
+/// Newest node protocol this app speaks. Sent with HELLO when connecting, and nodes whose
+/// MANIFEST reports a newer `protocol_version` are refused rather than misread.
+const NODE_PROTOCOL_VERSION: u64 = 1;
+
+fn parse_hello_reply(line: &str) -> Result<NodeManifestSummary, String> {
+    let Some(payload) = line.strip_prefix("MANIFEST ") else {
+        return Err(format!("Expected MANIFEST from HELLO, got: {line}"));
+    };
+    let manifest: Value =
+        serde_json::from_str(payload.trim()).map_err(|error| format!("Invalid MANIFEST JSON: {error}"))?;
+    let summary = parse_manifest_summary(&manifest);
+    if summary.protocol_version > NODE_PROTOCOL_VERSION {
+        return Err(format!(
+            "Node speaks protocol version {}, newer than the version {NODE_PROTOCOL_VERSION} this app understands; update the app",
+            summary.protocol_version
+        ));
+    }
+    Ok(summary)
+}
+
+/// The socket under a `NodeConnection`: plain TCP, TLS for `tls://` hosts, or a BLE UART link
+/// for `ble://` hosts.
+enum NodeStream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+    #[cfg(feature = "ble")]
+    Ble(ble::BleLink),
+}
+
+impl NodeStream {
+    /// Wraps `tcp` in TLS for `server_name`, checking the certificate against `pin` when set and
+    /// the webpki roots otherwise. The handshake runs on the first read or write.
+    fn tls(tcp: TcpStream, server_name: &str, pin: Option<&str>) -> Result<Self, String> {
+        let config = match pin {
+            Some(sha256) => pinned_tls_config(sha256)?,
+            None => {
+                let roots = rustls::RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                };
+                rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                    .with_safe_default_protocol_versions()
+                    .map_err(|e| format!("Failed to configure TLS: {e}"))?
+                    .with_root_certificates(roots)
+                    .with_no_client_auth()
+            }
+        };
+        let name = rustls::pki_types::ServerName::try_from(server_name.trim_matches(['[', ']']).to_string())
+            .map_err(|e| format!("Invalid TLS server name {server_name:?}: {e}"))?;
+        let session = rustls::ClientConnection::new(Arc::new(config), name)
+            .map_err(|e| format!("Failed to start TLS session: {e}"))?;
+        Ok(NodeStream::Tls(Box::new(rustls::StreamOwned::new(session, tcp))))
+    }
+
+    #[cfg(feature = "ble")]
+    fn ble(id: &str, timeouts: NodeTimeouts) -> Result<Self, String> {
+        ble::BleLink::open(id, timeouts.connect, timeouts.write).map(NodeStream::Ble)
+    }
+
+    #[cfg(not(feature = "ble"))]
+    fn ble(id: &str, _timeouts: NodeTimeouts) -> Result<Self, String> {
+        Err(format!("Cannot reach BLE node {id}: this build has no BLE support (rebuild with `--features ble`)"))
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        match self {
+            NodeStream::Plain(tcp) => {
+                let _ = tcp.set_read_timeout(timeout);
+            }
+            NodeStream::Tls(tls) => {
+                let _ = tls.get_ref().set_read_timeout(timeout);
+            }
+            #[cfg(feature = "ble")]
+            NodeStream::Ble(link) => link.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for NodeStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            NodeStream::Plain(tcp) => tcp.read(buf),
+            NodeStream::Tls(tls) => tls.read(buf),
+            #[cfg(feature = "ble")]
+            NodeStream::Ble(link) => link.read(buf),
+        }
+    }
+}
+
+impl Write for NodeStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            NodeStream::Plain(tcp) => tcp.write(buf),
+            NodeStream::Tls(tls) => tls.write(buf),
+            #[cfg(feature = "ble")]
+            NodeStream::Ble(link) => link.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            NodeStream::Plain(tcp) => tcp.flush(),
+            NodeStream::Tls(tls) => tls.flush(),
+            #[cfg(feature = "ble")]
+            NodeStream::Ble(link) => link.flush(),
+        }
+    }
+}
+
+/// An open connection to a node, handshaken with `AUTH` (for registry entries with a token)
+/// and HELLO. The protocol is strictly one reply
+/// line per request line, so callers serialize requests by holding the connection's mutex.
+/// `host` keeps its `tls://` or `ble://` scheme, so targets read `tls://host:port`.
+struct NodeConnection {
+    host: String,
+    port: u16,
+    /// The socket address connected to, or the peripheral for BLE nodes.
+    addr: String,
+    stream: BufReader<NodeStream>,
+    /// Bytes of a line cut short by a read timeout, completed by the next read.
+    pending: Vec<u8>,
+    summary: NodeManifestSummary,
+    connected_ts_ms: u128,
+    last_used: std::time::Instant,
+    requests: u64,
+    /// Set while subscribed: `TELEMETRY` lines are emitted as `node_telemetry` events.
+    telemetry: Option<AppHandle>,
+    /// Identifies the live telemetry pump thread; older pumps see a different id and exit.
+    pump_id: u64,
+    /// Also used when reconnecting; `read` bounds internal requests such as keep-alives.
+    timeouts: NodeTimeouts,
+    /// Where tracked commands are recorded; set for `NodeManager` connections.
+    command_log: Option<SharedNodeCommandLog>,
+    /// Ids of acknowledged commands, oldest first, that a `RESULT` line would complete.
+    awaiting_result: VecDeque<u64>,
+    /// Round trips of keep-alive HELLOs and pings on this connection.
+    rtt: NodeRtt,
+}
+
+#[derive(Clone, Copy, Default)]
+struct NodeRtt {
+    last_ms: Option<f64>,
+    /// Exponentially weighted (1/8 per sample, like TCP's SRTT).
+    smoothed_ms: Option<f64>,
+}
+
+impl NodeRtt {
+    fn record(&mut self, ms: f64) {
+        self.last_ms = Some(ms);
+        self.smoothed_ms = Some(self.smoothed_ms.map_or(ms, |avg| avg + (ms - avg) / 8.0));
+    }
+}
+
+fn elapsed_ms(started: std::time::Instant) -> f64 {
+    (started.elapsed().as_secs_f64() * 100_000.0).round() / 100.0
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeTelemetryEvent {
+    target: String,
+    device_name: Option<String>,
+    /// The payload as a JSON object: parsed directly if it is JSON, else from `key=value` pairs
+    /// with each value parsed as JSON where possible (`12`, `true`) and kept as a string otherwise.
+    data: Value,
+    raw: String,
+    ts_ms: u128,
+}
+
+fn parse_telemetry_payload(payload: &str) -> Value {
+    if let Ok(value) = serde_json::from_str::<Value>(payload) {
+        if value.is_object() {
+            return value;
+        }
+    }
+    Value::Object(
+        payload
+            .split_whitespace()
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| {
+                let parsed = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+                (key.to_string(), parsed)
+            })
+            .collect(),
+    )
+}
+
+impl NodeConnection {
+    fn open(tokens: &NodeAuthTokens, host: &str, port: u16) -> Result<Self, String> {
+        Self::open_with_timeouts(tokens, host, port, NodeTimeouts::resolve(host, port, None, None, None))
+    }
+
+    fn open_with_timeouts(tokens: &NodeAuthTokens, host: &str, port: u16, timeouts: NodeTimeouts) -> Result<Self, String> {
+        let host = host.trim();
+        if host.is_empty() {
+            return Err("host cannot be empty".to_string());
+        }
+        let security = node_settings(host, port);
+        let token = if security.auth {
+            Some(keychain_node_token(tokens, &format!("{host}:{port}"))?)
+        } else {
+            None
+        };
+        if let Some(id) = host.strip_prefix(BLE_NODE_SCHEME) {
+            let stream = NodeStream::ble(id, timeouts)?;
+            return Self::handshake(host, port, id.to_string(), stream, timeouts, token.as_deref());
+        }
+        let (tls, bare_host) = split_node_scheme(host);
+        let mut last_error = None;
+        for addr in resolve_socket_addrs(bare_host, port)? {
+            let tcp = match TcpStream::connect_timeout(&addr, timeouts.connect) {
+                Ok(tcp) => tcp,
+                Err(error) => {
+                    last_error = Some(format!("Connect to {addr} failed: {error}"));
+                    continue;
+                }
+            };
+            let _ = tcp.set_write_timeout(Some(timeouts.write));
+            let _ = tcp.set_nodelay(true);
+            let stream = if tls {
+                NodeStream::tls(tcp, bare_host, security.cert_sha256.as_deref())?
+            } else {
+                NodeStream::Plain(tcp)
+            };
+            return Self::handshake(host, port, addr.to_string(), stream, timeouts, token.as_deref());
+        }
+        Err(last_error.unwrap_or_else(|| "Node connect failed".to_string()))
+    }
+
+    /// AUTH (when there is a token) and HELLO over a freshly opened `stream`.
+    fn handshake(
+        host: &str,
+        port: u16,
+        addr: String,
+        stream: NodeStream,
+        timeouts: NodeTimeouts,
+        token: Option<&str>,
+    ) -> Result<Self, String> {
+        let mut conn = NodeConnection {
+            host: host.to_string(),
+            port,
+            addr,
+            stream: BufReader::new(stream),
+            pending: Vec::new(),
+            summary: NodeManifestSummary {
+                raw: Value::Null,
+                device_name: None,
+                node_id: None,
+                tokens: Vec::new(),
+                issues: Vec::new(),
+                protocol_version: NODE_PROTOCOL_VERSION,
+            },
+            connected_ts_ms: unix_ts_ms(),
+            last_used: std::time::Instant::now(),
+            requests: 0,
+            telemetry: None,
+            pump_id: 0,
+            timeouts,
+            command_log: None,
+            awaiting_result: VecDeque::new(),
+            rtt: NodeRtt::default(),
+        };
+        if let Some(token) = token {
+            let reply = conn.request(&format!("AUTH {token}"), timeouts.read)?;
+            if reply != "OK" {
+                return Err(format!("Node rejected AUTH: {reply}"));
+            }
+        }
+        // Older nodes only accept a bare HELLO and answer anything else with ERR.
+        let mut reply = conn.request(&format!("HELLO protocol_version={NODE_PROTOCOL_VERSION}"), timeouts.read)?;
+        if reply.starts_with("ERR") {
+            reply = conn.request("HELLO", timeouts.read)?;
+        }
+        conn.summary = parse_hello_reply(&reply)?;
+        Ok(conn)
+    }
+
+    /// Sends one line and returns the next reply line, skipping blank lines and passing
+    /// `TELEMETRY` lines to the subscription, if any. After an error the stream may be
+    /// mid-reply, so the connection should be reopened rather than reused.
+    fn request(&mut self, line: &str, timeout: Duration) -> Result<String, String> {
+        self.last_used = std::time::Instant::now();
+        self.requests += 1;
+        self.stream.get_mut().set_read_timeout(Some(timeout));
+        let writer = self.stream.get_mut();
+        writer
+            .write_all(format!("{}\n", line.trim()).as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|error| format!("Node write failed: {error}"))?;
+        loop {
+            let reply = self
+                .read_line()
+                .map_err(|error| match error.kind() {
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => NODE_READ_TIMEOUT_ERROR.to_string(),
+                    _ => format!("Node read failed: {error}"),
+                })?
+                .ok_or_else(|| "Node closed the connection".to_string())?;
+            if !reply.is_empty() && !self.handle_unsolicited(&reply) {
+                return Ok(reply);
+            }
+        }
+    }
+
+    /// Consumes lines a node may send between replies: `TELEMETRY` (forwarded to the
+    /// subscription) and `RESULT` (completes the oldest acknowledged command). Returns whether
+    /// `line` was one of them.
+    fn handle_unsolicited(&mut self, line: &str) -> bool {
+        if let Some(payload) = line.strip_prefix("TELEMETRY ") {
+            self.forward_telemetry(payload);
+        } else if let Some(payload) = line.strip_prefix("RESULT") {
+            if let (Some(id), Some(log)) = (self.awaiting_result.pop_front(), &self.command_log) {
+                let payload = payload.trim();
+                let (ok, data) = match payload.split_once(' ').unwrap_or((payload, "")) {
+                    ("ERR", rest) => (false, rest),
+                    _ => (true, payload),
+                };
+                if let Ok(mut log) = log.lock() {
+                    log.update(id, |record| {
+                        record.state = if ok { NodeCommandState::Completed } else { NodeCommandState::Failed };
+                        record.result = Some(parse_telemetry_payload(data));
+                        record.result_ts_ms = Some(unix_ts_ms());
+                        if !ok {
+                            record.error = Some(payload.to_string());
+                        }
+                    });
+                }
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Replaces this connection with a fresh one to the same node, carrying over the telemetry
+    /// subscription and command log. Returns whether telemetry was resubscribed, in which case
+    /// the caller must start a pump once the lock is released. Commands awaiting `RESULT` are
+    /// abandoned, since the node will not answer them on the new connection.
+    fn reopen(&mut self, tokens: &NodeAuthTokens) -> Result<bool, String> {
+        let mut fresh = NodeConnection::open_with_timeouts(tokens, &self.host, self.port, self.timeouts)?;
+        fresh.pump_id = self.pump_id;
+        fresh.command_log = self.command_log.take();
+        fresh.rtt = self.rtt;
+        let sink = self.telemetry.take();
+        *self = fresh;
+        Ok(sink.is_some_and(|app| self.subscribe(app).is_ok()))
+    }
+
+    /// The next trimmed line, `None` at end of stream. A timeout leaves any partial line in
+    /// `pending` for the next call.
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        loop {
+            if self.stream.read_until(b'\n', &mut self.pending)? == 0 {
+                return Ok(None);
+            }
+            if self.pending.ends_with(b"\n") {
+                let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+                self.pending.clear();
+                return Ok(Some(line));
+            }
+        }
+    }
+
+    /// `request` that expects a bare `OK`.
+    fn expect_ok(&mut self, line: &str) -> Result<(), String> {
+        match self.request(line, self.timeouts.read)? {
+            reply if reply == "OK" => Ok(()),
+            reply => Err(format!("{line} rejected: {reply}")),
+        }
+    }
+
+    fn subscribe(&mut self, app: AppHandle) -> Result<(), String> {
+        self.telemetry = Some(app);
+        let subscribed = self.expect_ok("SUB TELEMETRY");
+        if subscribed.is_err() {
+            self.telemetry = None;
+        }
+        subscribed
+    }
+
+    fn forward_telemetry(&self, payload: &str) {
+        let Some(app) = &self.telemetry else {
+            return;
+        };
+        let payload = payload.trim();
+        let _ = app.emit(
+            NODE_TELEMETRY_EVENT,
+            NodeTelemetryEvent {
+                target: format!("{}:{}", self.host, self.port),
+                device_name: self.summary.device_name.clone(),
+                data: parse_telemetry_payload(payload),
+                raw: payload.to_string(),
+                ts_ms: unix_ts_ms(),
+            },
+        );
+    }
+
+    /// Forwards whatever telemetry arrives within `wait`. Errs only if the connection is gone.
+    fn pump_telemetry(&mut self, wait: Duration) -> Result<(), String> {
+        self.stream.get_mut().set_read_timeout(Some(wait));
+        loop {
+            match self.read_line() {
+                Ok(Some(line)) => {
+                    self.handle_unsolicited(&line);
+                }
+                Ok(None) => return Err("Node closed the connection".to_string()),
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => return Ok(()),
+                Err(e) => return Err(format!("Node read failed: {e}")),
+            }
+        }
+    }
+
+    fn status(&self) -> NodeConnectionStatus {
+        NodeConnectionStatus {
+            target: format!("{}:{}", self.host, self.port),
+            host: self.host.clone(),
+            port: self.port,
+            addr: self.addr.clone(),
+            device_name: self.summary.device_name.clone(),
+            node_id: self.summary.node_id.clone(),
+            tokens: self.summary.tokens.clone(),
+            connected_ts_ms: self.connected_ts_ms,
+            idle_ms: self.last_used.elapsed().as_millis(),
+            requests: self.requests,
+            telemetry: self.telemetry.is_some(),
+            protocol_version: self.summary.protocol_version,
+            state: NodeLinkState::Connected,
+            reconnect_attempt: None,
+            last_error: None,
+            rtt_ms: self.rtt.last_ms,
+            smoothed_rtt_ms: self.rtt.smoothed_ms,
+        }
+    }
+}
+
+/// Reads a subscribed connection between requests, holding its lock for at most 50 ms at a time.
+/// Exits once unsubscribed, superseded by a newer pump, or the connection fails (the next request
+/// then reconnects and resubscribes).
+fn spawn_telemetry_pump(shared: &SharedNodeConnection) {
+    let id = match shared.lock() {
+        Ok(mut conn) => {
+            conn.pump_id += 1;
+            conn.pump_id
+        }
+        Err(_) => return,
+    };
+    let weak = Arc::downgrade(shared);
+    thread::spawn(move || {
+        while let Some(shared) = weak.upgrade() {
+            {
+                let Ok(mut conn) = shared.lock() else {
+                    break;
+                };
+                if conn.pump_id != id || conn.telemetry.is_none() {
+                    break;
+                }
+                if let Err(error) = conn.pump_telemetry(Duration::from_millis(50)) {
+                    let target = format!("{}:{}", conn.host, conn.port);
+                    append_desktop_audit_log("node.telemetry_interrupted", &json!({ "target": target, "error": error }));
+                    break;
+                }
+            }
+            drop(shared);
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeConnectionStatus {
+    target: String,
+    host: String,
+    port: u16,
+    /// Address the connection actually went to, after resolving `host`.
+    addr: String,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    tokens: Vec<String>,
+    connected_ts_ms: u128,
+    idle_ms: u128,
+    requests: u64,
+    /// Subscribed to the node's telemetry stream.
+    telemetry: bool,
+    protocol_version: u64,
+    /// `connected`, or `reconnecting` after the link dropped.
+    state: NodeLinkState,
+    /// Failed reconnect attempts so far, while reconnecting.
+    reconnect_attempt: Option<u32>,
+    last_error: Option<String>,
+    /// Latest and smoothed round trip of keep-alives and pings, in ms.
+    rtt_ms: Option<f64>,
+    smoothed_rtt_ms: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum NodeLinkState {
+    Connected,
+    Reconnecting,
+    Disconnected,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeConnectionStateEvent {
+    target: String,
+    state: NodeLinkState,
+    /// Reconnect attempts made so far (0 when the link first drops).
+    attempt: u32,
+    /// Delay before the next attempt, while reconnecting.
+    next_retry_ms: Option<u128>,
+    error: Option<String>,
+    /// On reconnect: the telemetry subscription was restored.
+    telemetry_resumed: bool,
+    ts_ms: u128,
+}
+
+/// Delay before reconnect attempt `attempt + 1`: 1s doubling per failure, capped at 60s.
+fn node_reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(6)).min(Duration::from_secs(60))
+}
+
+/// A managed connection whose link dropped and could not be reopened straight away. The
+/// supervisor retries it with backoff; it keeps its telemetry subscription and command log.
+struct NodeReconnect {
+    conn: SharedNodeConnection,
+    attempt: u32,
+    next_at: std::time::Instant,
+    last_error: String,
+}
+
+/// Idle connections are sent HELLO this often, so a dead link or a rebooted node is noticed
+/// before the next real command.
+const NODE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+type SharedNodeConnection = Arc<Mutex<NodeConnection>>;
+
+const NODE_READ_TIMEOUT_ERROR: &str = "Node read timed out";
+
+/// How many tracked commands `node_command_status` remembers.
+const NODE_COMMAND_HISTORY: usize = 200;
+
+/// Lifecycle of a command sent over a persistent connection: `pending` until the node replies,
+/// then `acked` (OK), `failed` (ERR, unexpected reply, or I/O error) or `timed_out`. An acked
+/// command becomes `completed` (or `failed`) if the node later sends `RESULT [ERR] <payload>`
+/// for it; nodes that never send RESULT leave their commands `acked`.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum NodeCommandState {
+    Pending,
+    Acked,
+    Completed,
+    Failed,
+    TimedOut,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCommandRecord {
+    id: u64,
+    target: String,
+    line: String,
+    state: NodeCommandState,
+    timeout_ms: u128,
+    sent_ts_ms: u128,
+    replied_ts_ms: Option<u128>,
+    /// The OK/ERR reply line.
+    response: Option<String>,
+    /// Parsed `RESULT` payload, like `node_telemetry` data.
+    result: Option<Value>,
+    result_ts_ms: Option<u128>,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct NodeCommandLog {
+    next_id: u64,
+    records: VecDeque<NodeCommandRecord>,
+}
+
+impl NodeCommandLog {
+    fn begin(&mut self, target: &str, line: &str, timeout: Duration) -> u64 {
+        self.next_id += 1;
+        if self.records.len() >= NODE_COMMAND_HISTORY {
+            self.records.pop_front();
+        }
+        self.records.push_back(NodeCommandRecord {
+            id: self.next_id,
+            target: target.to_string(),
+            line: line.to_string(),
+            state: NodeCommandState::Pending,
+            timeout_ms: timeout.as_millis(),
+            sent_ts_ms: unix_ts_ms(),
+            replied_ts_ms: None,
+            response: None,
+            result: None,
+            result_ts_ms: None,
+            error: None,
+        });
+        self.next_id
+    }
+
+    fn update(&mut self, id: u64, change: impl FnOnce(&mut NodeCommandRecord)) {
+        if let Some(record) = self.records.iter_mut().find(|r| r.id == id) {
+            change(record);
+        }
+    }
+}
+
+type SharedNodeCommandLog = Arc<Mutex<NodeCommandLog>>;
+/// AUTH tokens already read from the keychain, keyed like `NODE_SETTINGS`.
+type NodeAuthTokens = Mutex<HashMap<String, String>>;
+
+/// Long-lived node connections opened by `node_connect`, keyed by `host:port`.
+#[derive(Default)]
+struct NodeManager {
+    connections: Mutex<HashMap<String, SharedNodeConnection>>,
+    commands: SharedNodeCommandLog,
+    /// Dropped connections awaiting a reconnect, keyed like `connections`.
+    reconnecting: Mutex<HashMap<String, NodeReconnect>>,
+    /// For `node_connection_state` events; set by `supervise_node_connections`.
+    app: Mutex<Option<AppHandle>>,
+    /// Used by `NodeConnection::open` and reconnects for nodes with `auth` set.
+    auth_tokens: NodeAuthTokens,
+}
+
+impl NodeManager {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, SharedNodeConnection>>, String> {
+        self.connections.lock().map_err(|_| "State lock poisoned".to_string())
+    }
+
+    /// Opens a connection, replacing (and closing) any existing one to the same target.
+    fn connect(&self, host: &str, port: u16, timeouts: NodeTimeouts) -> Result<NodeConnectionStatus, String> {
+        let mut conn = NodeConnection::open_with_timeouts(&self.auth_tokens, host, port, timeouts)?;
+        conn.command_log = Some(self.commands.clone());
+        let status = conn.status();
+        if let Ok(mut reconnecting) = self.reconnecting.lock() {
+            reconnecting.remove(&status.target);
+        }
+        self.lock()?.insert(status.target.clone(), Arc::new(Mutex::new(conn)));
+        self.emit_state(&status.target, NodeLinkState::Connected, 0, None, None, false);
+        Ok(status)
+    }
+
+    /// Closes `target`, or stops reconnecting it.
+    fn disconnect(&self, target: &str) -> Result<bool, String> {
+        let removed = self.lock()?.remove(target);
+        let open = removed.is_some();
+        let rtt = removed.and_then(|shared| {
+            let conn = shared.try_lock().ok()?;
+            Some((conn.host.clone(), conn.port, conn.rtt.smoothed_ms?))
+        });
+        if let (Some((host, port, rtt_ms)), Some(app)) = (rtt, self.app.lock().ok().and_then(|app| app.clone())) {
+            record_node_rtt(&app, &host, port, rtt_ms);
+        }
+        let reconnecting = self
+            .reconnecting
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?
+            .remove(target)
+            .is_some();
+        if open || reconnecting {
+            self.emit_state(target, NodeLinkState::Disconnected, 0, None, None, false);
+        }
+        Ok(open || reconnecting)
+    }
+
+    fn emit_state(
+        &self,
+        target: &str,
+        state: NodeLinkState,
+        attempt: u32,
+        next_retry: Option<Duration>,
+        error: Option<&str>,
+        telemetry_resumed: bool,
+    ) {
+        let Some(app) = self.app.lock().ok().and_then(|app| app.clone()) else {
+            return;
+        };
+        let _ = app.emit(
+            NODE_CONNECTION_STATE_EVENT,
+            NodeConnectionStateEvent {
+                target: target.to_string(),
+                state,
+                attempt,
+                next_retry_ms: next_retry.map(|d| d.as_millis()),
+                error: error.map(str::to_string),
+                telemetry_resumed,
+                ts_ms: unix_ts_ms(),
+            },
+        );
+    }
+
+    fn get(&self, target: &str) -> Option<SharedNodeConnection> {
+        self.lock().ok()?.get(target).cloned()
+    }
+
+    /// Target of the open connection whose `host:port`, device name or node id is `node`.
+    fn find(&self, node: &str) -> Option<String> {
+        let conns = self.lock().ok()?.iter().map(|(t, c)| (t.clone(), c.clone())).collect::<Vec<_>>();
+        conns.into_iter().find_map(|(target, conn)| {
+            let conn = conn.lock().ok()?;
+            let names = [conn.summary.device_name.as_deref(), conn.summary.node_id.as_deref()];
+            (target == node || names.contains(&Some(node))).then_some(target)
+        })
+    }
+
+    fn summary(&self, target: &str) -> Option<NodeManifestSummary> {
+        let conn = self.get(target)?;
+        let summary = conn.lock().ok()?.summary.clone();
+        Some(summary)
+    }
+
+    /// Open connections and those being reconnected.
+    fn list(&self) -> Result<Vec<NodeConnectionStatus>, String> {
+        let conns = self.lock()?.values().cloned().collect::<Vec<_>>();
+        let mut statuses = conns
+            .iter()
+            .filter_map(|conn| conn.lock().ok().map(|c| c.status()))
+            .collect::<Vec<_>>();
+        let reconnecting = self
+            .reconnecting
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?
+            .values()
+            .map(|r| (r.conn.clone(), r.attempt, r.last_error.clone()))
+            .collect::<Vec<_>>();
+        for (conn, attempt, error) in reconnecting {
+            if let Ok(conn) = conn.lock() {
+                statuses.push(NodeConnectionStatus {
+                    state: NodeLinkState::Reconnecting,
+                    reconnect_attempt: Some(attempt),
+                    last_error: Some(error),
+                    ..conn.status()
+                });
+            }
+        }
+        statuses.sort_by(|a, b| a.target.cmp(&b.target));
+        Ok(statuses)
+    }
+
+    /// Sends `line` over the open connection to `target`; `None` if there is none. A failed
+    /// request is retried once on a fresh connection; if reconnecting fails, the target is dropped.
+    fn request(&self, target: &str, line: &str, timeout: Duration) -> Option<Result<String, String>> {
+        let shared = self.get(target)?;
+        let Ok(mut conn) = shared.lock() else {
+            return Some(Err("State lock poisoned".to_string()));
+        };
+        let first_error = match conn.request(line, timeout) {
+            Ok(reply) => return Some(Ok(reply)),
+            Err(error) => error,
+        };
+        match conn.reopen(&self.auth_tokens) {
+            Ok(resubscribed) => {
+                append_desktop_audit_log("node.reconnected", &json!({ "target": target, "error": first_error }));
+                let reply = conn.request(line, timeout);
+                drop(conn);
+                if resubscribed {
+                    spawn_telemetry_pump(&shared);
+                }
+                Some(reply)
+            }
+            Err(error) => {
+                drop(conn);
+                self.drop_connection(target, &shared, &error);
+                Some(Err(format!("{first_error}; reconnect failed: {error}")))
+            }
+        }
+    }
+
+    /// Sends a tracked command over the open connection to `target`, returning its id and reply;
+    /// `None` if there is no connection. Unlike `request`, a failed command is never resent, since
+    /// the node may already have acted on it: the command is marked failed or timed out and the
+    /// connection is reopened so a late reply cannot be mistaken for the next one.
+    fn send_command(&self, target: &str, line: &str, timeout: Duration) -> Option<(u64, Result<String, String>)> {
+        let shared = self.get(target)?;
+        let update = |id: u64, change: &dyn Fn(&mut NodeCommandRecord)| {
+            if let Ok(mut log) = self.commands.lock() {
+                log.update(id, change);
+            }
+        };
+        let id = match self.commands.lock() {
+            Ok(mut log) => log.begin(target, line, timeout),
+            Err(_) => return Some((0, Err("State lock poisoned".to_string()))),
+        };
+        let Ok(mut conn) = shared.lock() else {
+            update(id, &|record| {
+                record.state = NodeCommandState::Failed;
+                record.error = Some("State lock poisoned".to_string());
+            });
+            return Some((id, Err("State lock poisoned".to_string())));
+        };
+        let reply = conn.request(line, timeout);
+        let now = unix_ts_ms();
+        match &reply {
+            Ok(response) => {
+                let acked = response == "OK" || response.starts_with("OK ");
+                if acked {
+                    conn.awaiting_result.push_back(id);
+                    if conn.awaiting_result.len() > NODE_COMMAND_HISTORY {
+                        conn.awaiting_result.pop_front();
+                    }
+                }
+                update(id, &|record| {
+                    record.replied_ts_ms = Some(now);
+                    record.response = Some(response.clone());
+                    if acked {
+                        record.state = NodeCommandState::Acked;
+                    } else {
+                        record.state = NodeCommandState::Failed;
+                        record.error = Some(response.clone());
+                    }
+                });
+            }
+            Err(error) => {
+                update(id, &|record| {
+                    record.state = if error == NODE_READ_TIMEOUT_ERROR {
+                        NodeCommandState::TimedOut
+                    } else {
+                        NodeCommandState::Failed
+                    };
+                    record.error = Some(error.clone());
+                });
+                match conn.reopen(&self.auth_tokens) {
+                    Ok(resubscribed) => {
+                        append_desktop_audit_log("node.reconnected", &json!({ "target": target, "error": error }));
+                        drop(conn);
+                        if resubscribed {
+                            spawn_telemetry_pump(&shared);
+                        }
+                    }
+                    Err(reconnect_error) => {
+                        drop(conn);
+                        self.drop_connection(target, &shared, &reconnect_error);
+                    }
+                }
+            }
+        }
+        Some((id, reply))
+    }
+
+    /// Pings `target` over its open connection, locking it only for each round trip; `None` if
+    /// there is no connection. A failed round trip ends the run and hands the connection to the
+    /// reconnect queue, since a late reply would be read as the next one.
+    fn ping(&self, target: &str, count: u32, interval: Duration) -> Option<(String, Vec<Option<f64>>)> {
+        let shared = self.get(target)?;
+        let outcome = ping_rounds(count, interval, |line| {
+            let mut conn = shared.lock().map_err(|_| "State lock poisoned".to_string())?;
+            let (timeout, started) = (conn.timeouts.read, std::time::Instant::now());
+            let reply = conn.request(line, timeout)?;
+            let rtt_ms = elapsed_ms(started);
+            conn.rtt.record(rtt_ms);
+            Ok((reply, rtt_ms))
+        });
+        if let Some(error) = &outcome.2 {
+            self.drop_connection(target, &shared, error);
+        }
+        Some((outcome.0, outcome.1))
+    }
+
+    /// Tracked commands, newest first, optionally only those sent to `target`.
+    fn commands(&self, target: Option<&str>) -> Result<Vec<NodeCommandRecord>, String> {
+        let log = self.commands.lock().map_err(|_| "State lock poisoned".to_string())?;
+        Ok(log
+            .records
+            .iter()
+            .rev()
+            .filter(|r| target.is_none_or(|t| r.target == t))
+            .cloned()
+            .collect())
+    }
+
+    /// Subscribes (`app` set) or unsubscribes a connected node's telemetry.
+    fn set_telemetry(&self, target: &str, app: Option<AppHandle>) -> Result<NodeConnectionStatus, String> {
+        let shared = self
+            .get(target)
+            .ok_or_else(|| format!("Node {target} is not connected; call node_connect first"))?;
+        let subscribe = app.is_some();
+        let status = {
+            let mut conn = shared.lock().map_err(|_| "State lock poisoned".to_string())?;
+            match app {
+                Some(app) => conn.subscribe(app)?,
+                None if conn.telemetry.is_some() => {
+                    conn.telemetry = None;
+                    conn.expect_ok("UNSUB TELEMETRY")?;
+                }
+                None => {}
+            }
+            conn.status()
+        };
+        if subscribe {
+            spawn_telemetry_pump(&shared);
+        }
+        Ok(status)
+    }
+
+    /// Moves `target` to the reconnect queue, only if it still maps to `conn` so a concurrent
+    /// `node_connect` wins.
+    fn drop_connection(&self, target: &str, conn: &SharedNodeConnection, error: &str) {
+        let Ok(mut conns) = self.lock() else {
+            return;
+        };
+        if !conns.get(target).is_some_and(|current| Arc::ptr_eq(current, conn)) {
+            return;
+        }
+        conns.remove(target);
+        drop(conns);
+        append_desktop_audit_log("node.connection_lost", &json!({ "target": target, "error": error }));
+        let delay = node_reconnect_delay(0);
+        if let Ok(mut reconnecting) = self.reconnecting.lock() {
+            reconnecting.insert(
+                target.to_string(),
+                NodeReconnect {
+                    conn: conn.clone(),
+                    attempt: 0,
+                    next_at: std::time::Instant::now() + delay,
+                    last_error: error.to_string(),
+                },
+            );
+        }
+        self.emit_state(target, NodeLinkState::Reconnecting, 0, Some(delay), Some(error), false);
+    }
+
+    /// Retries every dropped connection whose backoff has elapsed. A reopened connection goes
+    /// back into service with its telemetry subscription restored; a failed attempt doubles the
+    /// delay (see `node_reconnect_delay`).
+    fn retry_reconnects(&self) {
+        let now = std::time::Instant::now();
+        let due = match self.reconnecting.lock() {
+            Ok(reconnecting) => reconnecting
+                .iter()
+                .filter(|(_, r)| r.next_at <= now)
+                .map(|(target, r)| (target.clone(), r.conn.clone(), r.attempt))
+                .collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+        for (target, shared, attempt) in due {
+            let Ok(mut conn) = shared.lock() else {
+                continue;
+            };
+            let reopened = conn.reopen(&self.auth_tokens);
+            drop(conn);
+            let Ok(mut reconnecting) = self.reconnecting.lock() else {
+                return;
+            };
+            // Superseded by `node_connect` or cancelled by `node_disconnect` meanwhile.
+            if !reconnecting.get(&target).is_some_and(|r| Arc::ptr_eq(&r.conn, &shared)) {
+                continue;
+            }
+            match reopened {
+                Ok(resubscribed) => {
+                    reconnecting.remove(&target);
+                    drop(reconnecting);
+                    if let Ok(mut conns) = self.lock() {
+                        conns.entry(target.clone()).or_insert_with(|| shared.clone());
+                    }
+                    if resubscribed {
+                        spawn_telemetry_pump(&shared);
+                    }
+                    append_desktop_audit_log("node.reconnected", &json!({ "target": target, "attempt": attempt + 1 }));
+                    self.emit_state(&target, NodeLinkState::Connected, attempt + 1, None, None, resubscribed);
+                }
+                Err(error) => {
+                    let delay = node_reconnect_delay(attempt + 1);
+                    if let Some(entry) = reconnecting.get_mut(&target) {
+                        entry.attempt = attempt + 1;
+                        entry.next_at = std::time::Instant::now() + delay;
+                        entry.last_error = error.clone();
+                    }
+                    drop(reconnecting);
+                    self.emit_state(&target, NodeLinkState::Reconnecting, attempt + 1, Some(delay), Some(&error), false);
+                }
+            }
+        }
+    }
+
+    /// Re-handshakes every connection idle for `NODE_KEEPALIVE_INTERVAL`, refreshing its manifest.
+    /// Busy connections are skipped; the request in flight proves they are alive.
+    fn keepalive(&self) {
+        let Ok(conns) = self.lock().map(|c| c.iter().map(|(t, c)| (t.clone(), c.clone())).collect::<Vec<_>>()) else {
+            return;
+        };
+        for (target, shared) in conns {
+            let due = shared
+                .try_lock()
+                .map(|conn| conn.last_used.elapsed() >= NODE_KEEPALIVE_INTERVAL)
+                .unwrap_or(false);
+            if !due {
+                continue;
+            }
+            let Ok((requests, timeout)) = shared.lock().map(|conn| (conn.requests, conn.timeouts.read)) else {
+                continue;
+            };
+            let started = std::time::Instant::now();
+            if let Some(Ok(reply)) = self.request(&target, "HELLO", timeout) {
+                let rtt_ms = elapsed_ms(started);
+                if let (Ok(summary), Ok(mut conn)) = (parse_hello_reply(&reply), shared.lock()) {
+                    conn.summary = summary;
+                    // Keep-alives are not the caller's requests.
+                    conn.requests = requests;
+                    // Kept in memory only; `disconnect` persists the smoothed value.
+                    conn.rtt.record(rtt_ms);
+                }
+            }
+        }
+    }
+}
+
+fn supervise_node_connections(app: AppHandle) {
+    if let Some(state) = app.try_state::<AppState>() {
+        if let Ok(mut handle) = state.nodes.app.lock() {
+            *handle = Some(app.clone());
+        }
+    }
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        if let Some(state) = app.try_state::<AppState>() {
+            state.nodes.keepalive();
+            state.nodes.retry_reconnects();
+        }
+    });
+}
+
+async fn orchestrator_request(
+    http: &HttpState,
+    method: reqwest::Method,
+    orchestrator_base_url: String,
+    path: &str,
+    body: Option<Value>,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Value, String> {
+    let base = normalize_base_url(&orchestrator_base_url)?;
+    let timeout = if path == "/execute_plan" && timeout_ms.is_none() {
+        None
+    } else {
+        request_timeout(http, timeout_ms, |c| c.orchestrator_timeout_ms)?
+    };
+    let started = std::time::Instant::now();
+    let result = send_orchestrator_request(http, method, &base, path, body, correlation_id, timeout).await;
+    record_orchestrator_request(&base, path, started.elapsed(), result.as_ref().err().map(String::as_str));
+    result
+}
+
+async fn send_orchestrator_request(
+    http: &HttpState,
+    method: reqwest::Method,
+    base: &str,
+    path: &str,
+    body: Option<Value>,
+    correlation_id: Option<String>,
+    timeout: Option<Duration>,
+) -> Result<Value, String> {
+    let policy = orchestrator_retry_policy(http)?;
+    let (client, prefix) = orchestrator_endpoint(http, base)?;
+    let url = format!("{prefix}{path}");
+    let idempotent = method == reqwest::Method::GET;
+    let max_attempts = policy.max_attempts.max(1);
+    let correlation_id = correlation_id_or_new(correlation_id);
+
+    append_desktop_audit_log(
+        "orchestrator.request",
+        &json!({
+            "method": method.to_string(),
+            "url": url.clone(),
+            "body": body,
+            "correlation_id": correlation_id
+        }),
+    );
+
+    let mut backoff = Duration::from_millis(policy.initial_backoff_ms);
+    let mut attempt = 1;
+    let response = loop {
+        let request = client
+            .request(method.clone(), &url)
+            .header("X-Correlation-Id", &correlation_id);
+        let request = with_endpoint_auth(http, request, base)?;
+        let request = match timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+        let request = if let Some(payload) = body.as_ref() {
+            request.json(payload)
+        } else {
+            request
+        };
+
+        let (retry_reason, outcome) = match request.send().await {
+            Ok(response) if idempotent && policy.retry_on_status.contains(&response.status().as_u16()) => {
+                (Some(format!("HTTP {}", response.status().as_u16())), Ok(response))
+            }
+            Ok(response) => (None, Ok(response)),
+            Err(error) => {
+                let msg = match timeout {
+                    Some(timeout) if error.is_timeout() => {
+                        format!("{method} {url} failed: timed out after {}ms", timeout.as_millis())
+                    }
+                    _ => format!("{method} {url} failed: network error: {error}"),
+                };
+                let safe_to_resend = idempotent || error.is_connect();
+                (safe_to_resend.then(|| error.to_string()), Err(msg))
+            }
+        };
+        match retry_reason {
+            Some(reason) if attempt < max_attempts => {
+                append_desktop_audit_log(
+                    "orchestrator.retry",
+                    &json!({
+                        "method": method.to_string(),
+                        "url": url.clone(),
+                        "attempt": attempt,
+                        "reason": reason,
+                        "backoff_ms": backoff.as_millis(),
+                        "correlation_id": correlation_id
+                    }),
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_millis(policy.max_backoff_ms));
+                attempt += 1;
+            }
+            _ => break outcome,
+        }
+    };
+    let response = response.map_err(|msg| {
+        append_desktop_audit_log(
+            "orchestrator.network_error",
+            &json!({
+                "method": method.to_string(),
+                "url": url.clone(),
+                "error": msg,
+                "attempts": attempt,
+                "correlation_id": correlation_id
+            }),
+        );
+        msg
+    })?;
+
+    let status = response.status();
+    let response_text = response
+        .text()
+        .await
+        .map_err(|error| format!("{method} {url} failed: could not read response body: {error}"))?;
+
+    append_desktop_audit_log(
+        "orchestrator.response",
+        &json!({
+            "method": method.to_string(),
+            "url": url.clone(),
+            "status": status.as_u16(),
+            "body": trunc_for_log(&response_text, 8000),
+            "correlation_id": correlation_id
+        }),
+    );
+
+    if !status.is_success() {
+        return Err(format!(
+            "{method} {url} failed: HTTP {} body={}",
+            status.as_u16(),
+            response_text
+        ));
+    }
+
+    serde_json::from_str::<Value>(&response_text).map_err(|error| {
+        format!("{method} {url} failed: invalid JSON response: {error}; body={response_text}")
+    })
+}
+
+async fn vision_request(
+    http: &HttpState,
+    method: reqwest::Method,
+    vision_base_url: String,
+    path: &str,
+    body: Option<Value>,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Value, String> {
+    let base = normalize_base_url(&vision_base_url)?;
+    let timeout = request_timeout(http, timeout_ms, |c| c.vision_timeout_ms)?;
+    let url = format!("{base}{path}");
+    let client = endpoint_client(http, &base)?;
+    let request_body = body.clone();
+    let correlation_id = correlation_id_or_new(correlation_id);
+
+    let request = client
+        .request(method.clone(), &url)
+        .header("X-Correlation-Id", &correlation_id);
+    let request = with_endpoint_auth(http, request, &base)?;
+    let request = match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    };
+    let request = if let Some(payload) = body {
+        request.json(&payload)
+    } else {
+        request
+    };
+
+    append_desktop_audit_log(
+        "vision.request",
+        &json!({
+            "method": method.to_string(),
+            "url": url.clone(),
+            "body": request_body,
+            "correlation_id": correlation_id
+        }),
+    );
+
+    let response = request.send().await.map_err(|error| {
+        let msg = match timeout {
+            Some(timeout) if error.is_timeout() => {
+                format!("{method} {url} failed: timed out after {}ms", timeout.as_millis())
+            }
+            _ => format!("{method} {url} failed: network error: {error}"),
+        };
+        append_desktop_audit_log(
+            "vision.network_error",
+            &json!({
+                "method": method.to_string(),
+                "url": url.clone(),
+                "error": msg,
+                "correlation_id": correlation_id
+            }),
+        );
+        msg
+    })?;
+
+    let status = response.status();
+    let response_text = response
+        .text()
+        .await
+        .map_err(|error| format!("{method} {url} failed: could not read response body: {error}"))?;
+
+    append_desktop_audit_log(
+        "vision.response",
+        &json!({
+            "method": method.to_string(),
+            "url": url.clone(),
+            "status": status.as_u16(),
+            "body": trunc_for_log(&response_text, 8000),
+            "correlation_id": correlation_id
+        }),
+    );
+
+    if !status.is_success() {
+        return Err(format!(
+            "{method} {url} failed: HTTP {} body={}",
+            status.as_u16(),
+            trunc_for_log(&response_text, 3000),
+        ));
+    }
+
+    serde_json::from_str::<Value>(&response_text).map_err(|error| {
+        format!("{method} {url} failed: invalid JSON response: {error}; body={response_text}")
+    })
+}
+
+/// Built-in critic system prompt. `{robot_description}` and `{task}` are filled in per step.
+// Keep it strict and conservative: fail closed if uncertain.
+// We do not request hidden reasoning; we only need the structured fields.
+const BUILTIN_CRITIC_PROMPT: &str = "You are a Vision-Language Reward Critic for {robot_description}.\n\
+Your job: evaluate the current camera frame against the user's task goal and produce a reward signal.\n\
+\n\
+You may be given multiple frames (ordered oldest -> newest). Use them to infer motion and progress.\n\
+\n\
+Hard constraints:\n\
+- If the robot is not clearly visible: success=false.\n\
+- If there is little/no visible motion across frames (or motion is ambiguous): success=false.\n\
+\n\
+Output rules:\n\
+- Return ONLY strict JSON that matches the provided schema.\n\
+- Be conservative: if uncertain, success=false and success_confidence<=0.5.\n\
+- Never claim success unless the goal is visually confirmed in the frame.\n\
+\n\
+Progress guidance:\n\
+- If you can see the robot moved in the intended direction between frames, reward should be positive (even if not yet complete).\n\
+- If you can see the robot moved in the wrong direction or did the opposite action, reward should be negative.\n\
+\n\
+Reward rules:\n\
+- reward in [-1.0, 1.0].\n\
+- +1.0 only when the task is clearly completed.\n\
+- 0.0 when no clear progress or unclear.\n\
+- negative when the robot regresses, interacts with the wrong object, or is unsafe.\n\
+\n\
+Safety rules:\n\
+- If imminent collision risk, falling risk, or leaving camera view: critical_failure=true.\n\
+\n\
+Recovery:\n\
+- If the task is not yet complete, set suggested_correction to one short imperative action that would help (e.g. \"turn left 30 degrees and drive forward\"); otherwise an empty string.\n\
+\n\
+TASK: {task}\n";
+
+fn build_critic_system_prompt(template: &str, task: &str, robot_description: &str) -> String {
+    template
+        .replace("{robot_description}", robot_description)
+        .replace("{task}", task)
+}
+
+fn critic_prompts_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join(CRITIC_PROMPTS_DIR))
+}
+
+fn validate_prompt_template_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("Template name must be non-empty and contain only letters, digits, '-' and '_'".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Resolves a template by name: `<app data>/critic_prompts/<name>.txt`, or the built-in prompt
+/// for `default` when no file overrides it.
+fn load_critic_prompt_template(app: &AppHandle, name: &str) -> Result<String, String> {
+    let name = validate_prompt_template_name(name)?;
+    let path = critic_prompts_dir(app)?.join(format!("{name}.txt"));
+    if path.exists() {
+        return std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()));
+    }
+    if name == DEFAULT_CRITIC_PROMPT_TEMPLATE {
+        return Ok(BUILTIN_CRITIC_PROMPT.to_string());
+    }
+    Err(format!("Unknown critic prompt template: {name}"))
+}
+
+/// How many of a step's frames reach the model, which ones, and how each is labeled.
+#[derive(Clone)]
+struct CriticFramePolicy {
+    max_frames: usize,
+    selection: CriticFrameSelection,
+    /// `{index}` (position among sent frames), `{count}` and `{source_index}` (position in the
+    /// step's frames) are substituted.
+    caption_format: String,
+}
+
+impl Default for CriticFramePolicy {
+    fn default() -> Self {
+        Self {
+            max_frames: 6,
+            selection: CriticFrameSelection::Recent,
+            caption_format: "frame_t{index}".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CriticFrameSelection {
+    /// The newest `max_frames`.
+    Recent,
+    /// `max_frames` spread across the whole window, always including the oldest and newest.
+    EvenlySpaced,
+}
+
+impl CriticFramePolicy {
+    /// Indices (ascending) of the frames to send out of `len`.
+    fn select(&self, len: usize) -> Vec<usize> {
+        let n = self.max_frames.min(len);
+        match self.selection {
+            _ if n == len => (0..len).collect(),
+            CriticFrameSelection::Recent => (len - n..len).collect(),
+            CriticFrameSelection::EvenlySpaced if n == 1 => vec![len - 1],
+            CriticFrameSelection::EvenlySpaced => {
+                (0..n).map(|i| (i * (len - 1) + (n - 1) / 2) / (n - 1)).collect()
+            }
+        }
+    }
+
+    fn caption(&self, index: usize, count: usize, source_index: usize) -> String {
+        self.caption_format
+            .replace("{index}", &index.to_string())
+            .replace("{count}", &count.to_string())
+            .replace("{source_index}", &source_index.to_string())
+    }
+}
+
+/// One critic evaluation, already reduced to what every backend needs.
+struct CriticRequest<'a> {
+    http: &'a HttpState,
+    model: &'a str,
+    task: &'a str,
+    system_prompt: String,
+    user_text: String,
+    /// Base64 JPEGs, oldest first; 1..=`CriticFramePolicy::max_frames` entries.
+    frames: Vec<String>,
+    /// Text label sent just before each frame, e.g. `frame_t0`.
+    frame_captions: Vec<String>,
+    /// JSON schema the output object must match.
+    schema: Value,
+    limits: CriticRequestLimits,
+    correlation_id: Option<&'a str>,
+}
+
+/// Retries for critic API calls on 429, 5xx and connection failures, so a transient rate limit
+/// does not fail the step (and reset the success streak).
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticRetryPolicy {
+    max_attempts: u32,
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+}
+
+impl Default for CriticRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8_000,
+        }
+    }
+}
+
+fn critic_retry_policy(http: &HttpState) -> Result<CriticRetryPolicy, String> {
+    http.critic_retry
+        .lock()
+        .map(|p| p.clone().unwrap_or_default())
+        .map_err(|_| "Retry policy lock poisoned".to_string())
+}
+
+/// Random-enough delay in `[backoff / 2, backoff]` so parallel clients do not retry in lockstep.
+fn jittered(backoff: Duration) -> Duration {
+    let half = backoff.as_millis() as u64 / 2;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    Duration::from_millis(half + nanos % (half + 1))
+}
+
+/// Marks critic errors caused by `CriticRequestLimits::timeout`; see `CriticErrorKind`.
+const CRITIC_TIMEOUT_ERROR: &str = "critic request timed out";
+
+/// Sends a critic API request under `critic_retry_policy`, each attempt bounded by the request's
+/// timeout. A `Retry-After` in seconds from the server replaces the computed delay (capped at a
+/// minute). The final response is returned as-is, whatever its status, so callers keep their own
+/// error reporting.
+async fn send_critic_request(
+    provider: &str,
+    request: reqwest::RequestBuilder,
+    critic_request: &CriticRequest<'_>,
+) -> Result<reqwest::Response, String> {
+    let (timeout, correlation_id) = (critic_request.limits.timeout, critic_request.correlation_id);
+    let request = request.timeout(timeout);
+    let policy = critic_retry_policy(critic_request.http)?;
+    let max_attempts = policy.max_attempts.max(1);
+    let mut backoff = Duration::from_millis(policy.initial_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        let attempt_request = request
+            .try_clone()
+            .ok_or_else(|| format!("{provider} request body cannot be resent"))?;
+        let (reason, retry_after) = match attempt_request.send().await {
+            Ok(resp) if attempt < max_attempts && (resp.status().as_u16() == 429 || resp.status().is_server_error()) => {
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                (format!("HTTP {}", resp.status().as_u16()), retry_after)
+            }
+            Ok(resp) => return Ok(resp),
+            Err(e) if attempt < max_attempts && (e.is_connect() || e.is_timeout()) => (e.to_string(), None),
+            Err(e) if e.is_timeout() => {
+                append_desktop_audit_log(
+                    "critic.timeout",
+                    &json!({ "provider": provider, "timeout_ms": timeout.as_millis(), "attempts": attempt, "cid": correlation_id }),
+                );
+                return Err(format!(
+                    "{provider} {CRITIC_TIMEOUT_ERROR} after {} ms ({attempt} attempt(s))",
+                    timeout.as_millis()
+                ));
+            }
+            Err(e) => return Err(format!("{provider} request failed after {attempt} attempt(s): {e}")),
+        };
+        let delay = match retry_after {
+            Some(retry_after) => retry_after.min(Duration::from_secs(60)),
+            None => jittered(backoff),
+        };
+        append_desktop_audit_log(
+            "critic.retry",
+            &json!({
+                "provider": provider,
+                "attempt": attempt,
+                "reason": reason,
+                "delay_ms": delay.as_millis(),
+                "retry_after": retry_after.is_some(),
+                "cid": correlation_id
+            }),
+        );
+        tokio::time::sleep(delay).await;
+        backoff = (backoff * 2).min(Duration::from_millis(policy.max_backoff_ms));
+        attempt += 1;
+    }
+}
+
+/// A backend's answer: the object matching the schema plus token usage, when reported.
+struct CriticEvaluation {
+    output: Value,
+    usage: Option<TokenUsage>,
+}
+
+type CriticFuture<'a> = Pin<Box<dyn Future<Output = Result<CriticEvaluation, String>> + Send + 'a>>;
+
+/// Reads `<input_key>`/`<output_key>` token counts from a provider's usage object.
+/// `cached_pointer` is a JSON pointer into the usage object for the cached-prompt token count.
+fn parse_token_usage(usage: Option<&Value>, input_key: &str, output_key: &str, cached_pointer: &str) -> Option<TokenUsage> {
+    let usage = usage?;
+    Some(TokenUsage {
+        input_tokens: usage.get(input_key)?.as_u64()?,
+        output_tokens: usage.get(output_key).and_then(|v| v.as_u64()).unwrap_or(0),
+        cached_input_tokens: usage.pointer(cached_pointer).and_then(|v| v.as_u64()).unwrap_or(0),
+    })
+}
+
+/// Anthropic reports cache reads and writes separately from `input_tokens`; fold them back in so
+/// `input_tokens` means the whole prompt, as for the other providers.
+fn parse_anthropic_usage(usage: Option<&Value>) -> Option<TokenUsage> {
+    let usage = usage?;
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let cache_read = count("cache_read_input_tokens");
+    Some(TokenUsage {
+        input_tokens: usage.get("input_tokens")?.as_u64()? + cache_read + count("cache_creation_input_tokens"),
+        output_tokens: count("output_tokens"),
+        cached_input_tokens: cache_read,
+    })
+}
+
+/// A vision-language model that scores frames. Implementations return the raw JSON object
+/// matching `CriticRequest::schema` plus usage; `critic_step` does the gating and streak
+/// bookkeeping.
+trait CriticBackend: Send + Sync {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a>;
+}
+
+/// Per-session backend options, passed to `critic_spawn` as `backend`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticBackendConfig {
+    /// OpenAI-compatible local server (Ollama, llama.cpp, vLLM), e.g. `http://127.0.0.1:11434/v1`.
+    /// When set, every model is sent there instead of a cloud API.
+    #[serde(default)]
+    local_base_url: Option<String>,
+    /// Overrides for the OpenAI backend (Azure OpenAI, proxies); unset fields use the app settings.
+    #[serde(default)]
+    openai: Option<OpenAiEndpointConfig>,
+    /// Overrides the built-in price table used for `CriticUsage::cost_usd`.
+    #[serde(default)]
+    pricing: Option<TokenPricing>,
+    /// Outputs for `mock-script`, played in order and repeated; each entry is merged over a
+    /// neutral output, so `{"reward": 0.5, "success": true}` is enough.
+    #[serde(default)]
+    mock_script: Option<Vec<Value>>,
+    /// Set from `CriticConfig::request_timeout_ms` and `max_output_tokens`.
+    #[serde(skip)]
+    limits: CriticRequestLimits,
+}
+
+/// Per-call limits every backend applies to its request.
+#[derive(Clone, Copy)]
+struct CriticRequestLimits {
+    timeout: Duration,
+    max_output_tokens: u32,
+}
+
+impl Default for CriticRequestLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            max_output_tokens: 1024,
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenAiEndpointConfig {
+    /// Replaces `https://api.openai.com/v1`; `/responses` is appended. For Azure, e.g.
+    /// `https://<resource>.openai.azure.com/openai/v1`.
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Sent as the `api-version` query parameter (Azure).
+    #[serde(default)]
+    api_version: Option<String>,
+    /// Extra request headers, e.g. `OpenAI-Organization`, `OpenAI-Project` or Azure's `api-key`.
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+impl OpenAiEndpointConfig {
+    /// `self` with unset fields taken from `fallback`; headers are merged, `self` winning.
+    fn or(&self, fallback: &OpenAiEndpointConfig) -> OpenAiEndpointConfig {
+        let mut headers = fallback.headers.clone();
+        headers.extend(self.headers.clone());
+        OpenAiEndpointConfig {
+            base_url: self.base_url.clone().or_else(|| fallback.base_url.clone()),
+            api_version: self.api_version.clone().or_else(|| fallback.api_version.clone()),
+            headers,
+        }
+    }
+
+    fn validate(mut self) -> Result<Self, String> {
+        self.base_url = self
+            .base_url
+            .as_deref()
+            .filter(|url| !url.trim().is_empty())
+            .map(normalize_base_url)
+            .transpose()?;
+        self.api_version = self.api_version.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        for (name, value) in &self.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name {name:?}"))?;
+            reqwest::header::HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header {name}"))?;
+        }
+        Ok(self)
+    }
+
+    /// Whether the extra headers already authenticate (Azure `api-key`, a proxy's own token).
+    fn has_auth_header(&self) -> bool {
+        self.headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("authorization") || k.eq_ignore_ascii_case("api-key"))
+    }
+}
+
+fn openai_config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join(OPENAI_CONFIG_FILE))
+}
+
+fn load_openai_config(app: &AppHandle) -> Result<Option<OpenAiEndpointConfig>, String> {
+    let path = openai_config_path(app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Invalid OpenAI config {}: {e}", path.display()))
+}
+
+fn save_openai_config(app: &AppHandle, config: &OpenAiEndpointConfig) -> Result<(), String> {
+    let path = openai_config_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let raw = serde_json::to_string_pretty(config).map_err(|e| format!("Failed to encode OpenAI config: {e}"))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    // Extra headers may carry API keys.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+fn openai_config(http: &HttpState) -> Result<OpenAiEndpointConfig, String> {
+    http.openai
+        .lock()
+        .map(|c| c.clone().unwrap_or_default())
+        .map_err(|_| "OpenAI config lock poisoned".to_string())
+}
+
+/// Picks the backend: a configured local server wins; otherwise `claude-*` goes to Anthropic,
+/// `gemini-*` to Google and everything else to OpenAI.
+fn critic_backend_for(
+    http: &HttpState,
+    model: &str,
+    config: &CriticBackendConfig,
+) -> Result<Box<dyn CriticBackend>, String> {
+    Ok(if model == "mock" || model.starts_with("mock-") {
+        Box::new(MockCritic {
+            script: config.mock_script.clone().unwrap_or_default(),
+        })
+    } else if let Some(base_url) = &config.local_base_url {
+        Box::new(LocalVlmCritic {
+            base_url: base_url.clone(),
+        })
+    } else if model.starts_with("claude-") {
+        Box::new(ClaudeCritic)
+    } else if model.starts_with("gemini-") {
+        Box::new(GeminiCritic)
+    } else {
+        let global = openai_config(http)?;
+        Box::new(OpenAiCritic {
+            endpoint: config.openai.as_ref().map(|c| c.or(&global)).unwrap_or(global),
+        })
+    })
+}
+
+fn critic_output_schema() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "describe": { "type": "string" },
+            "evaluate": { "type": "string" },
+            "reward": { "type": "number", "minimum": -1.0, "maximum": 1.0 },
+            "success": { "type": "boolean" },
+            "success_confidence": { "type": "number", "minimum": 0.0, "maximum": 1.0 },
+            "critical_failure": { "type": "boolean" },
+            "critical_failure_reason": { "type": "string" },
+            "failure_modes": {
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "enum": ["not_visible","target_not_visible","wrong_object","no_progress","regressing","collision_risk","edge_of_view","uncertain"]
+                }
+            },
+            "notes_short": { "type": "string" },
+            "suggested_correction": { "type": "string" }
+        },
+        "required": ["describe","evaluate","reward","success","success_confidence","critical_failure","critical_failure_reason","failure_modes","notes_short","suggested_correction"]
+    })
+}
+
+/// `critic_output_schema` plus caller-supplied properties, all of them required.
+fn critic_output_schema_with(extra: &serde_json::Map<String, Value>) -> Value {
+    let mut schema = critic_output_schema();
+    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+        properties.extend(extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    if let Some(required) = schema.get_mut("required").and_then(|r| r.as_array_mut()) {
+        required.extend(extra.keys().map(|k| json!(k)));
+    }
+    schema
+}
+
+fn validate_extra_output_properties(
+    extra: serde_json::Map<String, Value>,
+) -> Result<serde_json::Map<String, Value>, String> {
+    let base = critic_output_schema();
+    for (name, schema) in &extra {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid output property name: {name:?}"));
+        }
+        if base.pointer(&format!("/properties/{name}")).is_some() {
+            return Err(format!("Output property {name} is already part of the critic schema"));
+        }
+        if schema.get("type").and_then(|t| t.as_str()).is_none() {
+            return Err(format!("Output property {name} needs a JSON-schema object with a \"type\""));
+        }
+    }
+    Ok(extra)
+}
+
+/// Pulls the extra properties out of a critic output, typed per their declared schema.
+fn critic_extra_fields(raw: &Value, extra: &serde_json::Map<String, Value>) -> serde_json::Map<String, Value> {
+    extra
+        .iter()
+        .map(|(name, schema)| {
+            let value = raw.get(name);
+            let typed = match schema.get("type").and_then(|t| t.as_str()) {
+                Some("number") => value.and_then(|v| v.as_f64()).map(|v| json!(v)),
+                Some("integer") => value.and_then(|v| v.as_i64()).map(|v| json!(v)),
+                Some("boolean") => value.and_then(|v| v.as_bool()).map(|v| json!(v)),
+                Some("string") => value.and_then(|v| v.as_str()).map(|v| json!(v)),
+                _ => value.cloned(),
+            };
+            (name.clone(), typed.unwrap_or(Value::Null))
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn critic_eval(
+    http: &HttpState,
+    backend: &CriticBackendConfig,
+    model: &str,
+    task: &str,
+    system_prompt: String,
+    schema: Value,
+    frame_policy: &CriticFramePolicy,
+    frames_jpeg_base64: &[String],
+    motion_score: Option<f64>,
+    last_action_text: Option<&str>,
+    executed_plan: Option<&Value>,
+    correlation_id: Option<&str>,
+) -> Result<CriticEvaluation, String> {
+    let mut user_lines = vec![
+        format!("Goal: {task}"),
+        "You will receive multiple frames in time order (oldest -> newest). Use them to detect motion and progress.".to_string(),
+    ];
+    if let Some(a) = last_action_text {
+        let a = a.trim();
+        if !a.is_empty() {
+            user_lines.push(format!("Last action: {a}"));
+        }
+    }
+    if let Some(plan) = executed_plan {
+        // Keep it compact; tool output remains the source of truth.
+        let plan_short = trunc_for_log(&plan.to_string(), 800);
+        user_lines.push(format!("Executed plan (json): {plan_short}"));
+    }
+    if let Some(ms) = motion_score {
+        user_lines.push(format!(
+            "Computed pixel motion score across frames: {:.4} (0.0 means no visible motion). If < 0.004 treat as no progress.",
+            ms
+        ));
+    }
+    user_lines.push("If robot/target is not clearly visible, do not claim success.".to_string());
+
+    let available = frames_jpeg_base64
+        .iter()
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+    if available.is_empty() {
+        return Err("critic_step requires at least 1 frame".to_string());
+    }
+    let selected = frame_policy.select(available.len());
+    let frames = selected.iter().map(|&i| available[i].clone()).collect::<Vec<_>>();
+    let frame_captions = selected
+        .iter()
+        .enumerate()
+        .map(|(index, &source_index)| frame_policy.caption(index, selected.len(), source_index))
+        .collect();
+
+    let request = CriticRequest {
+        http,
+        model,
+        task,
+        system_prompt,
+        user_text: user_lines.join("\n"),
+        frames,
+        frame_captions,
+        schema,
+        limits: backend.limits,
+        correlation_id,
+    };
+    critic_backend_for(http, model, backend)?.evaluate(&request).await
+}
+
+/// Runs `critic_eval` for every chain concurrently on the same frames and prompt. Within a chain,
+/// models are tried in order until one succeeds; each outcome is tagged with the model that
+/// produced it (or the last one tried).
+#[allow(clippy::too_many_arguments)]
+async fn critic_eval_ensemble(
+    app: &AppHandle,
+    backend: &CriticBackendConfig,
+    chains: &[Vec<String>],
+    task: &str,
+    system_prompt: String,
+    schema: Value,
+    frame_policy: &CriticFramePolicy,
+    frames_jpeg_base64: &[String],
+    motion_score: Option<f64>,
+    last_action_text: Option<String>,
+    executed_plan: Option<Value>,
+    correlation_id: &str,
+) -> Vec<(String, Result<CriticEvaluation, String>)> {
+    let handles = chains
+        .iter()
+        .map(|chain| {
+            let (backend, chain, task) = (backend.clone(), chain.clone(), task.to_string());
+            let (system_prompt, schema, frames) = (system_prompt.clone(), schema.clone(), frames_jpeg_base64.to_vec());
+            let frame_policy = frame_policy.clone();
+            let (last_action_text, executed_plan) = (last_action_text.clone(), executed_plan.clone());
+            let (app, correlation_id) = (app.clone(), correlation_id.to_string());
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<AppState>();
+                let mut last = (String::new(), Err("no critic model configured".to_string()));
+                for (i, model) in chain.iter().enumerate() {
+                    let outcome = critic_eval(
+                        &state.http,
+                        &backend,
+                        model,
+                        &task,
+                        system_prompt.clone(),
+                        schema.clone(),
+                        &frame_policy,
+                        &frames,
+                        motion_score,
+                        last_action_text.as_deref(),
+                        executed_plan.as_ref(),
+                        Some(&correlation_id),
+                    )
+                    .await;
+                    if let (Err(error), Some(next)) = (&outcome, chain.get(i + 1)) {
+                        append_desktop_audit_log(
+                            "critic.model_fallback",
+                            &json!({ "correlation_id": correlation_id, "from": model, "to": next, "error": error }),
+                        );
+                    }
+                    last = (model.clone(), outcome);
+                    if last.1.is_ok() {
+                        break;
+                    }
+                }
+                last
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut outcomes = Vec::with_capacity(handles.len());
+    for (chain, handle) in chains.iter().zip(handles) {
+        let outcome = handle.await.unwrap_or_else(|e| {
+            (chain.first().cloned().unwrap_or_default(), Err(format!("critic task failed: {e}")))
+        });
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+/// Merges ensemble outputs into one critic output: majority vote on `success`, mean reward and
+/// confidence, and any model's critical failure wins. Failed models are reported but skipped.
+fn combine_critic_votes(
+    outcomes: Vec<(String, Result<CriticEvaluation, String>)>,
+) -> Result<(Value, Vec<CriticEnsembleVote>), String> {
+    let mut votes = Vec::with_capacity(outcomes.len());
+    let mut outputs = Vec::new();
+    for (model, outcome) in outcomes {
+        match outcome {
+            Ok(evaluation) => {
+                let out = evaluation.output;
+                votes.push(CriticEnsembleVote {
+                    model,
+                    reward: out.get("reward").and_then(|v| v.as_f64()),
+                    success: out.get("success").and_then(|v| v.as_bool()),
+                    success_confidence: out.get("success_confidence").and_then(|v| v.as_f64()),
+                    critical_failure: out.get("critical_failure").and_then(|v| v.as_bool()).unwrap_or(false),
+                    error: None,
+                });
+                outputs.push(out);
+            }
+            Err(e) => votes.push(CriticEnsembleVote {
+                model,
+                reward: None,
+                success: None,
+                success_confidence: None,
+                critical_failure: false,
+                error: Some(e),
+            }),
+        }
+    }
+    if outputs.is_empty() {
+        let errors = votes
+            .iter()
+            .filter_map(|v| v.error.as_ref().map(|e| format!("{}: {e}", v.model)))
+            .collect::<Vec<_>>();
+        return Err(format!("All ensemble critics failed: {}", errors.join("; ")));
+    }
+
+    let n = outputs.len() as f64;
+    let mean = |key: &str| outputs.iter().map(|o| o.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0)).sum::<f64>() / n;
+    let success_votes = outputs
+        .iter()
+        .filter(|o| o.get("success").and_then(|v| v.as_bool()).unwrap_or(false))
+        .count();
+    let critical_reasons = outputs
+        .iter()
+        .filter(|o| o.get("critical_failure").and_then(|v| v.as_bool()).unwrap_or(false))
+        .filter_map(|o| o.get("critical_failure_reason").and_then(|v| v.as_str()))
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+    let mut failure_modes: Vec<String> = Vec::new();
+    for mode in outputs
+        .iter()
+        .filter_map(|o| o.get("failure_modes").and_then(|v| v.as_array()))
+        .flatten()
+        .filter_map(|m| m.as_str())
+    {
+        if !failure_modes.iter().any(|m| m == mode) {
+            failure_modes.push(mode.to_string());
+        }
+    }
+    // A failure mode only one minority model saw should not veto a majority success.
+    if success_votes * 2 > outputs.len() {
+        failure_modes.retain(|m| m != "uncertain" && m != "not_visible" && m != "target_not_visible");
+    }
+    // Text fields and any task-specific extras come from the first model that answered.
+    let mut combined = outputs[0].clone();
+    if let Some(out) = combined.as_object_mut() {
+        out.insert("reward".to_string(), json!(mean("reward")));
+        out.insert("success".to_string(), json!(success_votes * 2 > outputs.len()));
+        out.insert("success_confidence".to_string(), json!(mean("success_confidence")));
+        out.insert("critical_failure".to_string(), json!(votes.iter().any(|v| v.critical_failure)));
+        out.insert("critical_failure_reason".to_string(), json!(critical_reasons.join("; ")));
+        out.insert("failure_modes".to_string(), json!(failure_modes));
+        out.insert("ensemble".to_string(), json!(outputs));
+    }
+    Ok((combined, votes))
+}
+
+struct OpenAiCritic {
+    endpoint: OpenAiEndpointConfig,
+}
+
+impl CriticBackend for OpenAiCritic {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a> {
+        Box::pin(openai_critic_eval(&self.endpoint, request))
+    }
+}
+
+/// Stable id for the cacheable part of a critic request (system prompt + output schema).
+fn critic_prompt_cache_key(request: &CriticRequest<'_>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(request.system_prompt.as_bytes());
+    hasher.update(request.schema.to_string().as_bytes());
+    let digest = hasher.finalize();
+    let hex = digest.iter().take(8).map(|b| format!("{b:02x}")).collect::<String>();
+    format!("daemon-critic-{hex}")
+}
+
+async fn openai_critic_eval(endpoint: &OpenAiEndpointConfig, request: &CriticRequest<'_>) -> Result<CriticEvaluation, String> {
+    let base_url = endpoint.base_url.as_deref().unwrap_or(OPENAI_API_BASE);
+    // OPENAI_API_KEY only ever goes to OpenAI itself; proxies, Azure and local servers get the
+    // bearer token configured for their base URL (`set_endpoint_auth`), if any.
+    let api_key = if base_url == OPENAI_API_BASE {
+        openai_api_key()
+    } else {
+        endpoint_settings(request.http, base_url)?.bearer_token
+    };
+    if api_key.is_none() && !endpoint.has_auth_header() {
+        return Err(if base_url == OPENAI_API_BASE {
+            "OPENAI_API_KEY missing in app environment".to_string()
+        } else {
+            format!("No credentials for {base_url}: set a bearer token with set_endpoint_auth or an auth header")
+        });
+    }
+    let (model, correlation_id) = (request.model, request.correlation_id);
+    let url = format!("{base_url}/responses");
+
+    let mut user_content: Vec<Value> = Vec::new();
+    user_content.push(json!({ "type": "input_text", "text": request.user_text }));
+    for (caption, b64) in request.frame_captions.iter().zip(&request.frames) {
+        // Tiny caption helps the model interpret ordering.
+        user_content.push(json!({ "type": "input_text", "text": caption }));
+        user_content.push(json!({ "type": "input_image", "image_url": format!("data:image/jpeg;base64,{b64}") }));
+    }
+    let mut body = json!({
+        "model": model,
+        "temperature": 0,
+        "max_output_tokens": request.limits.max_output_tokens,
+        "text": {
+            "format": {
+                "type": "json_schema",
+                "name": "critic_reward",
+                "schema": request.schema,
+                "strict": true
+            }
+        },
+        "input": [
+            { "role": "system", "content": [{ "type": "input_text", "text": request.system_prompt }] },
+            { "role": "user", "content": user_content }
+        ],
+        "metadata": {
+            "correlation_id": correlation_id,
+            "ts_ms": unix_ts_ms().to_string()
+        }
+    });
+
+    // The system prompt and schema lead the request and only change with the task, so OpenAI's
+    // automatic prefix cache can reuse them; the key keeps steps of one prompt on the same cache
+    // shard. Third-party endpoints may reject the field, so only send it to the official API.
+    if endpoint.base_url.is_none() {
+        body["prompt_cache_key"] = json!(critic_prompt_cache_key(request));
+    }
+
+    append_desktop_audit_log("openai.critic.request", &json!({ "model": model, "task": request.task, "cid": correlation_id }));
+
+    let client = openai_http_client(request.http)?;
+    let mut req = client.post(&url).header("Content-Type", "application/json");
+    if let Some(version) = &endpoint.api_version {
+        req = req.query(&[("api-version", version)]);
+    }
+    if let Some(api_key) = api_key {
+        req = req.bearer_auth(api_key);
+    }
+    for (name, value) in &endpoint.headers {
+        req = req.header(name, value);
+    }
+    let resp = send_critic_request("OpenAI", req.json(&body), request).await?;
+
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| format!("OpenAI read body failed: {e}"))?;
+    if !status.is_success() {
+        append_desktop_audit_log("openai.critic.http_error", &json!({ "status": status.as_u16(), "body": trunc_for_log(&text, 2000) }));
+        return Err(format!("OpenAI HTTP {}: {}", status.as_u16(), trunc_for_log(&text, 1200)));
+    }
+
+    let parsed: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("OpenAI invalid JSON: {e}; body={}", trunc_for_log(&text, 1200)))?;
+
+    // With json_schema, the model output should be valid JSON text.
+    if let Some(out_text) = extract_output_text(&parsed) {
+        if let Ok(v) = serde_json::from_str::<Value>(&out_text) {
+            let usage = parse_token_usage(parsed.get("usage"), "input_tokens", "output_tokens", "/input_tokens_details/cached_tokens");
+            append_desktop_audit_log("openai.critic.ok", &json!({ "cid": correlation_id, "out": v, "usage": parsed.get("usage") }));
+            return Ok(CriticEvaluation { output: v, usage });
+        }
+    }
+
+    // Fallback: if the provider ever returns already-parsed JSON in a field, return the whole response.
+    append_desktop_audit_log("openai.critic.parse_failed", &json!({ "cid": correlation_id, "body": trunc_for_log(&text, 1200) }));
+    Err("OpenAI critic response parse failed (no JSON tool output found)".to_string())
+}
+
+/// Offline critic for demos and CI. `mock-success`, `mock-fail` and `mock-critical` always
+/// answer the same way, `mock-script` plays `mock_script`, and `mock` (or anything else) derives a
+/// pseudo-random but deterministic verdict from a hash of the task and frames.
+struct MockCritic {
+    script: Vec<Value>,
+}
+
+impl CriticBackend for MockCritic {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a> {
+        Box::pin(async move { Ok(self.output(request)) })
+    }
+}
+
+impl MockCritic {
+    fn output(&self, request: &CriticRequest<'_>) -> CriticEvaluation {
+        let mut output = json!({
+            "describe": format!("mock critic ({})", request.model),
+            "evaluate": "",
+            "reward": 0.0,
+            "success": false,
+            "success_confidence": 0.0,
+            "critical_failure": false,
+            "critical_failure_reason": "",
+            "failure_modes": [],
+            "notes_short": "mock",
+            "suggested_correction": "",
+        });
+        let overrides = match request.model {
+            "mock-success" => json!({ "reward": 1.0, "success": true, "success_confidence": 0.95 }),
+            "mock-fail" => json!({
+                "reward": -0.5,
+                "failure_modes": ["no_progress"],
+                "suggested_correction": "drive forward slowly",
+            }),
+            "mock-critical" => json!({
+                "reward": -1.0,
+                "critical_failure": true,
+                "critical_failure_reason": "mock critical failure",
+                "failure_modes": ["collision_risk"],
+            }),
+            "mock-script" if !self.script.is_empty() => {
+                let seq = MOCK_CRITIC_SEQ.fetch_add(1, Ordering::Relaxed) as usize;
+                self.script[seq % self.script.len()].clone()
+            }
+            _ => {
+                let mut hasher = Sha256::new();
+                hasher.update(request.task.as_bytes());
+                for frame in &request.frames {
+                    hasher.update(frame.as_bytes());
+                }
+                let digest = hasher.finalize();
+                let unit = |i: usize| f64::from(digest[i]) / 255.0;
+                let success = unit(0) > 0.7;
+                json!({
+                    "reward": (unit(1) * 2.0 - 1.0).max(if success { 0.5 } else { -1.0 }),
+                    "success": success,
+                    "success_confidence": if success { 0.9 + unit(2) * 0.1 } else { unit(2) * 0.5 },
+                    "failure_modes": if success { json!([]) } else { json!(["no_progress"]) },
+                })
+            }
+        };
+        if let (Some(out), Some(overrides)) = (output.as_object_mut(), overrides.as_object()) {
+            for (key, value) in overrides {
+                out.insert(key.clone(), value.clone());
+            }
+        }
+        CriticEvaluation {
+            output,
+            usage: Some(TokenUsage::default()),
+        }
+    }
+}
+
+struct LocalVlmCritic {
+    base_url: String,
+}
+
+impl CriticBackend for LocalVlmCritic {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a> {
+        Box::pin(local_vlm_critic_eval(&self.base_url, request))
+    }
+}
+
+/// OpenAI-compatible `/chat/completions` on a local server, so the reward loop runs offline.
+async fn local_vlm_critic_eval(base_url: &str, request: &CriticRequest<'_>) -> Result<CriticEvaluation, String> {
+    let (model, correlation_id) = (request.model, request.correlation_id);
+    let url = format!("{base_url}/chat/completions");
+
+    let mut user_content: Vec<Value> = vec![json!({ "type": "text", "text": request.user_text })];
+    for (caption, b64) in request.frame_captions.iter().zip(&request.frames) {
+        user_content.push(json!({ "type": "text", "text": caption }));
+        user_content.push(json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:image/jpeg;base64,{b64}") }
+        }));
+    }
+    let body = json!({
+        "model": model,
+        "temperature": 0,
+        "max_tokens": request.limits.max_output_tokens,
+        "stream": false,
+        "response_format": {
+            "type": "json_schema",
+            "json_schema": { "name": "critic_reward", "schema": request.schema, "strict": true }
+        },
+        "messages": [
+            { "role": "system", "content": request.system_prompt },
+            { "role": "user", "content": user_content }
+        ]
+    });
+
+    append_desktop_audit_log(
+        "local_vlm.critic.request",
+        &json!({ "url": url, "model": model, "task": request.task, "cid": correlation_id }),
+    );
+
+    let resp = send_critic_request("Local VLM", openai_http_client(request.http)?.post(&url).json(&body), request).await?;
+
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| format!("Local VLM read body failed: {e}"))?;
+    if !status.is_success() {
+        append_desktop_audit_log("local_vlm.critic.http_error", &json!({ "status": status.as_u16(), "body": trunc_for_log(&text, 2000) }));
+        return Err(format!("Local VLM HTTP {}: {}", status.as_u16(), trunc_for_log(&text, 1200)));
+    }
+
+    let parsed: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Local VLM invalid JSON: {e}; body={}", trunc_for_log(&text, 1200)))?;
+    let content = parsed
+        .pointer("/choices/0/message/content")
+        .and_then(|c| c.as_str())
+        .unwrap_or_default();
+    // Small local models sometimes wrap the object in prose or code fences despite the schema.
+    let json_text = match (content.find('{'), content.rfind('}')) {
+        (Some(start), Some(end)) if end > start => &content[start..=end],
+        _ => content,
+    };
+    if let Ok(v) = serde_json::from_str::<Value>(json_text) {
+        if v.is_object() {
+            let usage = parse_token_usage(parsed.get("usage"), "prompt_tokens", "completion_tokens", "/prompt_tokens_details/cached_tokens");
+            append_desktop_audit_log("local_vlm.critic.ok", &json!({ "cid": correlation_id, "out": v }));
+            return Ok(CriticEvaluation { output: v, usage });
+        }
+    }
+
+    append_desktop_audit_log("local_vlm.critic.parse_failed", &json!({ "cid": correlation_id, "body": trunc_for_log(&text, 1200) }));
+    Err("Local VLM critic response parse failed (no JSON object in message content)".to_string())
+}
+
+struct GeminiCritic;
+
+impl CriticBackend for GeminiCritic {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a> {
+        Box::pin(gemini_critic_eval(request))
+    }
+}
+
+/// Gemini's `responseSchema` is an OpenAPI subset: upper-case type names and no
+/// `additionalProperties`.
+fn gemini_response_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| key.as_str() != "additionalProperties")
+                .map(|(key, value)| match (key.as_str(), value) {
+                    ("type", Value::String(t)) => (key.clone(), json!(t.to_ascii_uppercase())),
+                    _ => (key.clone(), gemini_response_schema(value)),
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(gemini_response_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+async fn gemini_critic_eval(request: &CriticRequest<'_>) -> Result<CriticEvaluation, String> {
+    let api_key = gemini_api_key().ok_or_else(|| "GEMINI_API_KEY missing in app environment".to_string())?;
+    let (model, correlation_id) = (request.model, request.correlation_id);
+
+    let mut parts: Vec<Value> = vec![json!({ "text": request.user_text })];
+    for (caption, b64) in request.frame_captions.iter().zip(&request.frames) {
+        parts.push(json!({ "text": caption }));
+        parts.push(json!({ "inline_data": { "mime_type": "image/jpeg", "data": b64 } }));
+    }
+    let body = json!({
+        "systemInstruction": { "parts": [{ "text": request.system_prompt }] },
+        "contents": [{ "role": "user", "parts": parts }],
+        "generationConfig": {
+            "temperature": 0,
+            "maxOutputTokens": request.limits.max_output_tokens,
+            "responseMimeType": "application/json",
+            "responseSchema": gemini_response_schema(&request.schema)
+        }
+    });
+
+    append_desktop_audit_log("gemini.critic.request", &json!({ "model": model, "task": request.task, "cid": correlation_id }));
+
+    let req = openai_http_client(request.http)?
+        .post(format!("{GEMINI_API_BASE}/models/{model}:generateContent"))
+        .header("x-goog-api-key", api_key)
+        .json(&body);
+    let resp = send_critic_request("Gemini", req, request).await?;
+
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| format!("Gemini read body failed: {e}"))?;
+    if !status.is_success() {
+        append_desktop_audit_log("gemini.critic.http_error", &json!({ "status": status.as_u16(), "body": trunc_for_log(&text, 2000) }));
+        return Err(format!("Gemini HTTP {}: {}", status.as_u16(), trunc_for_log(&text, 1200)));
+    }
+
+    let parsed: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Gemini invalid JSON: {e}; body={}", trunc_for_log(&text, 1200)))?;
+    let out_text = parsed
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    if let Ok(v) = serde_json::from_str::<Value>(&out_text) {
+        if v.is_object() {
+            let usage = parse_token_usage(parsed.get("usageMetadata"), "promptTokenCount", "candidatesTokenCount", "/cachedContentTokenCount");
+            append_desktop_audit_log("gemini.critic.ok", &json!({ "cid": correlation_id, "out": v, "usage": parsed.get("usageMetadata") }));
+            return Ok(CriticEvaluation { output: v, usage });
+        }
+    }
+
+    append_desktop_audit_log("gemini.critic.parse_failed", &json!({ "cid": correlation_id, "body": trunc_for_log(&text, 1200) }));
+    Err("Gemini critic response parse failed (no JSON object in candidate text)".to_string())
+}
+
+struct ClaudeCritic;
+
+impl CriticBackend for ClaudeCritic {
+    fn evaluate<'a>(&'a self, request: &'a CriticRequest<'a>) -> CriticFuture<'a> {
+        Box::pin(claude_critic_eval(request))
+    }
+}
+
+/// Anthropic Messages API. Structured output is obtained by forcing a single tool call whose
+/// input schema is the critic schema.
+async fn claude_critic_eval(request: &CriticRequest<'_>) -> Result<CriticEvaluation, String> {
+    let api_key = anthropic_api_key().ok_or_else(|| "ANTHROPIC_API_KEY missing in app environment".to_string())?;
+    let (model, correlation_id) = (request.model, request.correlation_id);
+
+    let mut user_content: Vec<Value> = vec![json!({ "type": "text", "text": request.user_text })];
+    for (caption, b64) in request.frame_captions.iter().zip(&request.frames) {
+        user_content.push(json!({ "type": "text", "text": caption }));
+        user_content.push(json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": "image/jpeg", "data": b64 }
+        }));
+    }
+    let body = json!({
+        "model": model,
+        "max_tokens": request.limits.max_output_tokens,
+        "temperature": 0,
+        // Tools and system come before the messages, so a breakpoint here caches both.
+        "system": [{
+            "type": "text",
+            "text": request.system_prompt,
+            "cache_control": { "type": "ephemeral" }
+        }],
+        "tools": [{
+            "name": "critic_reward",
+            "description": "Report the reward evaluation for the frames.",
+            "input_schema": request.schema
+        }],
+        "tool_choice": { "type": "tool", "name": "critic_reward" },
+        "messages": [{ "role": "user", "content": user_content }]
+    });
+
+    append_desktop_audit_log("claude.critic.request", &json!({ "model": model, "task": request.task, "cid": correlation_id }));
+
+    let req = openai_http_client(request.http)?
+        .post(ANTHROPIC_MESSAGES_URL)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_API_VERSION)
+        .json(&body);
+    let resp = send_critic_request("Anthropic", req, request).await?;
+
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| format!("Anthropic read body failed: {e}"))?;
+    if !status.is_success() {
+        append_desktop_audit_log("claude.critic.http_error", &json!({ "status": status.as_u16(), "body": trunc_for_log(&text, 2000) }));
+        return Err(format!("Anthropic HTTP {}: {}", status.as_u16(), trunc_for_log(&text, 1200)));
+    }
+
+    let parsed: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Anthropic invalid JSON: {e}; body={}", trunc_for_log(&text, 1200)))?;
+    let tool_input = parsed
+        .get("content")
+        .and_then(|c| c.as_array())
+        .and_then(|blocks| {
+            blocks
+                .iter()
+                .find(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+        })
+        .and_then(|b| b.get("input"))
+        .filter(|input| input.is_object());
+    if let Some(v) = tool_input {
+        let usage = parse_anthropic_usage(parsed.get("usage"));
+        append_desktop_audit_log("claude.critic.ok", &json!({ "cid": correlation_id, "out": v, "usage": parsed.get("usage") }));
+        return Ok(CriticEvaluation { output: v.clone(), usage });
+    }
+
+    append_desktop_audit_log("claude.critic.parse_failed", &json!({ "cid": correlation_id, "body": trunc_for_log(&text, 1200) }));
+    Err("Anthropic critic response parse failed (no critic_reward tool call found)".to_string())
+}
+
+#[tauri::command]
+fn read_desktop_audit_log(tail_lines: Option<usize>) -> Result<String, String> {
+    let path = repo_logs_dir()?.join("backend_audit.jsonl");
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let limit = tail_lines.unwrap_or(300).max(1);
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(limit);
+    Ok(lines[start..].join("\n"))
+}
+
+/// Tail of `.build/orchestrator_desktop[_<name>].log`. With `follow: true`, lines appended after
+/// this read are emitted as `orchestrator_log_file` events until `stop_orchestrator_log_follow`.
+#[tauri::command]
+fn read_orchestrator_log(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+    tail_lines: Option<usize>,
+    follow: Option<bool>,
+) -> Result<String, String> {
+    let instance = orchestrator_instance_name(name)?;
+    let install = resolve_orchestrator_install(&app)?;
+    let path = orchestrator_log_path(&install.work_dir, &instance);
+    let content = if path.exists() {
+        std::fs::read(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?
+    } else {
+        Vec::new()
+    };
+
+    if follow.unwrap_or(false) {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let mut lock = state
+            .orchestrator_log_followers
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        if let Some(previous) = lock.insert(instance.clone(), stop_tx) {
+            let _ = previous.send(());
+        }
+        tauri::async_runtime::spawn(follow_orchestrator_log(
+            app.clone(),
+            instance,
+            path,
+            content.len() as u64,
+            stop_rx,
+        ));
+    }
+
+    let content = String::from_utf8_lossy(&content);
+    let limit = tail_lines.unwrap_or(300).max(1);
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(limit);
+    Ok(lines[start..].join("\n"))
+}
+
+#[tauri::command]
+fn stop_orchestrator_log_follow(state: State<'_, AppState>, name: Option<String>) -> Result<bool, String> {
+    let instance = orchestrator_instance_name(name)?;
+    let follower = state
+        .orchestrator_log_followers
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .remove(&instance);
+    Ok(follower.is_some_and(|stop_tx| stop_tx.send(()).is_ok()))
+}
+
+#[tauri::command]
+fn list_serial_ports() -> Result<Vec<SerialPortEntry>, String> {
+    let ports = serialport::available_ports().map_err(|error| error.to_string())?;
+    let mut result = ports
+        .into_iter()
+        .map(|port| {
+            let usb = match &port.port_type {
+                serialport::SerialPortType::UsbPort(info) => Some(info.clone()),
+                _ => None,
+            };
+            SerialPortEntry {
+                port_type: port_type_name(&port.port_type),
+                port_name: port.port_name,
+                vid: usb.as_ref().map(|u| u.vid),
+                pid: usb.as_ref().map(|u| u.pid),
+                serial_number: usb.as_ref().and_then(|u| u.serial_number.clone()),
+                manufacturer: usb.as_ref().and_then(|u| u.manufacturer.clone()),
+                alias: usb.as_ref().map(serial_port_alias),
+            }
+        })
+        .collect::<Vec<_>>();
+    for mock in ["mock://echo", "mock://script"] {
+        result.push(SerialPortEntry {
+            port_name: mock.to_string(),
+            port_type: "mock".to_string(),
+            vid: None,
+            pid: None,
+            serial_number: None,
+            manufacturer: None,
+            alias: None,
+        });
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn connect_serial(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port_name: String,
+    baud_rate: Option<u32>,
+    delimiter: Option<String>,
+    include_filter: Option<String>,
+    exclude_filter: Option<String>,
+    batch_interval_ms: Option<u64>,
+    batch_capacity: Option<usize>,
+    auto_reconnect: Option<bool>,
+    mock_script: Option<Vec<SerialMockLine>>,
+    max_lines_per_sec: Option<f64>,
+    legacy_events: Option<bool>,
+    stall_timeout_secs: Option<f64>,
+    stall_stop_orchestrator: Option<bool>,
+) -> Result<ConnectionStatus, String> {
+    let baud = baud_rate.unwrap_or(115_200);
+    let delimiter = parse_serial_delimiter(delimiter.as_deref())?;
+    let filter = Arc::new(Mutex::new(SerialLineFilter::new(
+        include_filter.as_deref(),
+        exclude_filter.as_deref(),
+    )?));
+
+    let batch_interval = batch_interval_ms.filter(|ms| *ms > 0).map(Duration::from_millis);
+    let rate_limit = Arc::new(Mutex::new(SerialRateLimit::new(max_lines_per_sec, None)?));
+    let watchdog = Arc::new(Mutex::new(SerialWatchdog::new(
+        stall_timeout_secs,
+        stall_stop_orchestrator.unwrap_or(false),
+    )?));
+
+    let conn_state = Arc::new(Mutex::new(SerialConnectionState::Closed));
+    transition_serial_state(&app, &conn_state, &port_name, SerialConnectionState::Connecting, None);
+
+    let opened = SerialTransport::open(&port_name, baud, &delimiter, mock_script.unwrap_or_default());
+    let (device_path, stream) = match opened {
+        Ok(opened) => opened,
+        Err(msg) => {
+            transition_serial_state(&app, &conn_state, &port_name, SerialConnectionState::Closed, Some(msg.clone()));
+            return Err(msg);
+        }
+    };
+
+    let (cmd_tx, cmd_rx) = tokio::sync::mpsc::unbounded_channel::<SerialCommand>();
+    let capture: Arc<Mutex<Option<SerialCapture>>> = Arc::new(Mutex::new(None));
+    let history: Arc<Mutex<VecDeque<SerialHistoryEntry>>> =
+        Arc::new(Mutex::new(VecDeque::with_capacity(SERIAL_HISTORY_CAPACITY)));
+    let rx_notify = Arc::new(tokio::sync::Notify::new());
+
+    let device_path = Arc::new(Mutex::new(device_path));
+
+    let worker = SerialWorker {
+        app: app.clone(),
+        port_name: port_name.clone(),
+        device_path: device_path.clone(),
+        baud,
+        stream,
+        cmd_rx,
+        delimiter,
+        capture: capture.clone(),
+        history: history.clone(),
+        rx_notify: rx_notify.clone(),
+        filter: filter.clone(),
+        conn_state: conn_state.clone(),
+        batcher: batch_interval.map(|interval| {
+            SerialLineBatcher::new(&port_name, interval, batch_capacity.unwrap_or(5000))
+        }),
+        auto_reconnect: auto_reconnect.unwrap_or(true),
+        rate_limit: rate_limit.clone(),
+        write_queue: VecDeque::new(),
+        last_write_at: None,
+        watchdog: watchdog.clone(),
+        last_rx_at: tokio::time::Instant::now(),
+        legacy_events: legacy_events.unwrap_or(false),
+    };
+    transition_serial_state(&app, &conn_state, &port_name, SerialConnectionState::Connected, None);
+    tauri::async_runtime::spawn(worker.run());
+
+    {
+        let mut lock = state
+            .serial_sessions
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        let previous = lock.insert(port_name.clone(), SerialSession {
+            cmd_tx,
+            port_name: port_name.clone(),
+            device_path: device_path.clone(),
+            capture,
+            history,
+            rx_notify,
+            filter,
+            conn_state,
+            rate_limit,
+            watchdog,
+            legacy_events: legacy_events.unwrap_or(false),
+            connected_ts_ms: unix_ts_ms(),
+        });
+        if let Some(previous) = previous {
+            stop_serial_session(previous);
+        }
+    }
+
+    let device_path = device_path.lock().map(|p| p.clone()).ok();
+    Ok(ConnectionStatus {
+        connected: true,
+        port_name: Some(port_name),
+        device_path,
+        state: SerialConnectionState::Connected,
+    })
+}
+
+#[tauri::command]
+fn disconnect_serial(state: State<'_, AppState>, port: Option<String>) -> Result<ConnectionStatus, String> {
+    let mut lock = state
+        .serial_sessions
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    match port.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        Some(port) => {
+            let key = lock
+                .iter()
+                .find(|(_, session)| serial_session_matches(session, port))
+                .map(|(key, _)| key.clone());
+            if let Some(session) = key.and_then(|key| lock.remove(&key)) {
+                stop_serial_session(session);
+            }
+        }
+        // Without a port, behave like the single-session API did: close everything.
+        None => {
+            for (_, session) in lock.drain() {
+                stop_serial_session(session);
+            }
+        }
+    }
+
+    Ok(disconnected_status())
+}
+
+#[tauri::command]
+fn get_connection_status(state: State<'_, AppState>, port: Option<String>) -> Result<ConnectionStatus, String> {
+    let no_sessions = state
+        .serial_sessions
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .is_empty();
+    if no_sessions {
+        return Ok(disconnected_status());
+    }
+    match serial_session_for_port(&state, port.as_deref()) {
+        Ok(session) => Ok(serial_connection_status(&session)),
+        Err(_) if port.is_some() => Ok(disconnected_status()),
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+fn list_serial_sessions(state: State<'_, AppState>) -> Result<Vec<SerialSessionInfo>, String> {
+    let lock = state
+        .serial_sessions
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    let mut sessions = lock
+        .values()
+        .map(|session| {
+            let status = serial_connection_status(session);
+            SerialSessionInfo {
+                port_name: session.port_name.clone(),
+                device_path: status.device_path,
+                state: status.state,
+                connected_ts_ms: session.connected_ts_ms,
+                capturing: session.capture.lock().map(|c| c.is_some()).unwrap_or(false),
+                history_len: session.history.lock().map(|h| h.len()).unwrap_or(0),
+            }
+        })
+        .collect::<Vec<_>>();
+    sessions.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+    Ok(sessions)
+}
+
+#[tauri::command]
+async fn send_serial_line(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: Option<String>,
+    line: String,
+) -> Result<(), String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    write_serial_line(&app, &session, &line).await
+}
+
+#[tauri::command]
+async fn run_serial_macro(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: Option<String>,
+    steps: Vec<SerialMacroStep>,
+    stop_on_failure: Option<bool>,
+) -> Result<SerialMacroResult, String> {
+    if steps.is_empty() {
+        return Err("steps must contain at least one entry".to_string());
+    }
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let stop_on_failure = stop_on_failure.unwrap_or(true);
+
+    Ok(run_serial_macro_steps(&app, &session, &steps, stop_on_failure).await)
+}
+
+#[tauri::command]
+fn get_serial_history(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<SerialHistoryEntry>, String> {
+    let session = match serial_session_for_port(&state, port.as_deref()) {
+        Ok(session) => session,
+        Err(_) if port.is_none() => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+
+    let history = session
+        .history
+        .lock()
+        .map_err(|_| "Serial history lock poisoned".to_string())?;
+    let limit = limit.unwrap_or(SERIAL_HISTORY_CAPACITY).max(1);
+    let start = history.len().saturating_sub(limit);
+    Ok(history.iter().skip(start).cloned().collect())
+}
+
+#[tauri::command]
+fn set_serial_filter(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    include: Option<String>,
+    exclude: Option<String>,
+) -> Result<SerialFilterStatus, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let next = SerialLineFilter::new(include.as_deref(), exclude.as_deref())?;
+    let status = next.status();
+    let mut filter = session
+        .filter
+        .lock()
+        .map_err(|_| "Serial filter lock poisoned".to_string())?;
+    *filter = next;
+    Ok(status)
+}
+
+#[tauri::command]
+fn get_serial_rate_limit(state: State<'_, AppState>, port: Option<String>) -> Result<SerialRateLimit, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let limit = session
+        .rate_limit
+        .lock()
+        .map_err(|_| "Serial rate limit lock poisoned".to_string())?;
+    Ok(limit.clone())
+}
+
+#[tauri::command]
+fn set_serial_rate_limit(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    max_lines_per_sec: Option<f64>,
+    queue_capacity: Option<usize>,
+) -> Result<SerialRateLimit, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let mut limit = session
+        .rate_limit
+        .lock()
+        .map_err(|_| "Serial rate limit lock poisoned".to_string())?;
+    let next = SerialRateLimit::new(max_lines_per_sec, queue_capacity.or(Some(limit.queue_capacity)))?;
+    limit.max_lines_per_sec = next.max_lines_per_sec;
+    limit.queue_capacity = next.queue_capacity;
+    Ok(limit.clone())
+}
+
+#[tauri::command]
+fn get_serial_watchdog(state: State<'_, AppState>, port: Option<String>) -> Result<SerialWatchdog, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let watchdog = session
+        .watchdog
+        .lock()
+        .map_err(|_| "Serial watchdog lock poisoned".to_string())?;
+    Ok(watchdog.clone())
+}
+
+#[tauri::command]
+fn set_serial_watchdog(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    stall_timeout_secs: Option<f64>,
+    stop_orchestrator: Option<bool>,
+) -> Result<SerialWatchdog, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let mut watchdog = session
+        .watchdog
+        .lock()
+        .map_err(|_| "Serial watchdog lock poisoned".to_string())?;
+    let next = SerialWatchdog::new(stall_timeout_secs, stop_orchestrator.unwrap_or(watchdog.stop_orchestrator))?;
+    watchdog.stall_timeout_secs = next.stall_timeout_secs;
+    watchdog.stop_orchestrator = next.stop_orchestrator;
+    watchdog.stalled = false;
+    Ok(watchdog.clone())
+}
+
+#[tauri::command]
+async fn send_serial_raw(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: Option<String>,
+    data: String,
+    encoding: Option<String>,
+) -> Result<usize, String> {
+    let bytes = match encoding.as_deref().map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("escaped") => decode_escaped_bytes(&data)?,
+        Some("base64") => base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|e| format!("base64 decode failed: {e}"))?,
+        Some(other) => return Err(format!("Unsupported encoding: {other} (expected escaped or base64)")),
+    };
+    if bytes.is_empty() {
+        return Err("data is empty".to_string());
+    }
+
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let len = bytes.len();
+    let printable = escape_bytes_for_log(&bytes);
+    serial_request(&session, |reply| SerialCommand::Write { bytes, reply }).await?;
+
+    record_serial_traffic(&session.history, &session.capture, &session.port_name, "tx", &printable);
+    emit_serial_line(&app, &session.port_name, "tx", printable, session.legacy_events);
+
+    Ok(len)
+}
+
+#[tauri::command]
+async fn set_serial_control_lines(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    dtr: Option<bool>,
+    rts: Option<bool>,
+) -> Result<(), String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    serial_request(&session, |reply| SerialCommand::ControlLines { dtr, rts, reply }).await
+}
+
+#[tauri::command]
+async fn send_serial_break(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    duration_ms: Option<u64>,
+) -> Result<(), String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let duration = Duration::from_millis(duration_ms.unwrap_or(250).clamp(1, 5000));
+    serial_request(&session, |reply| SerialCommand::Break { duration, reply }).await?;
+    append_desktop_audit_log(
+        "serial.break",
+        &json!({ "port": session.port_name, "duration_ms": duration.as_millis() as u64 }),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+async fn reset_device(
+    state: State<'_, AppState>,
+    port: Option<String>,
+    bootloader: Option<bool>,
+    pulse_ms: Option<u64>,
+) -> Result<(), String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let pulse = Duration::from_millis(pulse_ms.unwrap_or(100).clamp(10, 2000));
+    let set_lines = |dtr: bool, rts: bool| {
+        serial_request(&session, move |reply| SerialCommand::ControlLines {
+            dtr: Some(dtr),
+            rts: Some(rts),
+            reply,
+        })
+    };
+
+    // ESP32/ESP8266 dev boards wire RTS to EN and DTR to IO0 (through inverting transistors);
+    // Arduino boards reset on the DTR edge. This is the same sequence esptool uses.
+    set_lines(false, true).await?;
+    tokio::time::sleep(pulse).await;
+    if bootloader.unwrap_or(false) {
+        set_lines(true, false).await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    set_lines(false, false).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn flash_firmware(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: String,
+    file: String,
+    chip: Option<String>,
+    baud_rate: Option<u32>,
+    flash_address: Option<String>,
+) -> Result<FlashFirmwareResult, String> {
+    let port = port.trim().to_string();
+    if port.starts_with("mock://") {
+        return Err("Cannot flash a mock serial device".to_string());
+    }
+    let file_path = PathBuf::from(file.trim());
+    if !file_path.is_file() {
+        return Err(format!("Firmware file not found: {}", file_path.display()));
+    }
+    let chip = chip
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "esp32".to_string());
+
+    // An open session holds the port exclusively; park it until the flasher is done.
+    let session = serial_session_for_port(&state, Some(&port)).ok();
+    let device_path = match &session {
+        Some(session) => session
+            .device_path
+            .lock()
+            .map_err(|_| "Serial device path lock poisoned".to_string())?
+            .clone(),
+        None => resolve_serial_target(&port)?,
+    };
+    let (tool, program, args) = flash_firmware_command(
+        &chip,
+        &device_path,
+        &file_path,
+        baud_rate,
+        flash_address.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()),
+    )?;
+
+    let mut resume_tx = None;
+    if let Some(session) = &session {
+        let (tx, rx) = oneshot::channel();
+        serial_request(session, move |reply| SerialCommand::Suspend { reply, resume: rx }).await?;
+        resume_tx = Some(tx);
+    }
+
+    append_desktop_audit_log(
+        "serial.flash_firmware.start",
+        &json!({ "port": port, "device_path": device_path, "chip": chip, "tool": tool, "args": args }),
+    );
+    emit_flash_progress(&app, &port, "start", Some(0), None);
+    let started = std::time::Instant::now();
+
+    let run_app = app.clone();
+    let run_port = port.clone();
+    let outcome = tauri::async_runtime::spawn_blocking(move || -> Result<(Option<i32>, Vec<String>), String> {
+        let mut child = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn {program}: {e}"))?;
+        let tail = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_reader = child.stderr.take().map(|stderr| {
+            let (app, port, tail) = (run_app.clone(), run_port.clone(), tail.clone());
+            thread::spawn(move || stream_flash_output(&app, &port, stderr, &tail))
+        });
+        if let Some(stdout) = child.stdout.take() {
+            stream_flash_output(&run_app, &run_port, stdout, &tail);
+        }
+        if let Some(handle) = stderr_reader {
+            let _ = handle.join();
+        }
+        let status = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for {program}: {e}"))?;
+        let tail = tail.lock().map(|t| t.iter().cloned().collect()).unwrap_or_default();
+        Ok((if status.success() { Some(0) } else { status.code().or(Some(-1)) }, tail))
+    })
+    .await
+    .map_err(|e| format!("Flash task failed: {e}"))
+    .and_then(|r| r);
+
+    // Reopen the session whatever happened; the worker retries until the board re-enumerates.
+    let session_resumed = resume_tx.map(|tx| tx.send(()).is_ok()).unwrap_or(false);
+    let duration_ms = started.elapsed().as_millis();
+
+    let failure = match &outcome {
+        Ok((Some(0), _)) => None,
+        Ok((code, tail)) => Some(format!(
+            "{tool} exited with code {}: {}",
+            code.unwrap_or(-1),
+            tail.join(" | ")
+        )),
+        Err(error) => Some(error.clone()),
+    };
+    append_desktop_audit_log(
+        "serial.flash_firmware.finish",
+        &json!({ "port": port, "chip": chip, "tool": tool, "duration_ms": duration_ms, "error": failure }),
+    );
+    if let Some(error) = failure {
+        emit_flash_progress(&app, &port, "failed", None, Some(error.clone()));
+        return Err(error);
+    }
+    emit_flash_progress(&app, &port, "done", Some(100), None);
+
+    Ok(FlashFirmwareResult {
+        port,
+        device_path,
+        chip,
+        tool,
+        exit_code: Some(0),
+        duration_ms,
+        session_resumed,
+    })
+}
+
+#[tauri::command]
+fn start_serial_capture(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: Option<String>,
+    file_name: Option<String>,
+) -> Result<SerialCaptureStatus, String> {
+    let session = serial_session_for_port(&state, port.as_deref())?;
+
+    let file_name = file_name
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| format!("serial_capture_{}.jsonl", unix_ts_ms()));
+    let safe_name = sanitize_log_file_name(&file_name)?;
+    let logs_dir = app_logs_dir(&app)?;
+    std::fs::create_dir_all(&logs_dir)
+        .map_err(|e| format!("Failed to create logs directory {}: {e}", logs_dir.display()))?;
+    let path = logs_dir.join(safe_name);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+
+    let mut capture = session
+        .capture
+        .lock()
+        .map_err(|_| "Serial capture lock poisoned".to_string())?;
+    // Starting a new capture closes any previous one for this session.
+    *capture = Some(SerialCapture {
+        file,
+        path,
+        started_ts_ms: unix_ts_ms(),
+        lines_written: 0,
+    });
+    Ok(serial_capture_status(&capture))
+}
+
+#[tauri::command]
+async fn replay_serial_capture(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: Option<String>,
+    file_name: String,
+    speed_factor: Option<f64>,
+) -> Result<SerialReplayResult, String> {
+    let speed = speed_factor.unwrap_or(1.0);
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(format!("speed_factor must be a positive number, got {speed}"));
+    }
+    let safe_name = sanitize_log_file_name(&file_name)?;
+    let path = app_logs_dir(&app)?.join(safe_name);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    // Only TX lines are replayed; RX lines in the capture are what the device said back.
+    let mut lines_skipped = 0;
+    let mut tx_lines: Vec<(u64, String)> = Vec::new();
+    for raw in content.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<Value>(raw) else {
+            lines_skipped += 1;
+            continue;
+        };
+        let direction = entry.get("direction").and_then(|v| v.as_str()).unwrap_or("");
+        let ts_ms = entry.get("ts_ms").and_then(|v| v.as_u64());
+        let line = entry.get("line").and_then(|v| v.as_str());
+        match (direction, ts_ms, line) {
+            ("tx", Some(ts_ms), Some(line)) => tx_lines.push((ts_ms, line.to_string())),
+            _ => lines_skipped += 1,
+        }
+    }
+    if tx_lines.is_empty() {
+        return Err(format!("No TX lines found in {}", path.display()));
+    }
+
+    let session = serial_session_for_port(&state, port.as_deref())?;
+    let start = tokio::time::Instant::now();
+    let first_ts = tx_lines[0].0;
+    let mut lines_sent = 0;
+    for (ts_ms, line) in &tx_lines {
+        let offset = Duration::from_secs_f64(ts_ms.saturating_sub(first_ts) as f64 / 1000.0 / speed);
+        tokio::time::sleep_until(start + offset).await;
+        write_serial_line(&app, &session, line)
+            .await
+            .map_err(|e| format!("Replay stopped after {lines_sent} lines: {e}"))?;
+        lines_sent += 1;
+    }
+
+    append_desktop_audit_log(
+        "serial.replay",
+        &json!({
+            "port": session.port_name,
+            "path": path.display().to_string(),
+            "lines_sent": lines_sent,
+            "speed_factor": speed
+        }),
+    );
+    Ok(SerialReplayResult {
+        path: path.display().to_string(),
+        lines_sent,
+        lines_skipped,
+        duration_ms: start.elapsed().as_millis(),
+    })
+}
+
+#[tauri::command]
+fn stop_serial_capture(state: State<'_, AppState>, port: Option<String>) -> Result<SerialCaptureStatus, String> {
+    let Ok(session) = serial_session_for_port(&state, port.as_deref()) else {
+        return Ok(serial_capture_status(&None));
+    };
+
+    let mut capture = session
+        .capture
+        .lock()
+        .map_err(|_| "Serial capture lock poisoned".to_string())?;
+    let status = serial_capture_status(&capture);
+    if let Some(mut cap) = capture.take() {
+        let _ = cap.file.flush();
+    }
+    Ok(SerialCaptureStatus { active: false, ..status })
+}
+
+#[tauri::command]
+fn get_http_client_config(state: State<'_, AppState>) -> Result<HttpClientConfig, String> {
+    with_http_clients(&state.http, |c| c.config)
+}
+
+#[tauri::command]
+fn set_http_client_config(
+    state: State<'_, AppState>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout_ms: Option<u64>,
+    connect_timeout_ms: Option<u64>,
+    openai_timeout_ms: Option<u64>,
+    orchestrator_timeout_ms: Option<u64>,
+    vision_timeout_ms: Option<u64>,
+) -> Result<HttpClientConfig, String> {
+    let current = with_http_clients(&state.http, |c| c.config)?;
+    let config = HttpClientConfig {
+        pool_max_idle_per_host: pool_max_idle_per_host.unwrap_or(current.pool_max_idle_per_host),
+        pool_idle_timeout_ms: pool_idle_timeout_ms.unwrap_or(current.pool_idle_timeout_ms),
+        connect_timeout_ms: connect_timeout_ms.unwrap_or(current.connect_timeout_ms).max(100),
+        openai_timeout_ms: openai_timeout_ms.unwrap_or(current.openai_timeout_ms).max(1_000),
+        orchestrator_timeout_ms: orchestrator_timeout_ms.unwrap_or(current.orchestrator_timeout_ms),
+        vision_timeout_ms: vision_timeout_ms.unwrap_or(current.vision_timeout_ms),
+    };
+    // Requests already in flight keep the old clients; new ones pick these up.
+    let clients = HttpClients::build(config)?;
+    *state
+        .http
+        .clients
+        .lock()
+        .map_err(|_| "HTTP client lock poisoned".to_string())? = Some(clients);
+    append_desktop_audit_log("http.client_config", &json!({ "config": config }));
+    Ok(config)
+}
+
+#[tauri::command]
+fn list_endpoint_settings(state: State<'_, AppState>) -> Result<Vec<EndpointSettingsSummary>, String> {
+    let lock = state
+        .http
+        .endpoints
+        .lock()
+        .map_err(|_| "Endpoint settings lock poisoned".to_string())?;
+    let mut summaries = lock
+        .iter()
+        .map(|(base_url, settings)| settings.summary(base_url))
+        .collect::<Vec<_>>();
+    summaries.sort_by(|a, b| a.base_url.cmp(&b.base_url));
+    Ok(summaries)
+}
+
+/// Sets (or, with an empty/missing token, clears) the bearer token for an orchestrator or
+/// vision base URL and persists it.
+#[tauri::command]
+fn set_endpoint_auth(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    base_url: String,
+    bearer_token: Option<String>,
+) -> Result<EndpointSettingsSummary, String> {
+    let base_url = normalize_base_url(&base_url)?;
+    let token = bearer_token.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    if token.as_deref().is_some_and(|t| t.chars().any(|c| c.is_control() || c.is_whitespace())) {
+        return Err("bearer_token must not contain whitespace or control characters".to_string());
+    }
+
+    let summary = update_endpoint_settings(&app, &state.http, &base_url, |entry| entry.bearer_token = token)?;
+    append_desktop_audit_log(
+        "endpoint.auth",
+        &json!({ "base_url": base_url, "has_bearer_token": summary.has_bearer_token }),
+    );
+    Ok(summary)
+}
+
+/// Configures certificate checking for an `https://` orchestrator or vision base URL: either
+/// pin a CA bundle (`ca_cert_path`) or accept one self-signed certificate by its SHA-256
+/// fingerprint (`cert_sha256`). Passing neither restores the default webpki roots.
+#[tauri::command]
+fn set_endpoint_tls(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    base_url: String,
+    ca_cert_path: Option<String>,
+    cert_sha256: Option<String>,
+) -> Result<EndpointSettingsSummary, String> {
+    let base_url = normalize_base_url(&base_url)?;
+    let ca_cert_path = ca_cert_path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    let cert_sha256 = cert_sha256
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| normalize_cert_sha256(&f))
+        .transpose()?;
+    if ca_cert_path.is_some() && cert_sha256.is_some() {
+        return Err("Set either ca_cert_path or cert_sha256, not both".to_string());
+    }
+    if (ca_cert_path.is_some() || cert_sha256.is_some()) && !base_url.starts_with("https://") {
+        return Err(format!("TLS settings need an https:// base URL, got {base_url}"));
+    }
+    if let Some(path) = &ca_cert_path {
+        // Fail now rather than on the first request.
+        load_ca_certificates(path)?;
+    }
+
+    let summary = update_endpoint_settings(&app, &state.http, &base_url, |entry| {
+        entry.ca_cert_path = ca_cert_path;
+        entry.cert_sha256 = cert_sha256;
+    })?;
+    append_desktop_audit_log(
+        "endpoint.tls",
+        &json!({
+            "base_url": base_url,
+            "ca_cert_path": summary.ca_cert_path,
+            "cert_sha256": summary.cert_sha256
+        }),
+    );
+    Ok(summary)
+}
+
+/// Header values are left out, since they usually carry credentials.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenAiConfigSummary {
+    base_url: String,
+    api_version: Option<String>,
+    header_names: Vec<String>,
+}
+
+#[tauri::command]
+fn get_openai_config(state: State<'_, AppState>) -> Result<OpenAiConfigSummary, String> {
+    let config = openai_config(&state.http)?;
+    let mut header_names = config.headers.keys().cloned().collect::<Vec<_>>();
+    header_names.sort();
+    Ok(OpenAiConfigSummary {
+        base_url: config.base_url.unwrap_or_else(|| OPENAI_API_BASE.to_string()),
+        api_version: config.api_version,
+        header_names,
+    })
+}
+
+/// App-wide OpenAI endpoint settings used by critic sessions that do not override them, saved
+/// across restarts. Passing no `base_url` restores `https://api.openai.com/v1`.
+#[tauri::command]
+fn set_openai_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    base_url: Option<String>,
+    api_version: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<OpenAiConfigSummary, String> {
+    let config = OpenAiEndpointConfig {
+        base_url,
+        api_version,
+        headers: headers.unwrap_or_default(),
+    }
+    .validate()?;
+    append_desktop_audit_log(
+        "openai.config",
+        &json!({
+            "base_url": config.base_url,
+            "api_version": config.api_version,
+            "header_names": config.headers.keys().collect::<Vec<_>>()
+        }),
+    );
+    save_openai_config(&app, &config)?;
+    *state
+        .http
+        .openai
+        .lock()
+        .map_err(|_| "OpenAI config lock poisoned".to_string())? = Some(config);
+    get_openai_config(state)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticPromptTemplateInfo {
+    name: String,
+    builtin: bool,
+    path: Option<String>,
+}
+
+/// Lists `default` plus every `*.txt` under the templates dir, sorted by name.
+#[tauri::command]
+fn list_critic_prompt_templates(app: AppHandle) -> Result<Vec<CriticPromptTemplateInfo>, String> {
+    let dir = critic_prompts_dir(&app)?;
+    let mut templates = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if validate_prompt_template_name(name).is_err() {
+                continue;
+            }
+            templates.push(CriticPromptTemplateInfo {
+                name: name.to_string(),
+                builtin: false,
+                path: Some(path.display().to_string()),
+            });
+        }
+    }
+    if !templates.iter().any(|t| t.name == DEFAULT_CRITIC_PROMPT_TEMPLATE) {
+        templates.push(CriticPromptTemplateInfo {
+            name: DEFAULT_CRITIC_PROMPT_TEMPLATE.to_string(),
+            builtin: true,
+            path: None,
+        });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+#[tauri::command]
+fn read_critic_prompt_template(app: AppHandle, name: String) -> Result<String, String> {
+    load_critic_prompt_template(&app, &name)
+}
+
+/// Writes (or with an empty `template`, deletes) a named template. Takes effect on the next
+/// `critic_spawn`; a running session keeps the template it started with.
+#[tauri::command]
+fn save_critic_prompt_template(app: AppHandle, name: String, template: String) -> Result<(), String> {
+    let name = validate_prompt_template_name(&name)?;
+    let dir = critic_prompts_dir(&app)?;
+    let path = dir.join(format!("{name}.txt"));
+    if template.trim().is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {e}", path.display()))?;
+        }
+    } else {
+        if !template.contains("{task}") {
+            return Err("Template must contain the {task} placeholder".to_string());
+        }
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        std::fs::write(&path, &template).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    }
+    append_desktop_audit_log(
+        "critic.prompt_template_saved",
+        &json!({ "name": name, "deleted": template.trim().is_empty() }),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+fn get_critic_retry_policy(state: State<'_, AppState>) -> Result<CriticRetryPolicy, String> {
+    critic_retry_policy(&state.http)
+}
+
+#[tauri::command]
+fn set_critic_retry_policy(
+    state: State<'_, AppState>,
+    max_attempts: Option<u32>,
+    initial_backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+) -> Result<CriticRetryPolicy, String> {
+    let current = critic_retry_policy(&state.http)?;
+    let policy = CriticRetryPolicy {
+        max_attempts: max_attempts.unwrap_or(current.max_attempts).clamp(1, 10),
+        initial_backoff_ms: initial_backoff_ms.unwrap_or(current.initial_backoff_ms),
+        max_backoff_ms: max_backoff_ms.unwrap_or(current.max_backoff_ms),
+    };
+    *state
+        .http
+        .critic_retry
+        .lock()
+        .map_err(|_| "Retry policy lock poisoned".to_string())? = Some(policy.clone());
+    Ok(policy)
+}
+
+#[tauri::command]
+fn get_orchestrator_retry_policy(state: State<'_, AppState>) -> Result<OrchestratorRetryPolicy, String> {
+    orchestrator_retry_policy(&state.http)
+}
+
+#[tauri::command]
+fn set_orchestrator_retry_policy(
+    state: State<'_, AppState>,
+    max_attempts: Option<u32>,
+    initial_backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+    retry_on_status: Option<Vec<u16>>,
+) -> Result<OrchestratorRetryPolicy, String> {
+    let current = orchestrator_retry_policy(&state.http)?;
+    let policy = OrchestratorRetryPolicy {
+        max_attempts: max_attempts.unwrap_or(current.max_attempts).clamp(1, 10),
+        initial_backoff_ms: initial_backoff_ms.unwrap_or(current.initial_backoff_ms),
+        max_backoff_ms: max_backoff_ms.unwrap_or(current.max_backoff_ms),
+        retry_on_status: retry_on_status.unwrap_or(current.retry_on_status),
+    };
+    if policy.retry_on_status.iter().any(|code| !(100..=599).contains(code)) {
+        return Err(format!("retry_on_status must contain HTTP status codes, got {:?}", policy.retry_on_status));
+    }
+    *state
+        .http
+        .orchestrator_retry
+        .lock()
+        .map_err(|_| "Retry policy lock poisoned".to_string())? = Some(policy.clone());
+    Ok(policy)
+}
+
+/// Probes one candidate with `request`, a `GET <base_url>/status`; only endpoints whose `/status`
+/// parses as an orchestrator status count.
+async fn probe_orchestrator_status(
+    request: reqwest::RequestBuilder,
+    host: String,
+    port: u16,
+    timeout: Duration,
+) -> Option<DiscoveredOrchestrator> {
+    let base_url = format!("http://{host}:{port}");
+    let started = std::time::Instant::now();
+    let response = request
+        .timeout(timeout)
+        .send()
+        .await
+        .ok()
+        .filter(|r| r.status().is_success())?;
+    let status = response.json::<OrchestratorStatusResponse>().await.ok().filter(|s| s.ok)?;
+    Some(DiscoveredOrchestrator {
+        base_url,
+        host,
+        port,
+        latency_ms: started.elapsed().as_millis(),
+        node_count: status.nodes.len(),
+        connected_nodes: status.nodes.iter().filter(|n| n.connected).count(),
+        managed_instance: None,
+    })
+}
+
+#[tauri::command]
+async fn orchestrator_discover(
+    state: State<'_, AppState>,
+    port_start: Option<u16>,
+    port_end: Option<u16>,
+    hosts: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<DiscoveredOrchestrator>, String> {
+    let port_start = port_start.unwrap_or(5050);
+    let port_end = port_end.unwrap_or(5070);
+    if port_end < port_start || port_end - port_start > 256 {
+        return Err(format!("Invalid port range {port_start}-{port_end} (at most 256 ports)"));
+    }
+    let mut scan_hosts = vec!["127.0.0.1".to_string()];
+    for host in hosts.unwrap_or_default() {
+        let host = host.trim().to_string();
+        if !host.is_empty() && !scan_hosts.contains(&host) {
+            scan_hosts.push(host);
+        }
+    }
+    if scan_hosts.len() > 16 {
+        return Err("At most 16 hosts can be scanned at once".to_string());
+    }
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(400).clamp(50, 5_000));
+
+    let client = http_client(&state.http)?;
+    let mut probes = Vec::new();
+    for host in &scan_hosts {
+        for port in port_start..=port_end {
+            let base_url = format!("http://{host}:{port}");
+            let request = with_endpoint_auth(&state.http, client.get(format!("{base_url}/status")), &base_url)?;
+            probes.push(tauri::async_runtime::spawn(probe_orchestrator_status(
+                request,
+                host.clone(),
+                port,
+                timeout,
+            )));
+        }
+    }
+    let mut found = Vec::new();
+    for probe in probes {
+        if let Ok(Some(entry)) = probe.await {
+            found.push(entry);
+        }
+    }
+
+    let managed = state
+        .processes
+        .list(Some(ORCHESTRATOR_PROCESS_KIND))?
+        .into_iter()
+        .filter_map(|p| Some((p.http_base_url?, p.name)))
+        .collect::<HashMap<_, _>>();
+    for entry in &mut found {
+        // Spawned instances are recorded by the host string they were started with.
+        let localhost_alias = entry.base_url.replace("127.0.0.1", "localhost");
+        entry.managed_instance = managed
+            .get(&entry.base_url)
+            .or_else(|| managed.get(&localhost_alias))
+            .cloned();
+    }
+    append_desktop_audit_log(
+        "orchestrator.discover",
+        &json!({ "hosts": scan_hosts, "port_start": port_start, "port_end": port_end, "found": found.len() }),
+    );
+    Ok(found)
+}
+
+#[tauri::command]
+async fn orchestrator_metrics(
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    timeout_ms: Option<u64>,
+) -> Result<OrchestratorMetrics, String> {
+    let base = normalize_base_url(&orchestrator_base_url)?;
+    let started = std::time::Instant::now();
+    let cid = new_correlation_id();
+    let status = orchestrator_request(&state.http, reqwest::Method::GET, base.clone(), "/status", None, Some(cid.clone()), timeout_ms)
+        .await
+        .and_then(|raw| typed_orchestrator_reply::<OrchestratorStatusResponse>("/status", raw, cid));
+    let status_latency_ms = started.elapsed().as_millis();
+    let counters = orchestrator_counters(&base);
+
+    let mut metrics = OrchestratorMetrics {
+        base_url: base.clone(),
+        reachable: status.is_ok(),
+        status_latency_ms: status.is_ok().then_some(status_latency_ms),
+        status_error: status.as_ref().err().cloned(),
+        node_count: 0,
+        connected_nodes: 0,
+        requests: counters.requests,
+        errors: counters.errors,
+        last_error: counters.last_error,
+        last_request_ts_ms: counters.last_request_ts_ms,
+        execute_plan_count: counters.execute_plan_count,
+        avg_execute_plan_ms: (counters.execute_plan_count > 0)
+            .then(|| counters.execute_plan_total_ms as f64 / counters.execute_plan_count as f64),
+        managed_instance: None,
+        child_pid: None,
+        child_uptime_ms: None,
+    };
+    if let Ok(reply) = status {
+        metrics.node_count = reply.data.nodes.len();
+        metrics.connected_nodes = reply.data.nodes.iter().filter(|n| n.connected).count();
+        if let Ok(mut cache) = state.node_manifests.lock() {
+            cache_status_manifests(&mut cache, &reply.raw);
+        }
+    }
+
+    let managed = state.processes.list(Some(ORCHESTRATOR_PROCESS_KIND))?.into_iter().find(|p| {
+        p.running
+            && p.http_base_url
+                .as_deref()
+                .and_then(|url| normalize_base_url(url).ok())
+                .as_deref()
+                == Some(base.as_str())
+    });
+    if let Some(proc_) = managed {
+        metrics.child_pid = proc_.pid;
+        metrics.child_uptime_ms = proc_.uptime_ms;
+        metrics.managed_instance = Some(proc_.name);
+    }
+    Ok(metrics)
+}
+
+#[tauri::command]
+async fn orchestrator_status(
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<OrchestratorReply<OrchestratorStatusResponse>, String> {
+    let cid = correlation_id_or_new(correlation_id);
+    let status = orchestrator_request(
+        &state.http,
+        reqwest::Method::GET,
+        orchestrator_base_url,
+        "/status",
+        None,
+        Some(cid.clone()),
+        timeout_ms,
+    )
+    .await?;
+    if let Ok(mut cache) = state.node_manifests.lock() {
+        cache_status_manifests(&mut cache, &status);
+    }
+    typed_orchestrator_reply("/status", status, cid)
+}
+
+/// Validates a raw orchestrator response against its Rust model, so a schema change on the
+/// Python side fails loudly here instead of surfacing as missing fields in the UI.
+fn typed_orchestrator_reply<T: serde::de::DeserializeOwned>(
+    path: &str,
+    raw: Value,
+    correlation_id: String,
+) -> Result<OrchestratorReply<T>, String> {
+    match serde_json::from_value::<T>(raw.clone()) {
+        Ok(data) => Ok(OrchestratorReply {
+            data,
+            request_correlation_id: correlation_id,
+            raw,
+        }),
+        Err(error) => {
+            append_desktop_audit_log(
+                "orchestrator.schema_mismatch",
+                &json!({ "path": path, "error": error.to_string(), "body": raw, "correlation_id": correlation_id }),
+            );
+            Err(format!("{path} response does not match the expected schema: {error}"))
+        }
+    }
+}
+
+#[tauri::command]
+async fn orchestrator_validate_plan(
+    state: State<'_, AppState>,
+    plan: Value,
+    orchestrator_base_url: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<PlanValidationResult, String> {
+    // Optionally refresh the cache first; validation itself never touches the orchestrator.
+    if let Some(base) = orchestrator_base_url.filter(|s| !s.trim().is_empty()) {
+        let status = orchestrator_request(&state.http, reqwest::Method::GET, base, "/status", None, None, timeout_ms).await?;
+        let mut cache = state
+            .node_manifests
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        cache_status_manifests(&mut cache, &status);
+    }
+    let nodes = state
+        .node_manifests
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    Ok(validate_plan_against_manifests(&plan, &nodes))
+}
+
+#[tauri::command]
+async fn orchestrator_execute_plan(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    plan: Vec<PlanStep>,
+    correlation_id: Option<String>,
+    stream_progress: Option<bool>,
+    timeout_ms: Option<u64>,
+) -> Result<OrchestratorReply<OrchestratorAck>, String> {
+    // Progress and aborts are matched by correlation id, so make sure there always is one.
+    let correlation_id = correlation_id_or_new(correlation_id);
+    let timeout = timeout_ms.filter(|ms| *ms > 0).map(Duration::from_millis);
+    if !stream_progress.unwrap_or(false) {
+        return execute_plan_tracked(&state, orchestrator_base_url, plan, correlation_id, timeout).await;
+    }
+    execute_plan_streaming(&app, &state, orchestrator_base_url, plan, correlation_id, timeout).await
+}
+
+/// Runs a plan while polling the orchestrator's `/progress` endpoint, emitting a
+/// `plan_progress` event whenever the snapshot for this correlation id changes.
+async fn execute_plan_streaming(
+    app: &AppHandle,
+    state: &AppState,
+    orchestrator_base_url: String,
+    plan: Vec<PlanStep>,
+    correlation_id: String,
+    timeout: Option<Duration>,
+) -> Result<OrchestratorReply<OrchestratorAck>, String> {
+    let base = normalize_base_url(&orchestrator_base_url)?;
+    let (client, prefix) = orchestrator_endpoint(&state.http, &base)?;
+    let mut last_seq = None;
+    let poll = || {
+        let url = format!("{prefix}/progress");
+        let request = with_endpoint_auth(&state.http, client.get(url), &base)
+            .map(|request| request.timeout(Duration::from_millis(500)).send());
+        async move {
+            let body = request.ok()?.await.ok()?.json::<Value>().await.ok()?;
+            body.get("progress").cloned()
+        }
+    };
+    let mut emit_if_new = |progress: Option<Value>| {
+        let Some(progress) = progress else {
+            return;
+        };
+        let ours = progress.get("correlation_id").and_then(|c| c.as_str()) == Some(correlation_id.as_str());
+        let seq = progress.get("seq").and_then(|s| s.as_u64());
+        if ours && seq != last_seq {
+            last_seq = seq;
+            let _ = app.emit(PLAN_PROGRESS_EVENT, progress);
+        }
+    };
+
+    let execute = execute_plan_tracked(state, orchestrator_base_url.clone(), plan, correlation_id.clone(), timeout);
+    tokio::pin!(execute);
+    let mut tick = tokio::time::interval(Duration::from_millis(200));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let result = loop {
+        tokio::select! {
+            result = &mut execute => break result,
+            _ = tick.tick() => emit_if_new(poll().await),
+        }
+    };
+    // One last read so the terminal ok/error snapshot is never missed between ticks.
+    emit_if_new(poll().await);
+    result
+}
+
+/// POSTs `/execute_plan` while recording it as the active plan (which arms serial watchdogs).
+/// The request can be cut short by `orchestrator_abort_request` or by `timeout`; a timeout also
+/// sends `/stop`, since the orchestrator keeps driving hardware after we stop waiting.
+async fn execute_plan_tracked(
+    state: &AppState,
+    orchestrator_base_url: String,
+    plan: Vec<PlanStep>,
+    correlation_id: String,
+    timeout: Option<Duration>,
+) -> Result<OrchestratorReply<OrchestratorAck>, String> {
+    // /execute_plan blocks until the plan finishes, so the request's lifetime is the plan's.
+    let active = ActivePlan {
+        orchestrator_base_url: orchestrator_base_url.clone(),
+        correlation_id: Some(correlation_id.clone()),
+        started: std::time::Instant::now(),
+    };
+    let started = active.started;
+    if let Ok(mut lock) = state.active_plan.lock() {
+        *lock = Some(active);
+    }
+    let (abort_tx, mut abort_rx) = oneshot::channel();
+    if let Ok(mut inflight) = state.inflight_plans.lock() {
+        inflight.insert(
+            correlation_id.clone(),
+            InflightPlan {
+                abort_tx,
+                orchestrator_base_url: orchestrator_base_url.clone(),
+            },
+        );
+    }
+
+    let request = orchestrator_request(
+        &state.http,
+        reqwest::Method::POST,
+        orchestrator_base_url.clone(),
+        "/execute_plan",
+        Some(json!({ "plan": plan, "correlation_id": correlation_id.clone() })),
+        Some(correlation_id.clone()),
+        None,
+    );
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let result = tokio::select! {
+        result = request => result,
+        // Only an explicit abort counts; a dropped sender (entry replaced) is not one.
+        Ok(()) = &mut abort_rx => Err(format!("execute_plan {correlation_id} aborted")),
+        _ = deadline => {
+            append_desktop_audit_log(
+                "orchestrator.execute_plan.timeout",
+                &json!({ "correlation_id": correlation_id, "timeout_ms": timeout.map(|t| t.as_millis()) }),
+            );
+            let _ = send_orchestrator_stop(&state.http, orchestrator_base_url, Some(correlation_id.clone()), None).await;
+            Err(format!(
+                "execute_plan {correlation_id} timed out after {}ms; sent /stop",
+                timeout.map(|t| t.as_millis()).unwrap_or(0)
+            ))
+        }
+    };
+
+    if let Ok(mut inflight) = state.inflight_plans.lock() {
+        inflight.remove(&correlation_id);
+    }
+    if let Ok(mut lock) = state.active_plan.lock() {
+        if lock.as_ref().map(|p| p.started) == Some(started) {
+            *lock = None;
+        }
+    }
+    typed_orchestrator_reply("/execute_plan", result?, correlation_id)
+}
+
+#[tauri::command]
+async fn orchestrator_abort_request(
+    state: State<'_, AppState>,
+    correlation_id: String,
+    send_stop: Option<bool>,
+) -> Result<bool, String> {
+    let inflight = state
+        .inflight_plans
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .remove(correlation_id.trim());
+    let Some(inflight) = inflight else {
+        return Ok(false);
+    };
+    let _ = inflight.abort_tx.send(());
+    append_desktop_audit_log(
+        "orchestrator.execute_plan.abort",
+        &json!({ "correlation_id": correlation_id, "send_stop": send_stop.unwrap_or(true) }),
+    );
+    if send_stop.unwrap_or(true) {
+        send_orchestrator_stop(&state.http, inflight.orchestrator_base_url, Some(correlation_id), None).await?;
+    }
+    Ok(true)
+}
+
+fn emit_plan_queue_event(app: &AppHandle, entry: &QueuedPlan) {
+    let _ = app.emit(PLAN_QUEUE_EVENT, entry.clone());
+}
+
+/// Drains the plan queue one entry at a time. Exits (clearing `worker_running` under the same
+/// lock) once nothing is queued, so a concurrent enqueue either sees it running or restarts it.
+async fn plan_queue_worker(app: AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    loop {
+        let next = {
+            let Ok(mut queue) = state.plan_queue.lock() else {
+                return;
+            };
+            let next = queue.entries.iter_mut().find(|e| e.state == PlanQueueState::Queued);
+            match next {
+                Some(entry) => {
+                    entry.state = PlanQueueState::Running;
+                    entry.started_ts_ms = Some(unix_ts_ms());
+                    emit_plan_queue_event(&app, entry);
+                    entry.clone()
+                }
+                None => {
+                    queue.worker_running = false;
+                    return;
+                }
+            }
+        };
+
+        let result = execute_plan_streaming(
+            &app,
+            &state,
+            next.orchestrator_base_url.clone(),
+            next.plan.clone(),
+            next.correlation_id.clone(),
+            None,
+        )
+        .await;
+
+        let Ok(mut queue) = state.plan_queue.lock() else {
+            return;
+        };
+        if let Some(entry) = queue.get_mut(&next.plan_id) {
+            // A cancel during the run already marked it; keep that outcome.
+            if entry.state == PlanQueueState::Running {
+                match result {
+                    Ok(value) => {
+                        entry.state = PlanQueueState::Succeeded;
+                        entry.result = Some(value);
+                    }
+                    Err(error) => {
+                        entry.state = PlanQueueState::Failed;
+                        entry.error = Some(error);
+                    }
+                }
+            }
+            entry.finished_ts_ms = Some(unix_ts_ms());
+            emit_plan_queue_event(&app, entry);
+        }
+        queue.prune_finished();
+    }
+}
+
+#[tauri::command]
+fn orchestrator_enqueue_plan(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    plan: Vec<PlanStep>,
+    correlation_id: Option<String>,
+) -> Result<QueuedPlan, String> {
+    let orchestrator_base_url = normalize_base_url(&orchestrator_base_url)?;
+    let mut queue = state
+        .plan_queue
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    queue.next_id += 1;
+    let plan_id = format!("plan-{}", queue.next_id);
+    let entry = QueuedPlan {
+        correlation_id: correlation_id
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| format!("{plan_id}-{}", unix_ts_ms())),
+        plan_id,
+        orchestrator_base_url,
+        plan,
+        state: PlanQueueState::Queued,
+        enqueued_ts_ms: unix_ts_ms(),
+        started_ts_ms: None,
+        finished_ts_ms: None,
+        result: None,
+        error: None,
+    };
+    queue.entries.push_back(entry.clone());
+    emit_plan_queue_event(&app, &entry);
+    if !queue.worker_running {
+        queue.worker_running = true;
+        tauri::async_runtime::spawn(plan_queue_worker(app.clone()));
+    }
+    Ok(entry)
+}
+
+#[tauri::command]
+fn orchestrator_queue_status(state: State<'_, AppState>) -> Result<Vec<QueuedPlan>, String> {
+    let queue = state
+        .plan_queue
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    Ok(queue.entries.iter().cloned().collect())
+}
+
+/// Cancels a queued plan outright; a running plan is marked cancelled and the orchestrator is
+/// sent `/stop`, which interrupts the in-flight `/execute_plan`.
+#[tauri::command]
+fn orchestrator_cancel(app: AppHandle, state: State<'_, AppState>, plan_id: String) -> Result<QueuedPlan, String> {
+    let mut queue = state
+        .plan_queue
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    let entry = queue
+        .get_mut(plan_id.trim())
+        .ok_or_else(|| format!("Unknown plan_id {plan_id}"))?;
+    match entry.state {
+        PlanQueueState::Queued => {
+            entry.finished_ts_ms = Some(unix_ts_ms());
+        }
+        PlanQueueState::Running => {
+            // Abort the in-flight request so the worker moves on, then stop the hardware.
+            if let Some(inflight) = state
+                .inflight_plans
+                .lock()
+                .map_err(|_| "State lock poisoned".to_string())?
+                .remove(&entry.correlation_id)
+            {
+                let _ = inflight.abort_tx.send(());
+            }
+            let base = entry.orchestrator_base_url.clone();
+            let cid = entry.correlation_id.clone();
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<AppState>();
+                let _ = send_orchestrator_stop(&state.http, base, Some(cid), None).await;
+            });
+        }
+        _ => return Err(format!("Plan {plan_id} already finished")),
+    }
+    entry.state = PlanQueueState::Cancelled;
+    emit_plan_queue_event(&app, entry);
+    Ok(entry.clone())
+}
+
+#[tauri::command]
+async fn orchestrator_stop(
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<OrchestratorReply<OrchestratorAck>, String> {
+    send_orchestrator_stop(&state.http, orchestrator_base_url, correlation_id, timeout_ms).await
+}
+
+async fn send_orchestrator_stop(
+    http: &HttpState,
+    orchestrator_base_url: String,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<OrchestratorReply<OrchestratorAck>, String> {
+    let cid = correlation_id_or_new(correlation_id);
+    let raw = orchestrator_request(
+        http,
+        reqwest::Method::POST,
+        orchestrator_base_url,
+        "/stop",
+        Some(json!({})),
+        Some(cid.clone()),
+        timeout_ms,
+    )
+    .await?;
+    typed_orchestrator_reply("/stop", raw, cid)
+}
+
+/// Polls `/status` every `interval` and emits `orchestrator_health`. Without an explicit base URL
+/// it follows the managed instance, so a respawn on a new port is picked up automatically.
+async fn orchestrator_health_loop(
+    app: AppHandle,
+    instance: String,
+    base_url: Option<String>,
+    interval: Duration,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = tick.tick() => {}
+        }
+
+        let base = base_url.clone().or_else(|| {
+            app.try_state::<AppState>()
+                .and_then(|state| managed_orchestrator_status(&state, &instance).ok().flatten()?.http_base_url)
+        });
+        let mut event = OrchestratorHealthEvent {
+            instance: instance.clone(),
+            base_url: base.clone(),
+            health: OrchestratorHealth::Unreachable,
+            latency_ms: None,
+            disconnected_nodes: Vec::new(),
+            error: None,
+            ts_ms: unix_ts_ms(),
+        };
+        match base {
+            None => event.error = Some(format!("orchestrator instance {instance} is not running")),
+            Some(base) => {
+                let started = std::time::Instant::now();
+                let state = app.state::<AppState>();
+                let request = orchestrator_endpoint(&state.http, &base).and_then(|(client, prefix)| {
+                    with_endpoint_auth(&state.http, client.get(format!("{prefix}/status")), &base)
+                });
+                let response = match request {
+                    Ok(request) => request
+                        .timeout(interval.min(Duration::from_secs(2)))
+                        .send()
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                let body = match response {
+                    Ok(r) if r.status().is_success() => r.json::<Value>().await.map_err(|e| e.to_string()),
+                    Ok(r) => Err(format!("HTTP {}", r.status())),
+                    Err(e) => Err(e),
+                };
+                event.latency_ms = Some(started.elapsed().as_millis());
+                match body.and_then(|b| {
+                    serde_json::from_value::<OrchestratorStatusResponse>(b)
+                        .map_err(|e| format!("/status schema mismatch: {e}"))
+                }) {
+                    Ok(status) => {
+                        event.disconnected_nodes = status
+                            .nodes
+                            .iter()
+                            .filter(|n| !n.connected)
+                            .map(|n| n.alias.clone())
+                            .collect();
+                        event.health = if status.ok && event.disconnected_nodes.is_empty() {
+                            OrchestratorHealth::Ok
+                        } else {
+                            OrchestratorHealth::Degraded
+                        };
+                    }
+                    Err(error) => event.error = Some(error),
+                }
+            }
+        }
+        let _ = app.emit(ORCHESTRATOR_HEALTH_EVENT, event);
+    }
+}
+
+/// Nodes the heartbeat watches: every registry entry plus every `node_connect` connection,
+/// keyed by `host:port`.
+fn heartbeat_targets(app: &AppHandle, state: &AppState) -> Vec<(String, String, u16, Option<String>)> {
+    let mut targets: Vec<(String, String, u16, Option<String>)> = Vec::new();
+    for entry in load_node_registry(app).unwrap_or_default() {
+        let host = entry.host.trim().to_string();
+        if host.starts_with(SERIAL_NODE_SCHEME) || host.starts_with(BLE_NODE_SCHEME) {
+            continue;
+        }
+        targets.push((format!("{host}:{}", entry.port), host, entry.port, Some(entry.name)));
+    }
+    for conn in state.nodes.list().unwrap_or_default() {
+        if conn.host.starts_with(BLE_NODE_SCHEME) {
+            continue;
+        }
+        if !targets.iter().any(|(target, ..)| *target == conn.target) {
+            targets.push((conn.target, conn.host, conn.port, conn.device_name));
+        }
+    }
+    targets
+}
+
+/// Every `interval`, TCP-connects to each watched node (no handshake, so it costs the node
+/// nothing) and emits `node_online` / `node_offline` when a node's reachability changes, and
+/// once for each node on its first check.
+async fn node_heartbeat_loop(app: AppHandle, interval: Duration, timeout: Duration, mut stop_rx: oneshot::Receiver<()>) {
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = tick.tick() => {}
+        }
+        let state = app.state::<AppState>();
+        let probes = heartbeat_targets(&app, &state)
+            .into_iter()
+            .map(|(target, host, port, name)| {
+                tauri::async_runtime::spawn_blocking(move || {
+                    let started = std::time::Instant::now();
+                    // Resolving `*.local` names can be slow; it counts towards the latency.
+                    let reached = resolve_socket_addrs(split_node_scheme(&host).1, port).and_then(|addrs| {
+                        let addr = addrs.iter().find(|a| a.is_ipv4()).unwrap_or(&addrs[0]);
+                        TcpStream::connect_timeout(addr, timeout).map_err(|e| format!("Connect to {addr} failed: {e}"))
+                    });
+                    (target, host, port, name, reached.map(|_| started.elapsed().as_millis()))
+                })
+            })
+            .collect::<Vec<_>>();
+        for probe in probes {
+            let Ok((target, host, port, name, outcome)) = probe.await else {
+                continue;
+            };
+            let now = unix_ts_ms();
+            let (latency_ms, error) = match outcome {
+                Ok(latency) => (Some(latency), None),
+                Err(error) => (None, Some(error)),
+            };
+            let online = error.is_none();
+            let changed = {
+                let Ok(mut liveness) = state.node_liveness.lock() else {
+                    continue;
+                };
+                let previous = liveness.get(&target).map(|l| (l.online, l.since_ts_ms));
+                let since_ts_ms = match previous {
+                    Some((was_online, since)) if was_online == online => since,
+                    _ => now,
+                };
+                let rtt_ms = state
+                    .nodes
+                    .get(&target)
+                    .and_then(|conn| conn.try_lock().ok()?.rtt.smoothed_ms);
+                let entry = NodeLiveness {
+                    target: target.clone(),
+                    name,
+                    online,
+                    latency_ms,
+                    rtt_ms,
+                    error,
+                    since_ts_ms,
+                    ts_ms: now,
+                };
+                liveness.insert(target, entry.clone());
+                (previous.map(|(was_online, _)| was_online) != Some(online)).then_some(entry)
+            };
+            if let Some(entry) = changed {
+                if online {
+                    record_node_seen(&app, &host, port, None);
+                }
+                append_desktop_audit_log(if online { "node.online" } else { "node.offline" }, &json!(entry));
+                let _ = app.emit(if online { NODE_ONLINE_EVENT } else { NODE_OFFLINE_EVENT }, entry);
+            }
+        }
+    }
+}
+
+/// Current status of a registry node from what the app already knows; never touches the network.
+/// Nodes with a `node_connect` connection are judged by it, others by the node heartbeat (when
+/// it runs) with device details from the registry's last MANIFEST.
+fn check_node_status(
+    entry: &NodeRegistryEntry,
+    connection: Option<&NodeConnectionStatus>,
+    liveness: Option<&NodeLiveness>,
+    state: &AppState,
+) -> NodeStatus {
+    let target = format!("{}:{}", entry.host.trim(), entry.port);
+    let mut status = NodeStatus {
+        name: entry.name.clone(),
+        target: target.clone(),
+        online: false,
+        link_state: connection.map(|c| c.state),
+        device_name: None,
+        node_id: None,
+        protocol_version: None,
+        firmware_version: None,
+        tokens: Vec::new(),
+        rtt_ms: connection.and_then(|c| c.smoothed_rtt_ms).or(entry.rtt_ms),
+        error: None,
+        since_ts_ms: 0,
+        checked_ts_ms: unix_ts_ms(),
+        revision: 0,
+        removed: false,
+    };
+    let summary = match (connection, liveness) {
+        (Some(conn), _) if conn.state == NodeLinkState::Connected => state
+            .nodes
+            .summary(&target)
+            .map(Some)
+            .ok_or_else(|| "Connection closed".to_string()),
+        (Some(conn), _) => Err(conn.last_error.clone().unwrap_or_else(|| "Reconnecting".to_string())),
+        (None, Some(live)) if live.online => Ok(entry.manifest.as_ref().map(parse_manifest_summary)),
+        (None, Some(live)) => Err(live.error.clone().unwrap_or_else(|| "Offline".to_string())),
+        (None, None) => Err("Not monitored: connect the node or start the node heartbeat".to_string()),
+    };
+    match summary {
+        Ok(summary) => {
+            status.online = true;
+            if let Some(summary) = summary {
+                status.firmware_version = manifest_firmware_version(&summary.raw);
+                status.device_name = summary.device_name;
+                status.node_id = summary.node_id;
+                status.protocol_version = Some(summary.protocol_version);
+                status.tokens = summary.tokens;
+            }
+        }
+        Err(error) => status.error = Some(error),
+    }
+    status
+}
+
+/// Keeps `AppState::fleet_status` current for every registry node and emits `node_status`
+/// whenever a node's status changes (and once when it is first checked or leaves the registry),
+/// so the UI can follow the fleet without polling `node_probe`. It only reads connection and
+/// heartbeat state, so it opens no connections of its own: some node firmware stops its motors
+/// whenever a client disconnects.
+fn supervise_node_status(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        let registry = cached_node_registry(&app, &state).unwrap_or_default();
+        let connections = state.nodes.list().unwrap_or_default();
+        let liveness = state.node_liveness.lock().map(|l| l.clone()).unwrap_or_default();
+        let checked = registry
+            .iter()
+            .map(|entry| {
+                let target = format!("{}:{}", entry.host.trim(), entry.port);
+                let connection = connections.iter().find(|c| c.target == target);
+                check_node_status(entry, connection, liveness.get(&target), &state)
+            })
+            .collect::<Vec<_>>();
+
+        let mut changes = Vec::new();
+        {
+            let Ok(mut fleet) = state.fleet_status.lock() else {
+                continue;
+            };
+            let gone = fleet
+                .nodes
+                .keys()
+                .filter(|name| !registry.iter().any(|e| e.name == **name))
+                .cloned()
+                .collect::<Vec<_>>();
+            for name in gone {
+                if let Some(mut status) = fleet.nodes.remove(&name) {
+                    fleet.revision += 1;
+                    status.revision = fleet.revision;
+                    status.removed = true;
+                    changes.push(status);
+                }
+            }
+            for mut status in checked {
+                let previous = fleet.nodes.get(&status.name);
+                status.since_ts_ms = match previous {
+                    Some(p) if p.online == status.online => p.since_ts_ms,
+                    _ => status.checked_ts_ms,
+                };
+                let changed = previous.is_none_or(|p| status.differs_from(p));
+                status.revision = match previous {
+                    Some(p) if !changed => p.revision,
+                    _ => {
+                        fleet.revision += 1;
+                        fleet.revision
+                    }
+                };
+                if changed {
+                    changes.push(status.clone());
+                }
+                fleet.nodes.insert(status.name.clone(), status);
+            }
+        }
+        for status in changes {
+            let _ = app.emit(NODE_STATUS_EVENT, status);
+        }
+    });
+}
+
+/// Every registry node's status, as of one fleet revision; `node_status` events with a higher
+/// `revision` are newer than this snapshot.
+#[tauri::command]
+fn get_fleet_status(state: State<'_, AppState>) -> Result<FleetStatusSnapshot, String> {
+    let fleet = state.fleet_status.lock().map_err(|_| "State lock poisoned".to_string())?;
+    let mut nodes = fleet.nodes.values().cloned().collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(FleetStatusSnapshot {
+        revision: fleet.revision,
+        nodes,
+        ts_ms: unix_ts_ms(),
+    })
+}
+
+fn node_heartbeat_status_of(state: &AppState) -> Result<NodeHeartbeatStatus, String> {
+    let monitor = state.node_heartbeat.lock().map_err(|_| "State lock poisoned".to_string())?;
+    let mut nodes = state
+        .node_liveness
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(NodeHeartbeatStatus {
+        running: monitor.as_ref().is_some_and(|m| !m.stop_tx.is_closed()),
+        interval_ms: monitor.as_ref().map(|m| m.interval_ms),
+        timeout_ms: monitor.as_ref().map(|m| m.timeout_ms),
+        nodes,
+    })
+}
+
+/// Starts (or restarts) the node heartbeat; see `node_heartbeat_loop`.
+#[tauri::command]
+fn node_heartbeat_start(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    interval_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<NodeHeartbeatStatus, String> {
+    let interval_ms = interval_ms.unwrap_or(2000).clamp(250, 60_000);
+    let timeout_ms = timeout_ms.unwrap_or(500).clamp(50, 5_000).min(interval_ms);
+    let (stop_tx, stop_rx) = oneshot::channel();
+    {
+        let mut lock = state.node_heartbeat.lock().map_err(|_| "State lock poisoned".to_string())?;
+        if let Some(previous) = lock.replace(NodeHeartbeatMonitor {
+            stop_tx,
+            interval_ms,
+            timeout_ms,
+        }) {
+            let _ = previous.stop_tx.send(());
+        }
+    }
+    tauri::async_runtime::spawn(node_heartbeat_loop(
+        app,
+        Duration::from_millis(interval_ms),
+        Duration::from_millis(timeout_ms),
+        stop_rx,
+    ));
+    node_heartbeat_status_of(&state)
+}
+
+#[tauri::command]
+fn node_heartbeat_status(state: State<'_, AppState>) -> Result<NodeHeartbeatStatus, String> {
+    node_heartbeat_status_of(&state)
+}
+
+#[tauri::command]
+fn node_heartbeat_stop(state: State<'_, AppState>) -> Result<NodeHeartbeatStatus, String> {
+    if let Some(monitor) = state.node_heartbeat.lock().map_err(|_| "State lock poisoned".to_string())?.take() {
+        let _ = monitor.stop_tx.send(());
+    }
+    // Without the monitor the states would only go stale.
+    if let Ok(mut liveness) = state.node_liveness.lock() {
+        liveness.clear();
+    }
+    node_heartbeat_status_of(&state)
+}
+
+#[tauri::command]
+fn orchestrator_health_start(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+    orchestrator_base_url: Option<String>,
+    interval_ms: Option<u64>,
+) -> Result<OrchestratorHealthMonitorStatus, String> {
+    let instance = orchestrator_instance_name(name)?;
+    let base_url = orchestrator_base_url
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| normalize_base_url(&s))
+        .transpose()?;
+    let interval_ms = interval_ms.unwrap_or(2000).clamp(250, 60_000);
+
+    let (stop_tx, stop_rx) = oneshot::channel();
+    {
+        let mut lock = state
+            .orchestrator_health_monitors
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        // Restarting replaces the previous poller for this instance.
+        if let Some(previous) = lock.insert(
+            instance.clone(),
+            OrchestratorHealthMonitor {
+                stop_tx,
+                base_url: base_url.clone(),
+                interval_ms,
+            },
+        ) {
+            let _ = previous.stop_tx.send(());
+        }
+    }
+    tauri::async_runtime::spawn(orchestrator_health_loop(
+        app,
+        instance.clone(),
+        base_url.clone(),
+        Duration::from_millis(interval_ms),
+        stop_rx,
+    ));
+
+    Ok(OrchestratorHealthMonitorStatus {
+        instance,
+        running: true,
+        base_url,
+        interval_ms: Some(interval_ms),
+    })
+}
+
+#[tauri::command]
+fn orchestrator_health_status(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<OrchestratorHealthMonitorStatus, String> {
+    let instance = orchestrator_instance_name(name)?;
+    let lock = state
+        .orchestrator_health_monitors
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    let monitor = lock.get(&instance);
+    Ok(OrchestratorHealthMonitorStatus {
+        running: monitor.is_some_and(|m| !m.stop_tx.is_closed()),
+        base_url: monitor.and_then(|m| m.base_url.clone()),
+        interval_ms: monitor.map(|m| m.interval_ms),
+        instance,
+    })
+}
+
+#[tauri::command]
+fn orchestrator_health_stop(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<OrchestratorHealthMonitorStatus, String> {
+    let instance = orchestrator_instance_name(name)?;
+    let mut lock = state
+        .orchestrator_health_monitors
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    if let Some(monitor) = lock.remove(&instance) {
+        let _ = monitor.stop_tx.send(());
+    }
+    Ok(OrchestratorHealthMonitorStatus {
+        instance,
+        running: false,
+        base_url: None,
+        interval_ms: None,
+    })
+}
+
+/// Starts the vision service (the Next.js app in `vercel-api/`) as a managed process. `mode` is
+/// `dev` (default) or `start`, which needs a prior `next build`. Waiting for the port can take
+/// up to a minute, so the spawn runs on a blocking worker rather than the main thread.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn vision_spawn(
+    app: AppHandle,
+    name: Option<String>,
+    http_port: Option<u16>,
+    http_host: Option<String>,
+    mode: Option<String>,
+    env: Option<HashMap<String, String>>,
+    restart_policy: Option<RestartPolicy>,
+) -> Result<ManagedProcessStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let name = orchestrator_instance_name(name)?;
+        if let Some(status) = state.processes.status(VISION_PROCESS_KIND, &name)?.filter(|s| s.running) {
+            return Ok(status);
+        }
+        let mode = mode.unwrap_or_else(|| "dev".to_string());
+        if mode != "dev" && mode != "start" {
+            return Err(format!("mode must be \"dev\" or \"start\", got {mode:?}"));
+        }
+        let env = env.unwrap_or_default();
+        if let Some(bad) = env.keys().find(|k| k.trim().is_empty() || k.contains('=') || k.contains('\0')) {
+            return Err(format!("Invalid environment variable name {bad:?}"));
+        }
+
+        let repo_root = find_repo_root()?;
+        let work_dir = repo_root.join("vercel-api");
+        // Run next's CLI with node directly; going through npm would leave the server running when
+        // only the npm wrapper gets killed.
+        let next_bin = work_dir.join("node_modules").join("next").join("dist").join("bin").join("next");
+        if !next_bin.exists() {
+            return Err(format!(
+                "next is not installed at {} (run `npm install` in vercel-api)",
+                next_bin.display()
+            ));
+        }
+
+        let http_host_raw = http_host.unwrap_or_else(|| "127.0.0.1".to_string());
+        let http_host_ip = normalize_local_host(&http_host_raw)?;
+        let http_port = pick_free_tcp_port(http_host_ip, http_port.unwrap_or(3000))?;
+        let args = vec![
+            next_bin.to_string_lossy().to_string(),
+            mode,
+            "--port".to_string(),
+            http_port.to_string(),
+            "--hostname".to_string(),
+            http_host_raw.trim().to_string(),
+        ];
+        let log_name = if name == DEFAULT_ORCHESTRATOR_INSTANCE {
+            "vision_desktop.log".to_string()
+        } else {
+            format!("vision_desktop_{name}.log")
+        };
+        let http_base_url = format!("http://{}:{}", http_host_raw.trim(), http_port);
+        let spec = ProcessSpec {
+            kind: VISION_PROCESS_KIND.to_string(),
+            name: name.clone(),
+            program: resolve_node(),
+            args,
+            env,
+            work_dir,
+            log_path: repo_root.join(".build").join(log_name),
+            log_event: VISION_LOG_EVENT,
+            listen: Some(ListenTarget::Tcp(SocketAddr::new(http_host_ip, http_port))),
+            // `next dev` listens quickly, but a cold start still has to boot the toolchain.
+            listen_timeout: Duration::from_secs(60),
+            http_base_url: Some(http_base_url.clone()),
+            pid_file: None,
+            meta: Value::Null,
+        };
+        let mut audit = json!({
+            "name": name,
+            "base_url": http_base_url,
+            "args": spec.args,
+            "env": redact_secret_env(&spec.env)
+        });
+        let status = state
+            .processes
+            .spawn(&app, spec, restart_policy.unwrap_or_default())?;
+        audit["pid"] = json!(status.pid);
+        append_desktop_audit_log("vision.spawned", &audit);
+        Ok(status)
+    })
+    .await
+    .map_err(|e| format!("Vision spawn task failed: {e}"))?
+}
+
+#[tauri::command]
+fn vision_stop_process(state: State<'_, AppState>, name: Option<String>) -> Result<bool, String> {
+    let name = orchestrator_instance_name(name)?;
+    state.processes.stop(VISION_PROCESS_KIND, &name)
+}
+
+#[tauri::command]
+fn vision_process_status(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<Option<ManagedProcessStatus>, String> {
+    let name = orchestrator_instance_name(name)?;
+    state.processes.status(VISION_PROCESS_KIND, &name)
+}
+
+#[tauri::command]
+async fn vision_step(
+    state: State<'_, AppState>,
+    vision_base_url: String,
+    path: Option<String>,
+    payload: Value,
+    correlation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Value, String> {
+    let path = path
+        .map(|raw| raw.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/api/vision_step".to_string());
+    if !path.starts_with('/') {
+        return Err(format!("vision_step path must start with '/', got: {path}"));
+    }
+    let cid = correlation_id_or_new(correlation_id);
+    let mut result = vision_request(
+        &state.http,
+        reqwest::Method::POST,
+        vision_base_url,
+        &path,
+        Some(payload),
+        Some(cid.clone()),
+        timeout_ms,
+    )
+    .await?;
+    // Surface the id the call was traced under unless the service already reports one.
+    if let Some(obj) = result.as_object_mut() {
+        obj.entry("correlation_id").or_insert(json!(cid));
+    }
+    Ok(result)
+}
+
+/// Starts a critic session. With `frame_source` and `loop_interval_ms`, the backend also pulls
+/// frames and runs `critic_step` itself, emitting `critic_step_result` / `critic_error`, so the
+/// loop keeps running (and can still stop the robot) if the webview freezes or closes.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn critic_spawn(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    orchestrator_base_url: String,
+    task: String,
+    config: Option<CriticConfig>,
+    frame_source: Option<CriticFrameSource>,
+    loop_interval_ms: Option<u64>,
+    frames_per_step: Option<usize>,
+) -> Result<CriticStatus, String> {
+    let task = task.trim().to_string();
+    if task.is_empty() {
+        return Err("task is empty".to_string());
+    }
+    if frame_source.is_some() != loop_interval_ms.is_some() {
+        return Err("frame_source and loop_interval_ms must be set together".to_string());
+    }
+    let loop_interval_ms = loop_interval_ms.map(|ms| ms.max(200));
+    let mut session = CriticSession::new(orchestrator_base_url.trim().to_string(), task, loop_interval_ms);
+    session.apply_config(&app, config.unwrap_or_default())?;
+    let status = session.status();
+    MOCK_CRITIC_SEQ.store(0, Ordering::Relaxed);
+    append_critic_episode(
+        &app,
+        &session.session_id,
+        &json!({
+            "ts_ms": unix_ts_ms(),
+            "event": "start",
+            "task": session.task,
+            "model": session.model,
+            "ensemble_models": session.ensemble_models,
+            "thresholds": critic_thresholds_json(&session),
+            "robot_description": session.robot_description,
+        }),
+    );
+    *state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())? = Some(session);
+
+    let stop_tx = match (frame_source, loop_interval_ms) {
+        (Some(source), Some(interval_ms)) => {
+            let (stop_tx, stop_rx) = oneshot::channel();
+            tauri::async_runtime::spawn(critic_loop(
+                app,
+                source,
+                Duration::from_millis(interval_ms),
+                frames_per_step.unwrap_or(3).clamp(1, 16),
+                stop_rx,
+            ));
+            Some(stop_tx)
+        }
+        _ => None,
+    };
+    let previous = std::mem::replace(
+        &mut *state.critic_loop.lock().map_err(|_| "State lock poisoned".to_string())?,
+        stop_tx,
+    );
+    if let Some(previous) = previous {
+        let _ = previous.send(());
+    }
+    Ok(status)
+}
+
+/// Applies the fields set in `config` to the running session. The streak, EMA and usage carry
+/// over, so thresholds or the model can be tuned without restarting; changing the success window
+/// clears the window's history.
+#[tauri::command]
+fn critic_update_config(app: AppHandle, state: State<'_, AppState>, config: CriticConfig) -> Result<CriticStatus, String> {
+    let mut lock = state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    let Some(sess) = &mut *lock else {
+        return Err("Critic not running. Click Start Critic first.".to_string());
+    };
+    // Validate against a copy so a bad field leaves the session untouched.
+    let mut updated = sess.clone();
+    updated.apply_config(&app, config)?;
+    *sess = updated;
+    append_critic_episode(
+        &app,
+        &sess.session_id,
+        &json!({
+            "ts_ms": unix_ts_ms(),
+            "event": "config",
+            "model": sess.model,
+            "thresholds": critic_thresholds_json(sess),
+        }),
+    );
+    Ok(sess.status())
+}
+
+/// Backend-driven critic: keeps a rolling window of the latest `frames_per_step` frames and
+/// evaluates it every `interval` until stopped or the session ends.
+async fn critic_loop(
+    app: AppHandle,
+    source: CriticFrameSource,
+    interval: Duration,
+    frames_per_step: usize,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut frames = VecDeque::with_capacity(frames_per_step);
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    append_desktop_audit_log("critic.loop_started", &json!({ "interval_ms": interval.as_millis() }));
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = tick.tick() => {}
+        }
+        let state = app.state::<AppState>();
+        let active = state
+            .critic_session
+            .lock()
+            .map(|s| s.as_ref().is_some_and(|sess| sess.budget_exceeded.is_none()))
+            .unwrap_or(false);
+        if !active {
+            break;
+        }
+        let step = match source.fetch_jpeg_base64(&app.state::<AppState>().http).await {
+            Ok(frame) => {
+                if frames.len() == frames_per_step {
+                    frames.pop_front();
+                }
+                frames.push_back(frame);
+                let window = frames.iter().cloned().collect::<Vec<_>>();
+                run_critic_step(&app, window, None, None, None, None).await
+            }
+            Err(e) => Err(e),
+        };
+        match step {
+            Ok(result) => {
+                let _ = app.emit(CRITIC_STEP_EVENT, result);
+            }
+            Err(error) => {
+                append_desktop_audit_log("critic.loop_error", &json!({ "error": error }));
+                let kind = CriticErrorKind::of(&error);
+                let _ = app.emit(CRITIC_ERROR_EVENT, CriticErrorEvent { kind, error, ts_ms: unix_ts_ms() });
+            }
+        }
+    }
+    append_desktop_audit_log("critic.loop_stopped", &json!({}));
+}
+
+#[tauri::command]
+fn critic_status(state: State<'_, AppState>) -> Result<CriticStatus, String> {
+    let lock = state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    Ok(lock.as_ref().map(CriticSession::status).unwrap_or_else(stopped_critic_status))
+}
+
+fn stopped_critic_status() -> CriticStatus {
+    CriticStatus {
+        running: false,
+        task: None,
+        model: None,
+        success_streak: 0,
+        success_n: 3,
+        loop_interval_ms: None,
+        usage: None,
+        session_id: None,
+        budget_exceeded: None,
+    }
+}
+
+fn default_critic_frames_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join("critic_frames"))
+}
+
+/// `dir` (relative to the app data dir, or absolute within it), created and canonicalized. The
+/// webview sets `frames_dir`, so keeping it under the app data dir is what keeps
+/// `read_critic_frame_files` from reading arbitrary files.
+fn pinned_critic_frames_dir(app: &AppHandle, dir: &Path) -> Result<PathBuf, String> {
+    let base = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?;
+    if dir.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err(format!("frames_dir {} must not contain '..'", dir.display()));
+    }
+    let path = base.join(dir);
+    if !path.starts_with(&base) {
+        return Err(format!("frames_dir must be inside the app data dir {}", base.display()));
+    }
+    std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+    // Catch symlinks out of the app data dir.
+    let resolved = path
+        .canonicalize()
+        .map_err(|e| format!("Frames dir {} unavailable: {e}", path.display()))?;
+    let base = base
+        .canonicalize()
+        .map_err(|e| format!("App data dir {} unavailable: {e}", base.display()))?;
+    if !resolved.starts_with(&base) {
+        return Err(format!("frames_dir must be inside the app data dir {}", base.display()));
+    }
+    Ok(resolved)
+}
+
+/// The directory the webview should write frames into for `critic_step`'s `frame_paths`.
+#[tauri::command]
+fn critic_frames_dir(app: AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    let configured = state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .as_ref()
+        .and_then(|sess| sess.frames_dir.clone());
+    let dir = match configured {
+        Some(dir) => pinned_critic_frames_dir(&app, &dir)?,
+        None => {
+            let dir = default_critic_frames_dir(&app)?;
+            std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+            dir
+        }
+    };
+    Ok(dir.display().to_string())
+}
+
+/// Reads frames by path, refusing anything that resolves outside `frames_dir`. Together with
+/// `pinned_critic_frames_dir` this keeps the webview from reading files outside the app data dir
+/// through the critic.
+fn read_critic_frame_files(frames_dir: &Path, paths: &[String]) -> Result<Vec<String>, String> {
+    let root = frames_dir
+        .canonicalize()
+        .map_err(|e| format!("Frames dir {} unavailable: {e}", frames_dir.display()))?;
+    paths
+        .iter()
+        .map(|raw| {
+            let path = root.join(raw.trim());
+            let resolved = path
+                .canonicalize()
+                .map_err(|e| format!("Frame {} unavailable: {e}", path.display()))?;
+            if !resolved.starts_with(&root) {
+                return Err(format!("Frame {raw} is outside the frames dir {}", root.display()));
+            }
+            let bytes = std::fs::read(&resolved).map_err(|e| format!("Failed to read {}: {e}", resolved.display()))?;
+            Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+        })
+        .collect()
+}
+
+/// Scores one set of frames, given inline as base64 or as `frame_paths` (absolute, or relative
+/// to `critic_frames_dir`), which avoids pushing megabytes through IPC on every step. The result
+/// is also emitted as `critic_step_result`, same as steps driven by the backend loop, so other
+/// windows can follow the reward stream.
+#[tauri::command]
+async fn critic_step(
+    app: AppHandle,
+    frames_jpeg_base64: Option<Vec<String>>,
+    frame_paths: Option<Vec<String>>,
+    last_action_text: Option<String>,
+    executed_plan: Option<Value>,
+    task_override: Option<String>,
+    correlation_id: Option<String>,
+) -> Result<CriticStepResult, String> {
+    let frames_jpeg_base64 = match (frames_jpeg_base64, frame_paths) {
+        (Some(frames), None) => frames,
+        (None, Some(paths)) => {
+            let frames_dir = critic_frames_dir(app.clone(), app.state::<AppState>())?;
+            tauri::async_runtime::spawn_blocking(move || read_critic_frame_files(Path::new(&frames_dir), &paths))
+                .await
+                .map_err(|e| format!("Frame read task failed: {e}"))??
+        }
+        _ => return Err("Pass exactly one of frames_jpeg_base64 or frame_paths".to_string()),
+    };
+    let result =
+        run_critic_step(&app, frames_jpeg_base64, last_action_text, executed_plan, task_override, correlation_id)
+            .await?;
+    let _ = app.emit(CRITIC_STEP_EVENT, &result);
+    Ok(result)
+}
+
+async fn run_critic_step(
+    app: &AppHandle,
+    frames_jpeg_base64: Vec<String>,
+    last_action_text: Option<String>,
+    executed_plan: Option<Value>,
+    task_override: Option<String>,
+    correlation_id: Option<String>,
+) -> Result<CriticStepResult, String> {
+    if let Some(reason) = enforce_critic_budget(app) {
+        return Err(format!("Critic budget exceeded: {reason}. Start a new critic session to continue."));
+    }
+    let state = app.state::<AppState>();
+    // Snapshot config without holding the mutex across await (tauri commands require Send futures).
+    let cfg = {
+        let mut lock = state
+            .critic_session
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        let Some(sess) = &mut *lock else {
+            return Err("Critic not running. Click Start Critic first.".to_string());
+        };
+        // Enforce the minimum spacing here, so a runaway frontend loop cannot hammer the API.
+        let now = std::time::Instant::now();
+        if let (Some(min), Some(last)) = (sess.min_step_interval, sess.last_step_started) {
+            let elapsed = now.duration_since(last);
+            if elapsed < min {
+                return Ok(CriticStepResult {
+                    success_streak: sess.success_streak,
+                    success_stable: sess.success_stable(),
+                    reward_ema: sess.reward_ema,
+                    window_successes: sess.window_successes(),
+                    throttled: true,
+                    retry_after_ms: Some((min - elapsed).as_millis() as u64),
+                    correlation_id: correlation_id_or_new(correlation_id),
+                    ..Default::default()
+                });
+            }
+        }
+        sess.last_step_started = Some(now);
+        sess.steps_started += 1;
+        sess.clone()
+    };
+
+    let cid = correlation_id_or_new(correlation_id);
+    let task_to_use = task_override.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()).unwrap_or(cfg.task.as_str());
+    let frame_bytes_original = frames_jpeg_base64.iter().map(|f| f.trim().len()).sum::<usize>();
+    let (max_dim, quality) = (cfg.frame_max_dimension, cfg.frame_jpeg_quality);
+    let dedupe = cfg.identical_frame_max_age.is_some();
+    // Decoding and re-encoding is CPU-bound; keep it off the async workers.
+    let (frames_jpeg_base64, frame_hashes) = tauri::async_runtime::spawn_blocking(move || {
+        let frames = frames_jpeg_base64
+            .iter()
+            .filter(|f| !f.trim().is_empty())
+            .map(|f| prepare_critic_frame(f, max_dim, quality))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hashes = if dedupe { frames_dhash(&frames) } else { None };
+        Ok::<_, String>((frames, hashes))
+    })
+    .await
+    .map_err(|e| format!("Frame preparation task failed: {e}"))??;
+    // A step is only a dedupe candidate if the robot sat still throughout it.
+    let step_hash = frame_hashes.and_then(|hashes| {
+        let last = *hashes.last()?;
+        hashes
+            .iter()
+            .all(|h| (h ^ last).count_ones() <= FRAME_HASH_IDENTICAL_DISTANCE)
+            .then_some(last)
+    });
+    if let Some(hash) = step_hash {
+        let lock = state
+            .critic_session
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        if let Some((prev_hash, _, prev)) = lock.as_ref().and_then(|sess| {
+            sess.identical_frame_max_age
+                .zip(sess.last_evaluated.as_ref())
+                .filter(|(max_age, (_, at, _))| at.elapsed() <= *max_age)
+                .map(|(_, last)| last)
+        }) {
+            if (hash ^ prev_hash).count_ones() <= FRAME_HASH_IDENTICAL_DISTANCE {
+                let result = CriticStepResult {
+                    cached: true,
+                    step_id: cfg.step_id(),
+                    interrupt_sent: false,
+                    frame_bytes_original,
+                    frame_bytes_sent: 0,
+                    correlation_id: cid,
+                    ..prev.clone()
+                };
+                drop(lock);
+                record_critic_step(app, &cfg, task_to_use, last_action_text.as_deref(), &[], &result);
+                return Ok(result);
+            }
+        }
+    }
+    let frame_bytes_sent = frames_jpeg_base64.iter().map(String::len).sum::<usize>();
+    let motion_score = compute_motion_score(&frames_jpeg_base64).unwrap_or(0.0);
+    let outcomes = critic_eval_ensemble(
+        app,
+        &cfg.backend,
+        &cfg.model_chains(),
+        task_to_use,
+        build_critic_system_prompt(&cfg.prompt_template, task_to_use, &cfg.robot_description),
+        critic_output_schema_with(&cfg.extra_output_properties),
+        &cfg.frame_policy,
+        &frames_jpeg_base64,
+        Some(motion_score),
+        last_action_text.clone(),
+        executed_plan,
+        &cid,
+    )
+    .await;
+    if let Ok(mut lock) = state.critic_session.lock() {
+        if let Some(sess) = &mut *lock {
+            for (model, outcome) in &outcomes {
+                if let Ok(evaluation) = outcome {
+                    let pricing = sess.pricing_for(model);
+                    sess.usage.record(evaluation.usage, pricing);
+                }
+            }
+        }
+    }
+    let model_used = outcomes.first().map(|(model, _)| model.clone()).unwrap_or_default();
+    let (raw, ensemble) = if outcomes.len() == 1 {
+        let (_, outcome) = outcomes.into_iter().next().expect("one outcome");
+        (outcome?.output, Vec::new())
+    } else {
+        combine_critic_votes(outcomes)?
+    };
+
+    let mut reward = clamp_f64(raw.get("reward").and_then(|v| v.as_f64()).unwrap_or(0.0), -1.0, 1.0);
+    let mut success = raw.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
+    let mut conf = clamp_f64(raw.get("success_confidence").and_then(|v| v.as_f64()).unwrap_or(0.0), 0.0, 1.0);
+    let critical = raw.get("critical_failure").and_then(|v| v.as_bool()).unwrap_or(false);
+    let critical_reason = raw
+        .get("critical_failure_reason")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let mut failure_modes = raw
+        .get("failure_modes")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|x| x.as_str().map(|s| s.to_string())).collect::<Vec<_>>())
+        .unwrap_or_else(|| vec!["uncertain".to_string()]);
+
+    // Motion gating: prevent stationary hallucinations from counting as success.
+    let mut motion_gate = false;
+    let mut motion_gate_reason = String::new();
+    if task_expects_motion(task_to_use) && motion_score < 0.004 {
+        motion_gate = true;
+        motion_gate_reason = format!("low_motion(score={motion_score:.4})");
+        success = false;
+        conf = conf.min(0.2);
+        reward = reward.min(0.0);
+        if !failure_modes.iter().any(|m| m == "no_progress") {
+            failure_modes.push("no_progress".to_string());
+        }
+    }
+    if failure_modes.iter().any(|m| m == "uncertain" || m == "not_visible" || m == "target_not_visible") {
+        success = false;
+    }
+
+    let suggested_correction = raw
+        .get("suggested_correction")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    // Update streak under lock (no await).
+    let (streak, stable, reward_ema, window_successes, correction_path, first_success) = {
+        let mut lock = state
+            .critic_session
+            .lock()
+            .map_err(|_| "State lock poisoned".to_string())?;
+        let Some(sess) = &mut *lock else {
+            return Err("Critic stopped while step was in-flight.".to_string());
+        };
+        // Keep session task in sync if the UI changes prompt mid-run.
+        if let Some(t) = task_override.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+            sess.task = t;
+        }
+        let hit = sess.record_frame(success && !motion_gate, conf, reward);
+        // Only after a failed, non-critical verdict (a critical one stops the robot instead), and
+        // no more often than the cooldown so a looping critic does not flood the planner.
+        let correction_path = sess
+            .correction_path
+            .clone()
+            .filter(|_| !hit && !critical && !suggested_correction.is_empty())
+            .filter(|_| sess.last_correction_at.is_none_or(|at| at.elapsed() >= sess.correction_cooldown));
+        if correction_path.is_some() {
+            sess.last_correction_at = Some(std::time::Instant::now());
+        }
+        let stable = sess.success_stable();
+        let first_success = stable && !sess.task_succeeded;
+        sess.task_succeeded |= stable;
+        (sess.success_streak, stable, sess.reward_ema, sess.window_successes(), correction_path, first_success)
+    };
+    let correction_forwarded = correction_path.is_some();
+    if let Some(path) = correction_path {
+        forward_critic_correction(app.clone(), cfg.orchestrator_base_url.clone(), path, task_to_use, &suggested_correction, &cid);
+    }
+
+    let critical_actions = if critical {
+        run_critical_failure_actions(app, &cfg, &critical_reason, &cid).await
+    } else {
+        Vec::new()
+    };
+    let interrupt_sent = critical_actions
+        .iter()
+        .any(|report| report.action == "stop_orchestrator" && report.ok);
+
+    let result = CriticStepResult {
+        reward,
+        success,
+        success_confidence: conf,
+        success_streak: streak,
+        success_stable: stable,
+        reward_ema,
+        window_successes,
+        motion_score,
+        motion_gate,
+        motion_gate_reason,
+        critical_failure: critical,
+        critical_failure_reason: critical_reason,
+        failure_modes,
+        describe: raw.get("describe").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        evaluate: raw.get("evaluate").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        notes_short: raw.get("notes_short").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        suggested_correction,
+        correction_forwarded,
+        interrupt_sent,
+        critical_actions,
+        throttled: false,
+        retry_after_ms: None,
+        cached: false,
+        extra_fields: critic_extra_fields(&raw, &cfg.extra_output_properties),
+        model: model_used,
+        ensemble,
+        frame_bytes_original,
+        frame_bytes_sent,
+        correlation_id: cid,
+        step_id: cfg.step_id(),
+        raw,
+    };
+    // Never replay a critical failure: the next step should re-check the scene for real.
+    if let (Some(hash), false) = (step_hash, critical) {
+        if let Ok(mut lock) = state.critic_session.lock() {
+            if let Some(sess) = &mut *lock {
+                sess.last_evaluated = Some((hash, std::time::Instant::now(), result.clone()));
+            }
+        }
+    }
+    record_critic_step(app, &cfg, task_to_use, last_action_text.as_deref(), &frames_jpeg_base64, &result);
+    // Flag the session as soon as this step used up the budget, not on the next attempt.
+    enforce_critic_budget(app);
+    if first_success {
+        announce_task_succeeded(app, &cfg, task_to_use, &result).await;
+    }
+    Ok(result)
+}
+
+/// Emits `task_succeeded` for the step that first made success stable and, if the session asks
+/// for it, stops the orchestrator and ends the critic session.
+async fn announce_task_succeeded(app: &AppHandle, cfg: &CriticSession, task: &str, result: &CriticStepResult) {
+    let event = TaskSucceededEvent {
+        session_id: cfg.session_id.clone(),
+        task: task.to_string(),
+        correlation_id: result.correlation_id.clone(),
+        success_streak: result.success_streak,
+        steps: cfg.steps_started,
+        elapsed_ms: cfg.started_at.elapsed().as_millis(),
+        auto_stopped: cfg.auto_stop_on_success,
+        ts_ms: unix_ts_ms(),
+    };
+    append_desktop_audit_log("critic.task_succeeded", &json!(event));
+    append_critic_episode(
+        app,
+        &event.session_id,
+        &json!({ "ts_ms": event.ts_ms, "event": "task_succeeded", "step": event.steps, "elapsed_ms": event.elapsed_ms }),
+    );
+    if cfg.auto_stop_on_success {
+        let http = &app.state::<AppState>().http;
+        let _ = send_orchestrator_stop(http, cfg.orchestrator_base_url.clone(), Some(event.correlation_id.clone()), None).await;
+        let _ = critic_stop(app.clone(), app.state::<AppState>());
+    }
+    let _ = app.emit(TASK_SUCCEEDED_EVENT, event);
+}
+
+/// Runs the session's `critical_actions` in order (best-effort: a failed action does not skip the
+/// rest) and emits `critic_critical_failure` with what each one did. A recovery plan only counts
+/// as dispatched; its outcome goes to the audit log.
+async fn run_critical_failure_actions(
+    app: &AppHandle,
+    cfg: &CriticSession,
+    reason: &str,
+    correlation_id: &str,
+) -> Vec<CriticActionReport> {
+    let mut reports = Vec::with_capacity(cfg.critical_actions.len());
+    for action in &cfg.critical_actions {
+        let (name, outcome) = match action {
+            CriticCriticalAction::StopOrchestrator => (
+                "stop_orchestrator",
+                send_orchestrator_stop(
+                    &app.state::<AppState>().http,
+                    cfg.orchestrator_base_url.clone(),
+                    Some(correlation_id.to_string()),
+                    None,
+                )
+                .await
+                    .map(|_| ()),
+            ),
+            CriticCriticalAction::RecoveryPlan { plan } => {
+                let (app, base_url, plan, cid) =
+                    (app.clone(), cfg.orchestrator_base_url.clone(), plan.clone(), correlation_id.to_string());
+                tauri::async_runtime::spawn(async move {
+                    let state = app.state::<AppState>();
+                    let outcome = execute_plan_tracked(&state, base_url, plan, cid.clone(), None).await;
+                    append_desktop_audit_log(
+                        "critic.recovery_plan",
+                        &json!({ "correlation_id": cid, "ok": outcome.is_ok(), "error": outcome.err() }),
+                    );
+                });
+                ("recovery_plan", Ok(()))
+            }
+            CriticCriticalAction::SerialEstop { token, port } => {
+                let state = app.state::<AppState>();
+                let outcome = match serial_session_for_port(&state, port.as_deref()) {
+                    Ok(session) => write_serial_line(app, &session, token).await,
+                    Err(e) => Err(e),
+                };
+                ("serial_estop", outcome)
+            }
+        };
+        reports.push(CriticActionReport {
+            action: name,
+            ok: outcome.is_ok(),
+            error: outcome.err(),
+        });
+    }
+    let event = CriticCriticalFailureEvent {
+        session_id: cfg.session_id.clone(),
+        correlation_id: correlation_id.to_string(),
+        reason: reason.to_string(),
+        actions: reports.clone(),
+        ts_ms: unix_ts_ms(),
+    };
+    append_desktop_audit_log("critic.critical_failure", &json!(event));
+    let _ = app.emit(CRITIC_CRITICAL_FAILURE_EVENT, event);
+    reports
+}
+
+/// Sends a critic's suggested correction to the orchestrator as a planner instruction in the
+/// background and reports the outcome as a `critic_correction` event.
+fn forward_critic_correction(
+    app: AppHandle,
+    orchestrator_base_url: String,
+    path: String,
+    task: &str,
+    suggestion: &str,
+    correlation_id: &str,
+) {
+    let body = json!({
+        "instruction": suggestion,
+        "task": task,
+        "source": "critic_correction",
+        "correlation_id": correlation_id,
+    });
+    let (suggestion, correlation_id) = (suggestion.to_string(), correlation_id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let outcome = orchestrator_request(
+            &state.http,
+            reqwest::Method::POST,
+            orchestrator_base_url,
+            &path,
+            Some(body),
+            Some(correlation_id.clone()),
+            None,
+        )
+        .await;
+        append_desktop_audit_log(
+            "critic.correction_forwarded",
+            &json!({ "correlation_id": correlation_id, "path": path, "suggestion": suggestion, "ok": outcome.is_ok() }),
+        );
+        let (response, error) = match outcome {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        let _ = app.emit(
+            CRITIC_CORRECTION_EVENT,
+            CriticCorrectionEvent {
+                correlation_id,
+                suggested_correction: suggestion,
+                ok: error.is_none(),
+                response,
+                error,
+                ts_ms: unix_ts_ms(),
+            },
+        );
+    });
+}
+
+/// Checks the session's budget and, the first time a limit is hit, marks the session exhausted,
+/// emits `critic_budget_exceeded` and (if configured) stops the robot. Returns the reason while
+/// the session is over budget.
+fn enforce_critic_budget(app: &AppHandle) -> Option<String> {
+    let state = app.state::<AppState>();
+    let (event, orchestrator_base_url) = {
+        let mut lock = state.critic_session.lock().ok()?;
+        let sess = lock.as_mut()?;
+        if let Some(reason) = &sess.budget_exceeded {
+            return Some(reason.clone());
+        }
+        let reason = sess.check_budget()?;
+        sess.budget_exceeded = Some(reason.clone());
+        let event = CriticBudgetExceededEvent {
+            session_id: sess.session_id.clone(),
+            reason,
+            steps: sess.steps_started,
+            elapsed_ms: sess.started_at.elapsed().as_millis(),
+            usage: sess.usage.clone(),
+            robot_stopped: sess.budget.stop_robot,
+            ts_ms: unix_ts_ms(),
+        };
+        (event, sess.budget.stop_robot.then(|| sess.orchestrator_base_url.clone()))
+    };
+    append_desktop_audit_log("critic.budget_exceeded", &json!(event));
+    append_critic_episode(app, &event.session_id, &json!({ "ts_ms": event.ts_ms, "event": "budget_exceeded", "reason": event.reason }));
+    if let Some(base_url) = orchestrator_base_url {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let state = app.state::<AppState>();
+            let _ = send_orchestrator_stop(&state.http, base_url, None, None).await;
+        });
+    }
+    let reason = event.reason.clone();
+    let _ = app.emit(CRITIC_BUDGET_EXCEEDED_EVENT, event);
+    Some(reason)
+}
+
+fn critic_episodes_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_logs_dir(app)?.join("critic"))
+}
+
+fn critic_thresholds_json(sess: &CriticSession) -> Value {
+    json!({
+        "success_confidence": sess.conf_threshold,
+        "success_reward": sess.reward_threshold,
+        "success_consecutive_frames": sess.success_n,
+        "reward_ema_alpha": sess.reward_ema_alpha,
+        "success_window": sess.success_window.map(|(n, k)| json!({ "size": n, "required": k })),
+    })
+}
+
+/// Best-effort append to `logs/critic/<session_id>.jsonl`, like the audit log.
+fn append_critic_episode(app: &AppHandle, session_id: &str, record: &Value) {
+    let Ok(dir) = critic_episodes_dir(app) else {
+        return;
+    };
+    if std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let Ok(mut file) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{session_id}.jsonl")))
+    else {
+        return;
+    };
+    let _ = writeln!(file, "{}", record);
+}
+
+/// Writes the frames a step was scored on to `logs/critic/<session_id>/`, returning their paths
+/// relative to `logs/critic/` (empty if the frames could not be saved).
+fn save_critic_step_frames(app: &AppHandle, session_id: &str, step: u64, frames_b64: &[String]) -> Vec<String> {
+    let Ok(dir) = critic_episodes_dir(app).map(|d| d.join(session_id)) else {
+        return Vec::new();
+    };
+    if frames_b64.is_empty() || std::fs::create_dir_all(&dir).is_err() {
+        return Vec::new();
+    }
+    let mut saved = Vec::new();
+    for (i, frame) in frames_b64.iter().enumerate() {
+        let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(frame.trim()) else {
+            continue;
+        };
+        let file_name = format!("step{step:06}_{i}.jpg");
+        if std::fs::write(dir.join(&file_name), bytes).is_ok() {
+            saved.push(format!("{session_id}/{file_name}"));
+        }
+    }
+    saved
+}
+
+fn record_critic_step(
+    app: &AppHandle,
+    cfg: &CriticSession,
+    task: &str,
+    last_action: Option<&str>,
+    frames_b64: &[String],
+    result: &CriticStepResult,
+) {
+    let frames = if cfg.record_frames {
+        save_critic_step_frames(app, &cfg.session_id, cfg.steps_started, frames_b64)
+    } else {
+        Vec::new()
+    };
+    append_critic_episode(
+        app,
+        &cfg.session_id,
+        &json!({
+            "ts_ms": unix_ts_ms(),
+            "event": "step",
+            "step": cfg.steps_started,
+            "correlation_id": result.correlation_id,
+            "task": task,
+            "model": cfg.model,
+            "last_action": last_action,
+            "thresholds": critic_thresholds_json(cfg),
+            "frames": frames,
+            "result": result,
+        }),
+    );
+}
+
+fn sanitize_critic_session_id(session_id: &str) -> Result<String, String> {
+    let id = session_id.trim();
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid critic session id: {session_id}"));
+    }
+    Ok(id.to_string())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticSessionSummary {
+    session_id: String,
+    started_ts_ms: Option<u64>,
+    task: Option<String>,
+    model: Option<String>,
+    steps: u64,
+    successes: u64,
+    /// False if the app exited before `critic_stop` was called.
+    ended: bool,
+}
+
+/// Episode logs under `logs/critic/`, newest first.
+#[tauri::command]
+fn list_critic_sessions(app: AppHandle, limit: Option<usize>) -> Result<Vec<CriticSessionSummary>, String> {
+    let dir = critic_episodes_dir(&app)?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut sessions = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let Some(session_id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut summary = CriticSessionSummary {
+            session_id,
+            started_ts_ms: None,
+            task: None,
+            model: None,
+            steps: 0,
+            successes: 0,
+            ended: false,
+        };
+        for record in content.lines().filter_map(|l| serde_json::from_str::<Value>(l).ok()) {
+            match record.get("event").and_then(|v| v.as_str()) {
+                Some("start") => {
+                    summary.started_ts_ms = record.get("ts_ms").and_then(|v| v.as_u64());
+                    summary.task = record.get("task").and_then(|v| v.as_str()).map(str::to_string);
+                    summary.model = record.get("model").and_then(|v| v.as_str()).map(str::to_string);
+                }
+                Some("step") => {
+                    summary.steps += 1;
+                    if record.pointer("/result/success").and_then(|v| v.as_bool()) == Some(true) {
+                        summary.successes += 1;
+                    }
+                }
+                Some("stop") => summary.ended = true,
+                _ => {}
+            }
+        }
+        sessions.push(summary);
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.started_ts_ms));
+    sessions.truncate(limit.unwrap_or(100).max(1));
+    Ok(sessions)
+}
+
+/// Records of one episode log in file order (`start`, `step`..., `stop`).
+#[tauri::command]
+fn read_critic_session(app: AppHandle, session_id: String, offset: Option<usize>, limit: Option<usize>) -> Result<Vec<Value>, String> {
+    let id = sanitize_critic_session_id(&session_id)?;
+    let path = critic_episodes_dir(&app)?.join(format!("{id}.jsonl"));
+    if !path.exists() {
+        return Err(format!("Unknown critic session: {id}"));
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Value>(l).ok())
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(1000).max(1))
+        .collect())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticBatchSummary {
+    results_path: String,
+    evaluated: usize,
+    failed: usize,
+    successes: usize,
+    mean_reward: Option<f64>,
+}
+
+/// One unit of batch work: the frames to score together (oldest first) and the task to score
+/// them against.
+struct CriticBatchItem {
+    frames: Vec<PathBuf>,
+    task: Option<String>,
+}
+
+fn critic_batch_items_from_dir(dir: &Path, frames_per_step: usize) -> Result<Vec<CriticBatchItem>, String> {
+    if !dir.is_dir() {
+        return Err(format!("Not a directory: {}", dir.display()));
+    }
+    let mut frames = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {e}", dir.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+        })
+        .collect::<Vec<_>>();
+    frames.sort();
+    // Like the live loop: each frame is scored together with the ones just before it.
+    Ok((0..frames.len())
+        .map(|i| CriticBatchItem {
+            frames: frames[(i + 1).saturating_sub(frames_per_step)..=i].to_vec(),
+            task: None,
+        })
+        .collect())
+}
+
+fn critic_batch_items_from_episode(app: &AppHandle, session_id: &str) -> Result<Vec<CriticBatchItem>, String> {
+    let id = sanitize_critic_session_id(session_id)?;
+    let dir = critic_episodes_dir(app)?;
+    let path = dir.join(format!("{id}.jsonl"));
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Value>(l).ok())
+        .filter(|r| r.get("event").and_then(|v| v.as_str()) == Some("step"))
+        .filter_map(|r| {
+            let frames = r
+                .get("frames")
+                .and_then(|v| v.as_array())?
+                .iter()
+                .filter_map(|f| f.as_str())
+                .filter(|f| !f.contains(".."))
+                .map(|f| dir.join(f))
+                .collect::<Vec<_>>();
+            (!frames.is_empty()).then(|| CriticBatchItem {
+                frames,
+                task: r.get("task").and_then(|v| v.as_str()).map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+/// Re-scores saved frames, either a directory of JPEGs or the frames recorded with an episode
+/// log, and writes one result per step to `logs/critic/batch/<id>.jsonl`. Model, backend and
+/// prompt default to the running critic session, if any. Calls are spaced `min_interval_ms`
+/// apart so a large batch does not trip provider rate limits.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn critic_batch_eval(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    frames_dir: Option<String>,
+    episode_id: Option<String>,
+    task: Option<String>,
+    model: Option<String>,
+    backend: Option<CriticBackendConfig>,
+    prompt_template: Option<String>,
+    frames_per_step: Option<usize>,
+    min_interval_ms: Option<u64>,
+    max_steps: Option<usize>,
+) -> Result<CriticBatchSummary, String> {
+    let items = match (frames_dir.as_deref(), episode_id.as_deref()) {
+        (Some(dir), None) => critic_batch_items_from_dir(Path::new(dir.trim()), frames_per_step.unwrap_or(3).clamp(1, 16))?,
+        (None, Some(id)) => critic_batch_items_from_episode(&app, id)?,
+        _ => return Err("Set exactly one of frames_dir or episode_id".to_string()),
+    };
+    let task = task.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    if task.is_none() && items.iter().any(|i| i.task.is_none()) {
+        return Err("task is required when the frames carry no task of their own".to_string());
+    }
+
+    let session = state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .clone();
+    let model = model
+        .or_else(|| session.as_ref().map(|s| s.model.clone()))
+        .unwrap_or_else(|| "gpt-5.2".to_string());
+    let backend = backend
+        .or_else(|| session.as_ref().map(|s| s.backend.clone()))
+        .unwrap_or_default();
+    let (template, robot_description) = match (prompt_template, &session) {
+        (Some(name), _) => (load_critic_prompt_template(&app, &name)?, DEFAULT_ROBOT_DESCRIPTION.to_string()),
+        (None, Some(s)) => (s.prompt_template.clone(), s.robot_description.clone()),
+        (None, None) => (BUILTIN_CRITIC_PROMPT.to_string(), DEFAULT_ROBOT_DESCRIPTION.to_string()),
+    };
+    let (max_dim, quality) = session
+        .as_ref()
+        .map(|s| (s.frame_max_dimension, s.frame_jpeg_quality))
+        .unwrap_or((768, 80));
+    let extra_properties = session
+        .as_ref()
+        .map(|s| s.extra_output_properties.clone())
+        .unwrap_or_default();
+    let frame_policy = session.as_ref().map(|s| s.frame_policy.clone()).unwrap_or_default();
+    let interval = Duration::from_millis(min_interval_ms.unwrap_or(1000));
+
+    let batch_id = format!("batch_{}", unix_ts_ms());
+    let results_dir = critic_episodes_dir(&app)?.join("batch");
+    std::fs::create_dir_all(&results_dir).map_err(|e| format!("Failed to create {}: {e}", results_dir.display()))?;
+    let results_path = results_dir.join(format!("{batch_id}.jsonl"));
+    let mut results = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&results_path)
+        .map_err(|e| format!("Failed to open {}: {e}", results_path.display()))?;
+    append_desktop_audit_log(
+        "critic.batch_started",
+        &json!({ "batch_id": batch_id, "model": model, "steps": items.len(), "frames_dir": frames_dir, "episode_id": episode_id }),
+    );
+
+    let mut summary = CriticBatchSummary {
+        results_path: results_path.display().to_string(),
+        evaluated: 0,
+        failed: 0,
+        successes: 0,
+        mean_reward: None,
+    };
+    let mut reward_sum = 0.0;
+    for (index, item) in items.iter().take(max_steps.unwrap_or(usize::MAX)).enumerate() {
+        if index > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        let step_task = task.clone().or_else(|| item.task.clone()).unwrap_or_default();
+        let cid = format!("{batch_id}-{index}");
+        let frames = item
+            .frames
+            .iter()
+            .map(|p| {
+                let bytes = std::fs::read(p).map_err(|e| format!("Failed to read {}: {e}", p.display()))?;
+                prepare_critic_frame(&base64::engine::general_purpose::STANDARD.encode(bytes), max_dim, quality)
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let motion_score = frames.as_ref().ok().and_then(|f| compute_motion_score(f).ok());
+        let outcome = match frames {
+            Ok(frames) => {
+                critic_eval(
+                    &state.http,
+                    &backend,
+                    &model,
+                    &step_task,
+                    build_critic_system_prompt(&template, &step_task, &robot_description),
+                    critic_output_schema_with(&extra_properties),
+                    &frame_policy,
+                    &frames,
+                    motion_score,
+                    None,
+                    None,
+                    Some(&cid),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        let frame_paths = item.frames.iter().map(|p| p.display().to_string()).collect::<Vec<_>>();
+        let record = match outcome {
+            Ok(evaluation) => {
+                let out = evaluation.output;
+                let reward = clamp_f64(out.get("reward").and_then(|v| v.as_f64()).unwrap_or(0.0), -1.0, 1.0);
+                let success = out.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
+                summary.evaluated += 1;
+                summary.successes += usize::from(success);
+                reward_sum += reward;
+                json!({
+                    "index": index,
+                    "correlation_id": cid,
+                    "task": step_task,
+                    "frames": frame_paths,
+                    "motion_score": motion_score,
+                    "reward": reward,
+                    "success": success,
+                    "success_confidence": out.get("success_confidence"),
+                    "critical_failure": out.get("critical_failure"),
+                    "failure_modes": out.get("failure_modes"),
+                    "extra_fields": critic_extra_fields(&out, &extra_properties),
+                    "input_tokens": evaluation.usage.map(|u| u.input_tokens),
+                    "output_tokens": evaluation.usage.map(|u| u.output_tokens),
+                    "raw": out,
+                })
+            }
+            Err(error) => {
+                summary.failed += 1;
+                json!({
+                    "index": index,
+                    "correlation_id": cid,
+                    "task": step_task,
+                    "frames": frame_paths,
+                    "error": error,
+                })
+            }
+        };
+        writeln!(results, "{}", record).map_err(|e| format!("Failed to write {}: {e}", results_path.display()))?;
+    }
+    summary.mean_reward = (summary.evaluated > 0).then(|| reward_sum / summary.evaluated as f64);
+    append_desktop_audit_log(
+        "critic.batch_finished",
+        &json!({ "batch_id": batch_id, "evaluated": summary.evaluated, "failed": summary.failed, "mean_reward": summary.mean_reward }),
+    );
+    Ok(summary)
+}
+
+/// Records a human verdict for a logged step (`CriticStepResult::step_id`). Labels are appended
+/// to the episode log; the latest one for a step wins.
+#[tauri::command]
+fn critic_label_step(app: AppHandle, step_id: String, success: bool, note: Option<String>) -> Result<(), String> {
+    let (session_id, step) = step_id
+        .trim()
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Invalid step id: {step_id}"))?;
+    let session_id = sanitize_critic_session_id(session_id)?;
+    let step: u64 = step.parse().map_err(|_| format!("Invalid step id: {step_id}"))?;
+    let path = critic_episodes_dir(&app)?.join(format!("{session_id}.jsonl"));
+    if !path.exists() {
+        return Err(format!("Unknown critic session: {session_id}"));
+    }
+    append_critic_episode(
+        &app,
+        &session_id,
+        &json!({ "ts_ms": unix_ts_ms(), "event": "label", "step": step, "success": success, "note": note }),
+    );
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CriticCalibrationTask {
+    task: String,
+    labeled: usize,
+    /// Fraction of labeled steps where the model's `success` matched the human label.
+    agreement: f64,
+    true_positives: usize,
+    false_positives: usize,
+    false_negatives: usize,
+    true_negatives: usize,
+    precision: Option<f64>,
+    recall: Option<f64>,
+    /// `success_confidence` cut-off that maximizes F1 against the labels, if any step was a
+    /// labeled success.
+    suggested_confidence_threshold: Option<f64>,
+}
+
+/// Confusion counts of `(model_success && confidence >= threshold)` against human labels.
+fn critic_confusion(samples: &[(bool, f64, bool)], threshold: f64) -> (usize, usize, usize, usize) {
+    let (mut tp, mut fp, mut fn_, mut tn) = (0, 0, 0, 0);
+    for &(predicted, confidence, label) in samples {
+        match (predicted && confidence >= threshold, label) {
+            (true, true) => tp += 1,
+            (true, false) => fp += 1,
+            (false, true) => fn_ += 1,
+            (false, false) => tn += 1,
+        }
+    }
+    (tp, fp, fn_, tn)
+}
+
+/// Agreement between critic verdicts and `critic_label_step` labels, per task, over the given
+/// episode logs (all of them by default).
+#[tauri::command]
+fn critic_calibration_report(app: AppHandle, session_ids: Option<Vec<String>>) -> Result<Vec<CriticCalibrationTask>, String> {
+    let dir = critic_episodes_dir(&app)?;
+    let paths = match session_ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| sanitize_critic_session_id(id).map(|id| dir.join(format!("{id}.jsonl"))))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => std::fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    // task -> (model success, confidence, human label)
+    let mut by_task: HashMap<String, Vec<(bool, f64, bool)>> = HashMap::new();
+    for path in paths {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut steps: HashMap<u64, (String, bool, f64)> = HashMap::new();
+        let mut labels: HashMap<u64, bool> = HashMap::new();
+        for record in content.lines().filter_map(|l| serde_json::from_str::<Value>(l).ok()) {
+            let Some(step) = record.get("step").and_then(|v| v.as_u64()) else {
+                continue;
+            };
+            match record.get("event").and_then(|v| v.as_str()) {
+                Some("step") => {
+                    let task = record.get("task").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    let success = record.pointer("/result/success").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let confidence = record
+                        .pointer("/result/successConfidence")
+                        .and_then(|v| v.as_f64())
+                        .unwrap_or(0.0);
+                    steps.insert(step, (task, success, confidence));
+                }
+                Some("label") => {
+                    if let Some(success) = record.get("success").and_then(|v| v.as_bool()) {
+                        labels.insert(step, success);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (step, label) in labels {
+            if let Some((task, success, confidence)) = steps.remove(&step) {
+                by_task.entry(task).or_default().push((success, confidence, label));
+            }
+        }
+    }
+
+    let mut report = by_task
+        .into_iter()
+        .map(|(task, samples)| {
+            let (tp, fp, fn_, tn) = critic_confusion(&samples, 0.0);
+            let ratio = |num: usize, den: usize| (den > 0).then(|| num as f64 / den as f64);
+            let mut candidates = samples.iter().map(|s| s.1).collect::<Vec<_>>();
+            candidates.sort_by(f64::total_cmp);
+            candidates.dedup();
+            let mut best: Option<(f64, f64)> = None;
+            for threshold in candidates {
+                let (tp, fp, fn_, _) = critic_confusion(&samples, threshold);
+                if tp == 0 {
+                    continue;
+                }
+                let f1 = 2.0 * tp as f64 / (2 * tp + fp + fn_) as f64;
+                // Ties go to the higher threshold: equally accurate, but more conservative.
+                if best.is_none_or(|(best_f1, _)| f1 >= best_f1) {
+                    best = Some((f1, threshold));
+                }
+            }
+            CriticCalibrationTask {
+                task,
+                labeled: samples.len(),
+                agreement: (tp + tn) as f64 / samples.len() as f64,
+                true_positives: tp,
+                false_positives: fp,
+                false_negatives: fn_,
+                true_negatives: tn,
+                precision: ratio(tp, tp + fp),
+                recall: ratio(tp, tp + fn_),
+                suggested_confidence_threshold: best.map(|(_, threshold)| threshold),
+            }
+        })
+        .collect::<Vec<_>>();
+    report.sort_by(|a, b| b.labeled.cmp(&a.labeled).then_with(|| a.task.cmp(&b.task)));
+    Ok(report)
+}
+
+const REWARD_EXPORT_COLUMNS: &[&str] = &[
+    "step",
+    "ts_ms",
+    "correlation_id",
+    "task",
+    "model",
+    "action",
+    "reward",
+    "reward_ema",
+    "success",
+    "success_confidence",
+    "success_streak",
+    "success_stable",
+    "motion_score",
+    "critical_failure",
+    "failure_modes",
+    "cached",
+    "frames",
+    "human_label",
+];
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Flattens a session's logged steps into one row per step (`REWARD_EXPORT_COLUMNS`), with
+/// list fields joined by `;` and the latest human label, if any.
+fn critic_reward_rows(content: &str) -> Vec<serde_json::Map<String, Value>> {
+    let records = content
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Value>(l).ok())
+        .collect::<Vec<_>>();
+    let mut labels: HashMap<u64, bool> = HashMap::new();
+    for record in records.iter().filter(|r| r.get("event").and_then(|v| v.as_str()) == Some("label")) {
+        if let (Some(step), Some(success)) = (
+            record.get("step").and_then(|v| v.as_u64()),
+            record.get("success").and_then(|v| v.as_bool()),
+        ) {
+            labels.insert(step, success);
+        }
+    }
+    let join = |v: Option<&Value>| {
+        v.and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|i| i.as_str()).collect::<Vec<_>>().join(";"))
+            .map(Value::String)
+            .unwrap_or(Value::Null)
+    };
+    records
+        .iter()
+        .filter(|r| r.get("event").and_then(|v| v.as_str()) == Some("step"))
+        .map(|r| {
+            let result = |key: &str| r.get("result").and_then(|res| res.get(key)).cloned().unwrap_or(Value::Null);
+            let step = r.get("step").and_then(|v| v.as_u64());
+            let mut row = serde_json::Map::new();
+            row.insert("step".into(), json!(step));
+            row.insert("ts_ms".into(), r.get("ts_ms").cloned().unwrap_or(Value::Null));
+            row.insert("correlation_id".into(), r.get("correlation_id").cloned().unwrap_or(Value::Null));
+            row.insert("task".into(), r.get("task").cloned().unwrap_or(Value::Null));
+            row.insert("model".into(), result("model"));
+            row.insert("action".into(), r.get("last_action").cloned().unwrap_or(Value::Null));
+            row.insert("reward".into(), result("reward"));
+            row.insert("reward_ema".into(), result("rewardEma"));
+            row.insert("success".into(), result("success"));
+            row.insert("success_confidence".into(), result("successConfidence"));
+            row.insert("success_streak".into(), result("successStreak"));
+            row.insert("success_stable".into(), result("successStable"));
+            row.insert("motion_score".into(), result("motionScore"));
+            row.insert("critical_failure".into(), result("criticalFailure"));
+            row.insert("failure_modes".into(), join(r.pointer("/result/failureModes")));
+            row.insert("cached".into(), result("cached"));
+            row.insert("frames".into(), join(r.get("frames")));
+            row.insert("human_label".into(), json!(step.and_then(|s| labels.get(&s))));
+            row
+        })
+        .collect()
+}
+
+/// Writes a session's reward stream to `logs/critic/exports/<session_id>.<csv|jsonl|parquet>` for
+/// training pipelines and returns the file path. Parquet needs the `parquet` feature.
+#[tauri::command]
+fn export_rewards(app: AppHandle, session_id: String, format: Option<String>) -> Result<String, String> {
+    let id = sanitize_critic_session_id(&session_id)?;
+    let format = format.unwrap_or_else(|| "csv".to_string()).trim().to_ascii_lowercase();
+    let dir = critic_episodes_dir(&app)?;
+    let source = dir.join(format!("{id}.jsonl"));
+    let content = std::fs::read_to_string(&source).map_err(|e| format!("Failed to read {}: {e}", source.display()))?;
+    let rows = critic_reward_rows(&content);
+
+    let (extension, out) = match format.as_str() {
+        "csv" => {
+            let mut out = String::new();
+            out.push_str(&REWARD_EXPORT_COLUMNS.join(","));
+            out.push('\n');
+            for row in &rows {
+                let fields = REWARD_EXPORT_COLUMNS
+                    .iter()
+                    .map(|col| csv_field(row.get(*col).unwrap_or(&Value::Null)))
+                    .collect::<Vec<_>>();
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+            ("csv", out.into_bytes())
+        }
+        "jsonl" | "json" => {
+            let mut out = String::new();
+            for row in rows {
+                out.push_str(&Value::Object(row).to_string());
+                out.push('\n');
+            }
+            ("jsonl", out.into_bytes())
+        }
+        #[cfg(feature = "parquet")]
+        "parquet" => ("parquet", reward_parquet::encode(&rows, REWARD_EXPORT_COLUMNS)?),
+        #[cfg(not(feature = "parquet"))]
+        "parquet" => {
+            return Err("This build has no Parquet support (rebuild with `--features parquet`); export csv instead".to_string())
+        }
+        other => return Err(format!("Unknown export format: {other} (expected csv, jsonl or parquet)")),
+    };
+    let export_dir = dir.join("exports");
+    std::fs::create_dir_all(&export_dir).map_err(|e| format!("Failed to create {}: {e}", export_dir.display()))?;
+    let path = export_dir.join(format!("{id}.{extension}"));
+    std::fs::write(&path, out).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+fn critic_stop(app: AppHandle, state: State<'_, AppState>) -> Result<CriticStatus, String> {
+    let mut lock = state
+        .critic_session
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?;
+    let ended = lock.take();
+    drop(lock);
+    if let Some(sess) = &ended {
+        append_desktop_audit_log(
+            "critic.session_summary",
+            &json!({ "session_id": sess.session_id, "task": sess.task, "model": sess.model, "usage": sess.usage }),
+        );
+        append_critic_episode(
+        &app,
+            &sess.session_id,
+            &json!({ "ts_ms": unix_ts_ms(), "event": "stop", "steps": sess.steps_started, "usage": sess.usage }),
+        );
+    }
+    if let Some(stop_tx) = state.critic_loop.lock().ok().and_then(|mut l| l.take()) {
+        let _ = stop_tx.send(());
+    }
+    // Report the final totals once, alongside the stopped state.
+    Ok(CriticStatus {
+        usage: ended.map(|sess| sess.usage),
+        ..stopped_critic_status()
+    })
+}
+
+/// Probes a node with HELLO. The `*_timeout_ms` values override the registry entry's (or the
+/// built-in 2s connect / 3s read / 2s write) timeouts.
+/// Runs on a blocking worker: opening a `ble://` node scans for it for several seconds.
+#[tauri::command]
+async fn node_probe(
+    app: AppHandle,
+    host: String,
+    port: u16,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+) -> Result<NodeProbeStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let timeouts = NodeTimeouts::resolve(&host, port, connect_timeout_ms, read_timeout_ms, write_timeout_ms);
+        Ok(probe_node_status(&app, &state, &host, port, timeouts))
+    })
+    .await
+    .map_err(|e| format!("Node probe task failed: {e}"))?
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeProbeManyReport {
+    /// One result per requested target, in request order.
+    results: Vec<NodeProbeStatus>,
+    ok_count: usize,
+    failed_count: usize,
+    elapsed_ms: u128,
+}
+
+/// Runs `run` over `items` on at most `max_parallel` threads and returns the results in item order.
+fn fan_out<T: Send>(items: &[String], max_parallel: usize, run: impl Fn(&str) -> T + Sync) -> Vec<T> {
+    let workers = max_parallel.clamp(1, 64).min(items.len().max(1));
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = run(item.trim());
+                if let Ok(mut results) = results.lock() {
+                    results[index] = Some(result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_default().into_iter().flatten().collect()
+}
+
+/// Probes every target (`host:port`, a registry name or alias, or a connected node) at most
+/// `max_parallel` (default 16) at a time, so the fleet view refreshes in one call. Failures,
+/// including unresolvable targets, are reported per target rather than failing the batch.
+/// Timeouts resolve per target as in `node_probe`.
+#[tauri::command]
+async fn node_probe_many(
+    app: AppHandle,
+    targets: Vec<String>,
+    max_parallel: Option<usize>,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+) -> Result<NodeProbeManyReport, String> {
+    let started = std::time::Instant::now();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        fan_out(&targets, max_parallel.unwrap_or(16), |raw| match resolve_node_address(&app, &state, raw) {
+            Ok((host, port)) => {
+                let timeouts = NodeTimeouts::resolve(&host, port, connect_timeout_ms, read_timeout_ms, write_timeout_ms);
+                probe_node_status(&app, &state, &host, port, timeouts)
+            }
+            Err(error) => NodeProbeStatus {
+                ok: false,
+                host: raw.to_string(),
+                port: 0,
+                target: raw.to_string(),
+                device_name: None,
+                node_id: None,
+                tokens: vec![],
+                manifest: Some(json!({ "error": error })),
+                validation_errors: Vec::new(),
+                protocol_version: None,
+            },
+        })
+    })
+    .await
+    .map_err(|e| format!("Batch probe task failed: {e}"))?;
+    let ok_count = results.iter().filter(|r| r.ok).count();
+    let report = NodeProbeManyReport {
+        ok_count,
+        failed_count: results.len() - ok_count,
+        results,
+        elapsed_ms: started.elapsed().as_millis(),
+    };
+    append_desktop_audit_log(
+        "node.probe_many",
+        &json!({ "targets": report.results.len(), "ok": report.ok_count, "failed": report.failed_count }),
+    );
+    Ok(report)
+}
+
+/// `node_probe` without the command plumbing: probes over the persistent connection when there
+/// is one, caches and records the manifest, and reports failures as `ok: false`.
+fn probe_node_status(app: &AppHandle, state: &AppState, host: &str, port: u16, timeouts: NodeTimeouts) -> NodeProbeStatus {
+    let target = format!("{}:{}", host.trim(), port);
+    // Reuse a `node_connect` socket when there is one instead of opening a throwaway connection.
+    let probed = match state.nodes.request(&target, "HELLO", timeouts.read) {
+        Some(reply) => reply.and_then(|line| parse_hello_reply(&line)),
+        None => probe_daemon_node(&state.nodes.auth_tokens, host, port, timeouts),
+    };
+    match probed {
+        Ok(summary) => {
+            if let Ok(mut cache) = state.node_manifests.lock() {
+                cache_probed_manifest(&mut cache, &target, &summary);
+            }
+            record_node_seen(app, host, port, Some(&summary));
+            NodeProbeStatus {
+                ok: true,
+                host: host.trim().to_string(),
+                port,
+                target,
+                device_name: summary.device_name,
+                node_id: summary.node_id,
+                tokens: summary.tokens,
+                manifest: Some(summary.raw),
+                validation_errors: summary.issues,
+                protocol_version: Some(summary.protocol_version),
+            }
+        }
+        Err(error) => NodeProbeStatus {
+            ok: false,
+            host: host.trim().to_string(),
+            port,
+            target,
+            device_name: None,
+            node_id: None,
+            tokens: vec![],
+            manifest: Some(json!({ "error": error })),
+            validation_errors: Vec::new(),
+            protocol_version: None,
+        },
+    }
+}
+
+/// Runs the HELLO/MANIFEST handshake against every host of `cidr` (e.g. `192.168.1.0/24`) on
+/// `port`, at most `max_parallel` (default 64) at a time with a `timeout_ms` (default 300) connect
+/// and reply timeout, and returns the nodes that answered. Found manifests are cached like
+/// `node_probe` results.
+#[tauri::command]
+async fn node_scan_subnet(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    cidr: String,
+    port: u16,
+    timeout_ms: Option<u64>,
+    max_parallel: Option<usize>,
+) -> Result<Vec<NodeProbeStatus>, String> {
+    let hosts = subnet_hosts(&cidr)?;
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(300).clamp(50, 5_000));
+    let workers = max_parallel.unwrap_or(64).clamp(1, 256).min(hosts.len().max(1));
+    let scanned = hosts.len();
+    let handle = app.clone();
+    let found = tauri::async_runtime::spawn_blocking(move || {
+        let tokens = &handle.state::<AppState>().nodes.auth_tokens;
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let found = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    while let Some(ip) = hosts.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let host = ip.to_string();
+                        if let Ok(conn) = NodeConnection::open_with_timeouts(tokens, &host, port, NodeTimeouts::uniform(timeout)) {
+                            if let Ok(mut found) = found.lock() {
+                                found.push((*ip, conn.summary));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        let mut found = found.into_inner().unwrap_or_default();
+        found.sort_by_key(|(ip, _)| *ip);
+        found
+    })
+    .await
+    .map_err(|e| format!("Subnet scan task failed: {e}"))?;
+
+    if let Ok(mut cache) = state.node_manifests.lock() {
+        for (ip, summary) in &found {
+            cache_probed_manifest(&mut cache, &format!("{ip}:{port}"), summary);
+        }
+    }
+    for (ip, summary) in &found {
+        record_node_seen(&app, &ip.to_string(), port, Some(summary));
+    }
+    append_desktop_audit_log(
+        "node.scan_subnet",
+        &json!({ "cidr": cidr, "port": port, "scanned": scanned, "found": found.len() }),
+    );
+    Ok(found
+        .into_iter()
+        .map(|(ip, summary)| NodeProbeStatus {
+            ok: true,
+            host: ip.to_string(),
+            port,
+            target: format!("{ip}:{port}"),
+            device_name: summary.device_name,
+            node_id: summary.node_id,
+            tokens: summary.tokens,
+            manifest: Some(summary.raw),
+            validation_errors: summary.issues,
+            protocol_version: Some(summary.protocol_version),
+        })
+        .collect())
+}
+
+/// A peripheral advertising the BLE UART service, as found by `node_scan_ble`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BleNodeCandidate {
+    /// Registry/connect host for the node, `ble://<id>` (port 0).
+    host: String,
+    id: String,
+    name: Option<String>,
+    address: String,
+    rssi: Option<i16>,
+    /// The HELLO/MANIFEST probe, when requested.
+    status: Option<NodeProbeStatus>,
+}
+
+/// Scans for `scan_ms` (default 4000) for BLE peripherals advertising the Nordic UART service.
+/// With `probe` (default true) each is probed one at a time like `node_probe`, so manifests are
+/// cached and registry entries refreshed. Found nodes connect and register as host `ble://<id>`,
+/// port 0.
+#[tauri::command]
+async fn node_scan_ble(app: AppHandle, scan_ms: Option<u64>, probe: Option<bool>) -> Result<Vec<BleNodeCandidate>, String> {
+    #[cfg(feature = "ble")]
+    let found = ble::scan(Duration::from_millis(scan_ms.unwrap_or(4_000).clamp(500, 30_000))).await?;
+    #[cfg(not(feature = "ble"))]
+    let found: Vec<BleNodeCandidate> = {
+        let _ = scan_ms;
+        Err("This build has no BLE support (rebuild with `--features ble`)".to_string())?
+    };
+    let found = if probe.unwrap_or(true) {
+        tauri::async_runtime::spawn_blocking({
+            let app = app.clone();
+            move || {
+                let state = app.state::<AppState>();
+                found
+                    .into_iter()
+                    .map(|mut candidate| {
+                        let timeouts = NodeTimeouts::resolve(&candidate.host, 0, None, None, None);
+                        candidate.status = Some(probe_node_status(&app, &state, &candidate.host, 0, timeouts));
+                        candidate
+                    })
+                    .collect::<Vec<_>>()
+            }
+        })
+        .await
+        .map_err(|e| format!("BLE probe task failed: {e}"))?
+    } else {
+        found
+    };
+    append_desktop_audit_log(
+        "node.scan_ble",
+        &json!({
+            "found": found.len(),
+            "probed_ok": found.iter().filter(|c| c.status.as_ref().is_some_and(|s| s.ok)).count(),
+        }),
+    );
+    Ok(found)
+}
+
+/// Probes a node on a serial port (a device path or `usb:VID:PID[:SERIAL]` alias) at `baud`
+/// (default 115200), waiting up to `timeout_ms` (default 3000) for its MANIFEST. The port must
+/// not have an open serial session. With `register_as`, the node is saved to the registry as
+/// host `serial://<port>`, port 0 (or that entry's manifest is refreshed).
+#[tauri::command]
+async fn node_probe_serial(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: String,
+    baud: Option<u32>,
+    timeout_ms: Option<u64>,
+    register_as: Option<String>,
+) -> Result<NodeProbeStatus, String> {
+    let port = port.trim().to_string();
+    if port.is_empty() {
+        return Err("port cannot be empty".to_string());
+    }
+    if serial_session_for_port(&state, Some(&port)).is_ok() {
+        return Err(format!("Serial port {port} is open in a session; disconnect it first"));
+    }
+    let register_as = register_as.map(|n| validate_registry_name(&n)).transpose()?;
+    let baud = baud.unwrap_or(115_200);
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3_000).clamp(500, 30_000));
+    let path = resolve_serial_target(&port)?;
+    let probed = tauri::async_runtime::spawn_blocking(move || probe_serial_node(&path, baud, timeout))
+        .await
+        .map_err(|e| format!("Serial probe task failed: {e}"))?;
+    let host = format!("{SERIAL_NODE_SCHEME}{port}");
+    let target = format!("{host}:0");
+    let summary = match probed {
+        Ok(summary) => summary,
+        Err(error) => {
+            return Ok(NodeProbeStatus {
+                ok: false,
+                host,
+                port: 0,
+                target,
+                device_name: None,
+                node_id: None,
+                tokens: vec![],
+                manifest: Some(json!({ "error": error })),
+                validation_errors: Vec::new(),
+                protocol_version: None,
+            })
+        }
+    };
+    if let Ok(mut cache) = state.node_manifests.lock() {
+        cache_probed_manifest(&mut cache, &target, &summary);
+    }
+    if let Some(name) = register_as {
+        let added = update_node_registry(&app, |entries| {
+            match entries.iter().find(|e| e.name == name) {
+                Some(existing) if existing.host.trim() != host => {
+                    Err(format!("Node {name:?} is already in the registry as {}:{}", existing.host, existing.port))
+                }
+                Some(_) => Ok(false),
+                None => {
+                    entries.push(NodeRegistryEntry {
+                        name: name.clone(),
+                        host: host.clone(),
+                        port: 0,
+                        alias: None,
+                        tags: Vec::new(),
+                        groups: Vec::new(),
+                        last_seen_ts_ms: None,
+                        manifest: None,
+                        cert_sha256: None,
+                        auth: false,
+                        connect_timeout_ms: None,
+                        read_timeout_ms: None,
+                        write_timeout_ms: None,
+                        protocol_version: None,
+                        rtt_ms: None,
+                        firmware_version: None,
+                    });
+                    Ok(true)
+                }
+            }
+        })?;
+        if added {
+            append_desktop_audit_log("node.registry_add", &json!({ "name": name, "host": host, "port": 0 }));
+        }
     }
+    record_node_seen(&app, &host, 0, Some(&summary));
+    append_desktop_audit_log("node.probe_serial", &json!({ "port": port, "baud": baud, "device_name": summary.device_name }));
+    Ok(NodeProbeStatus {
+        ok: true,
+        host,
+        port: 0,
+        target,
+        device_name: summary.device_name,
+        node_id: summary.node_id,
+        tokens: summary.tokens,
+        manifest: Some(summary.raw),
+        validation_errors: summary.issues,
+        protocol_version: Some(summary.protocol_version),
+    })
+}
 
-    let mut user_content: Vec<Value> = Vec::new();
-    user_content.push(json!({ "type": "input_text", "text": user_text }));
-    for (idx, b64) in frames.iter().enumerate() {
-        // Tiny caption helps the model interpret ordering.
-        user_content.push(json!({ "type": "input_text", "text": format!("frame_t{idx}") }));
-        user_content.push(json!({ "type": "input_image", "image_url": format!("data:image/jpeg;base64,{b64}") }));
-    }
-    let body = json!({
-        "model": model,
-        "temperature": 0,
-        "max_output_tokens": 350,
-        "text": {
-            "format": {
-                "type": "json_schema",
-                "name": "critic_reward",
-                "schema": schema,
-                "strict": true
+/// Opens (or reopens) a persistent connection to a node; later `node_probe` calls for the same
+/// `host:port` reuse it, and it is kept alive until `node_disconnect`. A dropped link is retried
+/// with backoff, reported as `node_connection_state` events. Timeouts resolve as in
+/// `node_probe` and are kept for reconnects.
+#[tauri::command]
+async fn node_connect(
+    app: AppHandle,
+    host: String,
+    port: u16,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+) -> Result<NodeConnectionStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let timeouts = NodeTimeouts::resolve(&host, port, connect_timeout_ms, read_timeout_ms, write_timeout_ms);
+        let status = state.nodes.connect(&host, port, timeouts)?;
+        if let Some(summary) = state.nodes.summary(&status.target) {
+            if let Ok(mut cache) = state.node_manifests.lock() {
+                cache_probed_manifest(&mut cache, &status.target, &summary);
             }
-        },
-        "input": [
-            { "role": "system", "content": [{ "type": "input_text", "text": sys }] },
-            { "role": "user", "content": user_content }
-        ],
-        "metadata": {
-            "correlation_id": correlation_id,
-            "ts_ms": unix_ts_ms().to_string()
+            record_node_seen(&app, &status.host, port, Some(&summary));
         }
-    });
+        append_desktop_audit_log("node.connected", &json!({ "target": status.target, "addr": status.addr }));
+        Ok(status)
+    })
+    .await
+    .map_err(|e| format!("Node connect task failed: {e}"))?
+}
+
+#[tauri::command]
+fn node_disconnect(state: State<'_, AppState>, target: String) -> Result<bool, String> {
+    let closed = state.nodes.disconnect(target.trim())?;
+    if closed {
+        append_desktop_audit_log("node.disconnected", &json!({ "target": target.trim() }));
+    }
+    Ok(closed)
+}
 
-    append_desktop_audit_log("openai.critic.request", &json!({ "model": model, "task": task, "cid": correlation_id }));
+/// Datagram `node_broadcast_discover` broadcasts. A node answers, to the sender, with
+/// `DAEMON_NODE {"name": ..., "node_id": ..., "port": <tcp port>}` (every field optional; `port`
+/// defaults to the discovery port) or with its HELLO reply, `MANIFEST <json>`.
+const NODE_DISCOVER_DATAGRAM: &[u8] = b"DAEMON_DISCOVER\n";
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(OPENAI_RESPONSES_URL)
-        .bearer_auth(api_key)
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("OpenAI request failed: {e}"))?;
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BroadcastNodeCandidate {
+    host: String,
+    port: u16,
+    target: String,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    latency_ms: u128,
+}
 
-    let status = resp.status();
-    let text = resp.text().await.map_err(|e| format!("OpenAI read body failed: {e}"))?;
-    if !status.is_success() {
-        append_desktop_audit_log("openai.critic.http_error", &json!({ "status": status.as_u16(), "body": trunc_for_log(&text, 2000) }));
-        return Err(format!("OpenAI HTTP {}: {}", status.as_u16(), trunc_for_log(&text, 1200)));
+fn parse_discover_reply(reply: &str, default_port: u16) -> Option<(u16, Option<String>, Option<String>)> {
+    let reply = reply.trim();
+    if let Some(payload) = reply.strip_prefix("DAEMON_NODE") {
+        let info = serde_json::from_str::<Value>(payload.trim()).unwrap_or(Value::Null);
+        let text = |key: &str| info.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let port = info
+            .get("port")
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .unwrap_or(default_port);
+        return Some((port, text("name"), text("node_id")));
     }
+    let summary = parse_hello_reply(reply).ok()?;
+    Some((default_port, summary.device_name, summary.node_id))
+}
 
-    let parsed: Value = serde_json::from_str(&text)
-        .map_err(|e| format!("OpenAI invalid JSON: {e}; body={}", trunc_for_log(&text, 1200)))?;
-
-    // With json_schema, the model output should be valid JSON text.
-    if let Some(out_text) = extract_output_text(&parsed) {
-        if let Ok(v) = serde_json::from_str::<Value>(&out_text) {
-            append_desktop_audit_log("openai.critic.ok", &json!({ "cid": correlation_id, "out": v }));
-            return Ok(v);
+/// UDP fallback for networks that block mDNS: broadcasts `NODE_DISCOVER_DATAGRAM` to `port`
+/// (resent every 500 ms) and collects replies for `timeout_ms` (default 2000). The results are
+/// candidates only; confirm them with `node_probe` before use.
+#[tauri::command]
+async fn node_broadcast_discover(
+    app: AppHandle,
+    port: u16,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<BroadcastNodeCandidate>, String> {
+    let window = Duration::from_millis(timeout_ms.unwrap_or(2000).clamp(200, 10_000));
+    let found = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<BroadcastNodeCandidate>, String> {
+        let socket = std::net::UdpSocket::bind(("0.0.0.0", 0)).map_err(|e| format!("UDP bind failed: {e}"))?;
+        socket.set_broadcast(true).map_err(|e| format!("Enabling UDP broadcast failed: {e}"))?;
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .map_err(|e| format!("UDP socket setup failed: {e}"))?;
+        let started = std::time::Instant::now();
+        let mut last_sent: Option<std::time::Instant> = None;
+        let mut found: Vec<BroadcastNodeCandidate> = Vec::new();
+        let mut buf = [0u8; 8192];
+        while started.elapsed() < window {
+            if last_sent.is_none_or(|at| at.elapsed() >= Duration::from_millis(500)) {
+                socket
+                    .send_to(NODE_DISCOVER_DATAGRAM, (std::net::Ipv4Addr::BROADCAST, port))
+                    .map_err(|e| format!("UDP broadcast failed: {e}"))?;
+                last_sent = Some(std::time::Instant::now());
+            }
+            let Ok((len, from)) = socket.recv_from(&mut buf) else {
+                continue;
+            };
+            let reply = String::from_utf8_lossy(&buf[..len]);
+            let Some((tcp_port, device_name, node_id)) = parse_discover_reply(&reply, port) else {
+                continue;
+            };
+            let host = from.ip().to_string();
+            if found.iter().any(|c| c.host == host && c.port == tcp_port) {
+                continue;
+            }
+            found.push(BroadcastNodeCandidate {
+                target: format!("{host}:{tcp_port}"),
+                host,
+                port: tcp_port,
+                device_name,
+                node_id,
+                latency_ms: started.elapsed().as_millis(),
+            });
         }
+        Ok(found)
+    })
+    .await
+    .map_err(|e| format!("Broadcast discovery task failed: {e}"))??;
+    for candidate in &found {
+        record_node_seen(&app, &candidate.host, candidate.port, None);
     }
+    append_desktop_audit_log("node.broadcast_discover", &json!({ "port": port, "found": found.len() }));
+    Ok(found)
+}
 
-    // Fallback: if the provider ever returns already-parsed JSON in a field, return the whole response.
-    append_desktop_audit_log("openai.critic.parse_failed", &json!({ "cid": correlation_id, "body": trunc_for_log(&text, 1200) }));
-    Err("OpenAI critic response parse failed (no JSON tool output found)".to_string())
+const NODE_FIRMWARE_OUTDATED_EVENT: &str = "node_firmware_outdated";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeFirmwareStatus {
+    name: String,
+    target: String,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    firmware_version: Option<String>,
+    /// Newest release found for this device, when a releases directory was given.
+    latest_version: Option<String>,
+    /// `None` when either version is unknown.
+    out_of_date: Option<bool>,
+    last_seen_ts_ms: Option<u128>,
 }
 
-#[tauri::command]
-fn read_desktop_audit_log(tail_lines: Option<usize>) -> Result<String, String> {
-    let path = repo_logs_dir()?.join("backend_audit.jsonl");
-    if !path.exists() {
-        return Ok(String::new());
-    }
-    let content = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
-    let limit = tail_lines.unwrap_or(300).max(1);
-    let lines: Vec<&str> = content.lines().collect();
-    let start = lines.len().saturating_sub(limit);
-    Ok(lines[start..].join("\n"))
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeFirmwareReport {
+    nodes: Vec<NodeFirmwareStatus>,
+    out_of_date_count: usize,
+    releases_dir: Option<String>,
 }
 
+/// Firmware versions across the registry, from each node's last MANIFEST. With `releases_dir`
+/// (laid out as `<dir>/<device name or node id>/<version>`), nodes older than the newest release
+/// are flagged and a `node_firmware_outdated` event is emitted for each.
 #[tauri::command]
-fn list_serial_ports() -> Result<Vec<SerialPortEntry>, String> {
-    let ports = serialport::available_ports().map_err(|error| error.to_string())?;
-    let result = ports
+fn node_firmware_report(app: AppHandle, releases_dir: Option<String>) -> Result<NodeFirmwareReport, String> {
+    let releases_dir = releases_dir.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    if let Some(dir) = &releases_dir {
+        if !Path::new(dir).is_dir() {
+            return Err(format!("Releases directory {dir} does not exist"));
+        }
+    }
+    let nodes = load_node_registry(&app)?
         .into_iter()
-        .map(|port| SerialPortEntry {
-            port_name: port.port_name,
-            port_type: port_type_name(&port.port_type),
+        .map(|entry| {
+            let manifest = entry.manifest.as_ref();
+            let text = |pointer: &str| manifest.and_then(|m| m.pointer(pointer)?.as_str()).map(str::to_string);
+            let (device_name, node_id) = (text("/device/name"), text("/device/node_id"));
+            let firmware_version = entry
+                .firmware_version
+                .clone()
+                .or_else(|| manifest.and_then(manifest_firmware_version));
+            let latest_version = releases_dir.as_ref().and_then(|dir| {
+                let keys = [device_name.as_deref(), node_id.as_deref(), Some(entry.name.as_str())];
+                latest_firmware_release(Path::new(dir), &keys.into_iter().flatten().collect::<Vec<_>>())
+            });
+            let out_of_date = firmware_version
+                .as_deref()
+                .zip(latest_version.as_deref())
+                .map(|(current, latest)| compare_versions(current, latest).is_lt());
+            NodeFirmwareStatus {
+                target: format!("{}:{}", entry.host.trim(), entry.port),
+                name: entry.name,
+                device_name,
+                node_id,
+                firmware_version,
+                latest_version,
+                out_of_date,
+                last_seen_ts_ms: entry.last_seen_ts_ms,
+            }
         })
         .collect::<Vec<_>>();
-    Ok(result)
+    let outdated = nodes.iter().filter(|n| n.out_of_date == Some(true)).collect::<Vec<_>>();
+    for node in &outdated {
+        let _ = app.emit(NODE_FIRMWARE_OUTDATED_EVENT, (*node).clone());
+    }
+    let out_of_date_count = outdated.len();
+    append_desktop_audit_log(
+        "node.firmware_report",
+        &json!({ "nodes": nodes.len(), "out_of_date": out_of_date_count, "releases_dir": releases_dir }),
+    );
+    Ok(NodeFirmwareReport {
+        nodes,
+        out_of_date_count,
+        releases_dir,
+    })
 }
 
-#[tauri::command]
-fn connect_serial(
-    app: AppHandle,
-    state: State<'_, AppState>,
-    port_name: String,
-    baud_rate: Option<u32>,
-) -> Result<ConnectionStatus, String> {
-    let baud = baud_rate.unwrap_or(115_200);
+/// How a simulated node answers; see `start_sim_node`.
+#[derive(Clone)]
+struct SimNodeBehavior {
+    manifest_line: String,
+    commands: Vec<NodeCommandSpec>,
+    reply_delay: Duration,
+    /// Fraction (0-1) of `RUN` commands answered `ERR SIM_FAILURE`, picked by hashing the
+    /// command's sequence number so runs are repeatable.
+    failure_rate: f64,
+    /// Tokens that always fail.
+    fail_tokens: Vec<String>,
+    telemetry_interval: Duration,
+}
 
-    let port = serialport::new(&port_name, baud)
-        .timeout(Duration::from_millis(120))
-        .open()
-        .map_err(|error| format!("Failed to open serial port {port_name}: {error}"))?;
+#[derive(Default)]
+struct SimNodeCounters {
+    connections: AtomicU64,
+    commands: AtomicU64,
+    failures: AtomicU64,
+}
 
-    let mut reader = port
-        .try_clone()
-        .map_err(|error| format!("Failed to clone serial reader: {error}"))?;
+struct SimNode {
+    device_name: Option<String>,
+    tokens: Vec<String>,
+    started_ts_ms: u128,
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    counters: Arc<SimNodeCounters>,
+}
 
-    let (stop_tx, stop_rx) = mpsc::channel::<()>();
-    let writer: Arc<Mutex<Box<dyn SerialPort + Send>>> =
-        Arc::new(Mutex::new(port as Box<dyn SerialPort + Send>));
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SimNodeStatus {
+    port: u16,
+    /// Address to probe, connect or register, e.g. `127.0.0.1:7070`.
+    target: String,
+    device_name: Option<String>,
+    tokens: Vec<String>,
+    started_ts_ms: u128,
+    connections: u64,
+    commands: u64,
+    failures: u64,
+}
 
-    let app_handle = app.clone();
-    thread::spawn(move || {
-        let mut read_buf = [0_u8; 512];
-        let mut pending = String::new();
+impl SimNode {
+    fn status(&self, port: u16) -> SimNodeStatus {
+        SimNodeStatus {
+            port,
+            target: format!("127.0.0.1:{port}"),
+            device_name: self.device_name.clone(),
+            tokens: self.tokens.clone(),
+            started_ts_ms: self.started_ts_ms,
+            connections: self.counters.connections.load(Ordering::Relaxed),
+            commands: self.counters.commands.load(Ordering::Relaxed),
+            failures: self.counters.failures.load(Ordering::Relaxed),
+        }
+    }
+}
 
-        loop {
-            if stop_rx.try_recv().is_ok() {
-                break;
+impl SimNodeBehavior {
+    /// The reply to one request line, as a node firmware would give it.
+    fn reply(&self, line: &str, subscribed: &mut bool, counters: &SimNodeCounters) -> String {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("").to_ascii_uppercase();
+        match command.as_str() {
+            "HELLO" | "READ_MANIFEST" => self.manifest_line.clone(),
+            "AUTH" | "STOP" => "OK".to_string(),
+            "PING" => "PONG".to_string(),
+            "SUB" | "UNSUB" if words.next().is_some_and(|w| w.eq_ignore_ascii_case("TELEMETRY")) => {
+                *subscribed = command == "SUB";
+                "OK".to_string()
+            }
+            "RUN" => {
+                let seq = counters.commands.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(self.reply_delay);
+                let Some(token) = words.next().map(str::to_ascii_uppercase) else {
+                    return "ERR BAD_ARGS missing_token".to_string();
+                };
+                let Some(spec) = self.commands.iter().find(|c| c.token.eq_ignore_ascii_case(&token)) else {
+                    return "ERR BAD_TOKEN unknown".to_string();
+                };
+                let args = words.map(|w| Value::String(w.to_string())).collect::<Vec<_>>();
+                if let Err((code, _)) = spec.check_args(&args) {
+                    return format!("ERR BAD_ARGS {code}");
+                }
+                let roll = Sha256::digest(format!("{seq}:{line}").as_bytes());
+                let roll = u64::from_be_bytes(roll[..8].try_into().unwrap_or_default()) as f64 / u64::MAX as f64;
+                if self.fail_tokens.contains(&token) || roll < self.failure_rate {
+                    counters.failures.fetch_add(1, Ordering::Relaxed);
+                    return "ERR SIM_FAILURE injected".to_string();
+                }
+                "OK".to_string()
             }
+            _ => "ERR BAD_REQUEST unsupported".to_string(),
+        }
+    }
+}
 
-            match reader.read(&mut read_buf) {
-                Ok(size) if size > 0 => {
-                    pending.push_str(&String::from_utf8_lossy(&read_buf[..size]));
-                    while let Some(index) = pending.find('\n') {
-                        let raw = pending[..index].trim().to_string();
-                        pending.drain(..=index);
-                        if !raw.is_empty() {
-                            emit_serial_line(&app_handle, raw);
-                        }
+/// Serves one client until it disconnects or the simulator stops. Telemetry goes out between
+/// replies while subscribed.
+fn serve_sim_client(
+    stream: TcpStream,
+    behavior: &SimNodeBehavior,
+    stop: &std::sync::atomic::AtomicBool,
+    counters: &SimNodeCounters,
+) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+    let started = std::time::Instant::now();
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut subscribed = false;
+    let mut last_telemetry = std::time::Instant::now();
+    let mut last_token = "-".to_string();
+    while !stop.load(Ordering::Relaxed) {
+        if subscribed && last_telemetry.elapsed() >= behavior.telemetry_interval {
+            last_telemetry = std::time::Instant::now();
+            let telemetry = format!("TELEMETRY uptime_ms={} last_token={last_token}\n", started.elapsed().as_millis());
+            if writer.write_all(telemetry.as_bytes()).is_err() {
+                return;
+            }
+        }
+        match reader.read_line(&mut line) {
+            Ok(0) => return,
+            Ok(_) => {
+                let request = line.trim().to_string();
+                line.clear();
+                if request.is_empty() {
+                    continue;
+                }
+                let reply = behavior.reply(&request, &mut subscribed, counters);
+                if reply == "OK" {
+                    if let Some(token) = request.strip_prefix("RUN ").and_then(|r| r.split_whitespace().next()) {
+                        last_token = token.to_ascii_uppercase();
+                    } else if request.eq_ignore_ascii_case("STOP") {
+                        last_token = "STOP".to_string();
                     }
                 }
-                Ok(_) => {}
-                Err(error) if error.kind() == std::io::ErrorKind::TimedOut => {}
-                Err(error) => {
-                    emit_serial_line(&app_handle, format!("ERR SERIAL_READ {error}"));
-                    break;
+                if writer.write_all(format!("{reply}\n").as_bytes()).is_err() {
+                    return;
                 }
             }
+            // A timeout keeps the partial line in `line` for the next read.
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(_) => return,
         }
-    });
+    }
+}
 
-    {
-        let mut lock = state.session.lock().map_err(|_| "State lock poisoned".to_string())?;
-        stop_session_locked(&mut lock);
-        *lock = Some(SerialSession {
-            writer,
-            stop_tx,
-            port_name: port_name.clone(),
-        });
+/// Starts an in-process node on `127.0.0.1:port` (port 0 or `None` picks a free one) that
+/// answers HELLO with `manifest_json` and acknowledges `RUN` commands the manifest declares
+/// after `reply_delay_ms` (default 0), validating their arguments like real firmware. Commands
+/// in `fail_tokens`, and a `failure_rate` fraction of the rest, are answered `ERR SIM_FAILURE`.
+/// Subscribers get `TELEMETRY` every `telemetry_interval_ms` (default 1000). For testing the
+/// orchestrator and UI without hardware.
+#[tauri::command]
+fn start_sim_node(
+    state: State<'_, AppState>,
+    manifest_json: String,
+    port: Option<u16>,
+    reply_delay_ms: Option<u64>,
+    failure_rate: Option<f64>,
+    fail_tokens: Option<Vec<String>>,
+    telemetry_interval_ms: Option<u64>,
+) -> Result<SimNodeStatus, String> {
+    let manifest: Value =
+        serde_json::from_str(manifest_json.trim()).map_err(|error| format!("Invalid manifest JSON: {error}"))?;
+    let manifest_line = format!("MANIFEST {manifest}");
+    // Refuse what a real node's MANIFEST would be refused for.
+    let summary = parse_hello_reply(&manifest_line)?;
+    if let Some(issue) = summary.issues.first() {
+        return Err(format!("Invalid manifest: {}: {}", issue.path, issue.message));
+    }
+    let failure_rate = failure_rate.unwrap_or(0.0);
+    if !(0.0..=1.0).contains(&failure_rate) {
+        return Err("failure_rate must be between 0 and 1".to_string());
     }
+    let behavior = Arc::new(SimNodeBehavior {
+        manifest_line,
+        commands: manifest_command_specs(&manifest),
+        reply_delay: Duration::from_millis(reply_delay_ms.unwrap_or(0).min(60_000)),
+        failure_rate,
+        fail_tokens: fail_tokens
+            .unwrap_or_default()
+            .iter()
+            .map(|t| t.trim().to_ascii_uppercase())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        telemetry_interval: Duration::from_millis(telemetry_interval_ms.unwrap_or(1000).clamp(50, 60_000)),
+    });
 
-    Ok(ConnectionStatus {
-        connected: true,
-        port_name: Some(port_name),
-    })
+    let listener = TcpListener::bind(("127.0.0.1", port.unwrap_or(0)))
+        .map_err(|error| format!("Failed to bind simulated node port: {error}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|error| format!("Failed to read simulated node port: {error}"))?
+        .port();
+    listener
+        .set_nonblocking(true)
+        .map_err(|error| format!("Failed to configure simulated node listener: {error}"))?;
+    let node = SimNode {
+        device_name: summary.device_name,
+        tokens: summary.tokens,
+        started_ts_ms: unix_ts_ms(),
+        stop: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        counters: Arc::new(SimNodeCounters::default()),
+    };
+    let (stop, counters) = (node.stop.clone(), node.counters.clone());
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    counters.connections.fetch_add(1, Ordering::Relaxed);
+                    let (behavior, stop, counters) = (behavior.clone(), stop.clone(), counters.clone());
+                    thread::spawn(move || serve_sim_client(stream, &behavior, &stop, &counters));
+                }
+                Err(_) => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    });
+
+    let status = node.status(port);
+    state
+        .sim_nodes
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .insert(port, node);
+    append_desktop_audit_log(
+        "node.sim_started",
+        &json!({ "port": port, "device_name": status.device_name, "failure_rate": failure_rate }),
+    );
+    Ok(status)
 }
 
+/// Stops a simulated node; its clients are disconnected within ~100 ms.
 #[tauri::command]
-fn disconnect_serial(state: State<'_, AppState>) -> Result<ConnectionStatus, String> {
-    let mut lock = state.session.lock().map_err(|_| "State lock poisoned".to_string())?;
-    stop_session_locked(&mut lock);
+fn stop_sim_node(state: State<'_, AppState>, port: u16) -> Result<bool, String> {
+    let removed = state
+        .sim_nodes
+        .lock()
+        .map_err(|_| "State lock poisoned".to_string())?
+        .remove(&port);
+    let Some(node) = removed else {
+        return Ok(false);
+    };
+    node.stop.store(true, Ordering::Relaxed);
+    append_desktop_audit_log("node.sim_stopped", &json!({ "port": port }));
+    Ok(true)
+}
 
-    Ok(ConnectionStatus {
-        connected: false,
-        port_name: None,
-    })
+#[tauri::command]
+fn list_sim_nodes(state: State<'_, AppState>) -> Result<Vec<SimNodeStatus>, String> {
+    let nodes = state.sim_nodes.lock().map_err(|_| "State lock poisoned".to_string())?;
+    let mut list = nodes.iter().map(|(port, node)| node.status(*port)).collect::<Vec<_>>();
+    list.sort_by_key(|s| s.port);
+    Ok(list)
 }
 
 #[tauri::command]
-fn get_connection_status(state: State<'_, AppState>) -> Result<ConnectionStatus, String> {
-    let lock = state.session.lock().map_err(|_| "State lock poisoned".to_string())?;
-    if let Some(session) = &*lock {
-        Ok(ConnectionStatus {
-            connected: true,
-            port_name: Some(session.port_name.clone()),
-        })
-    } else {
-        Ok(ConnectionStatus {
-            connected: false,
-            port_name: None,
-        })
+fn node_registry_list(app: AppHandle) -> Result<Vec<NodeRegistryEntry>, String> {
+    load_node_registry(&app)
+}
+
+/// Saves a node. `host` may be `tls://host` for nodes serving TLS, with `cert_sha256` pinning a
+/// self-signed certificate.
+#[tauri::command]
+fn node_registry_add(
+    app: AppHandle,
+    name: String,
+    host: String,
+    port: u16,
+    alias: Option<String>,
+    tags: Option<Vec<String>>,
+    cert_sha256: Option<String>,
+) -> Result<NodeRegistryEntry, String> {
+    let name = validate_registry_name(&name)?;
+    let host = host.trim().to_string();
+    if split_node_scheme(&host).1.is_empty() {
+        return Err("host cannot be empty".to_string());
+    }
+    let cert_sha256 = cert_sha256
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| normalize_cert_sha256(&f))
+        .transpose()?;
+    if cert_sha256.is_some() && !split_node_scheme(&host).0 {
+        return Err("cert_sha256 only applies to tls:// hosts".to_string());
     }
+    let alias = alias.map(|a| validate_registry_name(&a)).transpose()?;
+    let mut tags = tags
+        .unwrap_or_default()
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    let entry = update_node_registry(&app, |entries| {
+        if entries.iter().any(|e| e.name == name) {
+            return Err(format!("Node {name:?} is already in the registry"));
+        }
+        let entry = NodeRegistryEntry {
+            name,
+            host,
+            port,
+            alias,
+            tags,
+            groups: Vec::new(),
+            last_seen_ts_ms: None,
+            manifest: None,
+            cert_sha256,
+            auth: false,
+            connect_timeout_ms: None,
+            read_timeout_ms: None,
+            write_timeout_ms: None,
+            protocol_version: None,
+            rtt_ms: None,
+            firmware_version: None,
+        };
+        entries.push(entry.clone());
+        Ok(entry)
+    })?;
+    append_desktop_audit_log("node.registry_add", &json!({ "name": entry.name, "host": entry.host, "port": entry.port }));
+    Ok(entry)
 }
 
 #[tauri::command]
-fn send_serial_line(state: State<'_, AppState>, line: String) -> Result<(), String> {
-    let lock = state.session.lock().map_err(|_| "State lock poisoned".to_string())?;
-    let Some(session) = &*lock else {
-        return Err("No active serial connection".to_string());
+fn node_registry_remove(app: AppHandle, state: State<'_, AppState>, name: String) -> Result<bool, String> {
+    let name = name.trim().to_string();
+    let removed = update_node_registry(&app, |entries| {
+        let removed = entries.iter().position(|e| e.name == name).map(|index| entries.remove(index));
+        let orphaned_token = removed
+            .as_ref()
+            .filter(|r| r.auth && !entries.iter().any(|e| e.auth && e.host.trim() == r.host.trim() && e.port == r.port))
+            .map(|r| format!("{}:{}", r.host.trim(), r.port));
+        Ok(removed.map(|_| orphaned_token))
+    })?;
+    let Some(orphaned_token) = removed else {
+        return Ok(false);
     };
+    if let Some(target) = orphaned_token {
+        if let Err(error) = keychain_set_node_token(&state.nodes.auth_tokens, &target, None) {
+            append_desktop_audit_log("node.auth_token_delete_failed", &json!({ "target": target, "error": error }));
+        }
+    }
+    append_desktop_audit_log("node.registry_remove", &json!({ "name": name }));
+    Ok(true)
+}
 
-    let mut writer = session
-        .writer
-        .lock()
-        .map_err(|_| "Serial writer lock poisoned".to_string())?;
+/// Sets a registry node's default connect/read/write timeouts (in ms); `None` restores the
+/// built-in default for that timeout. Applies to connections opened afterwards.
+#[tauri::command]
+fn node_registry_set_timeouts(
+    app: AppHandle,
+    name: String,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+) -> Result<NodeRegistryEntry, String> {
+    let name = name.trim().to_string();
+    for (label, value) in [("connect", connect_timeout_ms), ("read", read_timeout_ms), ("write", write_timeout_ms)] {
+        if value.is_some_and(|ms| !(100..=120_000).contains(&ms)) {
+            return Err(format!("{label}_timeout_ms must be between 100 and 120000"));
+        }
+    }
+    let entry = update_node_registry(&app, |entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+        entry.connect_timeout_ms = connect_timeout_ms;
+        entry.read_timeout_ms = read_timeout_ms;
+        entry.write_timeout_ms = write_timeout_ms;
+        Ok(entry.clone())
+    })?;
+    append_desktop_audit_log(
+        "node.registry_timeouts",
+        &json!({
+            "name": name,
+            "connect_timeout_ms": connect_timeout_ms,
+            "read_timeout_ms": read_timeout_ms,
+            "write_timeout_ms": write_timeout_ms,
+        }),
+    );
+    Ok(entry)
+}
 
-    writer
-        .write_all(format!("{}\n", line.trim()).as_bytes())
-        .map_err(|error| format!("Serial write failed: {error}"))?;
-    writer
-        .flush()
-        .map_err(|error| format!("Serial flush failed: {error}"))?;
+/// Replaces the groups a registry node belongs to; an empty list removes it from all groups.
+#[tauri::command]
+fn node_registry_set_groups(app: AppHandle, name: String, groups: Vec<String>) -> Result<NodeRegistryEntry, String> {
+    let name = name.trim().to_string();
+    let mut groups = groups
+        .iter()
+        .filter(|g| !g.trim().is_empty())
+        .map(|g| validate_registry_name(g))
+        .collect::<Result<Vec<_>, _>>()?;
+    groups.sort();
+    groups.dedup();
+    let entry = update_node_registry(&app, |entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+        entry.groups = groups.clone();
+        Ok(entry.clone())
+    })?;
+    append_desktop_audit_log("node.registry_groups", &json!({ "name": name, "groups": groups }));
+    Ok(entry)
+}
 
-    Ok(())
+/// Registry names of the nodes in `group`, in registry order.
+fn node_group_members(app: &AppHandle, group: &str) -> Result<Vec<String>, String> {
+    let group = group.trim();
+    let members = load_node_registry(app)?
+        .into_iter()
+        .filter(|e| e.groups.iter().any(|g| g == group))
+        .map(|e| e.name)
+        .collect::<Vec<_>>();
+    if members.is_empty() {
+        return Err(format!("Group {group:?} has no nodes"));
+    }
+    Ok(members)
 }
 
+/// Stores the token a registry node expects as `AUTH <token>` before HELLO in the OS keychain
+/// and marks the entry so connections send it. `None` or an empty token removes it.
 #[tauri::command]
-async fn orchestrator_status(orchestrator_base_url: String) -> Result<Value, String> {
-    orchestrator_request(reqwest::Method::GET, orchestrator_base_url, "/status", None, None).await
+fn node_registry_set_auth(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    token: Option<String>,
+) -> Result<NodeRegistryEntry, String> {
+    let name = name.trim().to_string();
+    let token = token.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    if token.as_deref().is_some_and(|t| t.chars().any(|c| c.is_whitespace() || c.is_control())) {
+        return Err("token must not contain whitespace or control characters".to_string());
+    }
+    let entry = load_node_registry(&app)?
+        .into_iter()
+        .find(|e| e.name == name)
+        .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+    let target = format!("{}:{}", entry.host.trim(), entry.port);
+    keychain_set_node_token(&state.nodes.auth_tokens, &target, token.as_deref())?;
+    let entry = update_node_registry(&app, |entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+        entry.auth = token.is_some();
+        Ok(entry.clone())
+    })?;
+    append_desktop_audit_log("node.auth_token_set", &json!({ "name": name, "target": target, "auth": entry.auth }));
+    Ok(entry)
 }
 
 #[tauri::command]
-async fn orchestrator_execute_plan(
-    orchestrator_base_url: String,
-    plan: Value,
-    correlation_id: Option<String>,
-) -> Result<Value, String> {
-    orchestrator_request(
-        reqwest::Method::POST,
-        orchestrator_base_url,
-        "/execute_plan",
-        Some(json!({ "plan": plan, "correlation_id": correlation_id.clone() })),
-        correlation_id,
-    )
-    .await
+fn node_registry_rename(app: AppHandle, name: String, new_name: String) -> Result<NodeRegistryEntry, String> {
+    let name = name.trim().to_string();
+    let new_name = validate_registry_name(&new_name)?;
+    let entry = update_node_registry(&app, |entries| {
+        if name != new_name && entries.iter().any(|e| e.name == new_name) {
+            return Err(format!("Node {new_name:?} is already in the registry"));
+        }
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("Node {name:?} is not in the registry"))?;
+        entry.name = new_name.clone();
+        Ok(entry.clone())
+    })?;
+    append_desktop_audit_log("node.registry_rename", &json!({ "from": name, "to": new_name }));
+    Ok(entry)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCommandResult {
+    target: String,
+    /// The line sent, e.g. `RUN FWD 200`.
+    line: String,
+    ok: bool,
+    response: String,
+    /// For `ERR <code> <detail>` replies.
+    error_code: Option<String>,
+    error_detail: Option<String>,
+    /// Sent over a `node_connect` connection rather than a one-off one.
+    persistent: bool,
+    /// Id for `node_command_status`; only persistent-connection commands are tracked.
+    command_id: Option<u64>,
+    elapsed_ms: u128,
+}
+
+/// `node` as `(host, port)`: an open connection (by target, device name or node id), then a
+/// registry entry (by name or alias), then a literal `host:port`.
+fn resolve_node_address(app: &AppHandle, state: &AppState, node: &str) -> Result<(String, u16), String> {
+    let registry = load_node_registry(app).unwrap_or_default();
+    let target = state.nodes.find(node).or_else(|| {
+        registry
+            .iter()
+            .find(|e| e.name == node || e.alias.as_deref() == Some(node))
+            .map(|e| format!("{}:{}", e.host.trim(), e.port))
+    });
+    let target = target.as_deref().unwrap_or(node);
+    target
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host.trim_matches(['[', ']']).to_string(), port.parse().ok()?)))
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| format!("Unknown node {node:?}: not connected, not in the registry, and not host:port"))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeCapabilities {
+    target: String,
+    device_name: Option<String>,
+    node_id: Option<String>,
+    /// Where the manifest came from: `connection`, `registry`, `orchestrator`, or `probe`.
+    source: String,
+    commands: Vec<NodeCommandSpec>,
+}
+
+/// The manifest already known for `target` (the resolved address of `node`), without touching
+/// the network: the open connection's, then the registry's, then the orchestrator cache's.
+fn known_node_capabilities(app: &AppHandle, state: &AppState, node: &str, target: &str) -> Option<NodeCapabilities> {
+    if let Some(summary) = state.nodes.summary(target) {
+        return Some(NodeCapabilities {
+            target: target.to_string(),
+            commands: manifest_command_specs(&summary.raw),
+            device_name: summary.device_name,
+            node_id: summary.node_id,
+            source: "connection".to_string(),
+        });
+    }
+    let registry = load_node_registry(app).unwrap_or_default();
+    if let Some(manifest) = registry
+        .iter()
+        .filter(|e| format!("{}:{}", e.host.trim(), e.port) == target)
+        .find_map(|e| e.manifest.as_ref())
+    {
+        let text = |pointer: &str| manifest.pointer(pointer).and_then(|v| v.as_str()).map(str::to_string);
+        return Some(NodeCapabilities {
+            target: target.to_string(),
+            device_name: text("/device/name"),
+            node_id: text("/device/node_id"),
+            source: "registry".to_string(),
+            commands: manifest_command_specs(manifest),
+        });
+    }
+    let cache = state.node_manifests.lock().ok()?;
+    cache
+        .values()
+        .find(|cached| cached.names.iter().any(|name| name == node || name == target))
+        .map(|cached| NodeCapabilities {
+            target: target.to_string(),
+            device_name: Some(cached.alias.clone()),
+            node_id: None,
+            source: "orchestrator".to_string(),
+            commands: cached.commands.clone(),
+        })
 }
 
+/// Typed commands of a node: each token's arguments (type, range, enum), safety limits and NLP
+/// hints. Uses a known manifest when there is one, else probes the node.
 #[tauri::command]
-async fn orchestrator_stop(orchestrator_base_url: String) -> Result<Value, String> {
-    orchestrator_request(
-        reqwest::Method::POST,
-        orchestrator_base_url,
-        "/stop",
-        Some(json!({})),
-        None,
-    )
+async fn node_capabilities(app: AppHandle, node: String) -> Result<NodeCapabilities, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let (host, port) = resolve_node_address(&app, &state, node.trim())?;
+        let target = format!("{host}:{port}");
+        if let Some(capabilities) = known_node_capabilities(&app, &state, node.trim(), &target) {
+            return Ok(capabilities);
+        }
+        let status = probe_node_status(&app, &state, &host, port, NodeTimeouts::resolve(&host, port, None, None, None));
+        match status.manifest {
+            Some(manifest) if status.ok => Ok(NodeCapabilities {
+                target,
+                device_name: status.device_name,
+                node_id: status.node_id,
+                source: "probe".to_string(),
+                commands: manifest_command_specs(&manifest),
+            }),
+            manifest => Err(format!(
+                "Could not read the manifest of {target}: {}",
+                manifest.as_ref().and_then(|m| m.get("error")).and_then(|e| e.as_str()).unwrap_or("probe failed")
+            )),
+        }
+    })
     .await
+    .map_err(|e| format!("Node capabilities task failed: {e}"))?
 }
 
+/// Sends `RUN <token> <args...>` (or `STOP`) straight to a node, bypassing the orchestrator, and
+/// waits up to `timeout_ms` (default 3000) for its OK/ERR reply. Uses the `node_connect`
+/// connection when there is one, else a one-off connection. When the node's manifest is known,
+/// the token and arguments are checked against it before anything is sent.
 #[tauri::command]
-async fn vision_step(
-    vision_base_url: String,
-    path: Option<String>,
-    payload: Value,
-    correlation_id: Option<String>,
-) -> Result<Value, String> {
-    let path = path
-        .map(|raw| raw.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "/api/vision_step".to_string());
-    if !path.starts_with('/') {
-        return Err(format!("vision_step path must start with '/', got: {path}"));
-    }
-    vision_request(
-        reqwest::Method::POST,
-        vision_base_url,
-        &path,
-        Some(payload),
-        correlation_id,
-    )
+async fn node_send_command(
+    app: AppHandle,
+    node: String,
+    token: String,
+    args: Option<Vec<Value>>,
+    timeout_ms: Option<u64>,
+) -> Result<NodeCommandResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        send_node_command(&app, &state, &node, &token, args.unwrap_or_default(), timeout_ms)
+    })
     .await
+    .map_err(|e| format!("Node command task failed: {e}"))?
 }
 
-#[tauri::command]
-fn critic_spawn(
-    state: State<'_, AppState>,
-    orchestrator_base_url: String,
-    task: String,
-    model: Option<String>,
-    success_consecutive_frames: Option<u32>,
-    success_confidence_threshold: Option<f64>,
-    success_reward_threshold: Option<f64>,
-) -> Result<CriticStatus, String> {
-    let task = task.trim().to_string();
-    if task.is_empty() {
-        return Err("task is empty".to_string());
+fn send_node_command(
+    app: &AppHandle,
+    state: &AppState,
+    node: &str,
+    token: &str,
+    args: Vec<Value>,
+    timeout_ms: Option<u64>,
+) -> Result<NodeCommandResult, String> {
+    let token = token.trim().to_ascii_uppercase();
+    if token.is_empty() || token.contains(char::is_whitespace) {
+        return Err(format!("Invalid token: {token:?}"));
+    }
+    let (host, port) = resolve_node_address(app, state, node.trim())?;
+    let target = format!("{host}:{port}");
+    if token != "STOP" {
+        if let Some(capabilities) = known_node_capabilities(app, state, node.trim(), &target) {
+            let spec = capabilities
+                .commands
+                .iter()
+                .find(|c| c.token.eq_ignore_ascii_case(&token))
+                .ok_or_else(|| format!("{target} does not advertise token '{token}'"))?;
+            spec.check_args(&args).map_err(|(code, message)| format!("{code}: {message}"))?;
+        }
     }
+    let mut words = if token == "STOP" { vec![token] } else { vec!["RUN".to_string(), token] };
+    for arg in args {
+        let arg = match arg {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            return Err(format!("Invalid argument {arg:?}: node arguments cannot be empty or contain whitespace"));
+        }
+        words.push(arg);
+    }
+    let line = words.join(" ");
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000).clamp(100, 60_000));
 
-    let mut lock = state
-        .critic_session
-        .lock()
-        .map_err(|_| "State lock poisoned".to_string())?;
+    let started = std::time::Instant::now();
+    let (reply, command_id) = match state.nodes.send_command(&target, &line, timeout) {
+        Some((id, reply)) => (reply, Some(id)),
+        None => (NodeConnection::open(&state.nodes.auth_tokens, &host, port).and_then(|mut conn| conn.request(&line, timeout)), None),
+    };
+    let persistent = command_id.is_some();
+    append_desktop_audit_log(
+        "node.command",
+        &json!({ "target": target, "line": line, "persistent": persistent, "command_id": command_id, "reply": reply }),
+    );
+    let response = reply?;
+    let (ok, error_code, error_detail) = match response.split_once(' ').unwrap_or((&response, "")) {
+        ("OK", _) => (true, None, None),
+        ("ERR", rest) => {
+            let (code, detail) = rest.split_once(' ').unwrap_or((rest, ""));
+            (false, Some(code.to_string()), Some(detail.to_string()).filter(|d| !d.is_empty()))
+        }
+        _ => return Err(format!("Unexpected reply from {target} to {line:?}: {response}")),
+    };
+    Ok(NodeCommandResult {
+        target,
+        line,
+        ok,
+        response,
+        error_code,
+        error_detail,
+        persistent,
+        command_id,
+        elapsed_ms: started.elapsed().as_millis(),
+    })
+}
 
-    *lock = Some(CriticSession {
-        orchestrator_base_url: orchestrator_base_url.trim().to_string(),
-        task: task.clone(),
-        model: model.unwrap_or_else(|| "gpt-5.2".to_string()),
-        success_streak: 0,
-        success_n: success_consecutive_frames.unwrap_or(3).max(1),
-        conf_threshold: success_confidence_threshold.unwrap_or(0.9),
-        reward_threshold: success_reward_threshold.unwrap_or(0.1),
-    });
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupCommandOutcome {
+    /// Registry name of the node.
+    node: String,
+    /// The node answered OK.
+    ok: bool,
+    /// Absent when the command was never answered (unresolvable node, connect or read failure).
+    result: Option<NodeCommandResult>,
+    error: Option<String>,
+}
 
-    Ok(CriticStatus {
-        running: true,
-        task: Some(task),
-        model: lock.as_ref().map(|s| s.model.clone()),
-        success_streak: 0,
-        success_n: lock.as_ref().map(|s| s.success_n).unwrap_or(3),
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupCommandReport {
+    group: String,
+    /// One outcome per group member, in registry order.
+    results: Vec<GroupCommandOutcome>,
+    ok_count: usize,
+    failed_count: usize,
+    elapsed_ms: u128,
+}
+
+/// Sends `token` to every node of `group`, at most `max_parallel` (default 16) at a time, as
+/// `node_send_command` would to each. Failures are reported per node.
+async fn send_group_command(
+    app: AppHandle,
+    group: String,
+    token: String,
+    args: Vec<Value>,
+    timeout_ms: Option<u64>,
+    max_parallel: Option<usize>,
+) -> Result<GroupCommandReport, String> {
+    let started = std::time::Instant::now();
+    let group = group.trim().to_string();
+    let members = node_group_members(&app, &group)?;
+    let results = tauri::async_runtime::spawn_blocking({
+        let token = token.clone();
+        move || {
+            let state = app.state::<AppState>();
+            fan_out(&members, max_parallel.unwrap_or(16), |node| {
+                match send_node_command(&app, &state, node, &token, args.clone(), timeout_ms) {
+                    Ok(result) => GroupCommandOutcome {
+                        node: node.to_string(),
+                        ok: result.ok,
+                        error: result.error_code.clone(),
+                        result: Some(result),
+                    },
+                    Err(error) => GroupCommandOutcome {
+                        node: node.to_string(),
+                        ok: false,
+                        result: None,
+                        error: Some(error),
+                    },
+                }
+            })
+        }
     })
+    .await
+    .map_err(|e| format!("Group command task failed: {e}"))?;
+    let ok_count = results.iter().filter(|r| r.ok).count();
+    let report = GroupCommandReport {
+        group,
+        ok_count,
+        failed_count: results.len() - ok_count,
+        results,
+        elapsed_ms: started.elapsed().as_millis(),
+    };
+    append_desktop_audit_log(
+        "node.group_command",
+        &json!({ "group": report.group, "token": token, "ok": report.ok_count, "failed": report.failed_count }),
+    );
+    Ok(report)
 }
 
+/// `node_probe_many` over the nodes of `group`.
 #[tauri::command]
-fn critic_status(state: State<'_, AppState>) -> Result<CriticStatus, String> {
-    let lock = state
-        .critic_session
-        .lock()
-        .map_err(|_| "State lock poisoned".to_string())?;
-    if let Some(s) = &*lock {
-        Ok(CriticStatus {
-            running: true,
-            task: Some(s.task.clone()),
-            model: Some(s.model.clone()),
-            success_streak: s.success_streak,
-            success_n: s.success_n,
-        })
-    } else {
-        Ok(CriticStatus {
-            running: false,
-            task: None,
-            model: None,
-            success_streak: 0,
-            success_n: 3,
-        })
-    }
+async fn group_probe(
+    app: AppHandle,
+    group: String,
+    max_parallel: Option<usize>,
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+) -> Result<NodeProbeManyReport, String> {
+    let members = node_group_members(&app, &group)?;
+    node_probe_many(app, members, max_parallel, connect_timeout_ms, read_timeout_ms, write_timeout_ms).await
 }
 
+/// `node_send_command` to every node of `group` concurrently.
 #[tauri::command]
-async fn critic_step(
-    state: State<'_, AppState>,
-    frames_jpeg_base64: Vec<String>,
-    last_action_text: Option<String>,
-    executed_plan: Option<Value>,
-    task_override: Option<String>,
-    correlation_id: Option<String>,
-) -> Result<CriticStepResult, String> {
-    // Snapshot config without holding the mutex across await (tauri commands require Send futures).
-    let (orch_url, task, model, conf_th, reward_th, success_n) = {
-        let lock = state
-            .critic_session
-            .lock()
-            .map_err(|_| "State lock poisoned".to_string())?;
-        let Some(sess) = &*lock else {
-            return Err("Critic not running. Click Start Critic first.".to_string());
-        };
-        (
-            sess.orchestrator_base_url.clone(),
-            sess.task.clone(),
-            sess.model.clone(),
-            sess.conf_threshold,
-            sess.reward_threshold,
-            sess.success_n,
-        )
-    };
+async fn group_send_command(
+    app: AppHandle,
+    group: String,
+    token: String,
+    args: Option<Vec<Value>>,
+    timeout_ms: Option<u64>,
+    max_parallel: Option<usize>,
+) -> Result<GroupCommandReport, String> {
+    send_group_command(app, group, token, args.unwrap_or_default(), timeout_ms, max_parallel).await
+}
 
-    let cid = correlation_id.clone().unwrap_or_else(|| format!("ui-{}", unix_ts_ms()));
-    let task_to_use = task_override.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()).unwrap_or(task.as_str());
-    let motion_score = compute_motion_score(&frames_jpeg_base64).unwrap_or(0.0);
-    let raw = openai_critic_eval(
-        &model,
-        task_to_use,
-        &frames_jpeg_base64,
-        Some(motion_score),
-        last_action_text.as_deref(),
-        executed_plan.as_ref(),
-        Some(&cid),
-    )
-    .await?;
+/// Sends `STOP` to every node of `group` concurrently.
+#[tauri::command]
+async fn group_stop(
+    app: AppHandle,
+    group: String,
+    timeout_ms: Option<u64>,
+    max_parallel: Option<usize>,
+) -> Result<GroupCommandReport, String> {
+    send_group_command(app, group, "STOP".to_string(), Vec::new(), timeout_ms, max_parallel).await
+}
 
-    let mut reward = clamp_f64(raw.get("reward").and_then(|v| v.as_f64()).unwrap_or(0.0), -1.0, 1.0);
-    let mut success = raw.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-    let mut conf = clamp_f64(raw.get("success_confidence").and_then(|v| v.as_f64()).unwrap_or(0.0), 0.0, 1.0);
-    let critical = raw.get("critical_failure").and_then(|v| v.as_bool()).unwrap_or(false);
-    let critical_reason = raw
-        .get("critical_failure_reason")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    let mut failure_modes = raw
-        .get("failure_modes")
-        .and_then(|v| v.as_array())
-        .map(|arr| arr.iter().filter_map(|x| x.as_str().map(|s| s.to_string())).collect::<Vec<_>>())
-        .unwrap_or_else(|| vec!["uncertain".to_string()]);
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodePingReport {
+    target: String,
+    /// `PING`, or `HELLO` for nodes that answered PING with ERR.
+    method: String,
+    persistent: bool,
+    sent: usize,
+    received: usize,
+    min_ms: Option<f64>,
+    avg_ms: Option<f64>,
+    max_ms: Option<f64>,
+    /// Round trip of each ping in order; `null` for one that failed.
+    samples_ms: Vec<Option<f64>>,
+}
 
-    // Motion gating: prevent stationary hallucinations from counting as success.
-    let mut motion_gate = false;
-    let mut motion_gate_reason = String::new();
-    if task_expects_motion(task_to_use) && motion_score < 0.004 {
-        motion_gate = true;
-        motion_gate_reason = format!("low_motion(score={motion_score:.4})");
-        success = false;
-        conf = conf.min(0.2);
-        reward = reward.min(0.0);
-        if !failure_modes.iter().any(|m| m == "no_progress") {
-            failure_modes.push("no_progress".to_string());
+/// Runs up to `count` round trips through `send`, `interval` apart, returning the method used,
+/// the samples and the error that cut the run short, if any. Starts with `PING` and switches to
+/// `HELLO` once a node answers it with ERR (the ERR reply still counts as a round trip).
+fn ping_rounds(
+    count: u32,
+    interval: Duration,
+    mut send: impl FnMut(&str) -> Result<(String, f64), String>,
+) -> (String, Vec<Option<f64>>, Option<String>) {
+    let mut method = "PING";
+    let mut samples = Vec::new();
+    for round in 0..count {
+        if round > 0 {
+            thread::sleep(interval);
+        }
+        match send(method) {
+            Ok((reply, rtt_ms)) => {
+                samples.push(Some(rtt_ms));
+                if method == "PING" && reply.starts_with("ERR") {
+                    method = "HELLO";
+                }
+            }
+            Err(error) => {
+                samples.push(None);
+                return (method.to_string(), samples, Some(error));
+            }
         }
     }
-    if failure_modes.iter().any(|m| m == "uncertain" || m == "not_visible" || m == "target_not_visible") {
-        success = false;
-    }
-
-    let success_this_frame = success && conf >= conf_th && reward >= reward_th && !motion_gate;
+    (method.to_string(), samples, None)
+}
 
-    // Update streak under lock (no await).
-    let (streak, stable) = {
-        let mut lock = state
-            .critic_session
-            .lock()
-            .map_err(|_| "State lock poisoned".to_string())?;
-        let Some(sess) = &mut *lock else {
-            return Err("Critic stopped while step was in-flight.".to_string());
-        };
-        // Keep session task in sync if the UI changes prompt mid-run.
-        if let Some(t) = task_override.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
-            sess.task = t;
+/// Measures round trips to `node` (as in `node_send_command`) with `count` (default 4, at most
+/// 100) pings `interval_ms` (default 200) apart, over its persistent connection when there is
+/// one. Nodes without a PING handler are pinged with HELLO instead. Saves the average to the
+/// registry.
+#[tauri::command]
+async fn node_ping(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    node: String,
+    count: Option<u32>,
+    interval_ms: Option<u64>,
+) -> Result<NodePingReport, String> {
+    let (host, port) = resolve_node_address(&app, &state, node.trim())?;
+    let target = format!("{host}:{port}");
+    let count = count.unwrap_or(4).clamp(1, 100);
+    let interval = Duration::from_millis(interval_ms.unwrap_or(200).clamp(10, 10_000));
+    let handle = app.clone();
+    let (ping_target, ping_host) = (target.clone(), host.clone());
+    let (method, samples, persistent) = tauri::async_runtime::spawn_blocking(move || {
+        if let Some((method, samples)) = handle.state::<AppState>().nodes.ping(&ping_target, count, interval) {
+            return Ok((method, samples, true));
         }
-        sess.success_streak = if success_this_frame { sess.success_streak + 1 } else { 0 };
-        (sess.success_streak, sess.success_streak >= success_n)
+        // A one-off connection; the connect and handshake are not part of the samples.
+        let mut conn = NodeConnection::open(&handle.state::<AppState>().nodes.auth_tokens, &ping_host, port)?;
+        let (method, samples, _) = ping_rounds(count, interval, |line| {
+            let (timeout, started) = (conn.timeouts.read, std::time::Instant::now());
+            let reply = conn.request(line, timeout)?;
+            Ok((reply, elapsed_ms(started)))
+        });
+        Ok::<_, String>((method, samples, false))
+    })
+    .await
+    .map_err(|e| format!("Ping task failed: {e}"))??;
+
+    let received = samples.iter().flatten().copied().collect::<Vec<f64>>();
+    let avg_ms = (!received.is_empty())
+        .then(|| (received.iter().sum::<f64>() / received.len() as f64 * 100.0).round() / 100.0);
+    if let Some(avg) = avg_ms {
+        record_node_rtt(&app, &host, port, avg);
+    }
+    let report = NodePingReport {
+        target,
+        method,
+        persistent,
+        sent: samples.len(),
+        received: received.len(),
+        min_ms: received.iter().copied().reduce(f64::min),
+        avg_ms,
+        max_ms: received.iter().copied().reduce(f64::max),
+        samples_ms: samples,
     };
+    append_desktop_audit_log(
+        "node.ping",
+        &json!({ "target": report.target, "sent": report.sent, "received": report.received, "avg_ms": report.avg_ms }),
+    );
+    Ok(report)
+}
 
-    let mut interrupt_sent = false;
-    if critical {
-        // Hard safety stop (best-effort).
-        let _ = orchestrator_stop(orch_url).await;
-        interrupt_sent = true;
+/// State of commands sent with `node_send_command` over persistent connections: the one with
+/// `id`, else the latest `limit` (default 50), optionally only for `node`.
+#[tauri::command]
+fn node_command_status(
+    state: State<'_, AppState>,
+    id: Option<u64>,
+    node: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<NodeCommandRecord>, String> {
+    if let Some(id) = id {
+        return state
+            .nodes
+            .commands(None)?
+            .into_iter()
+            .find(|r| r.id == id)
+            .map(|r| vec![r])
+            .ok_or_else(|| format!("Unknown node command id {id}"));
     }
+    let target = node
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .map(|n| state.nodes.find(&n).unwrap_or(n));
+    let mut records = state.nodes.commands(target.as_deref())?;
+    records.truncate(limit.unwrap_or(50).clamp(1, NODE_COMMAND_HISTORY));
+    Ok(records)
+}
 
-    Ok(CriticStepResult {
-        reward,
-        success,
-        success_confidence: conf,
-        success_streak: streak,
-        success_stable: stable,
-        motion_score,
-        motion_gate,
-        motion_gate_reason,
-        critical_failure: critical,
-        critical_failure_reason: critical_reason,
-        failure_modes,
-        describe: raw.get("describe").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        evaluate: raw.get("evaluate").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        notes_short: raw.get("notes_short").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-        interrupt_sent,
-        raw,
-    })
+/// Subscribes to a connected node's telemetry (`SUB TELEMETRY`); each `TELEMETRY` line then
+/// arrives as a `node_telemetry` event until `node_telemetry_unsubscribe` or `node_disconnect`.
+#[tauri::command]
+fn node_telemetry_subscribe(app: AppHandle, state: State<'_, AppState>, node: String) -> Result<NodeConnectionStatus, String> {
+    let target = state.nodes.find(node.trim()).unwrap_or_else(|| node.trim().to_string());
+    let status = state.nodes.set_telemetry(&target, Some(app))?;
+    append_desktop_audit_log("node.telemetry_subscribed", &json!({ "target": target }));
+    Ok(status)
 }
 
 #[tauri::command]
-fn critic_stop(state: State<'_, AppState>) -> Result<CriticStatus, String> {
-    let mut lock = state
-        .critic_session
-        .lock()
-        .map_err(|_| "State lock poisoned".to_string())?;
-    *lock = None;
-    Ok(CriticStatus {
-        running: false,
-        task: None,
-        model: None,
-        success_streak: 0,
-        success_n: 3,
-    })
+fn node_telemetry_unsubscribe(state: State<'_, AppState>, node: String) -> Result<NodeConnectionStatus, String> {
+    let target = state.nodes.find(node.trim()).unwrap_or_else(|| node.trim().to_string());
+    let status = state.nodes.set_telemetry(&target, None)?;
+    append_desktop_audit_log("node.telemetry_unsubscribed", &json!({ "target": target }));
+    Ok(status)
 }
 
 #[tauri::command]
-fn node_probe(host: String, port: u16) -> Result<NodeProbeStatus, String> {
-    let target = format!("{}:{}", host.trim(), port);
-    match probe_daemon_node(&host, port) {
-        Ok(summary) => Ok(NodeProbeStatus {
-            ok: true,
-            host: host.trim().to_string(),
-            port,
-            target,
-            device_name: summary.device_name,
-            node_id: summary.node_id,
-            tokens: summary.tokens,
-            manifest: Some(summary.raw),
-        }),
-        Err(error) => Ok(NodeProbeStatus {
-            ok: false,
-            host: host.trim().to_string(),
-            port,
-            target,
-            device_name: None,
-            node_id: None,
-            tokens: vec![],
-            manifest: Some(json!({ "error": error })),
-        }),
-    }
+fn node_list_connections(state: State<'_, AppState>) -> Result<Vec<NodeConnectionStatus>, String> {
+    state.nodes.list()
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn orchestrator_spawn(
+    app: AppHandle,
     state: State<'_, AppState>,
+    name: Option<String>,
     nodes: Vec<String>,
     http_port: Option<u16>,
     http_host: Option<String>,
     planner_url: Option<String>,
     step_timeout_s: Option<f64>,
+    env: Option<HashMap<String, String>>,
+    extra_args: Option<Vec<String>>,
+    on_port_conflict: Option<PortConflictAction>,
+    unix_socket: Option<bool>,
 ) -> Result<OrchestratorProcessStatus, String> {
+    let name = orchestrator_instance_name(name)?;
+    let env = env.unwrap_or_default();
+    if let Some(bad) = env.keys().find(|k| k.trim().is_empty() || k.contains('=') || k.contains('\0')) {
+        return Err(format!("Invalid environment variable name {bad:?}"));
+    }
     // Snapshot/clear state without holding the mutex across awaits.
-    {
-        let mut lock = state
-            .orchestrator_proc
-            .lock()
-            .map_err(|_| "State lock poisoned".to_string())?;
-
-        // If already running, return status.
-        if let Some(proc_) = &mut *lock {
-            if proc_.child.try_wait().map_err(|e| format!("Failed to query orchestrator process: {e}"))?.is_none() {
-                return Ok(OrchestratorProcessStatus {
-                    running: true,
-                    pid: Some(proc_.child.id()),
-                    http_base_url: Some(proc_.http_base_url.clone()),
-                    args: Some(proc_.args.clone()),
-                });
-            }
-            // Child exited; clear and continue to respawn.
-            *lock = None;
+    // If already running, return status; an exited child is cleared so it can be respawned.
+    if let Some(status) = managed_orchestrator_status(&state, &name)?.filter(|s| s.running) {
+        return Ok(status);
+    }
+    let http_host_raw = http_host.unwrap_or_else(|| "127.0.0.1".to_string());
+    let launch = OrchestratorLaunch {
+        nodes,
+        http_host: http_host_raw.trim().to_string(),
+        unix_socket: unix_socket.unwrap_or(false),
+        planner_url: planner_url.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        step_timeout_s,
+        extra_args: extra_args
+            .unwrap_or_default()
+            .into_iter()
+            .map(|arg| arg.trim().to_string())
+            .filter(|arg| !arg.is_empty())
+            .collect(),
+    };
+    // A socket needs no port probing or conflict handling.
+    if launch.unix_socket {
+        if !cfg!(unix) {
+            return Err("unix_socket is only supported on macOS and Linux".to_string());
+        }
+        if launch.nodes.is_empty() {
+            return Err("nodes must contain at least one entry like base=vporto26.local:8765".to_string());
         }
+        return spawn_orchestrator(&app, &state, &name, &launch, 0, env, RestartPolicy::default());
     }
+    let other_instance_urls = state
+        .processes
+        .list(Some(ORCHESTRATOR_PROCESS_KIND))?
+        .into_iter()
+        .filter(|p| p.name != name)
+        .filter_map(|p| p.http_base_url)
+        .collect::<Vec<_>>();
 
-    let http_host_raw = http_host.unwrap_or_else(|| "127.0.0.1".to_string());
     let http_host_ip = normalize_local_host(&http_host_raw)?;
     let preferred_port = http_port.unwrap_or(5055);
 
@@ -1336,19 +13564,23 @@ async fn orchestrator_spawn(
     // If so, reuse it instead of spawning a second orchestrator on an ephemeral port.
     {
         let base = format!("http://{}:{}", http_host_raw.trim(), preferred_port);
+        // Another named instance owns that port; never hand its URL out as this instance's.
+        let owned_by_other = other_instance_urls.contains(&base);
         let url = format!("{base}/status");
-        let client = reqwest::Client::new();
-        let resp = client
-            .get(&url)
+        let resp = with_endpoint_auth(&state.http, http_client(&state.http)?.get(&url), &base)?
             .timeout(Duration::from_millis(400))
             .send()
             .await;
-        if let Ok(r) = resp {
+        if let (false, Ok(r)) = (owned_by_other, resp) {
             if r.status().is_success() {
                 if let Ok(v) = r.json::<Value>().await {
                     if v.get("ok").and_then(|x| x.as_bool()).unwrap_or(false) {
-                        append_desktop_audit_log("orchestrator.reuse_existing", &json!({ "base_url": base, "status": v }));
+                        append_desktop_audit_log(
+                            "orchestrator.reuse_existing",
+                            &json!({ "name": name, "base_url": base, "status": v }),
+                        );
                         return Ok(OrchestratorProcessStatus {
+                            name,
                             running: false,
                             pid: None,
                             http_base_url: Some(base),
@@ -1361,41 +13593,97 @@ async fn orchestrator_spawn(
     }
 
     // Re-check state (another call may have spawned while we were probing).
-    {
-        let mut lock = state
-            .orchestrator_proc
-            .lock()
-            .map_err(|_| "State lock poisoned".to_string())?;
-        if let Some(proc_) = &mut *lock {
-            if proc_.child.try_wait().map_err(|e| format!("Failed to query orchestrator process: {e}"))?.is_none() {
-                return Ok(OrchestratorProcessStatus {
-                    running: true,
-                    pid: Some(proc_.child.id()),
-                    http_base_url: Some(proc_.http_base_url.clone()),
-                    args: Some(proc_.args.clone()),
-                });
+    if let Some(status) = managed_orchestrator_status(&state, &name)?.filter(|s| s.running) {
+        return Ok(status);
+    }
+
+    if launch.nodes.is_empty() {
+        return Err("nodes must contain at least one entry like base=vporto26.local:8765".to_string());
+    }
+
+    // The preferred port did not answer as an orchestrator above; if it is still bound, something
+    // else (often a hung orchestrator from an earlier session) holds it.
+    if TcpListener::bind(SocketAddr::new(http_host_ip, preferred_port)).is_err() {
+        let action = on_port_conflict.unwrap_or_default();
+        let holders = find_port_holders(&app, &state, preferred_port);
+        append_desktop_audit_log(
+            "orchestrator.port_conflict",
+            &json!({ "name": name, "port": preferred_port, "holders": holders }),
+        );
+        let describe = || {
+            if holders.is_empty() {
+                format!("port {preferred_port} is in use by an unidentified process")
+            } else {
+                holders
+                    .iter()
+                    .map(|h| format!("port {} is held by pid {} ({})", h.port, h.pid, h.command.as_deref().unwrap_or("unknown")))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            }
+        };
+        match action {
+            PortConflictAction::Ephemeral => {}
+            PortConflictAction::Error => return Err(describe()),
+            PortConflictAction::KillStale => {
+                for holder in &holders {
+                    if !(holder.orchestrator && holder.recorded_by_us && !holder.managed) {
+                        append_desktop_audit_log(
+                            "orchestrator.port_conflict_kept",
+                            &json!({ "pid": holder.pid, "port": preferred_port, "command": holder.command }),
+                        );
+                        continue;
+                    }
+                    let killed = terminate_pid(holder.pid);
+                    append_desktop_audit_log(
+                        "orchestrator.port_conflict_kill",
+                        &json!({ "pid": holder.pid, "port": preferred_port, "killed": killed }),
+                    );
+                }
+                // The kernel can take a moment to release the listener after the process exits.
+                for _ in 0..20 {
+                    if TcpListener::bind(SocketAddr::new(http_host_ip, preferred_port)).is_ok() {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
             }
-            *lock = None;
         }
     }
 
     let http_port = pick_free_tcp_port(http_host_ip, preferred_port)?;
-    let repo_root = find_repo_root()?;
-    let orch_path = repo_root.join("orchestrator").join("orchestrator.py");
-    if !orch_path.exists() {
-        return Err(format!(
-            "orchestrator.py not found at {}",
-            orch_path.display()
-        ));
-    }
+    spawn_orchestrator(&app, &state, &name, &launch, http_port, env, RestartPolicy::default())
+}
 
-    if nodes.is_empty() {
-        return Err("nodes must contain at least one entry like base=vporto26.local:8765".to_string());
-    }
+/// The parts of an orchestrator command line `orchestrator_restart` can change.
+#[derive(Clone, Serialize, Deserialize)]
+struct OrchestratorLaunch {
+    nodes: Vec<String>,
+    http_host: String,
+    /// Serve the HTTP bridge on `<log path>.sock` instead of a TCP port (Unix only).
+    #[serde(default)]
+    unix_socket: bool,
+    planner_url: Option<String>,
+    step_timeout_s: Option<f64>,
+    extra_args: Vec<String>,
+}
+
+fn spawn_orchestrator(
+    app: &AppHandle,
+    state: &AppState,
+    name: &str,
+    launch: &OrchestratorLaunch,
+    http_port: u16,
+    mut env: HashMap<String, String>,
+    restart_policy: RestartPolicy,
+) -> Result<OrchestratorProcessStatus, String> {
+    let http_host_ip = normalize_local_host(&launch.http_host)?;
+    let install = resolve_orchestrator_install(app)?;
+    let work_dir = install.work_dir;
+    let log_path = orchestrator_log_path(&work_dir, name);
 
     let mut args: Vec<String> = Vec::new();
-    args.push(orch_path.to_string_lossy().to_string());
-    for node in &nodes {
+    args.push(install.script.to_string_lossy().to_string());
+    for node in &launch.nodes {
         let trimmed = node.trim();
         if trimmed.is_empty() {
             continue;
@@ -1403,114 +13691,305 @@ async fn orchestrator_spawn(
         args.push("--node".to_string());
         args.push(trimmed.to_string());
     }
-    if let Some(url) = planner_url.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+    if let Some(url) = &launch.planner_url {
         args.push("--planner-url".to_string());
-        args.push(url);
+        args.push(url.clone());
     }
-    if let Some(step_timeout) = step_timeout_s {
+    if let Some(step_timeout) = launch.step_timeout_s {
         args.push("--step-timeout".to_string());
         args.push(format!("{step_timeout}"));
     }
-    args.push("--http-host".to_string());
-    args.push(http_host_raw.trim().to_string());
-    args.push("--http-port".to_string());
-    args.push(http_port.to_string());
+    let (listen, http_base_url) = if launch.unix_socket {
+        let socket = log_path.with_extension("sock");
+        // sockaddr_un paths are capped at ~104 bytes on macOS.
+        if socket.as_os_str().len() > 100 {
+            return Err(format!("Unix socket path is too long: {}", socket.display()));
+        }
+        args.push("--http-unix-socket".to_string());
+        args.push(socket.to_string_lossy().to_string());
+        let base = format!("unix://{}", socket.display());
+        (ListenTarget::Unix(socket), base)
+    } else {
+        args.push("--http-host".to_string());
+        args.push(launch.http_host.clone());
+        args.push("--http-port".to_string());
+        args.push(http_port.to_string());
+        let base = format!("http://{}:{}", launch.http_host, http_port);
+        (ListenTarget::Tcp(SocketAddr::new(http_host_ip, http_port)), base)
+    };
+    args.extend(launch.extra_args.iter().cloned());
+
+    // Python block-buffers piped stdout; unbuffer it so log events arrive as they are printed.
+    env.insert("PYTHONUNBUFFERED".to_string(), "1".to_string());
+    let spec = ProcessSpec {
+        kind: ORCHESTRATOR_PROCESS_KIND.to_string(),
+        name: name.to_string(),
+        program: resolve_python3(),
+        args,
+        env,
+        work_dir,
+        pid_file: Some(log_path.with_extension("pid")),
+        log_path,
+        log_event: ORCHESTRATOR_LOG_EVENT,
+        listen: Some(listen),
+        // orchestrator.py connects to nodes before it starts the HTTP bridge, and each node connect
+        // can take a couple seconds (DNS + TCP timeout). Give it enough time to come up.
+        listen_timeout: Duration::from_secs(12),
+        http_base_url: Some(http_base_url.clone()),
+        meta: json!(launch),
+    };
+    let mut audit = json!({
+        "name": name,
+        "base_url": http_base_url,
+        "bundled": install.bundled,
+        "args": redact_secret_args(&spec.args),
+        "env": redact_secret_env(&spec.env)
+    });
+    let status = state
+        .processes
+        .spawn(app, spec, restart_policy)
+        .map_err(|e| format!("{e}. If a previous orchestrator is running, stop it or use a different port."))?;
+    audit["pid"] = json!(status.pid);
+    append_desktop_audit_log("orchestrator.spawned", &audit);
+    Ok(status.into())
+}
 
-    let python3 = resolve_python3();
-    let mut cmd = Command::new(python3);
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrchestratorRestartResult {
+    name: String,
+    old_pid: Option<u32>,
+    new_pid: Option<u32>,
+    old_base_url: Option<String>,
+    new_base_url: Option<String>,
+    /// False when the old port could be reused, so clients keep working without reconfiguring.
+    base_url_changed: bool,
+    status: OrchestratorProcessStatus,
+}
 
-    let log_path = repo_root.join(".build").join("orchestrator_desktop.log");
-    let _ = std::fs::create_dir_all(repo_root.join(".build"));
-    let log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .map_err(|e| format!("Failed to open orchestrator log file {}: {e}", log_path.display()))?;
-    let log_file_err = log_file
-        .try_clone()
-        .map_err(|e| format!("Failed to clone log file handle: {e}"))?;
+/// Stops a managed orchestrator and relaunches it with a new node list and/or flags. Omitted
+/// parameters keep their previous values (pass an empty `planner_url` to drop it). The old port
+/// is reused when it frees up in time; if the relaunch fails the previous command line is
+/// restarted so the instance is not left down.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn orchestrator_restart(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+    nodes: Option<Vec<String>>,
+    planner_url: Option<String>,
+    step_timeout_s: Option<f64>,
+    extra_args: Option<Vec<String>>,
+) -> Result<OrchestratorRestartResult, String> {
+    let name = orchestrator_instance_name(name)?;
+    let (old_spec, old_policy) = state
+        .processes
+        .spec(ORCHESTRATOR_PROCESS_KIND, &name)?
+        .ok_or_else(|| format!("Orchestrator instance {name} is not running; use orchestrator_spawn"))?;
+    let mut launch = serde_json::from_value::<OrchestratorLaunch>(old_spec.meta.clone())
+        .map_err(|e| format!("Cannot restart {name}: launch parameters unavailable ({e})"))?;
+    if let Some(nodes) = nodes {
+        launch.nodes = nodes.into_iter().map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+    }
+    if let Some(url) = planner_url {
+        launch.planner_url = Some(url.trim().to_string()).filter(|s| !s.is_empty());
+    }
+    if step_timeout_s.is_some() {
+        launch.step_timeout_s = step_timeout_s;
+    }
+    if let Some(extra_args) = extra_args {
+        launch.extra_args = extra_args
+            .into_iter()
+            .map(|arg| arg.trim().to_string())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+    }
+    if launch.nodes.is_empty() {
+        return Err("nodes must contain at least one entry like base=vporto26.local:8765".to_string());
+    }
 
-    cmd.args(&args)
-        .current_dir(repo_root)
-        .stdin(Stdio::null())
-        .stdout(Stdio::from(log_file))
-        .stderr(Stdio::from(log_file_err));
+    // Halt any running plan before the process goes away.
+    if let Some(base) = old_spec.http_base_url.as_deref() {
+        let stop = orchestrator_endpoint(&state.http, base)
+            .and_then(|(client, prefix)| with_endpoint_auth(&state.http, client.post(format!("{prefix}/stop")), base));
+        if let Ok(stop) = stop {
+            let _ = stop
+                .timeout(Duration::from_secs(1))
+                .send()
+                .await;
+        }
+    }
+    let previous = state
+        .processes
+        .take(ORCHESTRATOR_PROCESS_KIND, &name)?
+        .ok_or_else(|| format!("Orchestrator instance {name} was stopped during the restart"))?;
+    let old_pid = previous.child.id();
+    tauri::async_runtime::spawn_blocking(move || previous.terminate())
+        .await
+        .map_err(|e| format!("Failed to stop orchestrator {name}: {e}"))?;
 
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn orchestrator: {e}"))?;
-    // orchestrator.py connects to nodes before it starts the HTTP bridge, and each node connect
-    // can take a couple seconds (DNS + TCP timeout). Give it enough time to come up.
-    wait_for_tcp_listen(http_host_ip, http_port, &mut child, Duration::from_secs(12))
-        .map_err(|e| format!("{e}. If a previous orchestrator is running, stop it or use a different port."))?;
+    let respawned = match &old_spec.listen {
+        Some(ListenTarget::Tcp(addr)) => pick_free_tcp_port(addr.ip(), addr.port()).and_then(|http_port| {
+            spawn_orchestrator(&app, &state, &name, &launch, http_port, old_spec.env.clone(), old_policy.clone())
+        }),
+        Some(ListenTarget::Unix(_)) => {
+            spawn_orchestrator(&app, &state, &name, &launch, 0, old_spec.env.clone(), old_policy.clone())
+        }
+        None => Err(format!("Orchestrator {name} has no listen address")),
+    };
+    let status = match respawned {
+        Ok(status) => status,
+        Err(error) => {
+            let rollback = state.processes.spawn(&app, old_spec.clone(), old_policy);
+            append_desktop_audit_log(
+                "orchestrator.restart_failed",
+                &json!({ "name": name, "error": error, "rolled_back": rollback.is_ok() }),
+            );
+            return Err(match rollback {
+                Ok(status) => format!("{error} (previous orchestrator restored, pid {:?})", status.pid),
+                Err(rollback_error) => format!("{error} (restoring previous orchestrator also failed: {rollback_error})"),
+            });
+        }
+    };
 
-    let http_base_url = format!("http://{}:{}", http_host_raw.trim(), http_port);
-    {
-        let mut lock = state
-            .orchestrator_proc
-            .lock()
-            .map_err(|_| "State lock poisoned".to_string())?;
-        *lock = Some(OrchestratorProcess {
-            child,
-            args,
-            http_base_url: http_base_url.clone(),
-        });
+    let result = OrchestratorRestartResult {
+        name: name.clone(),
+        old_pid: Some(old_pid),
+        new_pid: status.pid,
+        base_url_changed: status.http_base_url != old_spec.http_base_url,
+        old_base_url: old_spec.http_base_url,
+        new_base_url: status.http_base_url.clone(),
+        status,
+    };
+    append_desktop_audit_log(
+        "orchestrator.restarted",
+        &json!({
+            "name": name,
+            "old_pid": result.old_pid,
+            "new_pid": result.new_pid,
+            "old_base_url": result.old_base_url,
+            "new_base_url": result.new_base_url
+        }),
+    );
+    Ok(result)
+}
 
-        Ok(OrchestratorProcessStatus {
-            running: true,
-            pid: lock.as_ref().map(|p| p.child.id()),
-            http_base_url: Some(http_base_url),
-            args: lock.as_ref().map(|p| p.args.clone()),
-        })
+/// `orchestrator_spawn` with registry entry names in place of raw `alias=host:port` strings.
+/// Addresses are resolved now, so nodes that moved since they were saved are picked up.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn orchestrator_spawn_from_registry(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+    registry_nodes: Vec<String>,
+    http_port: Option<u16>,
+    http_host: Option<String>,
+    planner_url: Option<String>,
+    step_timeout_s: Option<f64>,
+    env: Option<HashMap<String, String>>,
+    extra_args: Option<Vec<String>>,
+    on_port_conflict: Option<PortConflictAction>,
+    unix_socket: Option<bool>,
+) -> Result<OrchestratorProcessStatus, String> {
+    if registry_nodes.is_empty() {
+        return Err("At least one registry node is required".to_string());
     }
+    let registry = load_node_registry(&app)?;
+    let names = registry_nodes.clone();
+    // mDNS lookups can take seconds; keep them off the async runtime.
+    let nodes = tauri::async_runtime::spawn_blocking(move || resolve_registry_nodes(&registry, &names))
+        .await
+        .map_err(|e| format!("Node resolution task failed: {e}"))??;
+    append_desktop_audit_log(
+        "orchestrator.registry_nodes",
+        &json!({ "registry_nodes": registry_nodes, "resolved": nodes }),
+    );
+    orchestrator_spawn(
+        app,
+        state,
+        name,
+        nodes,
+        http_port,
+        http_host,
+        planner_url,
+        step_timeout_s,
+        env,
+        extra_args,
+        on_port_conflict,
+        unix_socket,
+    )
+    .await
 }
 
 #[tauri::command]
-fn orchestrator_stop_process(state: State<'_, AppState>) -> Result<OrchestratorProcessStatus, String> {
-    let mut lock = state
-        .orchestrator_proc
-        .lock()
-        .map_err(|_| "State lock poisoned".to_string())?;
-    stop_orchestrator_locked(&mut lock);
-    Ok(OrchestratorProcessStatus {
-        running: false,
-        pid: None,
-        http_base_url: None,
-        args: None,
-    })
+fn orchestrator_stop_process(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<OrchestratorProcessStatus, String> {
+    let name = orchestrator_instance_name(name)?;
+    state.processes.stop(ORCHESTRATOR_PROCESS_KIND, &name)?;
+    Ok(stopped_orchestrator_status(&name))
 }
 
 #[tauri::command]
-fn orchestrator_process_status(state: State<'_, AppState>) -> Result<OrchestratorProcessStatus, String> {
-    let mut lock = state
-        .orchestrator_proc
-        .lock()
-        .map_err(|_| "State lock poisoned".to_string())?;
-    if let Some(proc_) = &mut *lock {
-        match proc_.child.try_wait() {
-            Ok(None) => Ok(OrchestratorProcessStatus {
-                running: true,
-                pid: Some(proc_.child.id()),
-                http_base_url: Some(proc_.http_base_url.clone()),
-                args: Some(proc_.args.clone()),
-            }),
-            Ok(Some(_)) => {
-                *lock = None;
-                Ok(OrchestratorProcessStatus {
-                    running: false,
-                    pid: None,
-                    http_base_url: None,
-                    args: None,
-                })
-            }
-            Err(e) => Err(format!("Failed to query orchestrator process: {e}")),
-        }
-    } else {
-        Ok(OrchestratorProcessStatus {
-            running: false,
-            pid: None,
-            http_base_url: None,
-            args: None,
-        })
+fn orchestrator_process_status(
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<OrchestratorProcessStatus, String> {
+    let name = orchestrator_instance_name(name)?;
+    Ok(managed_orchestrator_status(&state, &name)?.unwrap_or_else(|| stopped_orchestrator_status(&name)))
+}
+
+#[tauri::command]
+fn orchestrator_port_holders(app: AppHandle, state: State<'_, AppState>, http_port: Option<u16>) -> Result<Vec<PortHolder>, String> {
+    Ok(find_port_holders(&app, &state, http_port.unwrap_or(5055)))
+}
+
+#[tauri::command]
+fn cleanup_orphans(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<OrphanCleanupEntry>, String> {
+    let managed_pids = state.processes.pids()?;
+    Ok(cleanup_orphaned_orchestrators(&app, &managed_pids))
+}
+
+#[tauri::command]
+fn orchestrator_list_processes(state: State<'_, AppState>) -> Result<Vec<OrchestratorProcessStatus>, String> {
+    Ok(state
+        .processes
+        .list(Some(ORCHESTRATOR_PROCESS_KIND))?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+#[tauri::command]
+fn process_list(state: State<'_, AppState>, kind: Option<String>) -> Result<Vec<ManagedProcessStatus>, String> {
+    state.processes.list(kind.as_deref())
+}
+
+#[tauri::command]
+fn process_status(state: State<'_, AppState>, kind: String, name: String) -> Result<Option<ManagedProcessStatus>, String> {
+    state.processes.status(&kind, &name)
+}
+
+#[tauri::command]
+fn process_stop(state: State<'_, AppState>, kind: String, name: String) -> Result<bool, String> {
+    let stopped = state.processes.stop(&kind, &name)?;
+    if stopped {
+        append_desktop_audit_log("process.stopped", &json!({ "kind": kind, "name": name }));
     }
+    Ok(stopped)
+}
+
+#[tauri::command]
+fn process_set_restart_policy(
+    state: State<'_, AppState>,
+    kind: String,
+    name: String,
+    policy: RestartPolicy,
+) -> Result<ManagedProcessStatus, String> {
+    state.processes.set_restart_policy(&kind, &name, policy)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1518,28 +13997,588 @@ pub fn run() {
     tauri::Builder::default()
         .manage(AppState::default())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // Nothing is managed yet, so every recorded orchestrator not owned by another running
+            // instance is left over from a crash. PID files written by this instance carry our
+            // own PID as owner and are skipped, so the cleanup can run in the background without
+            // racing a fresh spawn.
+            let cleanup_app = app.handle().clone();
+            thread::spawn(move || {
+                let managed_pids = cleanup_app.state::<AppState>().processes.pids().unwrap_or_default();
+                cleanup_orphaned_orchestrators(&cleanup_app, &managed_pids);
+            });
+            let endpoint_settings = load_endpoint_settings(app.handle()).unwrap_or_else(|e| {
+                append_desktop_audit_log("endpoint.settings_load_failed", &json!({ "error": e }));
+                HashMap::new()
+            });
+            let state = app.state::<AppState>();
+            if let Ok(mut lock) = state.http.endpoints.lock() {
+                *lock = endpoint_settings;
+            }
+            cache_node_settings(&load_node_registry(app.handle()).unwrap_or_default());
+            match load_openai_config(app.handle()) {
+                Ok(config) => {
+                    if let Ok(mut lock) = state.http.openai.lock() {
+                        *lock = config;
+                    }
+                }
+                Err(e) => append_desktop_audit_log("openai.config_load_failed", &json!({ "error": e })),
+            }
+            supervise_processes(app.handle().clone());
+            supervise_node_connections(app.handle().clone());
+            supervise_node_status(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             list_serial_ports,
             connect_serial,
             disconnect_serial,
             get_connection_status,
+            list_serial_sessions,
             send_serial_line,
+            start_serial_capture,
+            stop_serial_capture,
+            replay_serial_capture,
+            get_serial_history,
+            set_serial_filter,
+            get_serial_rate_limit,
+            set_serial_rate_limit,
+            get_serial_watchdog,
+            set_serial_watchdog,
+            send_serial_raw,
+            run_serial_macro,
+            set_serial_control_lines,
+            send_serial_break,
+            reset_device,
+            flash_firmware,
+            get_http_client_config,
+            set_http_client_config,
+            list_endpoint_settings,
+            set_endpoint_auth,
+            set_endpoint_tls,
+            get_openai_config,
+            set_openai_config,
+            list_critic_prompt_templates,
+            read_critic_prompt_template,
+            save_critic_prompt_template,
+            get_critic_retry_policy,
+            set_critic_retry_policy,
+            get_orchestrator_retry_policy,
+            set_orchestrator_retry_policy,
             orchestrator_status,
+            orchestrator_discover,
+            orchestrator_metrics,
+            read_orchestrator_log,
+            stop_orchestrator_log_follow,
             orchestrator_execute_plan,
             orchestrator_stop,
+            orchestrator_validate_plan,
+            orchestrator_enqueue_plan,
+            orchestrator_queue_status,
+            orchestrator_cancel,
+            orchestrator_abort_request,
+            orchestrator_health_start,
+            orchestrator_health_status,
+            orchestrator_health_stop,
             vision_step,
+            vision_spawn,
+            vision_stop_process,
+            vision_process_status,
             critic_spawn,
             critic_status,
             critic_step,
+            critic_frames_dir,
+            critic_update_config,
             critic_stop,
+            list_critic_sessions,
+            read_critic_session,
+            critic_batch_eval,
+            critic_label_step,
+            critic_calibration_report,
+            export_rewards,
             node_probe,
+            node_probe_serial,
+            node_scan_ble,
+            node_probe_many,
+            node_connect,
+            node_disconnect,
+            node_list_connections,
+            node_send_command,
+            node_capabilities,
+            group_probe,
+            group_send_command,
+            group_stop,
+            node_command_status,
+            node_ping,
+            node_telemetry_subscribe,
+            node_telemetry_unsubscribe,
+            node_broadcast_discover,
+            node_scan_subnet,
+            node_registry_list,
+            node_firmware_report,
+            start_sim_node,
+            stop_sim_node,
+            list_sim_nodes,
+            node_registry_add,
+            node_registry_remove,
+            node_registry_set_auth,
+            node_registry_set_timeouts,
+            node_registry_set_groups,
+            node_registry_rename,
+            node_heartbeat_start,
+            node_heartbeat_status,
+            get_fleet_status,
+            node_heartbeat_stop,
             write_debug_log,
             read_debug_log,
             read_desktop_audit_log,
             orchestrator_spawn,
+            orchestrator_spawn_from_registry,
+            orchestrator_restart,
             orchestrator_stop_process,
-            orchestrator_process_status
+            orchestrator_process_status,
+            orchestrator_list_processes,
+            process_list,
+            process_status,
+            process_stop,
+            process_set_restart_policy,
+            cleanup_orphans,
+            orchestrator_port_holders
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn subnet_hosts_skips_network_and_broadcast_on_a_24() {
+        let hosts = subnet_hosts("192.168.1.77/24").unwrap();
+        assert_eq!(hosts.len(), 254);
+        assert_eq!(hosts.first(), Some(&Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(hosts.last(), Some(&Ipv4Addr::new(192, 168, 1, 254)));
+    }
+
+    #[test]
+    fn subnet_hosts_keeps_both_addresses_of_a_31() {
+        assert_eq!(
+            subnet_hosts("10.0.0.5/31").unwrap(),
+            vec![Ipv4Addr::new(10, 0, 0, 4), Ipv4Addr::new(10, 0, 0, 5)]
+        );
+        assert_eq!(
+            subnet_hosts("255.255.255.254/31").unwrap(),
+            vec![Ipv4Addr::new(255, 255, 255, 254), Ipv4Addr::BROADCAST]
+        );
+    }
+
+    #[test]
+    fn subnet_hosts_of_a_32_is_the_address_itself() {
+        assert_eq!(subnet_hosts("10.0.0.5/32").unwrap(), vec![Ipv4Addr::new(10, 0, 0, 5)]);
+        assert_eq!(subnet_hosts("10.0.0.5").unwrap(), vec![Ipv4Addr::new(10, 0, 0, 5)]);
+        assert_eq!(subnet_hosts("255.255.255.255/32").unwrap(), vec![Ipv4Addr::BROADCAST]);
+    }
+
+    /// Answers each connection with the next status in `statuses` (the last one repeats) and an
+    /// empty JSON object; returns the base URL and the number of requests served so far.
+    fn stub_http_server(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    break;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+                let mut body = vec![0; content_length];
+                let _ = reader.read_exact(&mut body);
+                let seen = counter.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[seen.min(statuses.len() - 1)];
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status} Stub\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}"
+                );
+            }
+        });
+        (base, served)
+    }
+
+    fn orchestrator_call(
+        max_attempts: u32,
+        method: reqwest::Method,
+        base: &str,
+        path: &str,
+        correlation_id: Option<&str>,
+    ) -> Result<Value, String> {
+        let policy = OrchestratorRetryPolicy {
+            max_attempts,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+            ..OrchestratorRetryPolicy::default()
+        };
+        let body = (method == reqwest::Method::POST).then(|| json!({ "plan": [] }));
+        let http = HttpState {
+            orchestrator_retry: Mutex::new(Some(policy)),
+            ..HttpState::default()
+        };
+        tauri::async_runtime::block_on(send_orchestrator_request(
+            &http,
+            method,
+            base,
+            path,
+            body,
+            correlation_id.map(str::to_string),
+            Some(Duration::from_secs(5)),
+        ))
+    }
+
+    #[test]
+    fn execute_plan_is_not_resent_after_a_gateway_timeout() {
+        let (base, served) = stub_http_server(vec![504, 200]);
+        let result = orchestrator_call(3, reqwest::Method::POST, &base, "/execute_plan", Some("plan-1"));
+        assert!(result.unwrap_err().contains("HTTP 504"));
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn post_without_correlation_id_is_not_retried() {
+        let (base, served) = stub_http_server(vec![503, 200]);
+        assert!(orchestrator_call(3, reqwest::Method::POST, &base, "/stop", None).is_err());
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn get_is_retried_on_503() {
+        let (base, served) = stub_http_server(vec![503, 200]);
+        assert_eq!(orchestrator_call(3, reqwest::Method::GET, &base, "/status", None), Ok(json!({})));
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn retries_stop_at_max_attempts() {
+        let (base, served) = stub_http_server(vec![503]);
+        let result = orchestrator_call(3, reqwest::Method::GET, &base, "/status", None);
+        assert!(result.unwrap_err().contains("HTTP 503"));
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn ping_rounds_switches_to_hello_after_err() {
+        let mut sent = Vec::new();
+        let (method, samples, error) = ping_rounds(3, Duration::ZERO, |line| {
+            sent.push(line.to_string());
+            let reply = if line == "PING" { "ERR unknown command" } else { "HELLO node" };
+            Ok((reply.to_string(), 1.5))
+        });
+        assert_eq!(sent, ["PING", "HELLO", "HELLO"]);
+        assert_eq!(method, "HELLO");
+        assert_eq!(samples, vec![Some(1.5); 3]);
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn ping_rounds_stops_at_the_first_error() {
+        let mut calls = 0;
+        let (method, samples, error) = ping_rounds(5, Duration::ZERO, |_| {
+            calls += 1;
+            match calls {
+                1 => Ok(("PONG".to_string(), 2.0)),
+                _ => Err("read timed out".to_string()),
+            }
+        });
+        assert_eq!(calls, 2);
+        assert_eq!(method, "PING");
+        assert_eq!(samples, vec![Some(2.0), None]);
+        assert_eq!(error.as_deref(), Some("read timed out"));
+    }
+
+    #[test]
+    fn ping_rounds_takes_one_sample_per_round() {
+        let mut rtt = 0.0;
+        let (method, samples, error) = ping_rounds(4, Duration::ZERO, |_| {
+            rtt += 1.0;
+            Ok(("PONG".to_string(), rtt))
+        });
+        assert_eq!(method, "PING");
+        assert_eq!(samples, vec![Some(1.0), Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(error, None);
+    }
+
+    fn sim_behavior(failure_rate: f64, fail_tokens: &[&str]) -> SimNodeBehavior {
+        let manifest = json!({
+            "device": { "name": "sim" },
+            "commands": [
+                { "token": "FWD", "args": [{ "name": "speed", "type": "int", "min": 0, "max": 100 }] },
+                { "token": "BEEP", "args": [] }
+            ]
+        });
+        SimNodeBehavior {
+            manifest_line: format!("MANIFEST {manifest}"),
+            commands: manifest_command_specs(&manifest),
+            reply_delay: Duration::ZERO,
+            failure_rate,
+            fail_tokens: fail_tokens.iter().map(|t| t.to_string()).collect(),
+            telemetry_interval: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn sim_node_answers_hello_ping_and_subscriptions() {
+        let (behavior, counters) = (sim_behavior(0.0, &[]), SimNodeCounters::default());
+        let mut subscribed = false;
+        assert_eq!(behavior.reply("HELLO", &mut subscribed, &counters), behavior.manifest_line);
+        assert_eq!(behavior.reply("ping", &mut subscribed, &counters), "PONG");
+        assert_eq!(behavior.reply("SUB TELEMETRY", &mut subscribed, &counters), "OK");
+        assert!(subscribed);
+        assert_eq!(behavior.reply("UNSUB telemetry", &mut subscribed, &counters), "OK");
+        assert!(!subscribed);
+        assert_eq!(behavior.reply("SUB", &mut subscribed, &counters), "ERR BAD_REQUEST unsupported");
+        assert_eq!(counters.commands.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn sim_node_rejects_unknown_tokens_and_bad_args() {
+        let (behavior, counters) = (sim_behavior(0.0, &[]), SimNodeCounters::default());
+        let mut subscribed = false;
+        assert_eq!(behavior.reply("RUN", &mut subscribed, &counters), "ERR BAD_ARGS missing_token");
+        assert_eq!(behavior.reply("RUN JUMP", &mut subscribed, &counters), "ERR BAD_TOKEN unknown");
+        assert_eq!(behavior.reply("RUN FWD", &mut subscribed, &counters), "ERR BAD_ARGS arg_count");
+        assert_eq!(behavior.reply("RUN FWD 101", &mut subscribed, &counters), "ERR BAD_ARGS arg_range");
+        assert_eq!(behavior.reply("RUN FWD fast", &mut subscribed, &counters), "ERR BAD_ARGS arg_type");
+        assert_eq!(behavior.reply("RUN fwd 50", &mut subscribed, &counters), "OK");
+        assert_eq!(counters.failures.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn sim_node_always_fails_fail_tokens() {
+        let (behavior, counters) = (sim_behavior(0.0, &["BEEP"]), SimNodeCounters::default());
+        let mut subscribed = false;
+        for _ in 0..3 {
+            assert_eq!(behavior.reply("RUN BEEP", &mut subscribed, &counters), "ERR SIM_FAILURE injected");
+        }
+        assert_eq!(behavior.reply("RUN FWD 10", &mut subscribed, &counters), "OK");
+        assert_eq!(counters.failures.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn sim_node_failure_rate_bounds() {
+        let mut subscribed = false;
+        let (never, counters) = (sim_behavior(0.0, &[]), SimNodeCounters::default());
+        assert!((0..50).all(|_| never.reply("RUN FWD 10", &mut subscribed, &counters) == "OK"));
+        assert_eq!(counters.failures.load(Ordering::Relaxed), 0);
+
+        let (always, counters) = (sim_behavior(1.0, &[]), SimNodeCounters::default());
+        assert!((0..50).all(|_| always.reply("RUN FWD 10", &mut subscribed, &counters) == "ERR SIM_FAILURE injected"));
+        assert_eq!(counters.failures.load(Ordering::Relaxed), 50);
+        assert_eq!(counters.commands.load(Ordering::Relaxed), 50);
+    }
+
+    #[test]
+    fn telemetry_json_objects_pass_through() {
+        assert_eq!(
+            parse_telemetry_payload(r#"{"battery": 87, "mode": "idle"}"#),
+            json!({ "battery": 87, "mode": "idle" })
+        );
+    }
+
+    #[test]
+    fn telemetry_key_values_are_typed_where_possible() {
+        assert_eq!(
+            parse_telemetry_payload("uptime_ms=1200 armed=true voltage=7.4 last_token=FWD junk"),
+            json!({ "uptime_ms": 1200, "armed": true, "voltage": 7.4, "last_token": "FWD" })
+        );
+        assert_eq!(parse_telemetry_payload("[1, 2]"), json!({}));
+        assert_eq!(parse_telemetry_payload(""), json!({}));
+    }
+
+    #[test]
+    fn discover_reply_reads_the_daemon_node_datagram() {
+        assert_eq!(
+            parse_discover_reply(r#"DAEMON_NODE {"port": 7071, "name": "rover", "node_id": "n-1"}"#, 7070),
+            Some((7071, Some("rover".to_string()), Some("n-1".to_string())))
+        );
+        assert_eq!(parse_discover_reply("DAEMON_NODE", 7070), Some((7070, None, None)));
+        assert_eq!(parse_discover_reply(r#"DAEMON_NODE {"port": 70000}"#, 7070), Some((7070, None, None)));
+    }
+
+    #[test]
+    fn discover_reply_accepts_a_manifest_and_rejects_noise() {
+        let manifest = r#"MANIFEST {"device": {"name": "arm", "node_id": "n-2"}, "commands": []}"#;
+        assert_eq!(
+            parse_discover_reply(manifest, 7070),
+            Some((7070, Some("arm".to_string()), Some("n-2".to_string())))
+        );
+        assert_eq!(parse_discover_reply("HELLO", 7070), None);
+        assert_eq!(parse_discover_reply("MANIFEST {not json", 7070), None);
+    }
+
+    fn critic_vote(model: &str, output: Value) -> (String, Result<CriticEvaluation, String>) {
+        (model.to_string(), Ok(CriticEvaluation { output, usage: None }))
+    }
+
+    #[test]
+    fn critic_votes_take_the_majority_and_mean() {
+        let (combined, votes) = combine_critic_votes(vec![
+            critic_vote("a", json!({ "reward": 0.8, "success": true, "success_confidence": 0.9, "failure_modes": [] })),
+            critic_vote("b", json!({ "reward": 0.4, "success": true, "success_confidence": 0.7, "failure_modes": ["uncertain"] })),
+            critic_vote("c", json!({ "reward": -0.6, "success": false, "success_confidence": 0.2, "failure_modes": ["no_progress"] })),
+        ])
+        .unwrap();
+        assert_eq!(votes.len(), 3);
+        assert_eq!(combined["success"], json!(true));
+        assert!((combined["reward"].as_f64().unwrap() - 0.2).abs() < 1e-9);
+        assert!((combined["success_confidence"].as_f64().unwrap() - 0.6).abs() < 1e-9);
+        assert_eq!(combined["critical_failure"], json!(false));
+        // A majority success drops visibility doubts but keeps the other modes.
+        assert_eq!(combined["failure_modes"], json!(["no_progress"]));
+        assert_eq!(combined["ensemble"].as_array().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn critic_votes_skip_failed_models_and_any_critical_failure_wins() {
+        let (combined, votes) = combine_critic_votes(vec![
+            critic_vote("a", json!({ "reward": 1.0, "success": true, "success_confidence": 1.0 })),
+            ("b".to_string(), Err("timed out".to_string())),
+            critic_vote(
+                "c",
+                json!({ "reward": 0.0, "success": false, "critical_failure": true, "critical_failure_reason": "tipped over" }),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(votes[1].error.as_deref(), Some("timed out"));
+        assert_eq!(combined["reward"], json!(0.5));
+        // One of two answering models is not a majority.
+        assert_eq!(combined["success"], json!(false));
+        assert_eq!(combined["critical_failure"], json!(true));
+        assert_eq!(combined["critical_failure_reason"], json!("tipped over"));
+    }
+
+    #[test]
+    fn critic_votes_fail_when_every_model_failed() {
+        let error = combine_critic_votes(vec![
+            ("a".to_string(), Err("HTTP 500".to_string())),
+            ("b".to_string(), Err("timed out".to_string())),
+        ])
+        .map(|_| ())
+        .unwrap_err();
+        assert_eq!(error, "All ensemble critics failed: a: HTTP 500; b: timed out");
+    }
+
+    fn frame_policy(max_frames: usize, selection: CriticFrameSelection) -> CriticFramePolicy {
+        CriticFramePolicy {
+            max_frames,
+            selection,
+            ..CriticFramePolicy::default()
+        }
+    }
+
+    #[test]
+    fn recent_frame_selection_keeps_the_newest() {
+        let policy = frame_policy(3, CriticFrameSelection::Recent);
+        assert_eq!(policy.select(8), vec![5, 6, 7]);
+        assert_eq!(policy.select(2), vec![0, 1]);
+        assert_eq!(policy.select(0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn evenly_spaced_frame_selection_spans_the_window() {
+        assert_eq!(frame_policy(4, CriticFrameSelection::EvenlySpaced).select(10), vec![0, 3, 6, 9]);
+        assert_eq!(frame_policy(3, CriticFrameSelection::EvenlySpaced).select(5), vec![0, 2, 4]);
+        assert_eq!(frame_policy(1, CriticFrameSelection::EvenlySpaced).select(7), vec![6]);
+        assert_eq!(frame_policy(6, CriticFrameSelection::EvenlySpaced).select(4), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn critic_confusion_counts_each_quadrant() {
+        let samples = [(true, 0.9, true), (true, 0.8, false), (false, 0.9, true), (false, 0.1, false), (true, 0.95, true)];
+        assert_eq!(critic_confusion(&samples, 0.5), (2, 1, 1, 1));
+        assert_eq!(critic_confusion(&[], 0.5), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn critic_confusion_treats_low_confidence_successes_as_negative() {
+        let samples = [(true, 0.6, true), (true, 0.6, false), (true, 0.7, true)];
+        assert_eq!(critic_confusion(&samples, 0.7), (1, 0, 1, 1));
+        // The threshold itself counts as confident.
+        assert_eq!(critic_confusion(&samples, 0.6), (2, 1, 0, 0));
+    }
+
+    #[test]
+    fn compare_versions_is_numeric_per_part() {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Greater);
+        assert_eq!(compare_versions("1.9.2", "v1.9"), Greater);
+        assert_eq!(compare_versions("V1.4.0", " 1.4.0 "), Equal);
+        assert_eq!(compare_versions("2.0", "10.0"), Less);
+    }
+
+    #[test]
+    fn compare_versions_falls_back_to_text_for_non_numeric_parts() {
+        use std::cmp::Ordering::{Greater, Less};
+        assert_eq!(compare_versions("1.4.0-beta", "1.4.0-alpha"), Greater);
+        assert_eq!(compare_versions("1.4.x", "1.4.1"), Greater);
+        assert_eq!(compare_versions("1.4", "1.4.0"), Less);
+    }
+
+    #[test]
+    fn csv_field_writes_plain_values_bare() {
+        assert_eq!(csv_field(&Value::Null), "");
+        assert_eq!(csv_field(&json!("ok")), "ok");
+        assert_eq!(csv_field(&json!(0.25)), "0.25");
+        assert_eq!(csv_field(&json!(true)), "true");
+    }
+
+    #[test]
+    fn csv_field_quotes_separators_quotes_and_newlines() {
+        assert_eq!(csv_field(&json!("a,b")), "\"a,b\"");
+        assert_eq!(csv_field(&json!("say \"hi\"")), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(&json!("line\nbreak")), "\"line\nbreak\"");
+        assert_eq!(csv_field(&json!(["x", "y"])), "\"[\"\"x\"\",\"\"y\"\"]\"");
+    }
+
+    #[test]
+    fn node_reconnect_delay_doubles_up_to_a_minute() {
+        let secs = [0, 1, 2, 5, 6, 7, 40, u32::MAX].map(|attempt| node_reconnect_delay(attempt).as_secs());
+        assert_eq!(secs, [1, 2, 4, 32, 60, 60, 60, 60]);
+    }
+
+    #[test]
+    fn identical_manifests_have_no_diff() {
+        let manifest = json!({ "device": { "version": "1.0" }, "commands": [{ "token": "FWD" }] });
+        assert!(diff_node_manifests("rover", "10.0.0.2:7070", &manifest, &manifest.clone()).is_none());
+    }
+
+    #[test]
+    fn manifest_diff_reports_token_and_version_changes() {
+        let previous = json!({
+            "device": { "version": "1.0" },
+            "commands": [{ "token": "FWD", "args": [] }, { "token": "BEEP" }, { "token": "STOP" }]
+        });
+        let current = json!({
+            "device": { "version": "1.1" },
+            "commands": [{ "token": "STOP" }, { "token": "FWD", "args": [{ "name": "speed" }] }, { "token": "LIFT" }, { "token": "ARM" }]
+        });
+        let diff = diff_node_manifests("rover", "10.0.0.2:7070", &previous, &current).unwrap();
+        assert_eq!((diff.name.as_str(), diff.target.as_str()), ("rover", "10.0.0.2:7070"));
+        assert_eq!(diff.added_tokens, ["ARM", "LIFT"]);
+        assert_eq!(diff.removed_tokens, ["BEEP"]);
+        assert_eq!(diff.changed_tokens, ["FWD"]);
+        assert_eq!(diff.previous_version.as_deref(), Some("1.0"));
+        assert_eq!(diff.version.as_deref(), Some("1.1"));
+    }
+}
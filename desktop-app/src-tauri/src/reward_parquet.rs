@@ -0,0 +1,72 @@
+//! Parquet encoding for `export_rewards`. Reward rows are loosely typed JSON, so each column's
+//! Arrow type is inferred from the values it holds; every column is nullable.
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// Boolean, Int64 or Float64 when every non-null value fits, else Utf8 (also for empty columns).
+fn column_type(rows: &[Map<String, Value>], column: &str) -> DataType {
+    let values = rows
+        .iter()
+        .filter_map(|row| row.get(column))
+        .filter(|v| !v.is_null())
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        DataType::Utf8
+    } else if values.iter().all(|v| v.is_boolean()) {
+        DataType::Boolean
+    } else if values.iter().all(|v| v.is_i64()) {
+        DataType::Int64
+    } else if values.iter().all(|v| v.is_number()) {
+        DataType::Float64
+    } else {
+        DataType::Utf8
+    }
+}
+
+fn column_array(rows: &[Map<String, Value>], column: &str, data_type: &DataType) -> ArrayRef {
+    let values = rows.iter().map(|row| row.get(column).filter(|v| !v.is_null()));
+    match data_type {
+        DataType::Boolean => Arc::new(values.map(|v| v.and_then(Value::as_bool)).collect::<BooleanArray>()),
+        DataType::Int64 => Arc::new(values.map(|v| v.and_then(Value::as_i64)).collect::<Int64Array>()),
+        DataType::Float64 => Arc::new(values.map(|v| v.and_then(Value::as_f64)).collect::<Float64Array>()),
+        _ => Arc::new(
+            values
+                .map(|v| {
+                    v.map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Encodes `rows` as a single-row-group Parquet file with `columns` in order.
+pub(crate) fn encode(rows: &[Map<String, Value>], columns: &[&str]) -> Result<Vec<u8>, String> {
+    let types = columns.iter().map(|column| column_type(rows, column)).collect::<Vec<_>>();
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .zip(&types)
+            .map(|(column, data_type)| Field::new(*column, data_type.clone(), true))
+            .collect::<Vec<_>>(),
+    ));
+    let arrays = columns
+        .iter()
+        .zip(&types)
+        .map(|(column, data_type)| column_array(rows, column, data_type))
+        .collect::<Vec<_>>();
+    let batch =
+        RecordBatch::try_new(schema.clone(), arrays).map_err(|e| format!("Failed to build Parquet batch: {e}"))?;
+    let mut out = Vec::new();
+    let mut writer =
+        ArrowWriter::try_new(&mut out, schema, None).map_err(|e| format!("Failed to start Parquet file: {e}"))?;
+    writer.write(&batch).map_err(|e| format!("Failed to write Parquet rows: {e}"))?;
+    writer.close().map_err(|e| format!("Failed to finish Parquet file: {e}"))?;
+    Ok(out)
+}
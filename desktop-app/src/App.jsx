@@ -1209,13 +1209,12 @@ function App() {
         orchestratorBaseUrl,
         orchestrator_base_url: orchestratorBaseUrl,
         task,
-        model: criticModel,
-        successConsecutiveFrames: Number(criticSuccessN),
-        success_consecutive_frames: Number(criticSuccessN),
-        successConfidenceThreshold: Number(criticConfTh),
-        success_confidence_threshold: Number(criticConfTh),
-        successRewardThreshold: Number(criticRewardTh),
-        success_reward_threshold: Number(criticRewardTh)
+        config: {
+          model: criticModel,
+          successConsecutiveFrames: Number(criticSuccessN),
+          successConfidenceThreshold: Number(criticConfTh),
+          successRewardThreshold: Number(criticRewardTh)
+        }
       });
     } catch (error) {
       await stopCriticLoop();
@@ -2,10 +2,13 @@
 from __future__ import annotations
 
 import argparse
+import hmac
 import http.server
 import json
+import os
 import queue
 import socket
+import socketserver
 import threading
 import time
 import urllib.error
@@ -68,6 +71,18 @@ class Orchestrator:
         self.step_timeout_s = step_timeout_s
         self.catalog_qualified: dict[str, NodeInfo] = {}
         self.catalog_unqualified: dict[str, NodeInfo] = {}
+        # Snapshot of the current/last plan for GET /progress; `seq` bumps on every change so
+        # pollers can tell a new step from a repeated read.
+        self.progress_lock = threading.Lock()
+        self.progress: dict[str, Any] = {"seq": 0, "state": "idle"}
+
+    def _set_progress(self, **fields: Any) -> None:
+        with self.progress_lock:
+            self.progress = {**self.progress, **fields, "seq": int(self.progress.get("seq", 0)) + 1, "ts": _now_iso()}
+
+    def progress_snapshot(self) -> dict[str, Any]:
+        with self.progress_lock:
+            return dict(self.progress)
 
     def connect_all(self) -> None:
         errors: list[dict[str, str]] = []
@@ -637,15 +652,29 @@ class Orchestrator:
 
     def execute_plan(self, plan: list[dict[str, Any]], correlation_id: str | None = None) -> None:
         _log_event("orchestrator.execute_plan.start", correlation_id, plan_len=len(plan))
+        self._set_progress(
+            correlation_id=correlation_id,
+            state="running",
+            plan_len=len(plan),
+            step_index=None,
+            step=None,
+            completed_steps=0,
+            error=None,
+        )
         for index, step in enumerate(plan):
+            self._set_progress(step_index=index, step=step)
             try:
                 self.run_step(step, correlation_id=correlation_id)
             except Exception as exc:
                 try:
                     self.emergency_stop(correlation_id=correlation_id)
                 except Exception as stop_exc:
+                    self._set_progress(state="error", error=f"step[{index}] failed: {exc}; panic STOP failed: {stop_exc}")
                     raise RuntimeError(f"step[{index}] failed: {exc}; panic STOP failed: {stop_exc}") from exc
+                self._set_progress(state="error", error=f"step[{index}] failed: {exc}; panic STOP sent")
                 raise RuntimeError(f"step[{index}] failed: {exc}; panic STOP sent") from exc
+            self._set_progress(completed_steps=index + 1)
+        self._set_progress(state="ok", step_index=None, step=None)
         _log_event("orchestrator.execute_plan.ok", correlation_id, plan_len=len(plan))
 
     def emergency_stop(self, correlation_id: str | None = None) -> None:
@@ -802,7 +831,19 @@ def make_plan(
     return local
 
 
-def run_http_bridge(orchestrator: Orchestrator, host: str, port: int) -> None:
+if hasattr(socketserver, "UnixStreamServer"):
+
+    class _UnixHTTPServer(socketserver.ThreadingMixIn, socketserver.UnixStreamServer):
+        daemon_threads = True
+
+
+def run_http_bridge(
+    orchestrator: Orchestrator,
+    host: str,
+    port: int,
+    unix_socket: str | None = None,
+    auth_token: str | None = None,
+) -> None:
     # Prevent concurrent plans from interleaving on the wire, but NEVER let /stop hang.
     # If a plan thread wedges while holding the lock, /execute_plan should fail fast with 409
     # and /stop should still send best-effort STOPs.
@@ -831,7 +872,20 @@ def run_http_bridge(orchestrator: Orchestrator, host: str, port: int) -> None:
                 raise RuntimeError("request body must be a JSON object")
             return parsed
 
+        def _authorized(self) -> bool:
+            # With a token configured, every endpoint (including /stop) requires `Authorization: Bearer <token>`.
+            if not auth_token:
+                return True
+            header = self.headers.get("Authorization", "")
+            scheme, _, supplied = header.partition(" ")
+            if scheme.lower() == "bearer" and hmac.compare_digest(supplied.strip().encode("utf-8"), auth_token.encode("utf-8")):
+                return True
+            self._write_json(401, {"ok": False, "error": "unauthorized"})
+            return False
+
         def do_GET(self) -> None:  # noqa: N802
+            if not self._authorized():
+                return
             if self.path == "/telemetry":
                 self._write_json(
                     200,
@@ -842,6 +896,11 @@ def run_http_bridge(orchestrator: Orchestrator, host: str, port: int) -> None:
                 )
                 return
 
+            if self.path == "/progress":
+                # Served without execution_lock so it stays responsive while a plan runs.
+                self._write_json(200, {"ok": True, "progress": orchestrator.progress_snapshot()})
+                return
+
             if self.path != "/status":
                 self._write_json(404, {"ok": False, "error": "not_found"})
                 return
@@ -871,6 +930,8 @@ def run_http_bridge(orchestrator: Orchestrator, host: str, port: int) -> None:
             )
 
         def do_POST(self) -> None:  # noqa: N802
+            if not self._authorized():
+                return
             if self.path == "/stop":
                 correlation_id = self.headers.get("X-Correlation-Id") or f"orch-{uuid.uuid4().hex[:12]}"
                 _log_event("http.stop.request", correlation_id)
@@ -1009,6 +1070,23 @@ def run_http_bridge(orchestrator: Orchestrator, host: str, port: int) -> None:
         except Exception:
             return False
 
+    if unix_socket:
+        # Local desktop spawns can avoid TCP entirely (no port conflicts, no firewall prompts).
+        # A leftover socket file from a crashed instance would make bind() fail, so clear it first.
+        if os.path.exists(unix_socket):
+            os.unlink(unix_socket)
+        unix_server = _UnixHTTPServer(unix_socket, Handler)
+        print(f"http bridge listening on unix://{unix_socket}")
+        try:
+            unix_server.serve_forever()
+        finally:
+            unix_server.server_close()
+            try:
+                os.unlink(unix_socket)
+            except OSError:
+                pass
+        return
+
     try:
         server = http.server.ThreadingHTTPServer((host, port), Handler)
     except OSError as exc:
@@ -1081,20 +1159,27 @@ def parse_args() -> argparse.Namespace:
     parser.add_argument("--timeout", type=float, default=7.0, help="Node connect/HELLO timeout (seconds)")
     parser.add_argument("--http-host", default="127.0.0.1", help="HTTP bridge bind host")
     parser.add_argument("--http-port", type=int, default=None, help="HTTP bridge bind port")
+    parser.add_argument(
+        "--http-token",
+        default=os.environ.get("DAEMON_ORCHESTRATOR_TOKEN"),
+        help="Require this bearer token on every HTTP bridge request (default: $DAEMON_ORCHESTRATOR_TOKEN)",
+    )
+    parser.add_argument("--http-unix-socket", default=None, help="Serve the HTTP bridge on this Unix socket path instead of TCP")
     return parser.parse_args()
 
 
 def main() -> None:
     args = parse_args()
     nodes = [parse_node_arg(raw) for raw in args.node]
-    if args.http_port is not None and args.instruction:
-        raise RuntimeError("Use either --instruction one-shot mode or --http-port bridge mode, not both.")
+    bridge_mode = args.http_port is not None or bool(args.http_unix_socket)
+    if bridge_mode and args.instruction:
+        raise RuntimeError("Use either --instruction one-shot mode or --http-port/--http-unix-socket bridge mode, not both.")
 
     orchestrator = Orchestrator(nodes=nodes, telemetry=args.telemetry, timeout_s=args.timeout, step_timeout_s=args.step_timeout)
     try:
         orchestrator.connect_all()
-        if args.http_port is not None:
-            run_http_bridge(orchestrator, args.http_host, args.http_port)
+        if bridge_mode:
+            run_http_bridge(orchestrator, args.http_host, args.http_port or 0, unix_socket=args.http_unix_socket, auth_token=args.http_token)
         elif args.instruction:
             correlation_id = f"cli-{uuid.uuid4().hex[:12]}"
             planned = make_plan(args.instruction, orchestrator, args.planner_url, correlation_id=correlation_id)
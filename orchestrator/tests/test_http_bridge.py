@@ -1,5 +1,8 @@
+import http.client
 import json
+import os
 import socket
+import tempfile
 import threading
 import time
 import unittest
@@ -69,11 +72,111 @@ class HttpBridgeTests(unittest.TestCase):
         payload = json.loads(ctx.exception.read().decode("utf-8"))
         self.assertFalse(payload["ok"])
 
+    def test_progress_endpoint_tracks_last_plan(self):
+        status, payload = self.request(
+            "POST", "/execute_plan", {"plan": [{"type": "STOP"}], "correlation_id": "progress-test"}
+        )
+        self.assertEqual(status, 200)
+        status, payload = self.request("GET", "/progress")
+        self.assertEqual(status, 200)
+        progress = payload["progress"]
+        self.assertEqual(progress["correlation_id"], "progress-test")
+        self.assertEqual(progress["state"], "ok")
+        self.assertEqual(progress["plan_len"], 1)
+        self.assertEqual(progress["completed_steps"], 1)
+
     def test_stop_endpoint(self):
         status, payload = self.request("POST", "/stop", {})
         self.assertEqual(status, 200)
         self.assertTrue(payload["ok"])
 
 
+class HttpBridgeAuthTests(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.port = free_port()
+        cls.orchestrator = Orchestrator(nodes=[])
+        cls.thread = threading.Thread(
+            target=run_http_bridge,
+            args=(cls.orchestrator, "127.0.0.1", cls.port),
+            kwargs={"auth_token": "s3cret"},
+            daemon=True,
+        )
+        cls.thread.start()
+        time.sleep(0.2)
+
+    def status(self, headers: dict[str, str]) -> int:
+        req = urllib.request.Request(f"http://127.0.0.1:{self.port}/status", headers=headers)
+        try:
+            with urllib.request.urlopen(req, timeout=2) as resp:
+                return resp.status
+        except urllib.error.HTTPError as exc:
+            return exc.code
+
+    def test_missing_token_is_rejected(self):
+        self.assertEqual(self.status({}), 401)
+
+    def test_wrong_token_is_rejected(self):
+        self.assertEqual(self.status({"Authorization": "Bearer nope"}), 401)
+
+    def test_matching_token_is_accepted(self):
+        self.assertEqual(self.status({"Authorization": "Bearer s3cret"}), 200)
+
+
+class _UnixHTTPConnection(http.client.HTTPConnection):
+    def __init__(self, path: str):
+        super().__init__("localhost", timeout=2)
+        self.socket_path = path
+
+    def connect(self):
+        self.sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self.sock.settimeout(self.timeout)
+        self.sock.connect(self.socket_path)
+
+
+@unittest.skipUnless(hasattr(socket, "AF_UNIX"), "Unix sockets not available")
+class HttpBridgeUnixSocketTests(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.tmp = tempfile.TemporaryDirectory()
+        cls.path = os.path.join(cls.tmp.name, "orchestrator.sock")
+        # A stale socket file from a previous run must not block startup.
+        Path(cls.path).touch()
+        cls.orchestrator = Orchestrator(nodes=[])
+        cls.thread = threading.Thread(
+            target=run_http_bridge,
+            args=(cls.orchestrator, "127.0.0.1", 0),
+            kwargs={"unix_socket": cls.path},
+            daemon=True,
+        )
+        cls.thread.start()
+        time.sleep(0.2)
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.tmp.cleanup()
+
+    def request(self, method: str, path: str, body: dict | None = None):
+        conn = _UnixHTTPConnection(self.path)
+        try:
+            data = None if body is None else json.dumps(body).encode("utf-8")
+            conn.request(method, path, body=data, headers={"Content-Type": "application/json"})
+            resp = conn.getresponse()
+            return resp.status, json.loads(resp.read().decode("utf-8"))
+        finally:
+            conn.close()
+
+    def test_status_endpoint(self):
+        status, payload = self.request("GET", "/status")
+        self.assertEqual(status, 200)
+        self.assertTrue(payload["ok"])
+        self.assertEqual(payload["nodes"], [])
+
+    def test_execute_plan_endpoint(self):
+        status, payload = self.request("POST", "/execute_plan", {"plan": [{"type": "STOP"}]})
+        self.assertEqual(status, 200)
+        self.assertTrue(payload["ok"])
+
+
 if __name__ == "__main__":
     unittest.main()